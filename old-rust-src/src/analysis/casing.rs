@@ -0,0 +1,244 @@
+use std::fmt::Display;
+
+use crate::parser::{ast::{Declaration, Expression, GenericParam, LoopType, Pattern, Program, Spanned, Statement, StructElement}, visitor::{walk_declaration, walk_expression, walk_statement, Visitor}};
+
+// A lint for identifier casing. By default, this follows the usual conventions:
+// - Functions, variables, parameters, and struct fields are `snake_case`
+// - Structs, type aliases, enums, enum variants, and generic arguments are `PascalCase`
+// Names starting with an underscore are never flagged, so they can be used to opt out.
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Casing {
+    /// Lowercase words separated by underscores, like `read_file`.
+    SnakeCase,
+    /// Capitalized words with no separators, like `FileReader`.
+    PascalCase
+}
+
+impl Casing {
+    pub fn matches(&self, name: &str) -> bool {
+        match self {
+            Casing::SnakeCase => name.chars().all(|c| c.is_lowercase() || c.is_ascii_digit() || c == '_'),
+            Casing::PascalCase => name.starts_with(char::is_uppercase) && !name.contains('_')
+        }
+    }
+
+    /// Rewrites a name in this casing.
+    pub fn convert(&self, name: &str) -> String {
+        let words = split_words(name);
+        match self {
+            Casing::SnakeCase => words.iter().map(|word| word.to_lowercase()).collect::<Vec<_>>().join("_"),
+            Casing::PascalCase => words.iter().map(|word| {
+                let mut chars = word.chars();
+                match chars.next() {
+                    Some(first) => first.to_uppercase().chain(chars.flat_map(char::to_lowercase)).collect(),
+                    None => String::new()
+                }
+            }).collect()
+        }
+    }
+}
+
+impl Display for Casing {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Casing::SnakeCase => write!(f, "snake_case"),
+            Casing::PascalCase => write!(f, "PascalCase")
+        }
+    }
+}
+
+/// Splits a name into words at underscores and at lowercase-to-uppercase boundaries.
+fn split_words(name: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut previous_lowercase = false;
+    for c in name.chars() {
+        if c == '_' {
+            words.push(std::mem::take(&mut current));
+            previous_lowercase = false;
+            continue;
+        }
+        if c.is_uppercase() && previous_lowercase {
+            words.push(std::mem::take(&mut current));
+        }
+        previous_lowercase = c.is_lowercase() || c.is_ascii_digit();
+        current.push(c);
+    }
+    words.push(current);
+    words.into_iter().filter(|word| !word.is_empty()).collect()
+}
+
+/// The casing expected for each kind of name.
+#[derive(Debug, Clone, Copy)]
+pub struct CasingConventions {
+    /// Functions and methods
+    pub functions: Casing,
+    /// Variables, parameters, loop iterators, match bindings, and struct fields
+    pub variables: Casing,
+    /// Structs, type aliases, enums, enum variants, and generic arguments
+    pub types: Casing
+}
+
+impl Default for CasingConventions {
+    fn default() -> Self {
+        CasingConventions { functions: Casing::SnakeCase, variables: Casing::SnakeCase, types: Casing::PascalCase }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct CasingWarning {
+    /// What the name belongs to, like "Function" or "Struct"
+    pub kind: &'static str,
+    pub name: String,
+    pub expected: Casing
+}
+
+impl Display for CasingWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {} should be {}, like {}", self.kind, self.name, self.expected, self.expected.convert(&self.name))
+    }
+}
+
+/// Finds every name in a program that doesn't follow the given conventions, in source order.
+pub fn lint_casing(program: &Program, conventions: CasingConventions) -> Vec<CasingWarning> {
+    let mut linter = CasingLinter { conventions, warnings: Vec::new() };
+    linter.visit_program(program);
+    linter.warnings
+}
+
+struct CasingLinter {
+    conventions: CasingConventions,
+    warnings: Vec<CasingWarning>
+}
+
+impl CasingLinter {
+    fn check(&mut self, kind: &'static str, name: &str, expected: Casing) {
+        if name.starts_with('_') || expected.matches(name) {
+            return;
+        }
+        self.warnings.push(CasingWarning { kind, name: name.to_string(), expected });
+    }
+
+    fn check_generic_args(&mut self, generic_args: &[GenericParam]) {
+        for argument in generic_args {
+            self.check("Generic argument", &argument.name, self.conventions.types);
+        }
+    }
+}
+
+impl Visitor for CasingLinter {
+    fn visit_declaration(&mut self, declaration: &Declaration) {
+        match declaration {
+            Declaration::Function { name, params, generic_args, .. } => {
+                self.check("Function", name, self.conventions.functions);
+                self.check_generic_args(generic_args);
+                for param in params {
+                    self.check("Parameter", &param.name, self.conventions.variables);
+                }
+            },
+            Declaration::Struct { name, elements, generic_args, .. } => {
+                self.check("Struct", name, self.conventions.types);
+                self.check_generic_args(generic_args);
+                for element in elements {
+                    if let StructElement::Field { name, .. } = element {
+                        self.check("Field", name, self.conventions.variables);
+                    }
+                }
+            },
+            Declaration::TypeDeclaration { name, generic_args, .. } => {
+                self.check("Type", name, self.conventions.types);
+                self.check_generic_args(generic_args);
+            },
+            Declaration::Enum { name, variants, generic_args, .. } => {
+                self.check("Enum", name, self.conventions.types);
+                self.check_generic_args(generic_args);
+                for variant in variants {
+                    self.check("Enum variant", &variant.name, self.conventions.types);
+                }
+            },
+            // TODO: A convention for constants, which are usually `SCREAMING_SNAKE_CASE`
+            Declaration::Const { .. } | Declaration::Import { .. } => {}
+        }
+        walk_declaration(self, declaration);
+    }
+
+    fn visit_statement(&mut self, statement: &Statement) {
+        if let Statement::VariableDeclaration { name, .. } = statement {
+            self.check("Variable", name, self.conventions.variables);
+        }
+        walk_statement(self, statement);
+    }
+
+    fn visit_expression(&mut self, expression: &Spanned<Expression>) {
+        match &expression.node {
+            Expression::Loop(LoopType::Iterator { iterator, .. }) => {
+                self.check("Variable", iterator, self.conventions.variables);
+            },
+            Expression::Match { arms, .. } => {
+                for arm in arms {
+                    if let Pattern::Binding(name) = &arm.pattern {
+                        self.check("Variable", name, self.conventions.variables);
+                    }
+                }
+            },
+            Expression::IfLet { pattern: Pattern::Binding(name), .. } => {
+                self.check("Variable", name, self.conventions.variables);
+            },
+            _ => {}
+        }
+        walk_expression(self, expression);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser::Parser, tokenizer::Tokenizer};
+
+    fn lint(input: &str) -> Vec<String> {
+        let mut tokenizer = Tokenizer::new(input.to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let program = Parser::new(tokens).parse_program().unwrap();
+        lint_casing(&program, CasingConventions::default()).iter().map(|warning| warning.to_string()).collect()
+    }
+
+    #[test]
+    fn test_flags_wrong_casing() {
+        assert_eq!(lint(r#"
+            struct point_2d {
+                x: f64;
+                y: f64;
+            }
+
+            func ReadFile(filePath: String) -> nil {
+                const Contents: String = filePath;
+            }
+        "#), vec![
+            "Struct point_2d should be PascalCase, like Point2d",
+            "Function ReadFile should be snake_case, like read_file",
+            "Parameter filePath should be snake_case, like file_path",
+            "Variable Contents should be snake_case, like contents",
+        ]);
+    }
+
+    #[test]
+    fn test_allows_conventional_names() {
+        assert!(lint(r#"
+            struct Point<T> {
+                x_position: T;
+            }
+
+            type Distance = f64;
+
+            enum Shape { Empty, Circle(f64) }
+
+            func read_file(path_2: String, _Unused: i32) -> nil {
+                const contents: String = path_2;
+                loop (const line: [String, 1] { contents }) {
+                    print(line);
+                };
+            }
+        "#).is_empty());
+    }
+}
@@ -0,0 +1,420 @@
+use crate::parser::ast::{Expression, LoopType, Spanned, Statement, StringPart};
+
+// A control flow graph for a function body, as the basis for flow-sensitive analyses like dead code detection.
+// Each basic block is a straight-line run of statements and branch conditions, and edges point to the blocks that can run next.
+// Only `if`s, `match`es, loops, `break`, `continue`, `return`, and `?` split blocks; `&&` and `||` are treated as plain expressions.
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum CfgNode<'a> {
+    Statement(&'a Spanned<Statement>),
+    /// A branch condition, match scrutinee, or loop iterable, or a branch body that isn't a block.
+    Expression(&'a Spanned<Expression>)
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct BasicBlock<'a> {
+    /// The statements and branch conditions in this block, in the order they run.
+    /// Statements that contain control flow, like an `if` statement, are split across blocks instead of being listed.
+    pub nodes: Vec<CfgNode<'a>>,
+    /// The indices of the blocks that can run after this one.
+    pub successors: Vec<usize>
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Cfg<'a> {
+    pub blocks: Vec<BasicBlock<'a>>,
+    /// The block the body starts in.
+    pub entry: usize,
+    /// An empty block that every `return`, and the end of the body, leads to.
+    pub exit: usize
+}
+
+impl Cfg<'_> {
+    /// Every edge as a (from, to) pair, ordered by where they start.
+    pub fn edges(&self) -> Vec<(usize, usize)> {
+        self.blocks.iter().enumerate()
+            .flat_map(|(index, block)| block.successors.iter().map(move |&successor| (index, successor)))
+            .collect()
+    }
+
+    /// The blocks with an edge to `block`.
+    pub fn predecessors(&self, block: usize) -> Vec<usize> {
+        self.edges().into_iter().filter(|&(_, to)| to == block).map(|(from, _)| from).collect()
+    }
+}
+
+/// Builds the control flow graph for a function body.
+/// Code after a `return`, `break`, or `continue` goes in a block with no predecessors, so it can be found as unreachable.
+/// Nested function declarations are listed as single statements, since their bodies have graphs of their own.
+pub fn build_cfg(body: &Expression) -> Cfg<'_> {
+    let mut builder = CfgBuilder { blocks: Vec::new(), current: None, exit: 0, loops: Vec::new() };
+    let entry = builder.new_block();
+    builder.exit = builder.new_block();
+    builder.current = Some(entry);
+
+    builder.expression(body);
+    builder.jump(builder.exit);
+
+    Cfg { blocks: builder.blocks, entry, exit: builder.exit }
+}
+
+/// The targets for `continue` and `break` inside a loop.
+struct LoopTargets<'a> {
+    label: Option<&'a str>,
+    header: usize,
+    /// The blocks that end in a `break`. The block after the loop is only made once we know something reaches it.
+    breaks: Vec<usize>
+}
+
+struct CfgBuilder<'a> {
+    blocks: Vec<BasicBlock<'a>>,
+    /// The block we're adding to, or `None` right after a jump, when nothing can reach the next statement.
+    current: Option<usize>,
+    exit: usize,
+    loops: Vec<LoopTargets<'a>>
+}
+
+impl<'a> CfgBuilder<'a> {
+    fn new_block(&mut self) -> usize {
+        self.blocks.push(BasicBlock::default());
+        self.blocks.len() - 1
+    }
+
+    fn add_edge(&mut self, from: usize, to: usize) {
+        if !self.blocks[from].successors.contains(&to) {
+            self.blocks[from].successors.push(to);
+        }
+    }
+
+    /// Adds a node to the current block, starting an unreachable block if the last statement jumped away.
+    fn push_node(&mut self, node: CfgNode<'a>) {
+        let block = match self.current {
+            Some(block) => block,
+            None => {
+                let block = self.new_block();
+                self.current = Some(block);
+                block
+            }
+        };
+        self.blocks[block].nodes.push(node);
+    }
+
+    /// Ends the current block with a jump, so nothing falls through to the next statement.
+    fn jump(&mut self, to: usize) {
+        if let Some(from) = self.current {
+            self.add_edge(from, to);
+        }
+        self.current = None;
+    }
+
+    /// Starts a new block that `from` (usually the current block) leads to.
+    fn branch_from(&mut self, from: Option<usize>) -> usize {
+        let block = self.new_block();
+        if let Some(from) = from {
+            self.add_edge(from, block);
+        }
+        self.current = Some(block);
+        block
+    }
+
+    /// Joins the ends of branches into a new block, or leaves us in unreachable code if none of them fall through.
+    fn join(&mut self, ends: &[Option<usize>]) {
+        let ends = ends.iter().flatten().copied().collect::<Vec<_>>();
+        if ends.is_empty() {
+            self.current = None;
+            return;
+        }
+        let block = self.new_block();
+        for end in ends {
+            self.add_edge(end, block);
+        }
+        self.current = Some(block);
+    }
+
+    fn statement(&mut self, statement: &'a Spanned<Statement>) {
+        match &statement.node {
+            // Control flow statements don't need to be listed, since their parts are
+            Statement::Expression { expression, .. } if is_control_flow(&expression.node) => {
+                self.expression(expression);
+            },
+            Statement::Expression { expression: value, .. } | Statement::VariableDeclaration { value: Some(value), .. } => {
+                self.expression(value);
+                self.push_node(CfgNode::Statement(statement));
+            },
+            Statement::Return(value) => {
+                if let Some(value) = value {
+                    self.expression(value);
+                }
+                self.push_node(CfgNode::Statement(statement));
+                self.jump(self.exit);
+            },
+            Statement::Break(label) => {
+                self.push_node(CfgNode::Statement(statement));
+                if let Some(from) = self.current && let Some(targets) = self.loop_targets(label) {
+                    targets.breaks.push(from);
+                }
+                self.current = None;
+            },
+            Statement::Continue(label) => {
+                self.push_node(CfgNode::Statement(statement));
+                if let Some(header) = self.loop_targets(label).map(|targets| targets.header) {
+                    self.jump(header);
+                }
+            },
+            // Deferred expressions run when the function exits, not here, so they're only listed
+            Statement::Defer(_) | Statement::Declaration(_) | Statement::VariableDeclaration { value: None, .. } => {
+                self.push_node(CfgNode::Statement(statement));
+            }
+        }
+    }
+
+    fn expression(&mut self, expression: &'a Expression) {
+        match expression {
+            Expression::Block { statements, tail } => {
+                for statement in statements {
+                    self.statement(statement);
+                }
+                if let Some(tail) = tail {
+                    self.branch_body(tail);
+                }
+            },
+            Expression::If { condition, then_branch, else_branch } | Expression::IfLet { value: condition, then_branch, else_branch, .. } => {
+                self.expression(condition);
+                self.push_node(CfgNode::Expression(condition));
+                let branch = self.current;
+
+                self.branch_from(branch);
+                self.branch_body(then_branch);
+                let then_end = self.current;
+
+                let else_end = match else_branch {
+                    Some(else_branch) => {
+                        self.branch_from(branch);
+                        self.branch_body(else_branch);
+                        self.current
+                    },
+                    None => branch
+                };
+                self.join(&[then_end, else_end]);
+            },
+            Expression::Match { scrutinee, arms } => {
+                self.expression(scrutinee);
+                self.push_node(CfgNode::Expression(scrutinee));
+                let branch = self.current;
+
+                let mut ends = Vec::new();
+                for arm in arms {
+                    self.branch_from(branch);
+                    self.branch_body(&arm.body);
+                    ends.push(self.current);
+                }
+                self.join(&ends);
+            },
+            Expression::Loop(LoopType::Infinite { body, label }) => {
+                let header = self.branch_from(self.current);
+                self.loop_body(header, label.as_deref(), body);
+                // Only a `break` can leave an infinite loop
+                let breaks = self.loops.pop().unwrap().breaks;
+                self.join(&breaks.into_iter().map(Some).collect::<Vec<_>>());
+            },
+            Expression::Loop(LoopType::While { condition, body, label }) => {
+                let header = self.branch_from(self.current);
+                self.expression(condition);
+                self.push_node(CfgNode::Expression(condition));
+                let condition_end = self.current;
+
+                self.branch_from(condition_end);
+                self.loop_body(header, label.as_deref(), body);
+                let breaks = self.loops.pop().unwrap().breaks;
+                self.join(&std::iter::once(condition_end).chain(breaks.into_iter().map(Some)).collect::<Vec<_>>());
+            },
+            Expression::Loop(LoopType::Iterator { iterable, body, label, .. }) => {
+                self.expression(iterable);
+                self.push_node(CfgNode::Expression(iterable));
+                // The header is where the next element is taken, or the loop ends
+                let header = self.branch_from(self.current);
+
+                self.branch_from(Some(header));
+                self.loop_body(header, label.as_deref(), body);
+                let breaks = self.loops.pop().unwrap().breaks;
+                self.join(&std::iter::once(Some(header)).chain(breaks.into_iter().map(Some)).collect::<Vec<_>>());
+            },
+
+            // An empty optional returns from the function, so the code after a `?` might not run
+            Expression::Try { operand } => {
+                self.expression(operand);
+                let from = self.current;
+                if let Some(from) = from {
+                    self.add_edge(from, self.exit);
+                }
+                self.branch_from(from);
+            },
+
+            // These can't jump, but their operands might
+            Expression::FunctionCall { callee, args } | Expression::MethodCall { receiver: callee, args, .. } => {
+                self.expression(callee);
+                for arg in args {
+                    self.expression(arg);
+                }
+            },
+            Expression::BinaryOperation { left, right, .. } | Expression::Range { start: left, end: right, .. } |
+            Expression::MemberAssignment { object: left, value: right, .. } | Expression::Index { object: left, index: right } | Expression::Array { size: left, initial_value: right, .. } => {
+                self.expression(left);
+                self.expression(right);
+            },
+            Expression::IndexAssignment { object, index, value, .. } => {
+                self.expression(object);
+                self.expression(index);
+                self.expression(value);
+            },
+            Expression::UnaryOperation { operand, .. } | Expression::TypeOf(operand) | Expression::Cast { value: operand, .. } |
+            Expression::Await(operand) | Expression::Grouping(operand) | Expression::Assignment { value: operand, .. } | Expression::MemberAccess { object: operand, .. } => {
+                self.expression(operand);
+            },
+            Expression::StructCreation { fields, .. } => {
+                for (_, value) in fields {
+                    self.expression(value);
+                }
+            },
+            Expression::Tuple(elements) | Expression::ArrayLiteral(elements) => {
+                for element in elements {
+                    self.expression(element);
+                }
+            },
+            Expression::InterpolatedString { parts } => {
+                for part in parts {
+                    if let StringPart::Expr(expression) = part {
+                        self.expression(expression);
+                    }
+                }
+            },
+            Expression::IntegerLiteral { .. } | Expression::NumberLiteral { .. } | Expression::StringLiteral { .. } | Expression::CharLiteral(_) |
+            Expression::BooleanLiteral(_) | Expression::NilLiteral | Expression::Variable { .. } | Expression::SizeOf(_) => {}
+        }
+    }
+
+    /// Builds a loop body that starts in the current block, looping back to `header` at its end.
+    /// The loop's targets are left on the stack so the caller can see where it breaks.
+    fn loop_body(&mut self, header: usize, label: Option<&'a str>, body: &'a Spanned<Expression>) {
+        self.loops.push(LoopTargets { label, header, breaks: Vec::new() });
+        self.branch_body(body);
+        self.jump(header);
+    }
+
+    /// The loop a `break` or `continue` jumps to: the innermost one, or the one with the given label.
+    fn loop_targets(&mut self, label: &Option<String>) -> Option<&mut LoopTargets<'a>> {
+        match label {
+            Some(label) => self.loops.iter_mut().rev().find(|targets| targets.label == Some(label.as_str())),
+            None => self.loops.last_mut()
+        }
+    }
+
+    /// Builds the body of a branch or loop, listing it as a node unless it's split into blocks of its own.
+    fn branch_body(&mut self, body: &'a Spanned<Expression>) {
+        self.expression(body);
+        if !is_control_flow(&body.node) {
+            self.push_node(CfgNode::Expression(body));
+        }
+    }
+}
+
+/// Whether an expression is split into blocks of its own, rather than being a plain statement.
+pub fn is_control_flow(expression: &Expression) -> bool {
+    matches!(expression, Expression::Block { .. } | Expression::If { .. } | Expression::IfLet { .. } | Expression::Match { .. } | Expression::Loop(_))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser::{ast::Declaration, Parser}, tokenizer::Tokenizer};
+
+    fn parse_body(input: &str) -> Spanned<Expression> {
+        let mut tokenizer = Tokenizer::new(input.to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let declaration = Parser::new(tokens).parse_declaration().unwrap();
+        let Declaration::Function { body, .. } = declaration else {
+            panic!("Expected a function declaration");
+        };
+        *body
+    }
+
+    #[test]
+    fn test_if_and_while() {
+        let body = parse_body(r#"
+            func main(n: i32) -> nil {
+                let x: i32 = 0;
+                if (n > 0) {
+                    x = 1;
+                } else {
+                    x = 2;
+                };
+                loop (x < 10) {
+                    x += 1;
+                };
+                print(x);
+            }
+        "#);
+        let cfg = build_cfg(&body);
+        // Entry, exit, the two branches, the join after the `if`, the loop condition, the loop body, and the code after the loop
+        assert_eq!(cfg.blocks.len(), 8);
+        assert_eq!(cfg.edges(), vec![(0, 2), (0, 3), (2, 4), (3, 4), (4, 5), (5, 6), (5, 7), (6, 5), (7, 1)]);
+        assert_eq!(cfg.blocks[0].nodes.len(), 2);
+        assert_eq!(cfg.blocks[7].nodes.len(), 1);
+    }
+
+    #[test]
+    fn test_early_return() {
+        let body = parse_body(r#"
+            func check(n: i32) -> nil {
+                if (n < 0) {
+                    return;
+                    print(n);
+                };;
+                print(n);
+            }
+        "#);
+        let cfg = build_cfg(&body);
+        // The `print` after the `return` is in a block nothing leads to
+        assert_eq!(cfg.edges(), vec![(0, 2), (0, 4), (2, 1), (3, 4), (4, 1)]);
+        assert!(cfg.predecessors(3).is_empty());
+        assert_eq!(cfg.blocks[3].nodes.len(), 1);
+    }
+
+    #[test]
+    fn test_try_can_return() {
+        let body = parse_body(r#"
+            func main(value: i32?) -> nil {
+                print(1);
+                print(value?);
+            }
+        "#);
+        let cfg = build_cfg(&body);
+        // The `?` can leave before the second `print`, which is in the block after it
+        assert_eq!(cfg.edges(), vec![(0, 1), (0, 2), (2, 1)]);
+        assert_eq!(cfg.blocks[0].nodes.len(), 1);
+        assert_eq!(cfg.blocks[2].nodes.len(), 1);
+    }
+
+    #[test]
+    fn test_nested_loops() {
+        let body = parse_body(r#"
+            func main(a: bool, b: bool) -> nil {
+                loop {
+                    loop (a) {
+                        if (b) {
+                            continue;
+                        };;
+                        break;
+                    };
+                    return;
+                };
+            }
+        "#);
+        let cfg = build_cfg(&body);
+        // 2 is the outer loop, 3 the inner condition, 4 the inner body, 5 the `continue`, 6 after the `if`, and 7 after the inner loop
+        assert_eq!(cfg.edges(), vec![(0, 2), (2, 3), (3, 4), (3, 7), (4, 5), (4, 6), (5, 3), (6, 7), (7, 1)]);
+        // Nothing breaks out of the outer loop, so it never falls through to the exit
+        assert_eq!(cfg.predecessors(1), vec![7]);
+        assert!(cfg.predecessors(0).is_empty());
+    }
+}
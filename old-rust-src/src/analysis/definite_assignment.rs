@@ -0,0 +1,225 @@
+use std::collections::HashSet;
+
+use crate::parser::{ast::{BinaryOperator, Expression, Span, Spanned, Statement}, visitor::{walk_expression, Visitor}};
+
+use super::cfg::{build_cfg, is_control_flow, CfgNode};
+
+// Definite assignment: a variable declared without a value, like `let x: i32;`, can't be read until every path to the read assigns it.
+// This is a forward analysis over the function's control flow graph, tracking the variables that might still be unassigned.
+// Where paths join, a variable is unassigned if it is on any of them, and loops are repeated until nothing changes.
+// Variables are tracked by name, so a variable declared without a value in an inner block also hides an assigned one outside it.
+
+/// Checks that no variable in a function body is read on a path where it hasn't been assigned yet.
+/// Reports the first such read in the source.
+pub fn check_definite_assignment(body: &Expression) -> Result<(), String> {
+    let cfg = build_cfg(body);
+
+    // The variables that might be unassigned when each block starts, or `None` if nothing has reached it yet
+    let mut entry_states: Vec<Option<HashSet<String>>> = vec![None; cfg.blocks.len()];
+    entry_states[cfg.entry] = Some(HashSet::new());
+    let mut worklist = vec![cfg.entry];
+    while let Some(block) = worklist.pop() {
+        let mut tracker = AssignmentTracker { unassigned: entry_states[block].clone().unwrap_or_default(), reads: Vec::new() };
+        for node in &cfg.blocks[block].nodes {
+            tracker.node(node);
+        }
+        for &successor in &cfg.blocks[block].successors {
+            let changed = match &mut entry_states[successor] {
+                Some(state) => {
+                    let before = state.len();
+                    state.extend(tracker.unassigned.iter().cloned());
+                    state.len() != before
+                },
+                None => {
+                    entry_states[successor] = Some(tracker.unassigned.clone());
+                    true
+                }
+            };
+            if changed {
+                worklist.push(successor);
+            }
+        }
+    }
+
+    // Unreachable blocks never run, so they can't read anything
+    let mut reads = Vec::new();
+    for (block, state) in cfg.blocks.iter().zip(entry_states) {
+        let Some(state) = state else {
+            continue;
+        };
+        let mut tracker = AssignmentTracker { unassigned: state, reads: Vec::new() };
+        for node in &block.nodes {
+            tracker.node(node);
+        }
+        reads.extend(tracker.reads);
+    }
+
+    match reads.into_iter().min_by_key(|(_, span)| span.start) {
+        Some((name, span)) => Err(format!("Variable {} is read before it's definitely assigned, at {}", name, span)),
+        None => Ok(())
+    }
+}
+
+/// Runs the nodes of a block, keeping track of which variables might be unassigned and where they're read.
+struct AssignmentTracker {
+    unassigned: HashSet<String>,
+    reads: Vec<(String, Span)>
+}
+
+impl AssignmentTracker {
+    fn node(&mut self, node: &CfgNode) {
+        match node {
+            CfgNode::Statement(statement) => match &statement.node {
+                Statement::VariableDeclaration { name, value: None, .. } => {
+                    self.unassigned.insert(name.clone());
+                },
+                Statement::VariableDeclaration { name, value: Some(value), .. } => {
+                    self.visit_expression(value);
+                    self.unassigned.remove(name);
+                },
+                Statement::Expression { expression: value, .. } | Statement::Return(Some(value)) => {
+                    self.visit_expression(value);
+                },
+                // Deferred expressions run when the function exits, and nested functions are checked on their own
+                Statement::Defer(_) | Statement::Declaration(_) | Statement::Return(None) | Statement::Break(_) | Statement::Continue(_) => {}
+            },
+            CfgNode::Expression(expression) => self.visit_expression(expression)
+        }
+    }
+}
+
+impl Visitor for AssignmentTracker {
+    fn visit_expression(&mut self, expression: &Spanned<Expression>) {
+        match &expression.node {
+            // These have nodes of their own in the graph
+            node if is_control_flow(node) => {},
+            Expression::Variable { name, .. } => {
+                if self.unassigned.contains(name) {
+                    self.reads.push((name.clone(), expression.span));
+                }
+            },
+            Expression::Assignment { name, operator, value, .. } => {
+                self.visit_expression(value);
+                // A compound assignment like `x += 1` reads the variable first
+                if operator.is_some() && self.unassigned.contains(name) {
+                    self.reads.push((name.clone(), expression.span));
+                }
+                self.unassigned.remove(name);
+            },
+            // The right side of `&&` and `||` might not run, so its assignments don't count afterward
+            Expression::BinaryOperation { left, operator: BinaryOperator::And | BinaryOperator::Or, right } => {
+                self.visit_expression(left);
+                let unassigned = self.unassigned.clone();
+                self.visit_expression(right);
+                self.unassigned = unassigned;
+            },
+            _ => walk_expression(self, expression)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser::{ast::Declaration, Parser}, tokenizer::Tokenizer};
+
+    fn check(input: &str) -> Result<(), String> {
+        let mut tokenizer = Tokenizer::new(input.to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let declaration = Parser::new(tokens).parse_declaration().unwrap();
+        let Declaration::Function { body, .. } = declaration else {
+            panic!("Expected a function declaration");
+        };
+        check_definite_assignment(&body)
+    }
+
+    #[test]
+    fn test_read_before_assignment() {
+        assert!(check(r#"
+            func main() -> nil {
+                let x: i32;
+                print(x);
+                x = 1;
+            }
+        "#).unwrap_err().starts_with("Variable x is read before it's definitely assigned"));
+    }
+
+    #[test]
+    fn test_assigned_in_one_branch() {
+        assert!(check(r#"
+            func main(n: i32) -> nil {
+                let x: i32;
+                if (n > 0) {
+                    x = 1;
+                } else {
+                    print(n);
+                };
+                print(x);
+            }
+        "#).is_err());
+        // Without an `else`, skipping the branch leaves it unassigned too
+        assert!(check(r#"
+            func main(n: i32) -> nil {
+                let x: i32;
+                if (n > 0) {
+                    x = 1;
+                };;
+                print(x);
+            }
+        "#).is_err());
+    }
+
+    #[test]
+    fn test_assigned_in_both_branches() {
+        assert_eq!(check(r#"
+            func main(n: i32) -> nil {
+                let x: i32;
+                if (n > 0) {
+                    x = 1;
+                } else {
+                    x = 2;
+                };
+                print(x);
+            }
+        "#), Ok(()));
+    }
+
+    #[test]
+    fn test_loops_and_early_exits() {
+        // The loop might not run at all
+        assert!(check(r#"
+            func main(n: i32) -> nil {
+                let x: i32;
+                loop (n > 0) {
+                    x = n;
+                };
+                print(x);
+            }
+        "#).is_err());
+        // The only path that skips the assignment returns first
+        assert_eq!(check(r#"
+            func main(n: i32) -> nil {
+                let x: i32;
+                if (n > 0) {
+                    return;
+                };;
+                x = n;
+                loop {
+                    print(x);
+                    break;
+                };
+            }
+        "#), Ok(()));
+    }
+
+    #[test]
+    fn test_short_circuit_assignment() {
+        assert!(check(r#"
+            func main(a: bool) -> nil {
+                let x: bool;
+                const y: bool = a || (x = true);
+                print(x);
+            }
+        "#).is_err());
+    }
+}
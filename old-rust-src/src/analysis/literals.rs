@@ -0,0 +1,120 @@
+use crate::parser::{ast::{Expression, Pattern, Program, Span, Spanned, StringPart}, visitor::{walk_expression, Visitor}};
+
+/// Finds every string literal in a program along with where it appears, in source order.
+/// This is meant for tools that need to see all of a program's text, like translation or auditing tools.
+/// The text between the expressions of an interpolated string is included too. Those pieces don't have spans of their own,
+/// so each one has the span of the whole string.
+/// Literals in patterns are included as well. Patterns don't have spans either, so a literal in a match arm's pattern
+/// has the span of the arm's body, and one in an `if let` pattern has the span of the whole `if let`.
+/// Either way, sorting by span puts it where it was written.
+pub fn collect_string_literals(program: &Program) -> Vec<(String, Span)> {
+    let mut collector = LiteralCollector::default();
+    collector.visit_program(program);
+    // The sort is stable, so pieces of an interpolated string that share a span stay in order
+    collector.strings.sort_by_key(|(_, span)| span.start);
+    collector.strings
+}
+
+/// Like `collect_string_literals`, but for character literals.
+pub fn collect_char_literals(program: &Program) -> Vec<(char, Span)> {
+    let mut collector = LiteralCollector::default();
+    collector.visit_program(program);
+    collector.chars.sort_by_key(|(_, span)| span.start);
+    collector.chars
+}
+
+#[derive(Default)]
+struct LiteralCollector {
+    strings: Vec<(String, Span)>,
+    chars: Vec<(char, Span)>
+}
+
+impl LiteralCollector {
+    fn collect_pattern(&mut self, pattern: &Pattern, span: Span) {
+        match pattern {
+            Pattern::StringLiteral(value) => self.strings.push((value.clone(), span)),
+            Pattern::CharLiteral(value) => self.chars.push((*value, span)),
+            _ => {}
+        }
+    }
+}
+
+impl Visitor for LiteralCollector {
+    fn visit_expression(&mut self, expression: &Spanned<Expression>) {
+        match &expression.node {
+            Expression::StringLiteral { value, .. } => self.strings.push((value.clone(), expression.span)),
+            Expression::InterpolatedString { parts } => {
+                for part in parts {
+                    if let StringPart::Literal(text) = part {
+                        self.strings.push((text.clone(), expression.span));
+                    }
+                }
+            },
+            Expression::CharLiteral(value) => self.chars.push((*value, expression.span)),
+            Expression::Match { arms, .. } => {
+                for arm in arms {
+                    self.collect_pattern(&arm.pattern, arm.body.span);
+                }
+            },
+            Expression::IfLet { pattern, .. } => self.collect_pattern(pattern, expression.span),
+            _ => {}
+        }
+        walk_expression(self, expression);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser::Parser, tokenizer::Tokenizer};
+
+    #[test]
+    fn test_collect_literals() {
+        let input = r#"
+            func greet() -> nil {
+                print("Hello");
+            }
+
+            func main() -> nil {
+                const names: [String] = [String, 2] { "unknown" };
+                print(match ('y') { 'y' => 1, other => 0 });
+                print(if (let "n" = "no") { 'n' } else { 'x' });
+            }
+        "#;
+        let mut tokenizer = Tokenizer::new(input.to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let program = Parser::new(tokens).parse_program().unwrap();
+
+        let strings = collect_string_literals(&program);
+        assert_eq!(strings.iter().map(|(value, _)| value.as_str()).collect::<Vec<_>>(), vec!["Hello", "unknown", "n", "no"]);
+        for (value, span) in &strings[..2] {
+            assert_eq!(&input[span.start..span.end], format!("\"{}\"", value));
+        }
+        // The `if let` pattern has the span of the whole `if let`
+        assert!(input[strings[2].1.start..strings[2].1.end].starts_with("if (let"));
+
+        // The pattern's 'y' has the span of its arm's body, so it comes after the scrutinee
+        let chars = collect_char_literals(&program);
+        assert_eq!(chars.iter().map(|(value, _)| *value).collect::<Vec<_>>(), vec!['y', 'y', 'n', 'x']);
+        assert_eq!(&input[chars[0].1.start..chars[0].1.end], "'y'");
+        assert_eq!(&input[chars[1].1.start..chars[1].1.end], "1");
+    }
+
+    #[test]
+    fn test_interpolated_strings() {
+        let input = r#"
+            func main(name: String) -> nil {
+                print("Hello, ${name}! ${"nested"}");
+            }
+        "#;
+        let mut tokenizer = Tokenizer::new(input.to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let program = Parser::new(tokens).parse_program().unwrap();
+
+        // The text around the expressions has the span of the whole string, which starts before anything inside it
+        let strings = collect_string_literals(&program);
+        assert_eq!(strings.iter().map(|(value, _)| value.as_str()).collect::<Vec<_>>(), vec!["Hello, ", "! ", "nested"]);
+        assert_eq!(&input[strings[0].1.start..strings[0].1.end], r#""Hello, ${name}! ${"nested"}""#);
+        assert_eq!(strings[0].1, strings[1].1);
+    }
+}
@@ -0,0 +1,139 @@
+use std::fmt::Display;
+
+use crate::parser::{ast::{Expression, Pattern, Program, Span, Spanned}, visitor::{walk_expression, Visitor}};
+
+// A lint for match arms that can never be chosen, because an earlier arm already matches everything they would.
+// An arm is unreachable if it comes after a binding, which matches anything, or if an earlier arm has the same literal.
+// Arms for both `true` and `false` match anything too, so the arm after whichever of them comes last is unreachable.
+// Patterns don't have spans of their own, so arms are pointed to by their bodies.
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct UnreachableArm {
+    pub pattern: Pattern,
+    /// The body of the arm that can't be chosen.
+    pub span: Span,
+    /// The body of the earlier arm that matches first.
+    pub covered_by: Span
+}
+
+impl Display for UnreachableArm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Match arm {} at {} is unreachable because the arm at {} matches first", self.pattern, self.span, self.covered_by)
+    }
+}
+
+/// Finds every match arm that's covered by an earlier arm in the same match, in source order.
+pub fn find_unreachable_arms(program: &Program) -> Vec<UnreachableArm> {
+    let mut finder = ArmFinder::default();
+    finder.visit_program(program);
+    // Matches are checked before the matches inside their arms
+    finder.unreachable.sort_by_key(|unreachable| unreachable.span.start);
+    finder.unreachable
+}
+
+#[derive(Default)]
+struct ArmFinder {
+    unreachable: Vec<UnreachableArm>
+}
+
+impl Visitor for ArmFinder {
+    fn visit_expression(&mut self, expression: &Spanned<Expression>) {
+        if let Expression::Match { arms, .. } = &expression.node {
+            for (i, arm) in arms.iter().enumerate() {
+                let covering = arms[..i].iter().find(|earlier| {
+                    matches!(earlier.pattern, Pattern::Binding(_)) || earlier.pattern == arm.pattern
+                }).or_else(|| {
+                    let position = |value| arms[..i].iter().position(|earlier| earlier.pattern == Pattern::BooleanLiteral(value));
+                    Some(&arms[position(true)?.max(position(false)?)])
+                });
+                if let Some(covering) = covering {
+                    self.unreachable.push(UnreachableArm { pattern: arm.pattern.clone(), span: arm.body.span, covered_by: covering.body.span });
+                }
+            }
+        }
+        walk_expression(self, expression);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser::Parser, tokenizer::Tokenizer};
+
+    fn find(source: &str) -> Vec<(String, String)> {
+        let mut tokenizer = Tokenizer::new(source.to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let program = Parser::new(tokens).parse_program().unwrap();
+        find_unreachable_arms(&program).iter()
+            .map(|arm| (source[arm.span.start..arm.span.end].to_string(), source[arm.covered_by.start..arm.covered_by.end].to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_duplicate_literal() {
+        assert_eq!(find(r#"
+            func main(x: i32) -> String {
+                match (x) {
+                    1 => "one",
+                    2 => "two",
+                    1 => "uno",
+                    other => "many"
+                }
+            }
+        "#), vec![("\"uno\"".to_string(), "\"one\"".to_string())]);
+    }
+
+    #[test]
+    fn test_arms_after_binding() {
+        assert_eq!(find(r#"
+            func main(x: i32) -> i32 {
+                match (x) {
+                    0 => 1,
+                    other => other,
+                    1 => 2,
+                    again => 3
+                }
+            }
+        "#), vec![("2".to_string(), "other".to_string()), ("3".to_string(), "other".to_string())]);
+    }
+
+    #[test]
+    fn test_both_booleans() {
+        assert_eq!(find(r#"
+            func main(x: bool) -> i32 {
+                match (x) {
+                    false => 0,
+                    true => 1,
+                    other => 2
+                }
+            }
+        "#), vec![("2".to_string(), "1".to_string())]);
+
+        assert_eq!(find(r#"
+            func main(x: bool) -> i32 {
+                match (x) {
+                    true => 0,
+                    other => 1
+                }
+            }
+        "#), Vec::<(String, String)>::new());
+    }
+
+    #[test]
+    fn test_distinct_arms() {
+        assert_eq!(find(r#"
+            func main(x: i32, name: String) -> i32 {
+                match (x) {
+                    -1 => 0,
+                    0 => 1,
+                    1 => match (name) {
+                        "a" => 2,
+                        "b" => 3,
+                        other => 4
+                    },
+                    other => 5
+                }
+            }
+        "#), Vec::<(String, String)>::new());
+    }
+}
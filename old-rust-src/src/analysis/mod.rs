@@ -0,0 +1,8 @@
+pub mod casing;
+pub mod cfg;
+pub mod definite_assignment;
+pub mod literals;
+pub mod match_arms;
+pub mod shadowing;
+pub mod tail_calls;
+pub mod unreachable;
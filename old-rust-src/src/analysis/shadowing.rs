@@ -0,0 +1,178 @@
+use std::fmt::Display;
+
+use crate::parser::{ast::{Declaration, Expression, LoopType, Pattern, Program, Span, Spanned, Statement}, visitor::{walk_declaration, walk_expression, walk_statement, Visitor}};
+
+// A lint for variables that shadow a variable from an enclosing scope, which is often a mistake.
+// Blocks, functions, and loops each start a scope. Match arms and if lets do too, since their bindings are only visible in the arm or branch.
+// Reusing a name in sibling scopes, or declaring it again in the same scope, isn't shadowing an enclosing variable,
+// so neither is flagged. Functions declared inside a block can't see the block's variables, so they start over.
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct ShadowWarning {
+    pub name: String,
+    /// Where the shadowing variable is declared.
+    /// For loop iterators and match bindings, this is the whole loop or match.
+    pub span: Span,
+    /// Where the shadowed variable is declared, or `None` if it's a function parameter, since those don't have spans.
+    pub shadowed: Option<Span>
+}
+
+impl Display for ShadowWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.shadowed {
+            Some(shadowed) => write!(f, "Variable {} at {} shadows the one declared at {}", self.name, self.span, shadowed),
+            None => write!(f, "Variable {} at {} shadows a parameter", self.name, self.span)
+        }
+    }
+}
+
+/// Finds every variable that shadows one from an enclosing scope, in source order.
+pub fn check_shadowing(program: &Program) -> Vec<ShadowWarning> {
+    let mut checker = ShadowingChecker::default();
+    checker.visit_program(program);
+    checker.warnings
+}
+
+#[derive(Default)]
+struct ShadowingChecker {
+    /// The variables declared in each scope we're in, with where they were declared.
+    scopes: Vec<Vec<(String, Option<Span>)>>,
+    warnings: Vec<ShadowWarning>
+}
+
+impl ShadowingChecker {
+    fn declare(&mut self, name: &str, span: Span) {
+        let Some((current, enclosing)) = self.scopes.split_last_mut() else {
+            return;
+        };
+        if let Some((_, shadowed)) = enclosing.iter().rev().flatten().find(|(other, _)| other == name) {
+            self.warnings.push(ShadowWarning { name: name.to_string(), span, shadowed: *shadowed });
+        }
+        current.push((name.to_string(), Some(span)));
+    }
+
+    fn in_scope(&mut self, walk: impl FnOnce(&mut Self)) {
+        self.scopes.push(Vec::new());
+        walk(self);
+        self.scopes.pop();
+    }
+}
+
+impl Visitor for ShadowingChecker {
+    fn visit_declaration(&mut self, declaration: &Declaration) {
+        let Declaration::Function { params, .. } = declaration else {
+            walk_declaration(self, declaration);
+            return;
+        };
+        let outer = std::mem::take(&mut self.scopes);
+        self.scopes.push(params.iter().map(|param| (param.name.clone(), None)).collect());
+        walk_declaration(self, declaration);
+        self.scopes = outer;
+    }
+
+    fn visit_expression(&mut self, expression: &Spanned<Expression>) {
+        match &expression.node {
+            Expression::Block { statements, tail } => self.in_scope(|checker| {
+                for statement in statements {
+                    // Declared after its value is visited, since the value can't see the new variable
+                    walk_statement(checker, statement);
+                    if let Statement::VariableDeclaration { name, .. } = &statement.node {
+                        checker.declare(name, statement.span);
+                    }
+                }
+                if let Some(tail) = tail {
+                    checker.visit_expression(tail);
+                }
+            }),
+            Expression::Loop(LoopType::Iterator { iterator, iterable, body, .. }) => {
+                self.visit_expression(iterable);
+                self.in_scope(|checker| {
+                    checker.declare(iterator, expression.span);
+                    checker.visit_expression(body);
+                });
+            },
+            Expression::Match { scrutinee, arms } => {
+                self.visit_expression(scrutinee);
+                for arm in arms {
+                    self.in_scope(|checker| {
+                        if let Pattern::Binding(name) = &arm.pattern {
+                            checker.declare(name, expression.span);
+                        }
+                        checker.visit_expression(&arm.body);
+                    });
+                }
+            },
+            Expression::IfLet { pattern, value, then_branch, else_branch } => {
+                self.visit_expression(value);
+                self.in_scope(|checker| {
+                    if let Pattern::Binding(name) = pattern {
+                        checker.declare(name, expression.span);
+                    }
+                    checker.visit_expression(then_branch);
+                });
+                if let Some(else_branch) = else_branch {
+                    self.visit_expression(else_branch);
+                }
+            },
+            _ => walk_expression(self, expression)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser::Parser, tokenizer::Tokenizer};
+
+    fn check(source: &str) -> Vec<ShadowWarning> {
+        let mut tokenizer = Tokenizer::new(source.to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        check_shadowing(&Parser::new(tokens).parse_program().unwrap())
+    }
+
+    #[test]
+    fn test_nested_shadowing() {
+        let source = r#"
+            func main(limit: i32) -> nil {
+                let total: i32 = 0;
+                {
+                    const total: i32 = 1;
+                    print(total);
+                };
+                loop (const limit: 0..10) {
+                    print(limit);
+                };
+            }
+        "#;
+        let warnings = check(source);
+        assert_eq!(warnings.len(), 2);
+        assert_eq!(warnings[0].name, "total");
+        assert_eq!(&source[warnings[0].span.start..warnings[0].span.end], "const total: i32 = 1;");
+        assert_eq!(&source[warnings[0].shadowed.unwrap().start..warnings[0].shadowed.unwrap().end], "let total: i32 = 0;");
+        assert_eq!(warnings[1], ShadowWarning { name: "limit".to_string(), span: warnings[1].span, shadowed: None });
+        assert_eq!(warnings[1].to_string(), format!("Variable limit at {} shadows a parameter", warnings[1].span));
+    }
+
+    #[test]
+    fn test_sibling_scopes() {
+        assert_eq!(check(r#"
+            func main() -> nil {
+                {
+                    const value: i32 = 1;
+                    print(value);
+                };
+                {
+                    const value: i32 = 2;
+                    print(value);
+                };
+                print(match (3) { 1 => 0, value => value });
+            }
+
+            func other(value: i32) -> nil {
+                func inner() -> nil {
+                    const value: i32 = 4;
+                }
+            }
+        "#), vec![]);
+    }
+}
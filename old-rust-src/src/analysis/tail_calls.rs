@@ -0,0 +1,246 @@
+use crate::parser::ast::{Expression, LoopType, Spanned, Statement, StringPart};
+
+/// Whether evaluating `expression` in tail position always ends in a direct call to `enclosing_fn`,
+/// meaning the call's result is returned as-is and the caller's frame could be reused for it.
+/// Blocks are tail calls if their result value is, `if`s are if both branches are, and `match`es are if every arm is.
+pub fn is_tail_call(expression: &Expression, enclosing_fn: &str) -> bool {
+    match expression {
+        Expression::FunctionCall { callee, .. } => {
+            matches!(&callee.node, Expression::Variable { name, .. } if name == enclosing_fn)
+        },
+        // Deferred expressions run after the result value, so it isn't the last thing to happen
+        Expression::Block { statements, .. } if has_defer(statements) => false,
+        Expression::Block { tail, .. } => tail.as_ref().is_some_and(|tail| is_tail_call(tail, enclosing_fn)),
+        Expression::Grouping(inner) => is_tail_call(inner, enclosing_fn),
+        Expression::If { then_branch, else_branch: Some(else_branch), .. } | Expression::IfLet { then_branch, else_branch: Some(else_branch), .. } => {
+            is_tail_call(then_branch, enclosing_fn) && is_tail_call(else_branch, enclosing_fn)
+        },
+        Expression::Match { arms, .. } => {
+            !arms.is_empty() && arms.iter().all(|arm| is_tail_call(&arm.body, enclosing_fn))
+        },
+        _ => false
+    }
+}
+
+/// Finds every direct call to `enclosing_fn` in tail position within a function body.
+/// These are the body's result value and the values of its `return` statements, looking through blocks, `if` branches, and `match` arms.
+/// Nested function declarations are skipped, since their returns belong to a different function.
+pub fn find_tail_calls<'a>(body: &'a Expression, enclosing_fn: &str) -> Vec<&'a Expression> {
+    let mut calls = Vec::new();
+    collect_tail_calls(body, enclosing_fn, true, false, &mut calls);
+    calls
+}
+
+/// `defers_pending` is set inside blocks with a `defer`, since the deferred expressions run after a `return` value is computed.
+fn collect_tail_calls<'a>(expression: &'a Expression, enclosing_fn: &str, in_tail_position: bool, defers_pending: bool, calls: &mut Vec<&'a Expression>) {
+    match expression {
+        Expression::FunctionCall { callee, args } | Expression::MethodCall { receiver: callee, args, .. } => {
+            if in_tail_position && is_tail_call(expression, enclosing_fn) {
+                calls.push(expression);
+            }
+            collect_tail_calls(callee, enclosing_fn, false, defers_pending, calls);
+            for arg in args {
+                collect_tail_calls(arg, enclosing_fn, false, defers_pending, calls);
+            }
+        },
+        // Parentheses don't change where an expression is, so a call in them can still be in tail position
+        Expression::Grouping(inner) => collect_tail_calls(inner, enclosing_fn, in_tail_position, defers_pending, calls),
+        Expression::Block { statements, tail } => {
+            let defers_pending = defers_pending || has_defer(statements);
+            for statement in statements {
+                match &statement.node {
+                    Statement::Expression { expression } => {
+                        collect_tail_calls(expression, enclosing_fn, false, defers_pending, calls);
+                    },
+                    Statement::Return(Some(value)) => {
+                        collect_tail_calls(value, enclosing_fn, !defers_pending, defers_pending, calls);
+                    },
+                    Statement::Defer(value) => {
+                        collect_tail_calls(value, enclosing_fn, false, defers_pending, calls);
+                    },
+                    Statement::VariableDeclaration { value: Some(value), .. } => {
+                        collect_tail_calls(value, enclosing_fn, false, defers_pending, calls);
+                    },
+                    Statement::VariableDeclaration { value: None, .. } | Statement::Declaration(_) |
+                    Statement::Return(None) | Statement::Break(_) | Statement::Continue(_) => {}
+                }
+            }
+            if let Some(tail) = tail {
+                collect_tail_calls(tail, enclosing_fn, in_tail_position && !defers_pending, defers_pending, calls);
+            }
+        },
+        Expression::If { condition, then_branch, else_branch } | Expression::IfLet { value: condition, then_branch, else_branch, .. } => {
+            collect_tail_calls(condition, enclosing_fn, false, defers_pending, calls);
+            collect_tail_calls(then_branch, enclosing_fn, in_tail_position, defers_pending, calls);
+            if let Some(else_branch) = else_branch {
+                collect_tail_calls(else_branch, enclosing_fn, in_tail_position, defers_pending, calls);
+            }
+        },
+        Expression::Match { scrutinee, arms } => {
+            collect_tail_calls(scrutinee, enclosing_fn, false, defers_pending, calls);
+            for arm in arms {
+                collect_tail_calls(&arm.body, enclosing_fn, in_tail_position, defers_pending, calls);
+            }
+        },
+        Expression::Loop(LoopType::Infinite { body, .. }) => {
+            collect_tail_calls(body, enclosing_fn, false, defers_pending, calls);
+        },
+        Expression::Loop(LoopType::While { condition, body, .. }) => {
+            collect_tail_calls(condition, enclosing_fn, false, defers_pending, calls);
+            collect_tail_calls(body, enclosing_fn, false, defers_pending, calls);
+        },
+        Expression::Loop(LoopType::Iterator { iterable, body, .. }) => {
+            collect_tail_calls(iterable, enclosing_fn, false, defers_pending, calls);
+            collect_tail_calls(body, enclosing_fn, false, defers_pending, calls);
+        },
+        Expression::BinaryOperation { left, right, .. } | Expression::Range { start: left, end: right, .. } |
+        Expression::MemberAssignment { object: left, value: right, .. } => {
+            collect_tail_calls(left, enclosing_fn, false, defers_pending, calls);
+            collect_tail_calls(right, enclosing_fn, false, defers_pending, calls);
+        },
+        Expression::UnaryOperation { operand, .. } => {
+            collect_tail_calls(operand, enclosing_fn, false, defers_pending, calls);
+        },
+        Expression::Assignment { value, .. } => {
+            collect_tail_calls(value, enclosing_fn, false, defers_pending, calls);
+        },
+        Expression::MemberAccess { object, .. } => {
+            collect_tail_calls(object, enclosing_fn, false, defers_pending, calls);
+        },
+        Expression::Index { object, index } => {
+            collect_tail_calls(object, enclosing_fn, false, defers_pending, calls);
+            collect_tail_calls(index, enclosing_fn, false, defers_pending, calls);
+        },
+        Expression::IndexAssignment { object, index, value, .. } => {
+            collect_tail_calls(object, enclosing_fn, false, defers_pending, calls);
+            collect_tail_calls(index, enclosing_fn, false, defers_pending, calls);
+            collect_tail_calls(value, enclosing_fn, false, defers_pending, calls);
+        },
+        Expression::Array { size, initial_value, .. } => {
+            collect_tail_calls(size, enclosing_fn, false, defers_pending, calls);
+            collect_tail_calls(initial_value, enclosing_fn, false, defers_pending, calls);
+        },
+        Expression::StructCreation { fields, .. } => {
+            for (_, value) in fields {
+                collect_tail_calls(value, enclosing_fn, false, defers_pending, calls);
+            }
+        },
+        Expression::Tuple(elements) | Expression::ArrayLiteral(elements) => {
+            for element in elements {
+                collect_tail_calls(element, enclosing_fn, false, defers_pending, calls);
+            }
+        },
+        Expression::InterpolatedString { parts } => {
+            for part in parts {
+                if let StringPart::Expr(expression) = part {
+                    collect_tail_calls(expression, enclosing_fn, false, defers_pending, calls);
+                }
+            }
+        },
+        Expression::TypeOf(operand) | Expression::Cast { value: operand, .. } | Expression::Await(operand) |
+        Expression::Try { operand } => {
+            collect_tail_calls(operand, enclosing_fn, false, defers_pending, calls);
+        },
+        Expression::IntegerLiteral { .. } | Expression::NumberLiteral { .. } | Expression::StringLiteral { .. } | Expression::CharLiteral(_) |
+        Expression::BooleanLiteral(_) | Expression::NilLiteral | Expression::Variable { .. } | Expression::SizeOf(_) => {}
+    }
+}
+
+fn has_defer(statements: &[Spanned<Statement>]) -> bool {
+    statements.iter().any(|statement| matches!(statement.node, Statement::Defer(_)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser::{ast::Declaration, Parser}, tokenizer::Tokenizer};
+
+    macro_rules! parse {
+        ($input:expr, $parse_fn:ident) => {
+            {
+                let mut tokenizer = Tokenizer::new($input.to_string());
+                let tokens = tokenizer.tokenize().unwrap();
+                let mut parser = Parser::new(tokens);
+                let expression = parser.$parse_fn().unwrap();
+                expression
+            }
+        };
+    }
+
+    fn function_body(declaration: &Declaration) -> &Expression {
+        match declaration {
+            Declaration::Function { body, .. } => body,
+            _ => panic!("Expected a function declaration")
+        }
+    }
+
+    #[test]
+    fn test_tail_recursive_factorial() {
+        let declaration = parse!(r#"
+            func factorial(n: i32, acc: i32) -> i32 {
+                if (n <= 1) {
+                    return acc;
+                } else {
+                    return factorial(n - 1, acc * n);
+                }
+            }
+        "#, parse_declaration);
+        let body = function_body(&declaration);
+
+        let calls = find_tail_calls(body, "factorial");
+        assert_eq!(calls.len(), 1);
+        assert!(is_tail_call(calls[0], "factorial"));
+        // Calls to other functions aren't self-calls
+        assert!(!is_tail_call(calls[0], "other"));
+    }
+
+    #[test]
+    fn test_non_tail_recursion() {
+        let declaration = parse!(r#"
+            func factorial(n: i32) -> i32 {
+                if (n <= 1) {
+                    1
+                } else {
+                    n * factorial(n - 1)
+                }
+            }
+        "#, parse_declaration);
+        let body = function_body(&declaration);
+
+        assert!(find_tail_calls(body, "factorial").is_empty());
+        assert!(!is_tail_call(body, "factorial"));
+    }
+
+    #[test]
+    fn test_tail_call_through_branches() {
+        let expression = parse!(r#"
+            if (n <= 1) { count(n) } else { count(n - 1) }
+        "#, parse_expression);
+        assert!(is_tail_call(&expression, "count"));
+
+        let expression = parse!(r#"
+            if (n <= 1) { n } else { count(n - 1) }
+        "#, parse_expression);
+        assert!(!is_tail_call(&expression, "count"));
+        assert_eq!(find_tail_calls(&expression, "count").len(), 1);
+    }
+
+    #[test]
+    fn test_defer_prevents_tail_calls() {
+        let declaration = parse!(r#"
+            func count(n: i32) -> i32 {
+                defer print(n);
+                if (n <= 1) {
+                    return count(0);
+                } else {
+                    count(n - 1)
+                }
+            }
+        "#, parse_declaration);
+        let body = function_body(&declaration);
+
+        // Both calls happen before the deferred print
+        assert!(find_tail_calls(body, "count").is_empty());
+        assert!(!is_tail_call(body, "count"));
+    }
+}
@@ -0,0 +1,99 @@
+use std::fmt::Display;
+
+use crate::parser::{ast::{Expression, Program, Span, Spanned, Statement}, visitor::{walk_expression, Visitor}};
+
+// A lint for code that can never run because it comes after a `return`, `break`, or `continue` in the same block.
+// Each block is checked on its own, so a jump in one branch of an `if` doesn't make the code after the `if` unreachable.
+// The control flow graph in `cfg` finds more than this, but this is enough to catch the usual leftover code.
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Unreachable {
+    /// The statement that can't run, or the block's result value.
+    pub span: Span,
+    /// The `return`, `break`, or `continue` before it.
+    pub jump: Span
+}
+
+impl Display for Unreachable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Code at {} is unreachable because of the jump at {}", self.span, self.jump)
+    }
+}
+
+/// Finds every statement that comes after a jump in its block, in source order.
+pub fn find_unreachable(program: &Program) -> Vec<Unreachable> {
+    let mut finder = UnreachableFinder::default();
+    finder.visit_program(program);
+    // Blocks are checked before the blocks inside them, so an inner block's code would come after the outer block's
+    finder.unreachable.sort_by_key(|unreachable| unreachable.span.start);
+    finder.unreachable
+}
+
+#[derive(Default)]
+struct UnreachableFinder {
+    unreachable: Vec<Unreachable>
+}
+
+impl Visitor for UnreachableFinder {
+    fn visit_expression(&mut self, expression: &Spanned<Expression>) {
+        if let Expression::Block { statements, tail } = &expression.node {
+            let jump = statements.iter().position(|statement| {
+                matches!(statement.node, Statement::Return(_) | Statement::Break(_) | Statement::Continue(_))
+            });
+            if let Some(jump) = jump {
+                let after = statements[jump + 1..].iter().map(|statement| statement.span).chain(tail.iter().map(|tail| tail.span));
+                self.unreachable.extend(after.map(|span| Unreachable { span, jump: statements[jump].span }));
+            }
+        }
+        walk_expression(self, expression);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser::Parser, tokenizer::Tokenizer};
+
+    fn find(source: &str) -> Vec<String> {
+        let mut tokenizer = Tokenizer::new(source.to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let program = Parser::new(tokens).parse_program().unwrap();
+        find_unreachable(&program).iter().map(|unreachable| source[unreachable.span.start..unreachable.span.end].to_string()).collect()
+    }
+
+    #[test]
+    fn test_code_after_jumps() {
+        assert_eq!(find(r#"
+            func main(limit: i32) -> i32 {
+                loop (const i: 0..limit) {
+                    continue;
+                    print(i);
+                };
+                return 1;
+                print(limit);
+                2
+            }
+        "#), vec!["print(i);", "print(limit);", "2"]);
+    }
+
+    #[test]
+    fn test_jumps_in_branches() {
+        // Only the branch that returns stops early, so everything after the if can still run
+        assert_eq!(find(r#"
+            func main(limit: i32) -> i32 {
+                if (limit > 10) {
+                    return 0;
+                };;
+                loop {
+                    if (limit > 5) {
+                        break;
+                    } else {
+                        print(limit);
+                    };
+                    print(limit);
+                };
+                limit
+            }
+        "#), Vec::<String>::new());
+    }
+}
@@ -1,16 +1,19 @@
-use std::collections::HashMap;
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
 
 use value::Value;
 
-use crate::parser::ast::{BinaryOperator, Declaration, Expression, ExpressionId, LoopType, Program, Statement, Type, UnaryOperator};
+use crate::parser::ast::{BinaryOperator, Declaration, Expression, ExpressionId, FunctionParameter, LoopType, Pattern, Program, Span, Spanned, Statement, StringPart, Type, UnaryOperator};
+use crate::typechecker::is_integer;
 
 mod value;
 mod resolver;
 
 #[derive(Debug, PartialEq)]
 pub enum InterpreterControl {
-    Continue,
-    Break,
+    /// Continues the loop with the given label, or the innermost one
+    Continue(Option<String>),
+    /// Breaks out of the loop with the given label, or the innermost one
+    Break(Option<String>),
     Return(Value),
     RuntimeError(String)
 }
@@ -26,17 +29,57 @@ macro_rules! runtime_error {
     };
 }
 
+/// What happens when a float is divided by zero. Integer division by zero is always an error.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum FloatDivisionPolicy {
+    /// Dividing by zero is a runtime error, like it is for integers
+    #[default]
+    Error,
+    /// Dividing by zero follows IEEE 754, producing infinity or NaN
+    Ieee
+}
+
+/// A declared function, kept so it can be called.
+struct Function {
+    params: Vec<FunctionParameter>,
+    is_variadic: bool,
+    is_async: bool,
+    body: Spanned<Expression>
+}
+
 pub struct Interpreter {
-    locals: HashMap<ExpressionId, usize>
+    locals: HashMap<ExpressionId, usize>,
+    /// The variables of each scope that's running, innermost last. Every variable has its own cell,
+    /// so that a reference to it (like `&mut point`) can share it; see `Value`.
+    scopes: Vec<HashMap<String, Rc<RefCell<Value>>>>,
+    functions: HashMap<String, Rc<Function>>,
+    /// If set, printed values are collected here instead of being written to stdout.
+    captured_output: Option<Vec<String>>,
+    float_division: FloatDivisionPolicy
 }
 
 impl Interpreter {
     pub fn new() -> Self {
         Interpreter {
-            locals: HashMap::new()
+            locals: HashMap::new(),
+            scopes: vec![HashMap::new()],
+            functions: HashMap::new(),
+            captured_output: None,
+            float_division: FloatDivisionPolicy::default()
         }
     }
 
+    pub fn with_float_division(mut self, policy: FloatDivisionPolicy) -> Self {
+        self.float_division = policy;
+        self
+    }
+
+    /// Collects printed values instead of writing them to stdout, so they can be checked afterward.
+    pub fn with_captured_output(mut self) -> Self {
+        self.captured_output = Some(Vec::new());
+        self
+    }
+
     pub fn resolve(&mut self, expr_id: ExpressionId, depth: usize) {
         self.locals.insert(expr_id, depth);
     }
@@ -53,25 +96,36 @@ impl Interpreter {
         for statement in &program.declarations {
             self.interpret_declaration(statement)?;
         }
+        // Every function is declared before main runs, so functions can be called before they appear
+        if let Some(main) = self.functions.get("main").cloned() {
+            self.call_function(&main, Vec::new())?;
+        }
         Ok(())
     }
     fn interpret_declaration(&mut self, declaration: &Declaration) -> InterpreterResult<()> {
         match declaration {
-            Declaration::Function { name, params, return_type, body, generic_args } => {
-                // TODO: Functions
-                // TEMPORARY
-                if name == "main" {
-                    self.interpret_expression(body)?;
-                }
+            Declaration::Function { name, params, body, is_async, is_variadic, .. } => {
+                self.functions.insert(name.clone(), Rc::new(Function {
+                    params: params.clone(),
+                    is_variadic: *is_variadic,
+                    is_async: *is_async,
+                    body: (**body).clone()
+                }));
             },
-            Declaration::Import { path } => {
+            Declaration::Import { path, .. } => {
                 // TODO: Imports
             },
-            Declaration::Struct { name, elements: declarations, generic_args } => {
+            Declaration::Struct { name, elements: declarations, generic_args, .. } => {
 
             },
-            Declaration::TypeDeclaration { name, alias, generic_args } => {
+            Declaration::TypeDeclaration { name, alias, generic_args, .. } => {
 
+            },
+            Declaration::Enum { .. } => {
+                // TODO: Enum values
+            },
+            Declaration::Const { .. } => {
+                // TODO: Constants
             }
         }
         Ok(())
@@ -82,11 +136,11 @@ impl Interpreter {
                 self.interpret_declaration(declaration)?;
                 return Ok(());
             },
-            Statement::Break => {
-                return Err(InterpreterControl::Break);
+            Statement::Break(label) => {
+                return Err(InterpreterControl::Break(label.clone()));
             },
-            Statement::Continue => {
-                return Err(InterpreterControl::Continue);
+            Statement::Continue(label) => {
+                return Err(InterpreterControl::Continue(label.clone()));
             },
             Statement::Return(value) => {
                 return Err(InterpreterControl::Return(value
@@ -96,119 +150,142 @@ impl Interpreter {
                 ));
             },
 
-            Statement::Expression { expression, result } => {
-                let value = self.interpret_expression(expression)?;
-                if *result {
-                    return Err(InterpreterControl::Return(value));
-                } else {
-                    return Ok(());
-                }
+            Statement::Expression { expression } => {
+                self.interpret_expression(expression)?;
+                return Ok(());
             },
 
-            Statement::VariableDeclaration { mutability, name, variable_type, value } => {
-                todo!()
+            Statement::VariableDeclaration { name, value, .. } => {
+                // A variable that's assigned later is nil until then
+                let value = match value {
+                    Some(value) => self.interpret_expression(value)?,
+                    None => Value::default()
+                };
+                self.declare(name, value);
+                return Ok(());
+            },
+
+            Statement::Defer(_) => {
+                unreachable!("Deferred expressions are collected by their block instead of being interpreted directly");
             }
         };
     }
-    fn interpret_expression(&mut self, expression: &Expression) -> InterpreterResult {
-        match expression {
+    fn interpret_expression(&mut self, expression: &Spanned<Expression>) -> InterpreterResult {
+        match &expression.node {
             Expression::CharLiteral(c) => {
                 Ok(Value::Char(*c))
             },
-            Expression::StringLiteral(s) => {
+            Expression::StringLiteral { value: s, .. } => {
                 Ok(Value::String(s.clone()))
             },
-            Expression::NumberLiteral(n) => {
-                Ok(Value::Number(*n))
+            Expression::InterpolatedString { parts } => {
+                let mut output = String::new();
+                for part in parts {
+                    match part {
+                        StringPart::Literal(text) => output.push_str(text),
+                        StringPart::Expr(expression) => output.push_str(&self.interpret_expression(expression)?.to_string())
+                    }
+                }
+                Ok(Value::String(output))
+            },
+            Expression::IntegerLiteral { value: n, .. } => {
+                Ok(Value::Integer(*n))
+            },
+            Expression::NumberLiteral { value: n, .. } => {
+                Ok(Value::Number(n.0))
             },
             Expression::BooleanLiteral(b) => {
                 Ok(Value::Boolean(*b))
             },
+            Expression::NilLiteral => {
+                Ok(Value::Nil)
+            },
+
+            Expression::Variable { name, .. } => {
+                Ok(self.variable(name, expression.span)?.borrow().clone())
+            },
+            Expression::Assignment { name, operator, value, .. } => {
+                let mut value = self.interpret_expression(value)?;
+                let variable = self.variable(name, expression.span)?;
+                if let Some(operator) = operator {
+                    let current = variable.borrow().clone();
+                    value = self.evaluate_binary(operator, current, value, expression.span)?;
+                }
+                // A variable that holds a reference is pointed at the new value, instead of changing what it referred to
+                *variable.borrow_mut() = value.clone();
+                Ok(value)
+            },
+            Expression::MemberAssignment { object, member, operator, value } => {
+                let mut value = self.interpret_expression(value)?;
+                if let Some(operator) = operator {
+                    let Some(current) = self.interpret_expression(object)?.field(member) else {
+                        return runtime_error!("No field {} at {}", member, object.span);
+                    };
+                    value = self.evaluate_binary(operator, current, value, expression.span)?;
+                }
+                self.update_place(object, &mut |object| object.set_field(member, value.clone()))?;
+                Ok(value)
+            },
+            Expression::IndexAssignment { object, index, operator, value } => {
+                let index = self.interpret_index(index)?;
+                let mut value = self.interpret_expression(value)?;
+                if let Some(operator) = operator {
+                    let Some(current) = self.interpret_expression(object)?.element(index) else {
+                        return runtime_error!("Index {} is out of bounds at {}", index, object.span);
+                    };
+                    value = self.evaluate_binary(operator, current, value, expression.span)?;
+                }
+                self.update_place(object, &mut |object| object.set_element(index, value.clone()))?;
+                Ok(value)
+            },
 
             Expression::FunctionCall { callee, args } => {
-                // TODO
-                // TEMPORARY
-                if let Expression::Variable { name, .. } = callee.as_ref() {
-                    if name == "print" {
-                        for arg in args {
-                            let value = self.interpret_expression(arg)?;
-                            println!("{}", value);
+                // TODO: Calling functions that are values, like ones passed as arguments
+                let Expression::Variable { name, .. } = &callee.node else {
+                    return runtime_error!("Unsupported function call: {:?}", expression);
+                };
+                let args = args.iter().map(|arg| self.interpret_expression(arg)).collect::<Result<Vec<_>, _>>()?;
+                if name == "print" {
+                    for value in args {
+                        match &mut self.captured_output {
+                            Some(output) => output.push(value.to_string()),
+                            None => println!("{}", value)
                         }
-                        return Ok(Value::default());
-                    } else {
-                        return runtime_error!("Unknown function: {}", name);
                     }
+                    return Ok(Value::default());
                 }
-                return runtime_error!("Unsupported function call: {:?}", expression);
+                let Some(function) = self.functions.get(name).cloned() else {
+                    return runtime_error!("Unknown function: {}", name);
+                };
+                self.call_function(&function, args)
+            },
+            Expression::MethodCall { .. } => {
+                runtime_error!("Method calls are not yet supported at runtime")
             },
 
             Expression::BinaryOperation { left, operator, right } => {
                 // TODO: Short-circuit evaluation for logical operators
                 let left_value = self.interpret_expression(left)?;
                 let right_value = self.interpret_expression(right)?;
-                
-                match (operator, left_value, right_value) {
-                    (BinaryOperator::Add, Value::Number(l), Value::Number(r)) => {
-                        Ok(Value::Number(l + r))
-                    },
-                    (BinaryOperator::Add, Value::String(l), Value::String(r)) => {
-                        Ok(Value::String(format!("{}{}", l, r)))
-                    },
-
-                    (BinaryOperator::Subtract, Value::Number(l), Value::Number(r)) => {
-                        Ok(Value::Number(l - r))
-                    },
-                    (BinaryOperator::Multiply, Value::Number(l), Value::Number(r)) => {
-                        Ok(Value::Number(l * r))
-                    },
-                    (BinaryOperator::Divide, Value::Number(l), Value::Number(r)) => {
-                        if r == 0.0 {
-                            return runtime_error!("Division by zero");
-                        }
-                        Ok(Value::Number(l / r))
-                    },
-                    (BinaryOperator::Modulus, Value::Number(l), Value::Number(r)) => {
-                        if r == 0.0 {
-                            return runtime_error!("Division by zero");
-                        }
-                        Ok(Value::Number(l % r))
-                    },
-                    (BinaryOperator::Equal, l, r) => {
-                        Ok(Value::Boolean(l == r))
-                    },
-                    (BinaryOperator::NotEqual, l, r) => {
-                        Ok(Value::Boolean(l != r))
-                    },
-
-                    (BinaryOperator::LessThan, Value::Number(l), Value::Number(r)) => {
-                        Ok(Value::Boolean(l < r))
-                    },
-                    (BinaryOperator::LessThanOrEqual, Value::Number(l), Value::Number(r)) => {
-                        Ok(Value::Boolean(l <= r))
-                    },
-                    (BinaryOperator::GreaterThan, Value::Number(l), Value::Number(r)) => {
-                        Ok(Value::Boolean(l > r))
-                    },
-                    (BinaryOperator::GreaterThanOrEqual, Value::Number(l), Value::Number(r)) => {
-                        Ok(Value::Boolean(l >= r))
-                    },
-                    
-                    (BinaryOperator::And, Value::Boolean(l), Value::Boolean(r)) => {
-                        Ok(Value::Boolean(l && r))
-                    },
-                    (BinaryOperator::Or, Value::Boolean(l), Value::Boolean(r)) => {
-                        Ok(Value::Boolean(l || r))
-                    },
+                self.evaluate_binary(operator, left_value, right_value, expression.span)
+            },
 
-                    (_, l, r) => {
-                        return runtime_error!("Unsupported binary operation: {} {} {}", l, operator, r);
-                    }
+            // A reference to a variable shares its cell, so changes through the reference change the variable.
+            // Anything else isn't stored anywhere, so the reference gets a cell of its own.
+            Expression::UnaryOperation { operator: UnaryOperator::Reference { .. }, operand } => {
+                match &operand.node {
+                    Expression::Variable { name, .. } => Ok(Value::Reference(self.variable(name, operand.span)?)),
+                    _ => Ok(Value::reference(self.interpret_expression(operand)?))
                 }
             },
-
             Expression::UnaryOperation { operator, operand } => {
                 let operand_value = self.interpret_expression(operand)?;
                 match (operator, operand_value) {
+                    (UnaryOperator::Negate, Value::Integer(n)) => {
+                        n.checked_neg().map(Value::Integer).ok_or_else(|| {
+                            InterpreterControl::RuntimeError(format!("Integer overflow at {}: -{}", expression.span, n))
+                        })
+                    },
                     (UnaryOperator::Negate, Value::Number(n)) => {
                         Ok(Value::Number(-n))
                     },
@@ -221,23 +298,58 @@ impl Interpreter {
                 }
             },
 
-            Expression::Block(statements) => {
-                for statement in statements {
-                    if let Statement::Expression { result: true, expression } = statement {
-                        return Ok(self.interpret_expression(expression)?);
+            Expression::Cast { value, target_type } => {
+                let value = self.interpret_expression(value)?;
+                cast(value, target_type)
+            },
+
+            Expression::Await(_) => {
+                runtime_error!("await is not yet supported at runtime")
+            },
+
+            Expression::Grouping(inner) => self.interpret_expression(inner),
+
+            Expression::Try { operand } => {
+                match self.interpret_expression(operand)? {
+                    Value::Nil => Err(InterpreterControl::Return(Value::Nil)),
+                    value => Ok(value)
+                }
+            },
+
+            Expression::Match { scrutinee, arms } => {
+                let value = self.interpret_expression(scrutinee)?;
+                for arm in arms {
+                    if matches_pattern(&arm.pattern, &value) {
+                        return self.interpret_with_binding(&arm.pattern, value, &arm.body);
                     }
-                    _ = self.interpret_statement(statement)?;
                 }
                 Ok(Value::default())
             },
 
-            Expression::Loop(LoopType::Infinite { body }) => {
+            Expression::Block { statements, tail } => {
+                self.scopes.push(HashMap::new());
+                let mut deferred = Vec::new();
+                let mut result = self.interpret_block(statements, tail, &mut deferred);
+
+                // Deferred expressions run however the block exits, most recent first, and can still see the block's variables.
+                // If one of them fails, its error replaces the block's result.
+                for expression in deferred.into_iter().rev() {
+                    if let Err(error) = self.interpret_expression(expression) {
+                        result = Err(error);
+                        break;
+                    }
+                }
+                self.scopes.pop();
+                result
+            },
+
+            Expression::Loop(LoopType::Infinite { body, label }) => {
                 loop {
                     match self.interpret_expression(body) {
-                        Err(InterpreterControl::Break) => {
+                        Err(InterpreterControl::Break(target)) if jumps_to(&target, label) => {
                             return Ok(Value::default());
                         },
-                        Err(InterpreterControl::Continue) => {
+                        Err(InterpreterControl::Continue(target)) if jumps_to(&target, label) => {
                             continue;
                         },
 
@@ -248,17 +360,19 @@ impl Interpreter {
                     };
                 }
             },
-            Expression::Loop(LoopType::While { condition, body }) => {
+            Expression::Loop(LoopType::While { condition, body, label }) => {
                 loop {
-                    let condition_value = self.interpret_expression(condition)?;
-                    if let Value::Boolean(false) = condition_value {
+                    let Value::Boolean(condition_value) = self.interpret_expression(condition)? else {
+                        return runtime_error!("While loop conditions must be booleans");
+                    };
+                    if !condition_value {
                         return Ok(Value::default());
                     }
                     match self.interpret_expression(body) {
-                        Err(InterpreterControl::Break) => {
+                        Err(InterpreterControl::Break(target)) if jumps_to(&target, label) => {
                             return Ok(Value::default());
                         },
-                        Err(InterpreterControl::Continue) => {
+                        Err(InterpreterControl::Continue(target)) if jumps_to(&target, label) => {
                             continue;
                         },
 
@@ -269,13 +383,15 @@ impl Interpreter {
                     };
                 }
             },
-            Expression::Loop(LoopType::Iterator { mutability, iterator, iterable, body }) => {
+            Expression::Loop(LoopType::Iterator { mutability, iterator, iterable, body, .. }) => {
                 todo!()
             },
 
             Expression::If { condition, then_branch, else_branch } => {
-                let condition_value = self.interpret_expression(condition)?;
-                if let Value::Boolean(true) = condition_value {
+                let Value::Boolean(condition_value) = self.interpret_expression(condition)? else {
+                    return runtime_error!("If conditions must be booleans");
+                };
+                if condition_value {
                     return self.interpret_expression(then_branch);
                 } else if let Some(else_branch) = else_branch {
                     return self.interpret_expression(else_branch);
@@ -283,25 +399,350 @@ impl Interpreter {
                     return Ok(Value::default());
                 }
             },
+            Expression::IfLet { pattern, value, then_branch, else_branch } => {
+                // Nil is an empty optional, so nothing matches it
+                let value = self.interpret_expression(value)?;
+                if value != Value::Nil && matches_pattern(pattern, &value) {
+                    self.interpret_with_binding(pattern, value, then_branch)
+                } else if let Some(else_branch) = else_branch {
+                    self.interpret_expression(else_branch)
+                } else {
+                    Ok(Value::default())
+                }
+            },
+
+
+            // Structs and arrays are values, so fields and elements are copies (see `Value`)
+            Expression::StructCreation { fields, .. } => {
+                let mut values = Vec::new();
+                for (name, value) in fields {
+                    values.push((name.clone(), self.interpret_expression(value)?));
+                }
+                Ok(Value::Struct(Rc::new(values)))
+            },
+            Expression::MemberAccess { object, member } => {
+                let object = self.interpret_expression(object)?;
+                match object.field(member) {
+                    Some(value) => Ok(value),
+                    None => runtime_error!("No field {} on {} at {}", member, object, expression.span)
+                }
+            },
+            Expression::Array { size, initial_value, .. } => {
+                let Value::Integer(size) = self.interpret_expression(size)? else {
+                    return runtime_error!("Array sizes must be integers");
+                };
+                let Ok(size) = usize::try_from(size) else {
+                    return runtime_error!("Invalid array size at {}: {}", expression.span, size);
+                };
+                let initial_value = self.interpret_expression(initial_value)?;
+                Ok(Value::Vector(Rc::new(vec![initial_value; size])))
+            },
+            Expression::ArrayLiteral(elements) => {
+                let elements = elements.iter().map(|element| self.interpret_expression(element)).collect::<Result<Vec<_>, _>>()?;
+                Ok(Value::Vector(Rc::new(elements)))
+            },
+            Expression::Index { object, index } => {
+                let object = self.interpret_expression(object)?;
+                let Value::Integer(index) = self.interpret_expression(index)? else {
+                    return runtime_error!("Array indexes must be integers");
+                };
+                match usize::try_from(index).ok().and_then(|index| object.element(index)) {
+                    Some(value) => Ok(value),
+                    None => runtime_error!("Index {} is out of bounds for {} at {}", index, object, expression.span)
+                }
+            },
             
             _ => todo!("Unsupported expression: {:?}", expression)
         }
     }
+
+    /// Applies a binary operator to two values, for a binary operation or compound assignment at `span`.
+    fn evaluate_binary(&self, operator: &BinaryOperator, left_value: Value, right_value: Value, span: Span) -> InterpreterResult {
+        // Mixing an integer with a float makes a float
+        let (left_value, right_value) = match (left_value, right_value) {
+            (Value::Integer(l), Value::Number(r)) => (Value::Number(l as f64), Value::Number(r)),
+            (Value::Number(l), Value::Integer(r)) => (Value::Number(l), Value::Number(r as f64)),
+            values => values
+        };
+        if let (Value::Integer(l), Value::Integer(r)) = (&left_value, &right_value)
+            && let Some(result) = evaluate_integer_binary(operator, *l, *r, span) {
+            return result;
+        }
+
+        match (operator, left_value, right_value) {
+            (BinaryOperator::Add, Value::Number(l), Value::Number(r)) => {
+                Ok(Value::Number(l + r))
+            },
+            (BinaryOperator::Add, Value::String(l), Value::String(r)) => {
+                Ok(Value::String(format!("{}{}", l, r)))
+            },
+
+            (BinaryOperator::Subtract, Value::Number(l), Value::Number(r)) => {
+                Ok(Value::Number(l - r))
+            },
+            (BinaryOperator::Multiply, Value::Number(l), Value::Number(r)) => {
+                Ok(Value::Number(l * r))
+            },
+            (BinaryOperator::Divide | BinaryOperator::Modulus, Value::Number(l), Value::Number(r))
+                if r == 0.0 && self.float_division == FloatDivisionPolicy::Error => {
+                runtime_error!("Float division by zero at {}: {} {} {}", span, l, operator, r)
+            },
+            (BinaryOperator::Divide, Value::Number(l), Value::Number(r)) => {
+                Ok(Value::Number(l / r))
+            },
+            (BinaryOperator::Modulus, Value::Number(l), Value::Number(r)) => {
+                Ok(Value::Number(l % r))
+            },
+            (BinaryOperator::Equal, l, r) => {
+                Ok(Value::Boolean(l == r))
+            },
+            (BinaryOperator::NotEqual, l, r) => {
+                Ok(Value::Boolean(l != r))
+            },
+
+            (BinaryOperator::LessThan, Value::Number(l), Value::Number(r)) => {
+                Ok(Value::Boolean(l < r))
+            },
+            (BinaryOperator::LessThanOrEqual, Value::Number(l), Value::Number(r)) => {
+                Ok(Value::Boolean(l <= r))
+            },
+            (BinaryOperator::GreaterThan, Value::Number(l), Value::Number(r)) => {
+                Ok(Value::Boolean(l > r))
+            },
+            (BinaryOperator::GreaterThanOrEqual, Value::Number(l), Value::Number(r)) => {
+                Ok(Value::Boolean(l >= r))
+            },
+            
+            (BinaryOperator::And, Value::Boolean(l), Value::Boolean(r)) => {
+                Ok(Value::Boolean(l && r))
+            },
+            (BinaryOperator::Or, Value::Boolean(l), Value::Boolean(r)) => {
+                Ok(Value::Boolean(l || r))
+            },
+
+            (_, l, r) => {
+                return runtime_error!("Unsupported binary operation: {} {} {}", l, operator, r);
+            }
+        }
+    }
+
+    /// Declares a variable in the innermost scope, in a cell of its own.
+    fn declare(&mut self, name: &str, value: Value) {
+        let scope = self.scopes.last_mut().expect("The outermost scope is never popped");
+        scope.insert(name.to_string(), Rc::new(RefCell::new(value)));
+    }
+
+    /// The cell of the innermost variable with the given name. `span` is where it's used, for errors.
+    fn variable(&self, name: &str, span: Span) -> InterpreterResult<Rc<RefCell<Value>>> {
+        match self.scopes.iter().rev().find_map(|scope| scope.get(name)) {
+            Some(variable) => Ok(variable.clone()),
+            None => runtime_error!("Undefined variable {} at {}", name, span)
+        }
+    }
+
+    /// Runs the body of a match arm or if let whose pattern matched `value`, with the value bound if the pattern is a binding.
+    fn interpret_with_binding(&mut self, pattern: &Pattern, value: Value, body: &Spanned<Expression>) -> InterpreterResult {
+        let Pattern::Binding(name) = pattern else {
+            return self.interpret_expression(body);
+        };
+        self.scopes.push(HashMap::new());
+        self.declare(name, value);
+        let result = self.interpret_expression(body);
+        self.scopes.pop();
+        result
+    }
+
+    /// Changes the value that `place` evaluates to with `update`, for an assignment to one of its fields or elements.
+    /// If it's a variable, or a field or element of one, the variable's own copy changes. If it's a reference, the value it refers to changes.
+    fn update_place(&mut self, place: &Spanned<Expression>, update: &mut dyn FnMut(&mut Value) -> Result<(), String>) -> InterpreterResult<()> {
+        let result = match &place.node {
+            Expression::Variable { name, .. } => update(&mut self.variable(name, place.span)?.borrow_mut()),
+            // The outer value's copy of a field or element is changed, then put back
+            Expression::MemberAccess { object, member } => {
+                let Some(mut inner) = self.interpret_expression(object)?.field(member) else {
+                    return runtime_error!("No field {} at {}", member, place.span);
+                };
+                if let Err(message) = update(&mut inner) {
+                    return runtime_error!("{} at {}", message, place.span);
+                }
+                return self.update_place(object, &mut |outer| outer.set_field(member, inner.clone()));
+            },
+            Expression::Index { object, index } => {
+                let index = self.interpret_index(index)?;
+                let Some(mut inner) = self.interpret_expression(object)?.element(index) else {
+                    return runtime_error!("Index {} is out of bounds at {}", index, place.span);
+                };
+                if let Err(message) = update(&mut inner) {
+                    return runtime_error!("{} at {}", message, place.span);
+                }
+                return self.update_place(object, &mut |outer| outer.set_element(index, inner.clone()));
+            },
+            Expression::Grouping(inner) => return self.update_place(inner, update),
+            // Anything else isn't stored anywhere, so changing it only matters if it's a reference
+            _ => update(&mut self.interpret_expression(place)?)
+        };
+        result.map_err(|message| InterpreterControl::RuntimeError(format!("{} at {}", message, place.span)))
+    }
+
+    /// Evaluates an array index for an assignment, which has to be a nonnegative integer.
+    fn interpret_index(&mut self, index: &Spanned<Expression>) -> InterpreterResult<usize> {
+        let Value::Integer(value) = self.interpret_expression(index)? else {
+            return runtime_error!("Array indexes must be integers");
+        };
+        let Ok(index) = usize::try_from(value) else {
+            return runtime_error!("Index {} is out of bounds at {}", value, index.span);
+        };
+        Ok(index)
+    }
+
+    /// Calls a function with arguments that were already evaluated, returning its result.
+    /// Parameters that are references share the value they were passed; every other parameter gets its own copy.
+    fn call_function(&mut self, function: &Function, mut args: Vec<Value>) -> InterpreterResult {
+        if function.is_async {
+            return runtime_error!("Async functions are not yet supported at runtime");
+        }
+        // The extra arguments to a variadic function are passed to its last parameter as an array
+        let fixed = function.params.len() - usize::from(function.is_variadic);
+        if function.is_variadic {
+            let rest = args.split_off(fixed.min(args.len()));
+            args.push(Value::Vector(Rc::new(rest)));
+        } else if args.len() > function.params.len() {
+            return runtime_error!("Expected at most {} arguments, found {}", function.params.len(), args.len());
+        }
+
+        // The function can't see its caller's variables, and its defaults are evaluated before any parameter is bound
+        let caller_scopes = std::mem::replace(&mut self.scopes, vec![HashMap::new()]);
+        let result = self.bind_arguments(function, args).and_then(|()| self.interpret_expression(&function.body));
+        self.scopes = caller_scopes;
+        match result {
+            Err(InterpreterControl::Return(value)) => Ok(value),
+            result => result
+        }
+    }
+
+    /// Declares a function's parameters for a call, using defaults for the ones that weren't passed.
+    fn bind_arguments(&mut self, function: &Function, args: Vec<Value>) -> InterpreterResult<()> {
+        let mut values = Vec::new();
+        for (i, param) in function.params.iter().enumerate() {
+            let value = match (args.get(i), &param.default) {
+                (Some(value), _) => value.clone(),
+                (None, Some(default)) => self.interpret_expression(default)?,
+                (None, None) => return runtime_error!("Missing argument for parameter {}", param.name)
+            };
+            values.push(match param.param_type {
+                Type::Reference { .. } => value,
+                _ => value.dereference()
+            });
+        }
+        for (param, value) in function.params.iter().zip(values) {
+            self.declare(&param.name, value);
+        }
+        Ok(())
+    }
+
+    /// Runs a block's statements and then its tail, adding each `defer` that's reached to `deferred` instead of running it.
+    fn interpret_block<'a>(
+        &mut self,
+        statements: &'a [Spanned<Statement>],
+        tail: &Option<Box<Spanned<Expression>>>,
+        deferred: &mut Vec<&'a Spanned<Expression>>
+    ) -> InterpreterResult {
+        for statement in statements {
+            match &statement.node {
+                Statement::Defer(expression) => {
+                    deferred.push(expression);
+                },
+                _ => {
+                    self.interpret_statement(statement)?;
+                }
+            }
+        }
+        match tail {
+            Some(tail) => self.interpret_expression(tail),
+            None => Ok(Value::default())
+        }
+    }
 } 
 
+/// Whether a `break` or `continue` to `target` stops at a loop with the given label.
+/// Unlabeled jumps stop at the innermost loop, and labeled ones pass through loops until they find theirs.
+fn jumps_to(target: &Option<String>, label: &Option<String>) -> bool {
+    target.is_none() || target == label
+}
+
+/// Whether a match arm or if let with `pattern` runs for `value`.
+fn matches_pattern(pattern: &Pattern, value: &Value) -> bool {
+    match pattern {
+        Pattern::IntegerLiteral(n) => *value == Value::Integer(*n),
+        Pattern::NumberLiteral(n) => *value == Value::Number(n.0),
+        Pattern::StringLiteral(s) => *value == Value::String(s.clone()),
+        Pattern::CharLiteral(c) => *value == Value::Char(*c),
+        Pattern::BooleanLiteral(b) => *value == Value::Boolean(*b),
+        Pattern::Binding(_) => true
+    }
+}
+
+/// Converts a value for an `as` cast.
+/// Nonzero numbers become `true` and zero becomes `false`; `true` becomes 1 and `false` becomes 0.
+/// Numbers cast to integer types are truncated toward zero.
+fn cast(value: Value, target_type: &Type) -> InterpreterResult {
+    match (value, target_type) {
+        (Value::Integer(n), Type::Boolean) => Ok(Value::Boolean(n != 0)),
+        (Value::Number(n), Type::Boolean) => Ok(Value::Boolean(n != 0.0)),
+        (Value::Boolean(b), ty) if is_integer(ty) => Ok(Value::Integer(b as i128)),
+        (Value::Integer(n), ty) if is_integer(ty) => Ok(Value::Integer(n)),
+        (Value::Number(n), ty) if is_integer(ty) => Ok(Value::Integer(n.trunc() as i128)),
+        (Value::Integer(n), Type::F32 | Type::F64) => Ok(Value::Number(n as f64)),
+        (Value::Number(n), Type::F32 | Type::F64) => Ok(Value::Number(n)),
+        (Value::Boolean(b), Type::Boolean) => Ok(Value::Boolean(b)),
+        (value, _) => runtime_error!("Can't cast {} to {:?}", value, target_type)
+    }
+}
+
+/// Evaluates arithmetic, bitwise operations, and comparisons between integers, or returns `None` for other operators.
+/// `span` is the operation's span, for errors.
+fn evaluate_integer_binary(operator: &BinaryOperator, left: i128, right: i128, span: Span) -> Option<InterpreterResult> {
+    let result = match operator {
+        BinaryOperator::Add => left.checked_add(right),
+        BinaryOperator::Subtract => left.checked_sub(right),
+        BinaryOperator::Multiply => left.checked_mul(right),
+        BinaryOperator::Divide | BinaryOperator::Modulus if right == 0 => {
+            return Some(runtime_error!("Integer division by zero at {}: {} {} {}", span, left, operator, right));
+        },
+        // Integer division truncates toward zero
+        BinaryOperator::Divide => left.checked_div(right),
+        BinaryOperator::Modulus => left.checked_rem(right),
+        BinaryOperator::BitwiseAnd => Some(left & right),
+        BinaryOperator::BitwiseOr => Some(left | right),
+        BinaryOperator::BitwiseXor => Some(left ^ right),
+        // Shifting by a negative amount or by more bits than there are is an overflow
+        BinaryOperator::ShiftLeft => u32::try_from(right).ok().and_then(|right| left.checked_shl(right)),
+        BinaryOperator::ShiftRight => u32::try_from(right).ok().and_then(|right| left.checked_shr(right)),
+
+        BinaryOperator::LessThan => return Some(Ok(Value::Boolean(left < right))),
+        BinaryOperator::LessThanOrEqual => return Some(Ok(Value::Boolean(left <= right))),
+        BinaryOperator::GreaterThan => return Some(Ok(Value::Boolean(left > right))),
+        BinaryOperator::GreaterThanOrEqual => return Some(Ok(Value::Boolean(left >= right))),
+        _ => return None
+    };
+    Some(result.map(Value::Integer).ok_or_else(|| {
+        InterpreterControl::RuntimeError(format!("Integer overflow at {}: {} {} {}", span, left, operator, right))
+    }))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{parser::{ast::{BinaryOperator, Declaration, Expression, Program, Statement, Type}, Parser}, tokenizer::Tokenizer};
+    use crate::{parser::Parser, tokenizer::Tokenizer};
 
     macro_rules! parse {
         ($input:expr, $parse_fn:ident) => {
             {
                 let mut tokenizer = Tokenizer::new($input.to_string());
                 let tokens = tokenizer.tokenize().unwrap();
-                let mut parser = Parser::new(&tokens);
+                let mut parser = Parser::new(tokens);
                 let expression = parser.$parse_fn().unwrap();
-                expression
+                Spanned::from(expression)
             }
         };
     }
@@ -315,17 +756,19 @@ mod tests {
                     params: vec![],
                     return_type: Type::F64,
                     generic_args: vec![],
-                    body: Box::new(Expression::Block(vec![
-                        Statement::Expression {
-                            expression: Box::new(Expression::BinaryOperation {
-                                left: Box::new(Expression::NumberLiteral(5.0)),
-                                operator: BinaryOperator::Add,
-                                right: Box::new(Expression::NumberLiteral(3.0))
-                            }),
-                            result: true
-                        }
-                    ]))
-                }
+                    where_clauses: vec![],
+                    is_async: false,
+                    is_variadic: false,
+                    doc: None,
+                    body: Box::new(Expression::Block {
+                        statements: vec![],
+                        tail: Some(Box::new(Expression::BinaryOperation {
+                            left: Box::new(Expression::number(5.0).into()),
+                            operator: BinaryOperator::Add,
+                            right: Box::new(Expression::number(3.0).into())
+                        }.into()))
+                    }.into())
+                }.into()
             ]
         };
 
@@ -334,12 +777,281 @@ mod tests {
         assert_eq!(result, Ok(()));
     }
 
+    #[test]
+    fn test_casts() {
+        let cast = |input: &str| Interpreter::new().interpret_expression(&parse!(input, parse_expression));
+        assert_eq!(cast("1 as bool"), Ok(Value::Boolean(true)));
+        assert_eq!(cast("-2 as bool"), Ok(Value::Boolean(true)));
+        assert_eq!(cast("0 as bool"), Ok(Value::Boolean(false)));
+        // Integers and numbers compare equal, so these match on the variant too
+        assert!(matches!(cast("true as i32"), Ok(Value::Integer(1))));
+        assert!(matches!(cast("false as u8 + 2"), Ok(Value::Integer(2))));
+        assert!(matches!(cast("2.7 as i32"), Ok(Value::Integer(2))));
+
+        // Numbers are never used as conditions implicitly
+        assert!(cast("if (1) { 2 } else { 3 }").is_err());
+        assert!(matches!(cast("if (1 as bool) { 2 } else { 3 }"), Ok(Value::Integer(2))));
+    }
+
+    #[test]
+    fn test_match() {
+        let result = Interpreter::new().interpret_expression(&parse!(r#"
+            match (1 + 1) { 1 => "one", 2 => "two", other => "many" }
+        "#, parse_expression));
+        assert_eq!(result, Ok(Value::String("two".to_string())));
+
+        let result = Interpreter::new().interpret_expression(&parse!(r#"
+            match ('z') { 'a' => 1, other => 2 }
+        "#, parse_expression));
+        assert_eq!(result, Ok(Value::Number(2.0)));
+    }
+
+    fn printed(input: &str) -> (InterpreterResult, Vec<String>) {
+        let mut interpreter = Interpreter::new().with_captured_output();
+        let result = interpreter.interpret_expression(&parse!(input, parse_expression));
+        (result, interpreter.captured_output.unwrap())
+    }
+
+    #[test]
+    fn test_defer_order() {
+        let (result, output) = printed(r#"
+            {
+                defer print(1);
+                defer print(2);
+                print(3);
+                defer { print(4); };
+                5
+            }
+        "#);
+        assert_eq!(result, Ok(Value::Number(5.0)));
+        assert_eq!(output, vec!["3", "4", "2", "1"]);
+    }
+
+    #[test]
+    fn test_defer_on_early_exit() {
+        let (result, output) = printed(r#"
+            {
+                defer print("outer");
+                {
+                    defer print("inner");
+                    return 1;
+                    defer print("unreached");
+                };
+                print("after");
+            }
+        "#);
+        assert_eq!(result, Err(InterpreterControl::Return(Value::Number(1.0))));
+        assert_eq!(output, vec!["inner", "outer"]);
+
+        let (_, output) = printed(r#"
+            loop {
+                defer print("cleanup");
+                break;
+            }
+        "#);
+        assert_eq!(output, vec!["cleanup"]);
+    }
+
+    #[test]
+    fn test_labeled_break() {
+        let (result, output) = printed(r#"
+            {
+                'outer: loop {
+                    loop {
+                        print(1);
+                        break 'outer;
+                    };
+                    print("unreached");
+                };
+                print(2);
+            }
+        "#);
+        assert!(matches!(result, Ok(Value::Nil)));
+        assert_eq!(output, vec!["1", "2"]);
+
+        // A label that no loop has escapes all of them
+        let (result, _) = printed("loop { break 'missing; }");
+        assert_eq!(result, Err(InterpreterControl::Break(Some("missing".to_string()))));
+    }
+
+    #[test]
+    fn test_interpolated_strings() {
+        let run = |input: &str| Interpreter::new().interpret_expression(&parse!(input, parse_expression));
+        assert_eq!(run(r#""a${1}b${2 * 3}""#), Ok(Value::String("a1b6".to_string())));
+        assert_eq!(run(r#""${"hello"} ${if (true) { "world" } else { "there" }}!""#), Ok(Value::String("hello world!".to_string())));
+    }
+
+    #[test]
+    fn test_try() {
+        let run = |input: &str| Interpreter::new().interpret_expression(&parse!(input, parse_expression));
+        assert_eq!(run("(if (true) 1 else nil)? + 1"), Ok(Value::Integer(2)));
+        // An empty optional returns from the function instead of evaluating the rest
+        assert!(matches!(run("(if (false) 1 else nil)? + 1"), Err(InterpreterControl::Return(Value::Nil))));
+    }
+
+    #[test]
+    fn test_structs_and_arrays() {
+        let run = |input: &str| Interpreter::new().interpret_expression(&parse!(input, parse_expression));
+        assert_eq!(run("{ (new Point { x: 1, y: 2 }).y }"), Ok(Value::Integer(2)));
+        assert_eq!(run("{ ([i32, 3] { 4 })[2] }"), Ok(Value::Integer(4)));
+        assert!(matches!(run("{ ([i32, 3] { 4 })[3] }"), Err(InterpreterControl::RuntimeError(_))));
+        assert!(matches!(run("{ (new Point { x: 1 }).z }"), Err(InterpreterControl::RuntimeError(_))));
+    }
+
     #[test]
     fn test_associativity() {
         let result = Interpreter::new().interpret_expression(&parse!(r#"
             1 + 2 * 3 - 4 / 5 % 6
         "#, parse_expression));
 
-        assert_eq!(result, Ok(Value::Number(1.0 + 2.0 * 3.0 - 4.0 / 5.0 % 6.0)));
+        // Integer division truncates, so `4 / 5` is 0
+        assert!(matches!(result, Ok(Value::Integer(7))));
+    }
+
+    #[test]
+    fn test_integer_division() {
+        let run = |input: &str| Interpreter::new().interpret_expression(&parse!(input, parse_expression));
+        assert!(matches!(run("{ 7 / 2 }"), Ok(Value::Integer(3))));
+        // Toward zero, not down
+        assert!(matches!(run("{ -7 / 2 }"), Ok(Value::Integer(-3))));
+        assert!(matches!(run("{ 7.0 / 2.0 }"), Ok(Value::Number(3.5))));
+    }
+
+    #[test]
+    fn test_division_by_zero() {
+        let result = Interpreter::new().interpret_expression(&parse!("{ 1 + 5 / 0 }", parse_expression));
+        assert_eq!(result, Err(InterpreterControl::RuntimeError("Integer division by zero at [6..11]: 5 / 0".to_string())));
+        let result = Interpreter::new().interpret_expression(&parse!("{ 5 % 0 }", parse_expression));
+        assert!(matches!(result, Err(InterpreterControl::RuntimeError(message)) if message.starts_with("Integer division by zero")));
+
+        // Floats error by default, or follow IEEE 754 if asked
+        let result = Interpreter::new().interpret_expression(&parse!("{ 5.0 / 0.0 }", parse_expression));
+        assert_eq!(result, Err(InterpreterControl::RuntimeError("Float division by zero at [2..11]: 5 / 0".to_string())));
+        let ieee = |input: &str| Interpreter::new().with_float_division(FloatDivisionPolicy::Ieee).interpret_expression(&parse!(input, parse_expression));
+        assert_eq!(ieee("5.0 / 0.0"), Ok(Value::Number(f64::INFINITY)));
+        assert!(matches!(ieee("0.0 / 0.0"), Ok(Value::Number(n)) if n.is_nan()));
+        // Integers never follow IEEE 754
+        assert!(ieee("5 / 0").is_err());
+    }
+
+    #[test]
+    fn test_integer_overflow() {
+        let result = Interpreter::new().interpret_expression(&parse!("170141183460469231731687303715884105727 + 1", parse_expression));
+        assert!(matches!(result, Err(InterpreterControl::RuntimeError(message)) if message.starts_with("Integer overflow")));
+        let result = Interpreter::new().interpret_expression(&parse!("{ 1 << -1 }", parse_expression));
+        assert!(matches!(result, Err(InterpreterControl::RuntimeError(message)) if message.starts_with("Integer overflow")));
+    }
+
+    fn run_program(input: &str) -> (InterpreterResult<()>, Vec<String>) {
+        let mut tokenizer = Tokenizer::new(input.to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let program = Parser::new(tokens).parse_program().unwrap();
+        let mut interpreter = Interpreter::new().with_captured_output();
+        let result = interpreter.run(&program);
+        (result, interpreter.captured_output.unwrap())
+    }
+
+    #[test]
+    fn test_struct_arguments() {
+        let (result, output) = run_program(r#"
+            struct Point {
+                x: i32;
+                y: i32;
+            }
+
+            func move_copy(point: Point) -> nil {
+                point.x = 10;
+                print(point.x);
+            }
+
+            func move_shared(point: &mut Point) -> nil {
+                point.x = 10;
+                point.y += 1;
+            }
+
+            func clear(values: [i32]) -> nil {
+                values[0] = 0;
+            }
+
+            func clear_shared(values: &mut [i32]) -> nil {
+                values[0] = 0;
+            }
+
+            func main() -> nil {
+                let point: Point = new Point { x: 1, y: 2 };
+                move_copy(point);
+                print(point.x);
+                move_shared(&mut point);
+                print(point.x, point.y);
+
+                let values: [i32] = [i32, 2] { 1 };
+                values[1] = 2;
+                clear(values);
+                print(values);
+                clear_shared(&mut values);
+                print(values);
+            }
+        "#);
+        assert_eq!(result, Ok(()));
+        assert_eq!(output, vec!["10", "1", "10", "3", "[1, 2, ]", "[0, 2, ]"]);
+
+        // Structs inside structs are copied with them
+        let (result, output) = run_program(r#"
+            func main() -> nil {
+                let line: Line = new Line { start: new Point { x: 1, y: 2 }, end: new Point { x: 3, y: 4 } };
+                let copy: Line = line;
+                copy.start.x = 5;
+                print(line.start.x, copy.start.x, copy.end.x);
+
+                let lines: [Line] = [Line, 2] { line };
+                lines[1] = copy;
+                lines[1].end.y -= 4;
+                print(lines[1].end.y, copy.end.y);
+            }
+        "#);
+        assert_eq!(result, Ok(()));
+        assert_eq!(output, vec!["1", "5", "3", "0", "4"]);
+    }
+
+    #[test]
+    fn test_function_calls() {
+        let (result, output) = run_program(r#"
+            func main() -> nil {
+                print(scale(2), scale(2, 3), all(), all(1, 2), first_over(5, 1, 7, 9), later());
+            }
+
+            func scale(value: i32, factor: i32 = 10) -> i32 {
+                value * factor
+            }
+
+            func all(...values: i32) -> [i32] {
+                values
+            }
+
+            func first_over(limit: i32, ...values: i32) -> i32 {
+                let i: i32 = 0;
+                loop {
+                    match (values[i] > limit) {
+                        true => { return values[i]; },
+                        other => i += 1
+                    };
+                }
+            }
+
+            func later() -> String {
+                match (scale(1)) {
+                    10 => "declared after main",
+                    other => "wrong"
+                }
+            }
+        "#);
+        assert_eq!(result, Ok(()));
+        assert_eq!(output, vec!["20", "6", "[]", "[1, 2, ]", "7", "declared after main"]);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_bitwise_operators() {
+        let result = Interpreter::new().interpret_expression(&parse!("{ 12 & 10 | 1 ^ 3 << 1 }", parse_expression));
+        assert_eq!(result, Ok(Value::Integer(12 & 10 | 1 ^ 3 << 1)));
+    }
+}
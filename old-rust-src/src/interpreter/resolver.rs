@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 
-use crate::parser::ast::{Declaration, Expression, ExpressionId, LoopType, Program, Statement, Type};
+use crate::parser::ast::{Declaration, Expression, ExpressionId, LoopType, Pattern, Program, Span, Statement, StringPart, StructElement, Type};
 
 use super::Interpreter;
 
@@ -40,25 +40,65 @@ impl<'a> Resolver<'a> {
     }
 
     pub fn resolve_program(&mut self, program: &Program) -> Result<(), String> {
-        for declaration in &program.declarations {
+        check_duplicate_declarations(program)?;
+        self.begin_scope();
+        self.resolve_declarations(program.declarations.iter().map(|declaration| &declaration.node))?;
+        self.end_scope();
+        Ok(())
+    }
+
+    /// Resolves a group of declarations that can all see each other.
+    /// Every name is defined before any body is resolved, so declarations can be used before they appear (and can be mutually recursive).
+    fn resolve_declarations<'b>(&mut self, declarations: impl Iterator<Item = &'b Declaration> + Clone) -> Result<(), String> {
+        for declaration in declarations.clone() {
+            self.hoist_declaration(declaration);
+        }
+        for declaration in declarations {
             self.resolve_declaration(declaration)?;
         }
         Ok(())
     }
 
+    fn hoist_declaration(&mut self, declaration: &Declaration) {
+        match declaration {
+            Declaration::Function { name, .. } | Declaration::Struct { name, .. } |
+            Declaration::TypeDeclaration { name, .. } | Declaration::Enum { name, .. } | Declaration::Const { name, .. } => {
+                self.define(name.to_string());
+            },
+            Declaration::Import { .. } => {
+                // TODO: Imports
+            }
+        }
+    }
+
     fn resolve_declaration(&mut self, declaration: &Declaration) -> Result<(), String> {
         match declaration {
-            Declaration::Function { name, params, return_type, body, generic_args } => {
-                todo!()
+            Declaration::Function { params, body, .. } => {
+                // Defaults are resolved outside the function, since they can't see its parameters
+                for default in params.iter().filter_map(|param| param.default.as_ref()) {
+                    self.resolve_expression(default)?;
+                }
+                self.begin_scope();
+                for param in params {
+                    self.define(param.name.to_string());
+                }
+                self.resolve_expression(body)?;
+                self.end_scope();
             },
-            Declaration::Import { path } => {
-                todo!()
+            Declaration::Const { value, .. } => self.resolve_expression(value)?,
+            Declaration::Import { .. } => {
+                // TODO: Imports
             },
-            Declaration::Struct { name, elements: declarations, generic_args } => {
-                todo!()
+            Declaration::Struct { elements, .. } => {
+                self.begin_scope();
+                self.resolve_declarations(elements.iter().filter_map(|element| match element {
+                    StructElement::Declaration { declaration, .. } => Some(declaration),
+                    StructElement::Field { .. } => None
+                }))?;
+                self.end_scope();
             },
-            Declaration::TypeDeclaration { name, alias, generic_args } => {
-                todo!()
+            Declaration::TypeDeclaration { .. } | Declaration::Enum { .. } => {
+                // Nothing to resolve
             }
         }
         Ok(())
@@ -66,30 +106,34 @@ impl<'a> Resolver<'a> {
 
     fn resolve_expression(&mut self, expression: &Expression) -> Result<(), String> {
         match expression {
-            Expression::Assignment { name: variable, value, expression_id } => {
+            Expression::Assignment { name: variable, value, expression_id, .. } => {
                 self.resolve_expression(value)?;
                 self.record_local_depth(*expression_id, variable.to_string())?;
             },
-            Expression::BinaryOperation { left, right, .. } => {
+            Expression::BinaryOperation { left, right, .. } | Expression::Range { start: left, end: right, .. } |
+            Expression::MemberAssignment { object: left, value: right, .. } => {
                 self.resolve_expression(left)?;
                 self.resolve_expression(right)?;
             },
             Expression::UnaryOperation { operand, .. } => {
                 self.resolve_expression(&operand)?;
             },
-            Expression::Block(statements) => {
+            Expression::Block { statements, tail } => {
                 self.begin_scope();
 
                 for statement in statements {
                     self.resolve_statement(statement)?;
                 }
+                if let Some(tail) = tail {
+                    self.resolve_expression(tail)?;
+                }
 
                 self.end_scope();
             },
-            Expression::BooleanLiteral(_) | Expression::CharLiteral(_) | Expression::NumberLiteral(_) | Expression::StringLiteral(_) => {
+            Expression::BooleanLiteral(_) | Expression::NilLiteral | Expression::CharLiteral(_) | Expression::IntegerLiteral { .. } | Expression::NumberLiteral { .. } | Expression::StringLiteral { .. } => {
                 // Nothing
             },
-            Expression::FunctionCall { callee, args } => {
+            Expression::FunctionCall { callee, args } | Expression::MethodCall { receiver: callee, args, .. } => {
                 self.resolve_expression(&callee)?;
                 for arg in args {
                     self.resolve_expression(arg)?;
@@ -111,10 +155,22 @@ impl<'a> Resolver<'a> {
                     self.resolve_expression(&else_branch)?;
                 }
             },
-            Expression::Loop(LoopType::Infinite { body }) => {
+            Expression::IfLet { pattern, value, then_branch, else_branch } => {
+                self.resolve_expression(value)?;
+                self.begin_scope();
+                if let Pattern::Binding(name) = pattern {
+                    self.define(name.to_string());
+                }
+                self.resolve_expression(then_branch)?;
+                self.end_scope();
+                if let Some(else_branch) = else_branch {
+                    self.resolve_expression(else_branch)?;
+                }
+            },
+            Expression::Loop(LoopType::Infinite { body, .. }) => {
                 self.resolve_expression(&body)?;
             },
-            Expression::Loop(LoopType::While { condition, body }) => {
+            Expression::Loop(LoopType::While { condition, body, .. }) => {
                 self.resolve_expression(&condition)?;
                 self.resolve_expression(&body)?;
             },
@@ -128,9 +184,48 @@ impl<'a> Resolver<'a> {
             Expression::MemberAccess { object, .. } => {
                 self.resolve_expression(&object)?;
             },
+            Expression::Index { object, index } => {
+                self.resolve_expression(object)?;
+                self.resolve_expression(index)?;
+            },
+            Expression::IndexAssignment { object, index, value, .. } => {
+                self.resolve_expression(object)?;
+                self.resolve_expression(index)?;
+                self.resolve_expression(value)?;
+            },
+            Expression::TypeOf(operand) | Expression::Cast { value: operand, .. } | Expression::Await(operand) |
+            Expression::Try { operand } | Expression::Grouping(operand) => {
+                self.resolve_expression(operand)?;
+            },
+            Expression::Match { scrutinee, arms } => {
+                self.resolve_expression(scrutinee)?;
+                for arm in arms {
+                    self.begin_scope();
+                    if let Pattern::Binding(name) = &arm.pattern {
+                        self.define(name.to_string());
+                    }
+                    self.resolve_expression(&arm.body)?;
+                    self.end_scope();
+                }
+            },
+            Expression::SizeOf(_) => {
+                // Nothing
+            },
             Expression::Array { array_type, size, initial_value } => {
                 todo!()
             },
+            Expression::Tuple(elements) | Expression::ArrayLiteral(elements) => {
+                for element in elements {
+                    self.resolve_expression(element)?;
+                }
+            },
+            Expression::InterpolatedString { parts } => {
+                for part in parts {
+                    if let StringPart::Expr(expression) = part {
+                        self.resolve_expression(expression)?;
+                    }
+                }
+            },
             Expression::StructCreation { struct_type, fields } => {
                 todo!()
             },
@@ -141,9 +236,10 @@ impl<'a> Resolver<'a> {
     fn resolve_statement(&mut self, statement: &Statement) -> Result<(), String> {
         match statement {
             Statement::Declaration(declaration) => {
+                self.hoist_declaration(declaration);
                 self.resolve_declaration(declaration)?;
             },
-            Statement::Break | Statement::Continue => {
+            Statement::Break(_) | Statement::Continue(_) => {
                 // Nothing to do here
             },
             Statement::Expression { expression, .. } => {
@@ -154,9 +250,14 @@ impl<'a> Resolver<'a> {
                     self.resolve_expression(value)?;
                 }
             },
+            Statement::Defer(value) => {
+                self.resolve_expression(value)?;
+            },
             Statement::VariableDeclaration { name, variable_type, value, .. } => {
                 self.declare(name.to_string());
-                self.resolve_expression(value)?;
+                if let Some(value) = value {
+                    self.resolve_expression(value)?;
+                }
                 self.define(name.to_string());
 
                 self.resolve_type(variable_type);
@@ -180,4 +281,94 @@ impl<'a> Resolver<'a> {
             _ => todo!()
         };
     }
-}
\ No newline at end of file
+}
+
+/// Rejects top-level declarations with the same name.
+/// Functions, structs, enums, type aliases, and constants all share one namespace, since a name can refer to any of them.
+fn check_duplicate_declarations(program: &Program) -> Result<(), String> {
+    let mut seen: HashMap<&str, Span> = HashMap::new();
+    for declaration in &program.declarations {
+        let (Declaration::Function { name, .. } | Declaration::Struct { name, .. } |
+             Declaration::TypeDeclaration { name, .. } | Declaration::Enum { name, .. } | Declaration::Const { name, .. }) = &declaration.node else {
+            continue;
+        };
+        if let Some(first) = seen.get(name.as_str()) {
+            return Err(format!("Error: {} is declared twice, at {} and {}.", name, first, declaration.span));
+        }
+        seen.insert(name, declaration.span);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser::Parser, tokenizer::Tokenizer};
+
+    fn resolve(input: &str) -> Interpreter {
+        let mut tokenizer = Tokenizer::new(input.to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let program = Parser::new(tokens).parse_program().unwrap();
+
+        let mut interpreter = Interpreter::new();
+        Resolver::new(&mut interpreter).resolve_program(&program).unwrap();
+        interpreter
+    }
+
+    /// How many variables resolved to a top-level declaration from directly inside a function body.
+    /// Function bodies are blocks, so that's two scopes (the block and the parameters) out.
+    fn top_level_references(interpreter: &Interpreter) -> usize {
+        interpreter.locals.values().filter(|depth| **depth == 2).count()
+    }
+
+    #[test]
+    fn test_call_before_definition() {
+        let interpreter = resolve(r#"
+            func main() -> i32 {
+                later(1)
+            }
+            func later(x: i32) -> i32 {
+                x
+            }
+        "#);
+        assert_eq!(top_level_references(&interpreter), 1);
+    }
+
+    fn resolve_error(input: &str) -> String {
+        let mut tokenizer = Tokenizer::new(input.to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let program = Parser::new(tokens).parse_program().unwrap();
+
+        let mut interpreter = Interpreter::new();
+        Resolver::new(&mut interpreter).resolve_program(&program).unwrap_err()
+    }
+
+    #[test]
+    fn test_duplicate_functions() {
+        assert_eq!(
+            resolve_error("func f() -> nil {}\nfunc f() -> nil {}"),
+            "Error: f is declared twice, at [0..18] and [19..37]."
+        );
+    }
+
+    #[test]
+    fn test_function_and_struct_share_namespace() {
+        assert_eq!(
+            resolve_error("struct Point { x: i32; }\nfunc Point() -> nil {}"),
+            "Error: Point is declared twice, at [0..24] and [25..47]."
+        );
+    }
+
+    #[test]
+    fn test_mutual_recursion() {
+        let interpreter = resolve(r#"
+            func is_even(n: u32) -> bool {
+                is_odd(n)
+            }
+            func is_odd(n: u32) -> bool {
+                is_even(n)
+            }
+        "#);
+        assert_eq!(top_level_references(&interpreter), 2);
+    }
+}
@@ -1,13 +1,88 @@
+use std::{cell::RefCell, rc::Rc};
+
+// Structs and arrays have value semantics, like the type system expects: assigning one or passing it to a function acts like a copy,
+// so changing the copy leaves the original alone. They're kept behind an `Rc` and only copied when a shared one is changed,
+// so passing a large struct around doesn't copy it.
+// References (`&T` and `&mut T`) share one value instead, so a change made through a reference is seen through every other reference to it.
+
 #[derive(Debug, Clone)]
 pub enum Value {
+    Integer(i128),
     Number(f64),
     String(String),
     Boolean(bool),
     Char(char),
-    Vector(Vec<Value>),
+    Vector(Rc<Vec<Value>>),
+    /// A struct's fields, in the order they were created
+    Struct(Rc<Vec<(String, Value)>>),
+    Reference(Rc<RefCell<Value>>),
     Nil
 }
 
+impl Value {
+    /// Makes a new reference to a value, which every copy of the reference shares.
+    pub fn reference(value: Value) -> Self {
+        Value::Reference(Rc::new(RefCell::new(value)))
+    }
+
+    /// The value this refers to, or the value itself if it isn't a reference.
+    pub fn dereference(&self) -> Value {
+        match self {
+            Value::Reference(value) => value.borrow().dereference(),
+            value => value.clone()
+        }
+    }
+
+    /// Reads a struct field, looking through references.
+    pub fn field(&self, name: &str) -> Option<Value> {
+        match self {
+            Value::Struct(fields) => fields.iter().find(|(field, _)| field == name).map(|(_, value)| value.clone()),
+            Value::Reference(value) => value.borrow().field(name),
+            _ => None
+        }
+    }
+
+    /// Changes a struct field. If this is a reference, every other reference to the struct sees the change;
+    /// otherwise, only this copy of the struct changes.
+    pub fn set_field(&mut self, name: &str, new_value: Value) -> Result<(), String> {
+        match self {
+            Value::Struct(fields) => {
+                let Some((_, value)) = Rc::make_mut(fields).iter_mut().find(|(field, _)| field == name) else {
+                    return Err(format!("Struct has no field {}", name));
+                };
+                *value = new_value;
+                Ok(())
+            },
+            Value::Reference(value) => value.borrow_mut().set_field(name, new_value),
+            value => Err(format!("Can't set field {} on {}", name, value))
+        }
+    }
+
+    /// Reads an array element, looking through references.
+    pub fn element(&self, index: usize) -> Option<Value> {
+        match self {
+            Value::Vector(elements) => elements.get(index).cloned(),
+            Value::Reference(value) => value.borrow().element(index),
+            _ => None
+        }
+    }
+
+    /// Changes an array element, with the same sharing as `set_field`.
+    pub fn set_element(&mut self, index: usize, new_value: Value) -> Result<(), String> {
+        match self {
+            Value::Vector(elements) => {
+                let Some(element) = Rc::make_mut(elements).get_mut(index) else {
+                    return Err(format!("Index {} is out of bounds for an array of length {}", index, elements.len()));
+                };
+                *element = new_value;
+                Ok(())
+            },
+            Value::Reference(value) => value.borrow_mut().set_element(index, new_value),
+            value => Err(format!("Can't index into {}", value))
+        }
+    }
+}
+
 impl Default for Value {
     fn default() -> Self {
         Value::Nil
@@ -22,16 +97,25 @@ impl std::fmt::Display for Value {
             } else {
                 write!(f, "false")
             },
+            Value::Integer(n) => write!(f, "{}", n),
             Value::Number(n) => write!(f, "{}", n),
             Value::String(s) => write!(f, "{}", s),
             Value::Char(c) => write!(f, "{}", c),
             Value::Vector(vec) => {
                 write!(f, "[")?;
-                for value in vec {
+                for value in vec.iter() {
                     write!(f, "{}, ", value)?;
                 }
                 write!(f, "]")
             },
+            Value::Struct(fields) => {
+                write!(f, "{{ ")?;
+                for (name, value) in fields.iter() {
+                    write!(f, "{}: {}, ", name, value)?;
+                }
+                write!(f, "}}")
+            },
+            Value::Reference(value) => write!(f, "{}", value.borrow()),
             Value::Nil => write!(f, "nil"),
         }
     }
@@ -40,12 +124,67 @@ impl std::fmt::Display for Value {
 impl PartialEq for Value {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
+            (Value::Integer(l), Value::Integer(r)) => l == r,
             (Value::Number(l), Value::Number(r)) => l == r,
+            // Integers and floats with the same value are equal, so `1 == 1.0`
+            (Value::Integer(l), Value::Number(r)) | (Value::Number(r), Value::Integer(l)) => *l as f64 == *r,
             (Value::String(l), Value::String(r)) => l == r,
             (Value::Boolean(l), Value::Boolean(r)) => l == r,
             (Value::Char(l), Value::Char(r)) => l == r,
             (Value::Vector(l), Value::Vector(r)) => l == r,
+            (Value::Struct(l), Value::Struct(r)) => l == r,
+            // References compare what they refer to
+            (Value::Reference(l), r) => *l.borrow() == *r,
+            (l, Value::Reference(r)) => *l == *r.borrow(),
             _ => false,
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(x: i128, y: i128) -> Value {
+        Value::Struct(Rc::new(vec![("x".to_string(), Value::Integer(x)), ("y".to_string(), Value::Integer(y))]))
+    }
+
+    #[test]
+    fn test_structs_are_copied() {
+        // Like passing a struct to a function by value and changing it there
+        let caller = point(1, 2);
+        let mut callee = caller.clone();
+        callee.set_field("x", Value::Integer(5)).unwrap();
+
+        assert_eq!(caller.field("x"), Some(Value::Integer(1)));
+        assert_eq!(callee.field("x"), Some(Value::Integer(5)));
+        assert_eq!(callee.set_field("z", Value::Nil), Err("Struct has no field z".to_string()));
+    }
+
+    #[test]
+    fn test_references_are_shared() {
+        let caller = Value::reference(point(1, 2));
+        let mut callee = caller.clone();
+        callee.set_field("x", Value::Integer(5)).unwrap();
+
+        assert_eq!(caller.field("x"), Some(Value::Integer(5)));
+        assert_eq!(caller, point(5, 2));
+        // Taking the value out of a reference copies it again
+        let mut copy = caller.dereference();
+        copy.set_field("y", Value::Integer(0)).unwrap();
+        assert_eq!(caller.field("y"), Some(Value::Integer(2)));
+    }
+
+    #[test]
+    fn test_arrays() {
+        let caller = Value::Vector(Rc::new(vec![Value::Integer(0); 3]));
+        let mut copy = caller.clone();
+        copy.set_element(1, Value::Integer(7)).unwrap();
+        assert_eq!(caller.element(1), Some(Value::Integer(0)));
+
+        let mut reference = Value::reference(caller.clone());
+        reference.clone().set_element(1, Value::Integer(7)).unwrap();
+        assert_eq!(reference.element(1), Some(Value::Integer(7)));
+        assert!(reference.set_element(3, Value::Nil).is_err());
+    }
+}
@@ -1,6 +1,6 @@
 #![feature(let_chains)]
 
-use std::fs;
+use std::{fs, io::IsTerminal};
 
 use clap::{command, Parser};
 use parser::ast_printer::ASTPrinter;
@@ -8,6 +8,9 @@ use parser::ast_printer::ASTPrinter;
 mod tokenizer;
 mod parser;
 mod interpreter;
+mod typechecker;
+mod analysis;
+mod optimizer;
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
@@ -19,9 +22,38 @@ struct Args {
     #[arg(long)]
     only_print_ast: bool,
 
+    /// If we should print the AST as JSON and exit
+    #[cfg(feature = "serde")]
+    #[arg(long)]
+    only_print_ast_json: bool,
+
+    /// If we should print the AST as an HTML fragment and exit
+    #[arg(long)]
+    only_print_ast_html: bool,
+
     /// If we should print the tokens and exit
     #[arg(long)]
     only_print_tokens: bool,
+
+    /// If we should type check the program before running it
+    #[arg(long)]
+    type_check: bool,
+
+    /// If records should allow width and depth subtyping when type checking
+    #[arg(long)]
+    structural_records: bool,
+
+    /// If we should warn about names that don't follow the usual casing conventions, variables that shadow others, and unreachable code
+    #[arg(long)]
+    lint: bool,
+
+    /// If we should fold constant expressions before running the program
+    #[arg(long)]
+    optimize: bool,
+
+    /// If float division by zero should produce infinity or NaN instead of an error
+    #[arg(long)]
+    ieee_float_division: bool,
 }
 
 fn main() {
@@ -49,7 +81,7 @@ fn main() {
     }
 
     let mut parser: parser::Parser = parser::Parser::new(&tokens);
-    let program = match parser.parse_program() {
+    let mut program = match parser.parse_program() {
         Some(program) => program,
         None => {
             eprintln!("Error: Failed to parse the program.");
@@ -57,25 +89,92 @@ fn main() {
         }
     };
 
+    #[cfg(feature = "serde")]
+    if args.only_print_ast_json {
+        match serde_json::to_string_pretty(&program) {
+            Ok(json) => println!("{}", json),
+            Err(e) => eprintln!("Error: {}", e)
+        }
+        return;
+    }
+
+    if args.only_print_ast_html {
+        println!("{}", parser::html_printer::HtmlPrinter::new().print_program(&program));
+        return;
+    }
+
     if args.only_print_ast {
-        let mut printer = ASTPrinter::new();
+        // Escape codes are just noise when the output is redirected to a file
+        let mut printer = ASTPrinter::new().with_color(std::io::stdout().is_terminal());
+        // Show the inferred types too if we're type checking
+        if args.type_check {
+            let rules = typechecker::SubtypingRules { width: args.structural_records, depth: args.structural_records };
+            match typechecker::TypeChecker::new().with_subtyping(rules).check_program(&program) {
+                Ok(types) => printer = printer.with_inferred_types(types),
+                Err(e) => {
+                    eprintln!("Type error: {}", e);
+                    return;
+                }
+            }
+        }
         println!("Parsed program:\n{}", printer.print_program(&program));
         return;
     }
 
-    let mut interpreter: interpreter::Interpreter = interpreter::Interpreter::new();
+    if args.lint {
+        for warning in analysis::casing::lint_casing(&program, analysis::casing::CasingConventions::default()) {
+            eprintln!("Warning: {}", warning);
+        }
+        for warning in analysis::shadowing::check_shadowing(&program) {
+            eprintln!("Warning: {}", warning);
+        }
+        for unreachable in analysis::unreachable::find_unreachable(&program) {
+            eprintln!("Warning: {}", unreachable);
+        }
+        for arm in analysis::match_arms::find_unreachable_arms(&program) {
+            eprintln!("Warning: {}", arm);
+        }
+    }
+
+    if args.type_check {
+        let rules = typechecker::SubtypingRules { width: args.structural_records, depth: args.structural_records };
+        let mut checker = typechecker::TypeChecker::new().with_subtyping(rules);
+        if let Err(e) = checker.check_program(&program) {
+            eprintln!("Type error: {}", e);
+            return;
+        }
+    }
+
+    let float_division = if args.ieee_float_division {
+        interpreter::FloatDivisionPolicy::Ieee
+    } else {
+        interpreter::FloatDivisionPolicy::Error
+    };
+
+    if args.optimize {
+        let mut folder = optimizer::folding::ConstantFolder::new().with_float_division(float_division);
+        if let Err(e) = folder.fold_program(&mut program) {
+            eprintln!("Error: {}", e);
+            return;
+        }
+    }
+
+    let mut interpreter: interpreter::Interpreter = interpreter::Interpreter::new().with_float_division(float_division);
     match interpreter.run(&program) {
         Ok(_) => {
             println!("Program executed successfully.");
         },
         Err(e) => {
             match e {
-                interpreter::InterpreterControl::Continue => {
+                interpreter::InterpreterControl::Continue(None) => {
                     eprintln!("Error: Program continued outside of a loop.");
                 },
-                interpreter::InterpreterControl::Break => {
+                interpreter::InterpreterControl::Break(None) => {
                     eprintln!("Error: Program broke outside of a loop.");
                 },
+                interpreter::InterpreterControl::Continue(Some(label)) | interpreter::InterpreterControl::Break(Some(label)) => {
+                    eprintln!("Error: No loop is labeled '{}.", label);
+                },
                 interpreter::InterpreterControl::Return(value) => {
                     eprintln!("Error: Program returned ouside of a function: {}", value);
                 },
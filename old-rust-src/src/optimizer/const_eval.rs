@@ -0,0 +1,242 @@
+use crate::{interpreter::FloatDivisionPolicy, parser::ast::{BinaryOperator, Expression, UnaryOperator}, typechecker::TypeChecker};
+
+/// A value known at compile time.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConstValue {
+    Integer(i128),
+    Number(f64),
+    String(String),
+    Boolean(bool),
+    Char(char)
+}
+
+/// `Ok(None)` means the expression isn't constant, or its value can't be written as a literal.
+pub type ConstResult = Result<Option<ConstValue>, String>;
+
+/// Evaluates an expression at compile time, treating float division by zero as an error.
+/// No structs are known, so only the sizes of types made of primitives fold.
+pub fn const_eval(expression: &Expression) -> ConstResult {
+    const_eval_with(expression, FloatDivisionPolicy::default(), &TypeChecker::new())
+}
+
+/// Evaluates an expression at compile time.
+/// Errors if the expression is constant but can't be evaluated, like when it divides by zero.
+/// Numbers are never allowed to become NaN or infinite, and integers are never allowed to overflow,
+/// so any result can be written back into the AST as a literal. With `FloatDivisionPolicy::Ieee`, float
+/// expressions that would become NaN or infinite are left for the interpreter instead of being errors.
+/// `types` is used to find the sizes of types, so it should have collected the program's structs and aliases.
+pub fn const_eval_with(expression: &Expression, float_division: FloatDivisionPolicy, types: &TypeChecker) -> ConstResult {
+    match expression {
+        Expression::IntegerLiteral { value: n, .. } => Ok(Some(ConstValue::Integer(*n))),
+        Expression::NumberLiteral { value: n, .. } => Ok(Some(ConstValue::Number(n.0))),
+        Expression::StringLiteral { value: s, .. } => Ok(Some(ConstValue::String(s.clone()))),
+        Expression::BooleanLiteral(b) => Ok(Some(ConstValue::Boolean(*b))),
+        Expression::CharLiteral(c) => Ok(Some(ConstValue::Char(*c))),
+        Expression::NilLiteral => Ok(None),
+        Expression::Grouping(inner) => const_eval_with(inner, float_division, types),
+
+        Expression::UnaryOperation { operator, operand } => {
+            let Some(operand) = const_eval_with(operand, float_division, types)? else {
+                return Ok(None);
+            };
+            match (operator, operand) {
+                (UnaryOperator::Negate, ConstValue::Integer(n)) => {
+                    n.checked_neg().map(|n| Some(ConstValue::Integer(n))).ok_or_else(|| format!("Constant expression overflowed: -{}", n))
+                },
+                (UnaryOperator::Negate, ConstValue::Number(n)) => Ok(Some(ConstValue::Number(-n))),
+                (UnaryOperator::Not, ConstValue::Boolean(b)) => Ok(Some(ConstValue::Boolean(!b))),
+                (_, operand) => Err(format!("Unsupported unary operation: {} {:?}", operator, operand))
+            }
+        },
+        Expression::BinaryOperation { left, operator, right } => {
+            let left = const_eval_with(left, float_division, types)?;
+            if let Some(ConstValue::Boolean(value)) = left && short_circuits(operator, value) {
+                return Ok(Some(ConstValue::Boolean(value)));
+            }
+            let (Some(left), Some(right)) = (left, const_eval_with(right, float_division, types)?) else {
+                return Ok(None);
+            };
+            evaluate_binary(operator, left, right, float_division)
+        },
+
+        // Types without a fixed size are left for the type checker to report
+        Expression::SizeOf(ty) => Ok(types.size_of(ty).ok().map(|size| ConstValue::Integer(size as i128))),
+
+        _ => Ok(None)
+    }
+}
+
+/// Whether a logical operator's result is decided by its left side alone, so the right side isn't evaluated.
+/// That's `false && _` and `true || _`.
+pub fn short_circuits(operator: &BinaryOperator, left: bool) -> bool {
+    matches!((operator, left), (BinaryOperator::And, false) | (BinaryOperator::Or, true))
+}
+
+fn evaluate_binary(operator: &BinaryOperator, left: ConstValue, right: ConstValue, float_division: FloatDivisionPolicy) -> ConstResult {
+    // Mixing an integer with a float makes a float
+    let (left, right) = match (left, right) {
+        (ConstValue::Integer(l), ConstValue::Number(r)) => (ConstValue::Number(l as f64), ConstValue::Number(r)),
+        (ConstValue::Number(l), ConstValue::Integer(r)) => (ConstValue::Number(l), ConstValue::Number(r as f64)),
+        values => values
+    };
+
+    if let (ConstValue::Integer(l), ConstValue::Integer(r)) = (&left, &right)
+        && let Some(value) = evaluate_integer_binary(operator, *l, *r)? {
+        return Ok(Some(value));
+    }
+
+    let value = match (operator, left, right) {
+        (BinaryOperator::Add, ConstValue::Number(l), ConstValue::Number(r)) => ConstValue::Number(l + r),
+        (BinaryOperator::Add, ConstValue::String(l), ConstValue::String(r)) => ConstValue::String(format!("{}{}", l, r)),
+        (BinaryOperator::Subtract, ConstValue::Number(l), ConstValue::Number(r)) => ConstValue::Number(l - r),
+        (BinaryOperator::Multiply, ConstValue::Number(l), ConstValue::Number(r)) => ConstValue::Number(l * r),
+        // Dividing by zero would produce infinity or NaN, which literals can't hold
+        (BinaryOperator::Divide | BinaryOperator::Modulus, ConstValue::Number(l), ConstValue::Number(r))
+            if r == 0.0 && float_division == FloatDivisionPolicy::Error => {
+            return Err(format!("Float division by zero: {} {} {}", l, operator, r));
+        },
+        (BinaryOperator::Divide, ConstValue::Number(l), ConstValue::Number(r)) => ConstValue::Number(l / r),
+        (BinaryOperator::Modulus, ConstValue::Number(l), ConstValue::Number(r)) => ConstValue::Number(l % r),
+
+        (BinaryOperator::Equal, l, r) => ConstValue::Boolean(l == r),
+        (BinaryOperator::NotEqual, l, r) => ConstValue::Boolean(l != r),
+        (BinaryOperator::LessThan, ConstValue::Number(l), ConstValue::Number(r)) => ConstValue::Boolean(l < r),
+        (BinaryOperator::LessThanOrEqual, ConstValue::Number(l), ConstValue::Number(r)) => ConstValue::Boolean(l <= r),
+        (BinaryOperator::GreaterThan, ConstValue::Number(l), ConstValue::Number(r)) => ConstValue::Boolean(l > r),
+        (BinaryOperator::GreaterThanOrEqual, ConstValue::Number(l), ConstValue::Number(r)) => ConstValue::Boolean(l >= r),
+
+        (BinaryOperator::And, ConstValue::Boolean(l), ConstValue::Boolean(r)) => ConstValue::Boolean(l && r),
+        (BinaryOperator::Or, ConstValue::Boolean(l), ConstValue::Boolean(r)) => ConstValue::Boolean(l || r),
+
+        (_, l, r) => return Err(format!("Unsupported binary operation: {:?} {} {:?}", l, operator, r))
+    };
+
+    if let ConstValue::Number(n) = value && !n.is_finite() {
+        return match float_division {
+            FloatDivisionPolicy::Error => Err(format!("Constant expression overflowed: {}", n)),
+            FloatDivisionPolicy::Ieee => Ok(None)
+        };
+    }
+    Ok(Some(value))
+}
+
+/// Evaluates arithmetic, bitwise operations, and comparisons between integers, or returns `None` for other operators.
+fn evaluate_integer_binary(operator: &BinaryOperator, left: i128, right: i128) -> Result<Option<ConstValue>, String> {
+    let result = match operator {
+        BinaryOperator::Add => left.checked_add(right),
+        BinaryOperator::Subtract => left.checked_sub(right),
+        BinaryOperator::Multiply => left.checked_mul(right),
+        BinaryOperator::Divide | BinaryOperator::Modulus if right == 0 => {
+            return Err(format!("Integer division by zero: {} {} {}", left, operator, right));
+        },
+        // Integer division truncates toward zero, like at runtime
+        BinaryOperator::Divide => left.checked_div(right),
+        BinaryOperator::Modulus => left.checked_rem(right),
+        BinaryOperator::BitwiseAnd => Some(left & right),
+        BinaryOperator::BitwiseOr => Some(left | right),
+        BinaryOperator::BitwiseXor => Some(left ^ right),
+        BinaryOperator::ShiftLeft => u32::try_from(right).ok().and_then(|right| left.checked_shl(right)),
+        BinaryOperator::ShiftRight => u32::try_from(right).ok().and_then(|right| left.checked_shr(right)),
+
+        BinaryOperator::LessThan => return Ok(Some(ConstValue::Boolean(left < right))),
+        BinaryOperator::LessThanOrEqual => return Ok(Some(ConstValue::Boolean(left <= right))),
+        BinaryOperator::GreaterThan => return Ok(Some(ConstValue::Boolean(left > right))),
+        BinaryOperator::GreaterThanOrEqual => return Ok(Some(ConstValue::Boolean(left >= right))),
+        _ => return Ok(None)
+    };
+    match result {
+        Some(value) => Ok(Some(ConstValue::Integer(value))),
+        None => Err(format!("Constant expression overflowed: {} {} {}", left, operator, right))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser::Parser, tokenizer::Tokenizer};
+
+    macro_rules! parse {
+        ($input:expr, $parse_fn:ident) => {
+            {
+                let mut tokenizer = Tokenizer::new($input.to_string());
+                let tokens = tokenizer.tokenize().unwrap();
+                let mut parser = Parser::new(tokens);
+                let expression = parser.$parse_fn().unwrap();
+                expression
+            }
+        };
+    }
+
+    #[test]
+    fn test_const_eval() {
+        assert_eq!(const_eval(&parse!("1.0 / 4.0 + 1", parse_expression)), Ok(Some(ConstValue::Number(1.25))));
+        assert_eq!(const_eval(&parse!("!(2 < 3)", parse_expression)), Ok(Some(ConstValue::Boolean(false))));
+        assert_eq!(const_eval(&parse!("x + 1", parse_expression)), Ok(None));
+    }
+
+    #[test]
+    fn test_sizeof() {
+        assert_eq!(const_eval(&parse!("sizeof(i64)", parse_expression)), Ok(Some(ConstValue::Integer(8))));
+        assert_eq!(const_eval(&parse!("sizeof(i32) * 2", parse_expression)), Ok(Some(ConstValue::Integer(8))));
+        assert_eq!(const_eval(&parse!("sizeof([i32])", parse_expression)), Ok(None));
+    }
+
+    #[test]
+    fn test_integer_arithmetic() {
+        // Too big to be exact as an f64
+        assert_eq!(
+            const_eval(&parse!("18446744073709551614 + 1", parse_expression)),
+            Ok(Some(ConstValue::Integer(18446744073709551615)))
+        );
+        assert_eq!(const_eval(&parse!("6 / 3", parse_expression)), Ok(Some(ConstValue::Integer(2))));
+        assert_eq!(const_eval(&parse!("7 / 2", parse_expression)), Ok(Some(ConstValue::Integer(3))));
+        assert_eq!(const_eval(&parse!("-7 / 2", parse_expression)), Ok(Some(ConstValue::Integer(-3))));
+        assert_eq!(const_eval(&parse!("1 == 1.0", parse_expression)), Ok(Some(ConstValue::Boolean(true))));
+        assert_eq!(const_eval(&parse!("1 / 0", parse_expression)), Err("Integer division by zero: 1 / 0".to_string()));
+    }
+
+    #[test]
+    fn test_bitwise_operators() {
+        assert_eq!(const_eval(&parse!("6 & 3 | 8 ^ 1", parse_expression)), Ok(Some(ConstValue::Integer(11))));
+        assert_eq!(const_eval(&parse!("1 << 4 >> 1", parse_expression)), Ok(Some(ConstValue::Integer(8))));
+        assert_eq!(const_eval(&parse!("1 << 128", parse_expression)), Err("Constant expression overflowed: 1 << 128".to_string()));
+        assert!(const_eval(&parse!("1.5 & 1", parse_expression)).is_err());
+    }
+
+    #[test]
+    fn test_short_circuit() {
+        // The right sides would divide by zero if they were evaluated
+        assert_eq!(const_eval(&parse!("false && (1 / 0 == 0)", parse_expression)), Ok(Some(ConstValue::Boolean(false))));
+        assert_eq!(const_eval(&parse!("true || (1 / 0 == 0)", parse_expression)), Ok(Some(ConstValue::Boolean(true))));
+        assert_eq!(const_eval(&parse!("false || x", parse_expression)), Ok(None));
+        assert_eq!(const_eval(&parse!("true && (1 / 0 == 0)", parse_expression)), Err("Integer division by zero: 1 / 0".to_string()));
+    }
+
+    #[test]
+    fn test_division_by_zero() {
+        assert_eq!(const_eval(&parse!("1.0 / 0.0", parse_expression)), Err("Float division by zero: 1 / 0".to_string()));
+        assert_eq!(const_eval(&parse!("1.0 % 0.0", parse_expression)), Err("Float division by zero: 1 % 0".to_string()));
+
+        // With IEEE division, the result is left for the interpreter since it can't be a literal
+        let ieee = |input: &str| const_eval_with(&parse!(input, parse_expression), FloatDivisionPolicy::Ieee, &TypeChecker::new());
+        assert_eq!(ieee("5.0 / 0.0"), Ok(None));
+        assert_eq!(ieee("5.0 / 2.0"), Ok(Some(ConstValue::Number(2.5))));
+        // Integers never follow IEEE 754
+        assert!(ieee("5 / 0").is_err());
+    }
+
+    #[test]
+    fn test_no_infinite_results() {
+        // `inf` isn't a literal; it's just a variable name
+        let expression = parse!("inf", parse_expression);
+        assert!(matches!(expression, Expression::Variable { .. }));
+        assert_eq!(const_eval(&expression), Ok(None));
+
+        let overflow = Expression::BinaryOperation {
+            left: Box::new(Expression::number(f64::MAX).into()),
+            operator: BinaryOperator::Multiply,
+            right: Box::new(Expression::number(2.0).into())
+        };
+        assert!(const_eval(&overflow).is_err());
+    }
+}
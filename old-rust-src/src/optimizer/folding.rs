@@ -0,0 +1,410 @@
+use std::collections::HashMap;
+
+use crate::{interpreter::FloatDivisionPolicy, parser::ast::{Declaration, Expression, LoopType, Pattern, Program, Statement, StringPart, StructElement, UnaryOperator, VariableMutability}, typechecker::TypeChecker};
+
+use super::const_eval::{const_eval_with, short_circuits, ConstValue};
+
+/// Replaces constant expressions with their values, including uses of immutable (`const`) variables with constant initializers.
+/// Mutable (`let`) variables are never propagated, since their value can change.
+pub struct ConstantFolder {
+    /// The variables in scope, innermost scope last.
+    /// Variables without a known constant value are still tracked so they shadow outer constants with the same name.
+    scopes: Vec<HashMap<String, Option<ConstValue>>>,
+    /// Should match the interpreter's policy, so folding doesn't reject programs that would run
+    float_division: FloatDivisionPolicy,
+    /// Knows the program's top-level structs, so `sizeof` can fold for them too.
+    types: TypeChecker
+}
+
+impl ConstantFolder {
+    pub fn new() -> Self {
+        ConstantFolder { scopes: Vec::new(), float_division: FloatDivisionPolicy::default(), types: TypeChecker::new() }
+    }
+
+    pub fn with_float_division(mut self, policy: FloatDivisionPolicy) -> Self {
+        self.float_division = policy;
+        self
+    }
+
+    fn begin_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    fn end_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    fn declare(&mut self, name: String, value: Option<ConstValue>) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name, value);
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Option<&ConstValue> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name)).and_then(|value| value.as_ref())
+    }
+
+    /// Stops propagating a variable once it's assigned to.
+    fn invalidate(&mut self, name: &str) {
+        if let Some(value) = self.scopes.iter_mut().rev().find_map(|scope| scope.get_mut(name)) {
+            *value = None;
+        }
+    }
+
+    pub fn fold_program(&mut self, program: &mut Program) -> Result<(), String> {
+        self.types.collect_program(program);
+        self.begin_scope();
+        for declaration in &mut program.declarations {
+            self.fold_declaration(declaration)?;
+        }
+        self.end_scope();
+        Ok(())
+    }
+
+    fn fold_declaration(&mut self, declaration: &mut Declaration) -> Result<(), String> {
+        match declaration {
+            Declaration::Function { params, body, .. } => {
+                for default in params.iter_mut().filter_map(|param| param.default.as_mut()) {
+                    self.fold_expression(default)?;
+                }
+                self.begin_scope();
+                for param in params.iter() {
+                    self.declare(param.name.clone(), None);
+                }
+                self.fold_expression(body)?;
+                self.end_scope();
+            },
+            Declaration::Struct { elements, .. } => {
+                for element in elements {
+                    if let StructElement::Declaration { declaration, .. } = element {
+                        self.fold_declaration(declaration)?;
+                    }
+                }
+            },
+            // Top-level constants can be used before they're declared, so only their own value is folded for now
+            Declaration::Const { value, .. } => self.fold_expression(value)?,
+            Declaration::TypeDeclaration { .. } | Declaration::Enum { .. } | Declaration::Import { .. } => {
+                // Nothing to fold
+            }
+        }
+        Ok(())
+    }
+
+    fn fold_statement(&mut self, statement: &mut Statement) -> Result<(), String> {
+        match statement {
+            Statement::Declaration(declaration) => {
+                if let Declaration::Function { name, .. } = declaration {
+                    self.declare(name.clone(), None);
+                }
+                self.fold_declaration(declaration)?;
+            },
+            Statement::Expression { expression, .. } => {
+                self.fold_expression(expression)?;
+            },
+            Statement::VariableDeclaration { mutability, name, value, .. } => {
+                let constant = match value {
+                    Some(value) => {
+                        self.fold_expression(value)?;
+                        match mutability {
+                            VariableMutability::Immutable => const_eval_with(value, self.float_division, &self.types)?,
+                            VariableMutability::Mutable => None
+                        }
+                    },
+                    None => None
+                };
+                self.declare(name.clone(), constant);
+            },
+            Statement::Return(Some(value)) | Statement::Defer(value) => {
+                self.fold_expression(value)?;
+            },
+            Statement::Return(None) | Statement::Break(_) | Statement::Continue(_) => {}
+        }
+        Ok(())
+    }
+
+    fn fold_expression(&mut self, expression: &mut Expression) -> Result<(), String> {
+        match expression {
+            Expression::Variable { name, .. } => {
+                if let Some(value) = self.lookup(name).cloned() {
+                    *expression = literal(value);
+                }
+                return Ok(());
+            },
+            Expression::IntegerLiteral { .. } | Expression::NumberLiteral { .. } | Expression::StringLiteral { .. } |
+            Expression::CharLiteral(_) | Expression::BooleanLiteral(_) | Expression::NilLiteral => {
+                return Ok(());
+            },
+            Expression::TypeOf(_) => {
+                // Folding the operand could change its type
+                return Ok(());
+            },
+
+            Expression::Block { statements, tail } => {
+                self.begin_scope();
+                for statement in statements {
+                    self.fold_statement(statement)?;
+                }
+                if let Some(tail) = tail {
+                    self.fold_expression(tail)?;
+                }
+                self.end_scope();
+            },
+            Expression::FunctionCall { callee, args } | Expression::MethodCall { receiver: callee, args, .. } => {
+                self.fold_expression(callee)?;
+                for arg in args {
+                    self.fold_expression(arg)?;
+                }
+            },
+            Expression::BinaryOperation { left, operator, right } => {
+                self.fold_expression(left)?;
+                // The right side of a short-circuiting `&&` or `||` never runs, so it shouldn't be folded (or fail to fold)
+                if let Expression::BooleanLiteral(value) = left.node && short_circuits(operator, value) {
+                    *expression = Expression::BooleanLiteral(value);
+                    return Ok(());
+                }
+                self.fold_expression(right)?;
+            },
+            // A reference is to a variable, not its value, so the variable isn't replaced by its value
+            Expression::UnaryOperation { operator: UnaryOperator::Reference { .. }, operand } if matches!(operand.node, Expression::Variable { .. }) => {},
+            Expression::UnaryOperation { operand, .. } => {
+                self.fold_expression(operand)?;
+            },
+            Expression::Cast { value, .. } | Expression::Await(value) | Expression::Try { operand: value } |
+            Expression::Grouping(value) => {
+                self.fold_expression(value)?;
+            },
+            Expression::Range { start, end, .. } => {
+                self.fold_expression(start)?;
+                self.fold_expression(end)?;
+            },
+            Expression::Match { scrutinee, arms } => {
+                self.fold_expression(scrutinee)?;
+                for arm in arms {
+                    self.begin_scope();
+                    if let Pattern::Binding(name) = &arm.pattern {
+                        self.declare(name.clone(), None);
+                    }
+                    self.fold_expression(&mut arm.body)?;
+                    self.end_scope();
+                }
+            },
+            Expression::Assignment { name, value, .. } => {
+                self.fold_expression(value)?;
+                self.invalidate(name);
+            },
+            Expression::MemberAccess { object, .. } => {
+                self.fold_expression(object)?;
+            },
+            // Like a reference, the object being changed stays a variable
+            Expression::MemberAssignment { value, .. } => {
+                self.fold_expression(value)?;
+            },
+            Expression::IndexAssignment { index, value, .. } => {
+                self.fold_expression(index)?;
+                self.fold_expression(value)?;
+            },
+            Expression::Index { object, index } => {
+                self.fold_expression(object)?;
+                self.fold_expression(index)?;
+            },
+            Expression::Array { size, initial_value, .. } => {
+                self.fold_expression(size)?;
+                self.fold_expression(initial_value)?;
+            },
+            Expression::StructCreation { fields, .. } => {
+                for (_, value) in fields {
+                    self.fold_expression(value)?;
+                }
+            },
+            Expression::Tuple(elements) | Expression::ArrayLiteral(elements) => {
+                for element in elements {
+                    self.fold_expression(element)?;
+                }
+            },
+            Expression::InterpolatedString { parts } => {
+                for part in parts {
+                    if let StringPart::Expr(expression) = part {
+                        self.fold_expression(expression)?;
+                    }
+                }
+            },
+            Expression::If { condition, then_branch, else_branch } => {
+                self.fold_expression(condition)?;
+                self.fold_expression(then_branch)?;
+                if let Some(else_branch) = else_branch {
+                    self.fold_expression(else_branch)?;
+                }
+            },
+            Expression::IfLet { pattern, value, then_branch, else_branch } => {
+                self.fold_expression(value)?;
+                self.begin_scope();
+                if let Pattern::Binding(name) = pattern {
+                    self.declare(name.clone(), None);
+                }
+                self.fold_expression(then_branch)?;
+                self.end_scope();
+                if let Some(else_branch) = else_branch {
+                    self.fold_expression(else_branch)?;
+                }
+            },
+            Expression::Loop(LoopType::Infinite { body, .. }) => {
+                self.fold_expression(body)?;
+            },
+            Expression::Loop(LoopType::While { condition, body, .. }) => {
+                self.fold_expression(condition)?;
+                self.fold_expression(body)?;
+            },
+            Expression::Loop(LoopType::Iterator { iterator, iterable, body, .. }) => {
+                self.fold_expression(iterable)?;
+                self.begin_scope();
+                self.declare(iterator.clone(), None);
+                self.fold_expression(body)?;
+                self.end_scope();
+            },
+            Expression::SizeOf(_) => {}
+        }
+
+        if let Some(value) = const_eval_with(expression, self.float_division, &self.types)? {
+            *expression = literal(value);
+        }
+        Ok(())
+    }
+}
+
+/// The literal expression for a constant value.
+fn literal(value: ConstValue) -> Expression {
+    match value {
+        ConstValue::Integer(n) => Expression::integer(n),
+        ConstValue::Number(n) => Expression::number(n),
+        ConstValue::String(s) => Expression::string(s),
+        ConstValue::Boolean(b) => Expression::BooleanLiteral(b),
+        ConstValue::Char(c) => Expression::CharLiteral(c)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser::Parser, tokenizer::Tokenizer};
+
+    /// Folds a program and returns the initializers of the variables declared in its `main` function, including in nested blocks.
+    fn fold(input: &str) -> HashMap<String, Expression> {
+        let mut tokenizer = Tokenizer::new(input.to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut program = Parser::new(tokens).parse_program().unwrap();
+        ConstantFolder::new().fold_program(&mut program).unwrap();
+
+        let Some(Declaration::Function { body, .. }) = program.declarations.into_iter().next().map(|declaration| declaration.node) else {
+            panic!("Expected a main function");
+        };
+        let mut values = HashMap::new();
+        collect_declarations(body.node, &mut values);
+        values
+    }
+
+    fn collect_declarations(block: Expression, values: &mut HashMap<String, Expression>) {
+        let Expression::Block { statements, tail } = block else {
+            return;
+        };
+        for statement in statements {
+            match statement.node {
+                Statement::VariableDeclaration { name, value: Some(value), .. } => {
+                    values.insert(name, value.node);
+                },
+                Statement::Expression { expression } => collect_declarations(expression.node, values),
+                _ => {}
+            }
+        }
+        if let Some(tail) = tail {
+            collect_declarations(tail.node, values);
+        }
+    }
+
+    #[test]
+    fn test_propagate_immutable_chain() {
+        let values = fold(r#"
+            func main() -> nil {
+                const x: i32 = 5;
+                const y: i32 = x * 2;
+                const z: i32 = y + x;
+                const w: i32 = (x + 1) * (y - z);
+            }
+        "#);
+        assert_eq!(values["y"], Expression::integer(10));
+        assert_eq!(values["z"], Expression::integer(15));
+        // Parentheses don't stop folding
+        assert_eq!(values["w"], Expression::integer(-30));
+    }
+
+    #[test]
+    fn test_no_propagation_through_mutable() {
+        let values = fold(r#"
+            func main() -> nil {
+                let x: i32 = 5;
+                x = 6;
+                const y: i32 = x * 2;
+                const z: i32 = 1 + 2;
+            }
+        "#);
+        assert!(matches!(values["y"], Expression::BinaryOperation { .. }));
+        // Constant subexpressions still fold
+        assert_eq!(values["z"], Expression::integer(3));
+    }
+
+    #[test]
+    fn test_propagation_respects_scopes() {
+        let values = fold(r#"
+            func main() -> nil {
+                const x: i32 = 5;
+                {
+                    let x: i32 = 1;
+                    const inner: i32 = x;
+                };
+                const y: i32 = x;
+            }
+        "#);
+        assert!(matches!(values["inner"], Expression::Variable { .. }));
+        assert_eq!(values["y"], Expression::integer(5));
+    }
+
+    #[test]
+    fn test_short_circuit() {
+        let values = fold(r#"
+            func main(x: bool) -> nil {
+                const a: bool = false && (1 / 0 == 0);
+                const b: bool = true || x;
+                const c: bool = x && true;
+            }
+        "#);
+        assert_eq!(values["a"], Expression::BooleanLiteral(false));
+        assert_eq!(values["b"], Expression::BooleanLiteral(true));
+        assert!(matches!(values["c"], Expression::BinaryOperation { .. }));
+    }
+
+    #[test]
+    fn test_struct_sizes() {
+        let values = fold(r#"
+            func main() -> nil {
+                const point: u64 = sizeof(Point);
+                const line: u64 = sizeof(Line) * 2;
+                const list: u64 = sizeof(List);
+            }
+
+            struct Point {
+                pub x: i32;
+                pub y: i64;
+            }
+
+            type Line = (Point, Point);
+
+            struct List {
+                pub values: [i32];
+            }
+        "#);
+        assert_eq!(values["point"], Expression::integer(12));
+        // Aliases and tuples of structs fold too
+        assert_eq!(values["line"], Expression::integer(48));
+        // A struct with an unsized field doesn't have a size, so it's left for the type checker to report
+        assert!(matches!(values["list"], Expression::SizeOf(_)));
+    }
+}
@@ -0,0 +1,2 @@
+pub mod const_eval;
+pub mod folding;
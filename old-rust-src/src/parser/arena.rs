@@ -0,0 +1,744 @@
+use super::ast::{BinaryOperator, Declaration, EnumVariant, Expression, ExpressionId, FunctionParameter, GenericParam, LiteralFloat, LoopType, MatchArm, Pattern, Program, Span, Spanned, Statement, StringPart, StructElement, Type, UnaryOperator, VariableMutability, Visibility, WhereClause};
+
+// An alternative form of the AST where expressions live in one `Vec` and refer to each other by index.
+// This avoids a separate allocation for every boxed child, which adds up for large files.
+// Everything except expressions mirrors the boxed AST in `ast.rs`.
+// Expression spans are kept alongside the expressions, and statements and declarations keep theirs in a `Spanned`.
+
+/// A reference to an expression in an `ExpressionArena`.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct ExprRef(pub u32);
+
+#[derive(Debug, PartialEq, Default)]
+pub struct ExpressionArena {
+    expressions: Vec<ArenaExpression>,
+    /// The span of each expression, at the same index
+    spans: Vec<Span>
+}
+
+impl ExpressionArena {
+    pub fn new() -> Self {
+        ExpressionArena { expressions: Vec::new(), spans: Vec::new() }
+    }
+
+    pub fn alloc(&mut self, expression: ArenaExpression, span: Span) -> ExprRef {
+        self.expressions.push(expression);
+        self.spans.push(span);
+        ExprRef((self.expressions.len() - 1) as u32)
+    }
+
+    pub fn get(&self, expression: ExprRef) -> &ArenaExpression {
+        &self.expressions[expression.0 as usize]
+    }
+
+    pub fn span(&self, expression: ExprRef) -> Span {
+        self.spans[expression.0 as usize]
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.expressions.is_empty()
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ArenaExpression {
+    Block {
+        statements: Vec<Spanned<ArenaStatement>>,
+        tail: Option<ExprRef>
+    },
+
+    IntegerLiteral {
+        value: i128,
+        lexeme: Option<String>
+    },
+    NumberLiteral {
+        value: LiteralFloat,
+        lexeme: Option<String>
+    },
+    StringLiteral {
+        value: String,
+        raw: bool
+    },
+    CharLiteral(char),
+    InterpolatedString {
+        parts: Vec<ArenaStringPart>
+    },
+    Variable {
+        name: String,
+        expression_id: ExpressionId
+    },
+    BooleanLiteral(bool),
+    NilLiteral,
+
+    FunctionCall {
+        callee: ExprRef,
+        args: Vec<ExprRef>
+    },
+    MethodCall {
+        receiver: ExprRef,
+        method: String,
+        args: Vec<ExprRef>
+    },
+
+    BinaryOperation {
+        left: ExprRef,
+        operator: BinaryOperator,
+        right: ExprRef
+    },
+    UnaryOperation {
+        operator: UnaryOperator,
+        operand: ExprRef
+    },
+
+    Assignment {
+        name: String,
+        operator: Option<BinaryOperator>,
+        value: ExprRef,
+        expression_id: ExpressionId
+    },
+    MemberAssignment {
+        object: ExprRef,
+        member: String,
+        operator: Option<BinaryOperator>,
+        value: ExprRef
+    },
+    IndexAssignment {
+        object: ExprRef,
+        index: ExprRef,
+        operator: Option<BinaryOperator>,
+        value: ExprRef
+    },
+    MemberAccess {
+        object: ExprRef,
+        member: String
+    },
+    Index {
+        object: ExprRef,
+        index: ExprRef
+    },
+
+    Array {
+        array_type: Type,
+        size: ExprRef,
+        initial_value: ExprRef
+    },
+    ArrayLiteral(Vec<ExprRef>),
+    StructCreation {
+        struct_type: Type,
+        fields: Vec<(String, ExprRef)>
+    },
+    Tuple(Vec<ExprRef>),
+
+    If {
+        condition: ExprRef,
+        then_branch: ExprRef,
+        else_branch: Option<ExprRef>
+    },
+    IfLet {
+        pattern: Pattern,
+        value: ExprRef,
+        then_branch: ExprRef,
+        else_branch: Option<ExprRef>
+    },
+    Loop(ArenaLoopType),
+
+    SizeOf(Type),
+    TypeOf(ExprRef),
+    Cast {
+        value: ExprRef,
+        target_type: Type
+    },
+    Await(ExprRef),
+    Try {
+        operand: ExprRef
+    },
+    Grouping(ExprRef),
+    Match {
+        scrutinee: ExprRef,
+        arms: Vec<ArenaMatchArm>
+    },
+    Range {
+        start: ExprRef,
+        end: ExprRef,
+        inclusive: bool
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ArenaStringPart {
+    Literal(String),
+    Expr(ExprRef)
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ArenaMatchArm {
+    pub pattern: Pattern,
+    pub body: ExprRef
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ArenaLoopType {
+    While {
+        condition: ExprRef,
+        body: ExprRef,
+        label: Option<String>
+    },
+    Infinite {
+        body: ExprRef,
+        label: Option<String>
+    },
+    Iterator {
+        mutability: VariableMutability,
+        iterator: String,
+        iterable: ExprRef,
+        body: ExprRef,
+        label: Option<String>
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ArenaDeclaration {
+    Function {
+        name: String,
+        params: Vec<ArenaFunctionParameter>,
+        generic_args: Vec<GenericParam>,
+        where_clauses: Vec<WhereClause>,
+        return_type: Type,
+        body: ExprRef,
+        is_async: bool,
+        is_variadic: bool,
+        doc: Option<String>
+    },
+    Struct {
+        name: String,
+        elements: Vec<ArenaStructElement>,
+        generic_args: Vec<GenericParam>,
+        where_clauses: Vec<WhereClause>,
+        doc: Option<String>
+    },
+    TypeDeclaration {
+        name: String,
+        generic_args: Vec<GenericParam>,
+        where_clauses: Vec<WhereClause>,
+        alias: Type,
+        doc: Option<String>
+    },
+    Enum {
+        name: String,
+        variants: Vec<EnumVariant>,
+        generic_args: Vec<GenericParam>,
+        where_clauses: Vec<WhereClause>,
+        doc: Option<String>
+    },
+    Const {
+        name: String,
+        const_type: Type,
+        value: ExprRef
+    },
+    Import {
+        path: Vec<String>,
+        alias: Option<String>,
+        items: Option<Vec<String>>
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ArenaFunctionParameter {
+    pub name: String,
+    pub param_type: Type,
+    pub default: Option<ExprRef>
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ArenaStructElement {
+    Declaration {
+        declaration: ArenaDeclaration,
+        visibility: Visibility
+    },
+    Field {
+        name: String,
+        field_type: Type,
+        visibility: Visibility
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ArenaStatement {
+    Declaration(ArenaDeclaration),
+    Expression {
+        expression: ExprRef
+    },
+    VariableDeclaration {
+        mutability: VariableMutability,
+        name: String,
+        variable_type: Type,
+        value: Option<ExprRef>
+    },
+    Break(Option<String>),
+    Continue(Option<String>),
+    Return(Option<ExprRef>),
+    Defer(ExprRef)
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ArenaProgram {
+    pub arena: ExpressionArena,
+    pub declarations: Vec<Spanned<ArenaDeclaration>>
+}
+
+impl ArenaProgram {
+    /// Moves a boxed program into an arena.
+    pub fn from_program(program: Program) -> Self {
+        let mut arena = ExpressionArena::new();
+        let declarations = program.declarations.into_iter()
+            .map(|declaration| Spanned::new(arena.lower_declaration(declaration.node), declaration.span))
+            .collect();
+        ArenaProgram { arena, declarations }
+    }
+
+    /// Copies the program out of the arena into the boxed form.
+    pub fn to_program(&self) -> Program {
+        let declarations = self.declarations.iter()
+            .map(|declaration| Spanned::new(self.arena.boxed_declaration(&declaration.node), declaration.span))
+            .collect();
+        Program { declarations }
+    }
+}
+
+impl ExpressionArena {
+    /// Moves a boxed declaration (and every expression in it) into the arena.
+    pub(crate) fn lower_declaration(&mut self, declaration: Declaration) -> ArenaDeclaration {
+        match declaration {
+            Declaration::Function { name, params, generic_args, where_clauses, return_type, body, is_async, is_variadic, doc } => {
+                let params = params.into_iter().map(|FunctionParameter { name, param_type, default }| ArenaFunctionParameter {
+                    name,
+                    param_type,
+                    default: default.map(|default| self.lower_expression(*default))
+                }).collect();
+                let body = self.lower_expression(*body);
+                ArenaDeclaration::Function { name, params, generic_args, where_clauses, return_type, body, is_async, is_variadic, doc }
+            },
+            Declaration::Struct { name, elements, generic_args, where_clauses, doc } => {
+                let elements = elements.into_iter().map(|element| match element {
+                    StructElement::Declaration { declaration, visibility } => {
+                        ArenaStructElement::Declaration { declaration: self.lower_declaration(declaration), visibility }
+                    },
+                    StructElement::Field { name, field_type, visibility } => ArenaStructElement::Field { name, field_type, visibility }
+                }).collect();
+                ArenaDeclaration::Struct { name, elements, generic_args, where_clauses, doc }
+            },
+            Declaration::TypeDeclaration { name, generic_args, where_clauses, alias, doc } => {
+                ArenaDeclaration::TypeDeclaration { name, generic_args, where_clauses, alias, doc }
+            },
+            Declaration::Enum { name, variants, generic_args, where_clauses, doc } => {
+                ArenaDeclaration::Enum { name, variants, generic_args, where_clauses, doc }
+            },
+            Declaration::Const { name, const_type, value } => {
+                let value = self.lower_expression(*value);
+                ArenaDeclaration::Const { name, const_type, value }
+            },
+            Declaration::Import { path, alias, items } => ArenaDeclaration::Import { path, alias, items }
+        }
+    }
+
+    fn lower_statement(&mut self, statement: Spanned<Statement>) -> Spanned<ArenaStatement> {
+        let lowered = match statement.node {
+            Statement::Declaration(declaration) => ArenaStatement::Declaration(self.lower_declaration(declaration)),
+            Statement::Expression { expression } => ArenaStatement::Expression { expression: self.lower_expression(*expression) },
+            Statement::VariableDeclaration { mutability, name, variable_type, value } => {
+                let value = value.map(|value| self.lower_expression(*value));
+                ArenaStatement::VariableDeclaration { mutability, name, variable_type, value }
+            },
+            Statement::Break(label) => ArenaStatement::Break(label),
+            Statement::Continue(label) => ArenaStatement::Continue(label),
+            Statement::Return(value) => ArenaStatement::Return(value.map(|value| self.lower_expression(*value))),
+            Statement::Defer(value) => ArenaStatement::Defer(self.lower_expression(*value))
+        };
+        Spanned::new(lowered, statement.span)
+    }
+
+    /// Moves a boxed expression (and all of its children) into the arena.
+    pub fn lower_expression(&mut self, expression: Spanned<Expression>) -> ExprRef {
+        let span = expression.span;
+        let expression = match expression.node {
+            Expression::Block { statements, tail } => {
+                let statements = statements.into_iter().map(|statement| self.lower_statement(statement)).collect();
+                let tail = tail.map(|tail| self.lower_expression(*tail));
+                ArenaExpression::Block { statements, tail }
+            },
+            Expression::IntegerLiteral { value, lexeme } => ArenaExpression::IntegerLiteral { value, lexeme },
+            Expression::NumberLiteral { value, lexeme } => ArenaExpression::NumberLiteral { value, lexeme },
+            Expression::StringLiteral { value, raw } => ArenaExpression::StringLiteral { value, raw },
+            Expression::InterpolatedString { parts } => ArenaExpression::InterpolatedString {
+                parts: parts.into_iter().map(|part| match part {
+                    StringPart::Literal(text) => ArenaStringPart::Literal(text),
+                    StringPart::Expr(expression) => ArenaStringPart::Expr(self.lower_expression(*expression))
+                }).collect()
+            },
+            Expression::CharLiteral(value) => ArenaExpression::CharLiteral(value),
+            Expression::BooleanLiteral(value) => ArenaExpression::BooleanLiteral(value),
+            Expression::NilLiteral => ArenaExpression::NilLiteral,
+            Expression::Variable { name, expression_id } => ArenaExpression::Variable { name, expression_id },
+            Expression::FunctionCall { callee, args } => {
+                let callee = self.lower_expression(*callee);
+                let args = args.into_iter().map(|arg| self.lower_expression(arg)).collect();
+                ArenaExpression::FunctionCall { callee, args }
+            },
+            Expression::MethodCall { receiver, method, args } => {
+                let receiver = self.lower_expression(*receiver);
+                let args = args.into_iter().map(|arg| self.lower_expression(arg)).collect();
+                ArenaExpression::MethodCall { receiver, method, args }
+            },
+            Expression::BinaryOperation { left, operator, right } => {
+                let left = self.lower_expression(*left);
+                let right = self.lower_expression(*right);
+                ArenaExpression::BinaryOperation { left, operator, right }
+            },
+            Expression::UnaryOperation { operator, operand } => {
+                ArenaExpression::UnaryOperation { operator, operand: self.lower_expression(*operand) }
+            },
+            Expression::Assignment { name, operator, value, expression_id } => {
+                ArenaExpression::Assignment { name, operator, value: self.lower_expression(*value), expression_id }
+            },
+            Expression::MemberAssignment { object, member, operator, value } => {
+                ArenaExpression::MemberAssignment { object: self.lower_expression(*object), member, operator, value: self.lower_expression(*value) }
+            },
+            Expression::IndexAssignment { object, index, operator, value } => {
+                ArenaExpression::IndexAssignment {
+                    object: self.lower_expression(*object),
+                    index: self.lower_expression(*index),
+                    operator,
+                    value: self.lower_expression(*value)
+                }
+            },
+            Expression::MemberAccess { object, member } => {
+                ArenaExpression::MemberAccess { object: self.lower_expression(*object), member }
+            },
+            Expression::Index { object, index } => {
+                let object = self.lower_expression(*object);
+                let index = self.lower_expression(*index);
+                ArenaExpression::Index { object, index }
+            },
+            Expression::Array { array_type, size, initial_value } => {
+                let size = self.lower_expression(*size);
+                let initial_value = self.lower_expression(*initial_value);
+                ArenaExpression::Array { array_type, size, initial_value }
+            },
+            Expression::ArrayLiteral(elements) => {
+                ArenaExpression::ArrayLiteral(elements.into_iter().map(|element| self.lower_expression(element)).collect())
+            },
+            Expression::Tuple(elements) => {
+                ArenaExpression::Tuple(elements.into_iter().map(|element| self.lower_expression(element)).collect())
+            },
+            Expression::StructCreation { struct_type, fields } => {
+                let fields = fields.into_iter().map(|(name, value)| (name, self.lower_expression(*value))).collect();
+                ArenaExpression::StructCreation { struct_type, fields }
+            },
+            Expression::If { condition, then_branch, else_branch } => {
+                let condition = self.lower_expression(*condition);
+                let then_branch = self.lower_expression(*then_branch);
+                let else_branch = else_branch.map(|else_branch| self.lower_expression(*else_branch));
+                ArenaExpression::If { condition, then_branch, else_branch }
+            },
+            Expression::IfLet { pattern, value, then_branch, else_branch } => {
+                let value = self.lower_expression(*value);
+                let then_branch = self.lower_expression(*then_branch);
+                let else_branch = else_branch.map(|else_branch| self.lower_expression(*else_branch));
+                ArenaExpression::IfLet { pattern, value, then_branch, else_branch }
+            },
+            Expression::Loop(LoopType::While { condition, body, label }) => {
+                let condition = self.lower_expression(*condition);
+                let body = self.lower_expression(*body);
+                ArenaExpression::Loop(ArenaLoopType::While { condition, body, label })
+            },
+            Expression::Loop(LoopType::Infinite { body, label }) => {
+                ArenaExpression::Loop(ArenaLoopType::Infinite { body: self.lower_expression(*body), label })
+            },
+            Expression::Loop(LoopType::Iterator { mutability, iterator, iterable, body, label }) => {
+                let iterable = self.lower_expression(*iterable);
+                let body = self.lower_expression(*body);
+                ArenaExpression::Loop(ArenaLoopType::Iterator { mutability, iterator, iterable, body, label })
+            },
+            Expression::SizeOf(ty) => ArenaExpression::SizeOf(ty),
+            Expression::TypeOf(operand) => ArenaExpression::TypeOf(self.lower_expression(*operand)),
+            Expression::Cast { value, target_type } => {
+                ArenaExpression::Cast { value: self.lower_expression(*value), target_type }
+            },
+            Expression::Await(operand) => ArenaExpression::Await(self.lower_expression(*operand)),
+            Expression::Try { operand } => ArenaExpression::Try { operand: self.lower_expression(*operand) },
+            Expression::Grouping(inner) => ArenaExpression::Grouping(self.lower_expression(*inner)),
+            Expression::Match { scrutinee, arms } => {
+                let scrutinee = self.lower_expression(*scrutinee);
+                let arms = arms.into_iter()
+                    .map(|arm| ArenaMatchArm { pattern: arm.pattern, body: self.lower_expression(*arm.body) })
+                    .collect();
+                ArenaExpression::Match { scrutinee, arms }
+            },
+            Expression::Range { start, end, inclusive } => {
+                let start = self.lower_expression(*start);
+                let end = self.lower_expression(*end);
+                ArenaExpression::Range { start, end, inclusive }
+            }
+        };
+        self.alloc(expression, span)
+    }
+}
+
+impl ExpressionArena {
+    /// Copies a declaration (and every expression in it) out of the arena into the boxed form.
+    fn boxed_declaration(&self, declaration: &ArenaDeclaration) -> Declaration {
+        match declaration {
+            ArenaDeclaration::Function { name, params, generic_args, where_clauses, return_type, body, is_async, is_variadic, doc } => {
+                let params = params.iter().map(|param| FunctionParameter {
+                    name: param.name.clone(),
+                    param_type: param.param_type.clone(),
+                    default: param.default.map(|default| self.boxed(default))
+                }).collect();
+                Declaration::Function {
+                    name: name.clone(),
+                    params,
+                    generic_args: generic_args.clone(),
+                    where_clauses: where_clauses.clone(),
+                    return_type: return_type.clone(),
+                    body: self.boxed(*body),
+                    is_async: *is_async,
+                    is_variadic: *is_variadic,
+                    doc: doc.clone()
+                }
+            },
+            ArenaDeclaration::Struct { name, elements, generic_args, where_clauses, doc } => {
+                let elements = elements.iter().map(|element| match element {
+                    ArenaStructElement::Declaration { declaration, visibility } => {
+                        StructElement::Declaration { declaration: self.boxed_declaration(declaration), visibility: *visibility }
+                    },
+                    ArenaStructElement::Field { name, field_type, visibility } => {
+                        StructElement::Field { name: name.clone(), field_type: field_type.clone(), visibility: *visibility }
+                    }
+                }).collect();
+                Declaration::Struct { name: name.clone(), elements, generic_args: generic_args.clone(), where_clauses: where_clauses.clone(), doc: doc.clone() }
+            },
+            ArenaDeclaration::TypeDeclaration { name, generic_args, where_clauses, alias, doc } => Declaration::TypeDeclaration {
+                name: name.clone(),
+                generic_args: generic_args.clone(),
+                where_clauses: where_clauses.clone(),
+                alias: alias.clone(),
+                doc: doc.clone()
+            },
+            ArenaDeclaration::Enum { name, variants, generic_args, where_clauses, doc } => Declaration::Enum {
+                name: name.clone(),
+                variants: variants.clone(),
+                generic_args: generic_args.clone(),
+                where_clauses: where_clauses.clone(),
+                doc: doc.clone()
+            },
+            ArenaDeclaration::Const { name, const_type, value } => {
+                Declaration::Const { name: name.clone(), const_type: const_type.clone(), value: self.boxed(*value) }
+            },
+            ArenaDeclaration::Import { path, alias, items } => {
+                Declaration::Import { path: path.clone(), alias: alias.clone(), items: items.clone() }
+            }
+        }
+    }
+
+    fn boxed_statement(&self, statement: &Spanned<ArenaStatement>) -> Spanned<Statement> {
+        let boxed = match &statement.node {
+            ArenaStatement::Declaration(declaration) => Statement::Declaration(self.boxed_declaration(declaration)),
+            ArenaStatement::Expression { expression } => Statement::Expression { expression: self.boxed(*expression) },
+            ArenaStatement::VariableDeclaration { mutability, name, variable_type, value } => Statement::VariableDeclaration {
+                mutability: mutability.clone(),
+                name: name.clone(),
+                variable_type: variable_type.clone(),
+                value: value.map(|value| self.boxed(value))
+            },
+            ArenaStatement::Break(label) => Statement::Break(label.clone()),
+            ArenaStatement::Continue(label) => Statement::Continue(label.clone()),
+            ArenaStatement::Return(value) => Statement::Return(value.map(|value| self.boxed(value))),
+            ArenaStatement::Defer(value) => Statement::Defer(self.boxed(*value))
+        };
+        Spanned::new(boxed, statement.span)
+    }
+
+    fn boxed(&self, expression: ExprRef) -> Box<Spanned<Expression>> {
+        Box::new(self.boxed_expression(expression))
+    }
+
+    /// Copies an expression (and all of its children) out of the arena into the boxed form.
+    pub fn boxed_expression(&self, expression: ExprRef) -> Spanned<Expression> {
+        let boxed = match self.get(expression) {
+            ArenaExpression::Block { statements, tail } => Expression::Block {
+                statements: statements.iter().map(|statement| self.boxed_statement(statement)).collect(),
+                tail: tail.map(|tail| self.boxed(tail))
+            },
+            ArenaExpression::IntegerLiteral { value, lexeme } => Expression::IntegerLiteral { value: *value, lexeme: lexeme.clone() },
+            ArenaExpression::NumberLiteral { value, lexeme } => Expression::NumberLiteral { value: *value, lexeme: lexeme.clone() },
+            ArenaExpression::StringLiteral { value, raw } => Expression::StringLiteral { value: value.clone(), raw: *raw },
+            ArenaExpression::InterpolatedString { parts } => Expression::InterpolatedString {
+                parts: parts.iter().map(|part| match part {
+                    ArenaStringPart::Literal(text) => StringPart::Literal(text.clone()),
+                    ArenaStringPart::Expr(expression) => StringPart::Expr(self.boxed(*expression))
+                }).collect()
+            },
+            ArenaExpression::CharLiteral(value) => Expression::CharLiteral(*value),
+            ArenaExpression::BooleanLiteral(value) => Expression::BooleanLiteral(*value),
+            ArenaExpression::NilLiteral => Expression::NilLiteral,
+            ArenaExpression::Variable { name, expression_id } => Expression::Variable { name: name.clone(), expression_id: *expression_id },
+            ArenaExpression::FunctionCall { callee, args } => Expression::FunctionCall {
+                callee: self.boxed(*callee),
+                args: args.iter().map(|arg| self.boxed_expression(*arg)).collect()
+            },
+            ArenaExpression::MethodCall { receiver, method, args } => Expression::MethodCall {
+                receiver: self.boxed(*receiver),
+                method: method.clone(),
+                args: args.iter().map(|arg| self.boxed_expression(*arg)).collect()
+            },
+            ArenaExpression::BinaryOperation { left, operator, right } => Expression::BinaryOperation {
+                left: self.boxed(*left),
+                operator: operator.clone(),
+                right: self.boxed(*right)
+            },
+            ArenaExpression::UnaryOperation { operator, operand } => Expression::UnaryOperation {
+                operator: operator.clone(),
+                operand: self.boxed(*operand)
+            },
+            ArenaExpression::Assignment { name, operator, value, expression_id } => Expression::Assignment {
+                name: name.clone(),
+                operator: operator.clone(),
+                value: self.boxed(*value),
+                expression_id: *expression_id
+            },
+            ArenaExpression::MemberAssignment { object, member, operator, value } => Expression::MemberAssignment {
+                object: self.boxed(*object),
+                member: member.clone(),
+                operator: operator.clone(),
+                value: self.boxed(*value)
+            },
+            ArenaExpression::IndexAssignment { object, index, operator, value } => Expression::IndexAssignment {
+                object: self.boxed(*object),
+                index: self.boxed(*index),
+                operator: operator.clone(),
+                value: self.boxed(*value)
+            },
+            ArenaExpression::MemberAccess { object, member } => Expression::MemberAccess { object: self.boxed(*object), member: member.clone() },
+            ArenaExpression::Index { object, index } => Expression::Index { object: self.boxed(*object), index: self.boxed(*index) },
+            ArenaExpression::Array { array_type, size, initial_value } => Expression::Array {
+                array_type: array_type.clone(),
+                size: self.boxed(*size),
+                initial_value: self.boxed(*initial_value)
+            },
+            ArenaExpression::ArrayLiteral(elements) => {
+                Expression::ArrayLiteral(elements.iter().map(|element| self.boxed_expression(*element)).collect())
+            },
+            ArenaExpression::Tuple(elements) => {
+                Expression::Tuple(elements.iter().map(|element| self.boxed_expression(*element)).collect())
+            },
+            ArenaExpression::StructCreation { struct_type, fields } => Expression::StructCreation {
+                struct_type: struct_type.clone(),
+                fields: fields.iter().map(|(name, value)| (name.clone(), self.boxed(*value))).collect()
+            },
+            ArenaExpression::If { condition, then_branch, else_branch } => Expression::If {
+                condition: self.boxed(*condition),
+                then_branch: self.boxed(*then_branch),
+                else_branch: else_branch.map(|else_branch| self.boxed(else_branch))
+            },
+            ArenaExpression::IfLet { pattern, value, then_branch, else_branch } => Expression::IfLet {
+                pattern: pattern.clone(),
+                value: self.boxed(*value),
+                then_branch: self.boxed(*then_branch),
+                else_branch: else_branch.map(|else_branch| self.boxed(else_branch))
+            },
+            ArenaExpression::Loop(ArenaLoopType::While { condition, body, label }) => Expression::Loop(LoopType::While {
+                condition: self.boxed(*condition),
+                body: self.boxed(*body),
+                label: label.clone()
+            }),
+            ArenaExpression::Loop(ArenaLoopType::Infinite { body, label }) => Expression::Loop(LoopType::Infinite {
+                body: self.boxed(*body),
+                label: label.clone()
+            }),
+            ArenaExpression::Loop(ArenaLoopType::Iterator { mutability, iterator, iterable, body, label }) => Expression::Loop(LoopType::Iterator {
+                mutability: mutability.clone(),
+                iterator: iterator.clone(),
+                iterable: self.boxed(*iterable),
+                body: self.boxed(*body),
+                label: label.clone()
+            }),
+            ArenaExpression::SizeOf(ty) => Expression::SizeOf(ty.clone()),
+            ArenaExpression::TypeOf(operand) => Expression::TypeOf(self.boxed(*operand)),
+            ArenaExpression::Cast { value, target_type } => Expression::Cast { value: self.boxed(*value), target_type: target_type.clone() },
+            ArenaExpression::Await(operand) => Expression::Await(self.boxed(*operand)),
+            ArenaExpression::Try { operand } => Expression::Try { operand: self.boxed(*operand) },
+            ArenaExpression::Grouping(inner) => Expression::Grouping(self.boxed(*inner)),
+            ArenaExpression::Match { scrutinee, arms } => Expression::Match {
+                scrutinee: self.boxed(*scrutinee),
+                arms: arms.iter().map(|arm| MatchArm { pattern: arm.pattern.clone(), body: self.boxed(arm.body) }).collect()
+            },
+            ArenaExpression::Range { start, end, inclusive } => Expression::Range {
+                start: self.boxed(*start),
+                end: self.boxed(*end),
+                inclusive: *inclusive
+            }
+        };
+        Spanned::new(boxed, self.span(expression))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser::{ast_printer::ASTPrinter, Parser}, tokenizer::Tokenizer};
+
+    const SOURCE: &str = r#"
+        const LIMIT: i32 = 10;
+
+        /// Doubles until it passes the limit
+        func main(start: i32 = 1) -> i32 {
+            let total: i32 = start;
+            loop (total < LIMIT) {
+                let step: i32 = match (total) { 1 => 2, other => other * 2 };
+                total += step;
+            };
+            total
+        }
+
+        struct Point {
+            pub x: i32;
+            pub func length() -> i32 { "{x}".len() }
+        }
+    "#;
+
+    fn parse() -> Program {
+        let mut tokenizer = Tokenizer::new(SOURCE.to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        Parser::new(tokens).parse_program().unwrap()
+    }
+
+    #[test]
+    fn test_parse_into_arena() {
+        let mut tokenizer = Tokenizer::new(SOURCE.to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let arena = Parser::new(tokens).parse_program_arena().unwrap();
+        assert_eq!(arena, ArenaProgram::from_program(parse()));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let program = parse();
+        let arena = ArenaProgram::from_program(program.clone());
+        assert_eq!(arena.to_program(), program);
+
+        // Spans aren't compared, so they're checked by printing them
+        let mut printer = ASTPrinter::new().with_color(false).with_spans(true);
+        assert_eq!(printer.print_arena_program(&arena), printer.print_program(&program));
+    }
+
+    #[test]
+    fn test_printing_with_max_depth() {
+        // Every statement and declaration counts as a node, like in the boxed form
+        let program = parse();
+        let arena = ArenaProgram::from_program(program.clone());
+        for max_depth in 0..6 {
+            let mut printer = ASTPrinter::new().with_color(false).with_max_depth(max_depth);
+            assert_eq!(printer.print_arena_program(&arena), printer.print_program(&program), "At depth {}", max_depth);
+        }
+    }
+}
@@ -1,86 +1,341 @@
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ExpressionId(pub u32);
 
-#[derive(Debug, PartialEq)]
+/// A range of bytes in the source code.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Span {
+    pub start: usize,
+    pub end: usize
+}
+
+impl std::fmt::Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}..{}]", self.start, self.end)
+    }
+}
+
+/// An AST node along with the part of the source it was parsed from.
+/// Spans are ignored when comparing nodes, so the same code parsed from differently formatted source is still equal.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Spanned<T> {
+    pub node: T,
+    pub span: Span
+}
+
+impl<T> Spanned<T> {
+    pub fn new(node: T, span: Span) -> Self {
+        Spanned { node, span }
+    }
+}
+
+impl<T: PartialEq> PartialEq for Spanned<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.node == other.node
+    }
+}
+
+impl<T> std::ops::Deref for Spanned<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.node
+    }
+}
+
+impl<T> std::ops::DerefMut for Spanned<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.node
+    }
+}
+
+/// Nodes built outside the parser (like in tests or by the optimizer) don't come from anywhere in the source, so they get an empty span.
+impl<T> From<T> for Spanned<T> {
+    fn from(node: T) -> Self {
+        Spanned { node, span: Span::default() }
+    }
+}
+
+/// The value of a float literal.
+/// Literals compare by their bits instead of as floats, so comparing ASTs is deterministic:
+/// a `NaN` literal equals itself (which matters for comparing a tree to a copy of it), and `0.0` and `-0.0` are different literals.
+#[derive(Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(transparent))]
+pub struct LiteralFloat(pub f64);
+
+impl Expression {
+    /// An integer literal without a spelling.
+    pub fn integer(value: i128) -> Self {
+        Expression::IntegerLiteral { value, lexeme: None }
+    }
+
+    /// A number literal without a spelling.
+    pub fn number(value: f64) -> Self {
+        Expression::NumberLiteral { value: LiteralFloat(value), lexeme: None }
+    }
+
+    /// A string literal that isn't raw.
+    pub fn string(value: String) -> Self {
+        Expression::StringLiteral { value, raw: false }
+    }
+}
+
+impl PartialEq for LiteralFloat {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.to_bits() == other.0.to_bits()
+    }
+}
+
+impl Eq for LiteralFloat {}
+
+// Printed like the plain float, so debug output reads the same as before
+impl std::fmt::Debug for LiteralFloat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.0)
+    }
+}
+
+impl std::fmt::Display for LiteralFloat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Expression {
-    Block(Vec<Statement>),
+    /// A block's statements, then its `tail`: the expression at the end without a semicolon, which is the block's value.
+    /// Blocks without a tail evaluate to `nil`.
+    Block {
+        statements: Vec<Spanned<Statement>>,
+        tail: Option<Box<Spanned<Expression>>>
+    },
 
-    NumberLiteral(f64),
-    StringLiteral(String),
+    /// A literal written without a decimal point, like `5` or `0xFF`.
+    /// These are kept as integers so that values like `u64::MAX` don't lose precision.
+    IntegerLiteral {
+        value: i128,
+        /// How the literal was written, like `1_000`, if the parser was asked to keep spellings.
+        /// Literals made by other passes, like constant folding, never have one.
+        lexeme: Option<String>
+    },
+    /// A literal written with a decimal point, like `5.0` or `.5`.
+    NumberLiteral {
+        value: LiteralFloat,
+        /// How the literal was written, like `1_000.5`, if the parser was asked to keep spellings.
+        lexeme: Option<String>
+    },
+    StringLiteral {
+        value: String,
+        /// Raw strings, like `r"C:\files"`, keep their backslashes instead of treating them as escapes.
+        raw: bool
+    },
     CharLiteral(char),
+    /// A string with expressions in it, like `"hello ${name}"`. Empty pieces of text between expressions aren't kept,
+    /// so `"${a}${b}"` is just the two expressions.
+    InterpolatedString {
+        parts: Vec<StringPart>
+    },
     Variable {
         name: String,
         expression_id: ExpressionId
     },
     BooleanLiteral(bool),
+    /// `nil`, the only value of the `nil` type.
+    NilLiteral,
 
     FunctionCall {
-        callee: Box<Expression>,
-        args: Vec<Expression>
+        callee: Box<Spanned<Expression>>,
+        args: Vec<Spanned<Expression>>
+    },
+    /// A call written directly after a member access, like `list.push(1)`.
+    /// The parser makes these instead of a `FunctionCall` whose callee is a `MemberAccess`, so passes can tell them apart
+    /// without looking inside the callee. A member access in parentheses, like `(list.push)(1)`, is still a `FunctionCall`.
+    MethodCall {
+        receiver: Box<Spanned<Expression>>,
+        method: String,
+        args: Vec<Spanned<Expression>>
     },
     
     BinaryOperation {
-        left: Box<Expression>,
+        left: Box<Spanned<Expression>>,
         operator: BinaryOperator,
-        right: Box<Expression>
+        right: Box<Spanned<Expression>>
     },
     UnaryOperation {
         operator: UnaryOperator,
-        operand: Box<Expression>
+        operand: Box<Spanned<Expression>>
     },
     
+    /// `name = value`, or a compound assignment like `name += value` if `operator` is set.
     Assignment {
         name: String,
-        value: Box<Expression>,
+        operator: Option<BinaryOperator>,
+        value: Box<Spanned<Expression>>,
         expression_id: ExpressionId
     },
+    /// `object.member = value`, or a compound assignment like `object.member += value` if `operator` is set.
+    /// If `object` is a reference, the struct it refers to is changed.
+    MemberAssignment {
+        object: Box<Spanned<Expression>>,
+        member: String,
+        operator: Option<BinaryOperator>,
+        value: Box<Spanned<Expression>>
+    },
+    /// `object[index] = value`, or a compound assignment like `object[index] += value` if `operator` is set.
+    /// Like a member assignment, this changes the array that `object` refers to if it's a reference.
+    IndexAssignment {
+        object: Box<Spanned<Expression>>,
+        index: Box<Spanned<Expression>>,
+        operator: Option<BinaryOperator>,
+        value: Box<Spanned<Expression>>
+    },
     MemberAccess {
-        object: Box<Expression>,
+        object: Box<Spanned<Expression>>,
         member: String
     },
+    /// Reading an element, like `values[i]`.
+    Index {
+        object: Box<Spanned<Expression>>,
+        index: Box<Spanned<Expression>>
+    },
 
     Array {
         array_type: Type,
-        size: Box<Expression>,
-        initial_value: Box<Expression>
+        size: Box<Spanned<Expression>>,
+        initial_value: Box<Spanned<Expression>>
     },
+    /// An array of the listed elements, like `[1, 2, 3]`. An empty one takes its element type from where it's used.
+    ArrayLiteral(Vec<Spanned<Expression>>),
+    /// Creating a struct, like `new Point { x: 1.0 }`.
+    /// Anonymous ones, like `new { x: 1.0 }`, have a `struct_type` of `Type::Inferred` and take their type from where they're used.
     StructCreation {
         struct_type: Type,
-        fields: Vec<(String, Box<Expression>)>
+        fields: Vec<(String, Box<Spanned<Expression>>)>
     },
+    /// A group of values, like `(1, true)`. One-element tuples need a trailing comma, like `(1,)`,
+    /// and `()` is the empty tuple, which is a value of its own rather than `nil`.
+    Tuple(Vec<Spanned<Expression>>),
 
     If {
-        condition: Box<Expression>,
-        then_branch: Box<Expression>,
-        else_branch: Option<Box<Expression>>
+        condition: Box<Spanned<Expression>>,
+        then_branch: Box<Spanned<Expression>>,
+        else_branch: Option<Box<Spanned<Expression>>>
+    },
+    /// `if (let pattern = value) then_branch`, which runs the then branch if the value matches the pattern.
+    /// A binding only matches values that aren't nil, and binds the value inside an optional, so this unwraps optionals.
+    IfLet {
+        pattern: Pattern,
+        value: Box<Spanned<Expression>>,
+        then_branch: Box<Spanned<Expression>>,
+        else_branch: Option<Box<Spanned<Expression>>>
     },
-    Loop(LoopType)
+    Loop(LoopType),
+
+    /// `sizeof(Type)`: the size of a type in bytes, as a `u64`.
+    SizeOf(Type),
+    /// `typeof(expression)`: a description of the expression's type.
+    TypeOf(Box<Spanned<Expression>>),
+    /// An explicit conversion like `1 as bool`. Booleans and integers are never converted implicitly.
+    Cast {
+        value: Box<Spanned<Expression>>,
+        target_type: Type
+    },
+    /// Waits for the result of an async operation. Only valid inside async functions.
+    Await(Box<Spanned<Expression>>),
+    /// An expression the source wrapped in parentheses, like `(a + b)`. It's kept so printers can show the parentheses
+    /// where they were written instead of only where precedence needs them; otherwise it means the same as the expression inside.
+    Grouping(Box<Spanned<Expression>>),
+    /// `operand?`: the value inside an optional, or returns `nil` from the enclosing function if there isn't one.
+    /// Like calls and member accesses, this binds tighter than any prefix or binary operator, so `a?.b` accesses `b` on `a?`.
+    Try {
+        operand: Box<Spanned<Expression>>
+    },
+    /// Evaluates the body of the first arm whose pattern matches the scrutinee.
+    Match {
+        scrutinee: Box<Spanned<Expression>>,
+        arms: Vec<MatchArm>
+    },
+    /// The integers from `start` up to `end`, like `0..10`. `end` is included if this was written `start..=end`.
+    Range {
+        start: Box<Spanned<Expression>>,
+        end: Box<Spanned<Expression>>,
+        inclusive: bool
+    }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum StringPart {
+    Literal(String),
+    Expr(Box<Spanned<Expression>>)
+}
+
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub body: Box<Spanned<Expression>>
+}
+
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Pattern {
+    IntegerLiteral(i128),
+    NumberLiteral(LiteralFloat),
+    StringLiteral(String),
+    CharLiteral(char),
+    BooleanLiteral(bool),
+    /// Matches anything, binding the value to a variable in the arm's body.
+    Binding(String)
+}
+
+impl std::fmt::Display for Pattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Pattern::IntegerLiteral(value) => write!(f, "{}", value),
+            Pattern::NumberLiteral(value) => write!(f, "{}", value),
+            Pattern::StringLiteral(value) => write!(f, "{:?}", value),
+            Pattern::CharLiteral(value) => write!(f, "{:?}", value),
+            Pattern::BooleanLiteral(value) => write!(f, "{}", value),
+            Pattern::Binding(name) => write!(f, "{}", name)
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum VariableMutability {
     Mutable,
     Immutable
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Each kind of loop can be labeled, like `'outer: loop { ... }`, so a `break` or `continue` inside a nested loop can name it.
 pub enum LoopType {
     While {
-        condition: Box<Expression>,
-        body: Box<Expression>
+        condition: Box<Spanned<Expression>>,
+        body: Box<Spanned<Expression>>,
+        label: Option<String>
     },
     Infinite {
-        body: Box<Expression>
+        body: Box<Spanned<Expression>>,
+        label: Option<String>
     },
     Iterator {
         mutability: VariableMutability,
         iterator: String,
-        iterable: Box<Expression>,
-        body: Box<Expression>
+        iterable: Box<Spanned<Expression>>,
+        body: Box<Spanned<Expression>>,
+        label: Option<String>
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum BinaryOperator {
     Add,
     Subtract,
@@ -91,6 +346,12 @@ pub enum BinaryOperator {
     And,
     Or,
 
+    BitwiseAnd,
+    BitwiseOr,
+    BitwiseXor,
+    ShiftLeft,
+    ShiftRight,
+
     Equal,
     NotEqual,
     LessThan,
@@ -109,6 +370,11 @@ impl std::fmt::Display for BinaryOperator {
             BinaryOperator::Modulus => "%",
             BinaryOperator::And => "&&",
             BinaryOperator::Or => "||",
+            BinaryOperator::BitwiseAnd => "&",
+            BinaryOperator::BitwiseOr => "|",
+            BinaryOperator::BitwiseXor => "^",
+            BinaryOperator::ShiftLeft => "<<",
+            BinaryOperator::ShiftRight => ">>",
             BinaryOperator::Equal => "==",
             BinaryOperator::NotEqual => "!=",
             BinaryOperator::LessThan => "<",
@@ -119,79 +385,224 @@ impl std::fmt::Display for BinaryOperator {
     }
 }
 
-#[derive(Debug, PartialEq)]
+impl BinaryOperator {
+    /// How tightly this operator binds, where higher levels bind tighter.
+    /// This matches the order of the `parse_*_or_lower` functions in the parser, so it should be updated alongside them.
+    pub fn precedence(&self) -> u8 {
+        match self {
+            BinaryOperator::Or => 1,
+            BinaryOperator::And => 2,
+            BinaryOperator::Equal | BinaryOperator::NotEqual => 3,
+            BinaryOperator::LessThan | BinaryOperator::GreaterThan |
+            BinaryOperator::LessThanOrEqual | BinaryOperator::GreaterThanOrEqual => 4,
+            // Like in Rust, bitwise operators bind tighter than comparisons, so `flags & MASK == 0` compares the masked flags
+            BinaryOperator::BitwiseOr => 5,
+            BinaryOperator::BitwiseXor => 6,
+            BinaryOperator::BitwiseAnd => 7,
+            BinaryOperator::ShiftLeft | BinaryOperator::ShiftRight => 8,
+            BinaryOperator::Add | BinaryOperator::Subtract => 9,
+            BinaryOperator::Multiply | BinaryOperator::Divide | BinaryOperator::Modulus => 10
+        }
+    }
+
+    pub fn associativity(&self) -> Associativity {
+        // Every binary operator is parsed in a loop, so they all group to the left
+        Associativity::Left
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Associativity {
+    Left
+}
+
+impl std::fmt::Display for Associativity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", match self {
+            Associativity::Left => "left-assoc"
+        })
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum UnaryOperator {
     Negate,
-    Not
+    Not,
+    /// `&value` or `&mut value`, which makes a reference to the value instead of copying it.
+    Reference {
+        mutable: bool
+    }
 }
 
 impl std::fmt::Display for UnaryOperator {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", match self {
             UnaryOperator::Negate => "-",
-            UnaryOperator::Not => "!"
+            UnaryOperator::Not => "!",
+            UnaryOperator::Reference { mutable: false } => "&",
+            UnaryOperator::Reference { mutable: true } => "&mut "
         })
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Declaration {
     Function {
         name: String,
         params: Vec<FunctionParameter>,
-        generic_args: Vec<String>,
+        generic_args: Vec<GenericParam>,
+        where_clauses: Vec<WhereClause>,
         return_type: Type,
-        body: Box<Expression>
+        body: Box<Spanned<Expression>>,
+        /// If this was declared with `async func`. Async functions can be parsed and type checked, but not run yet.
+        is_async: bool,
+        /// If the last parameter was written like `...values: i32`, so it collects the rest of the arguments into an array.
+        /// The parameter's type is the type of each argument, not of the array.
+        is_variadic: bool,
+        /// The `///` comments written right before the declaration, one line each, without the slashes.
+        doc: Option<String>
     },
     Struct {
         name: String,
         elements: Vec<StructElement>,
-        generic_args: Vec<String>
+        generic_args: Vec<GenericParam>,
+        where_clauses: Vec<WhereClause>,
+        doc: Option<String>
     },
     TypeDeclaration {
         name: String,
-        generic_args: Vec<String>,
-        alias: Type
+        generic_args: Vec<GenericParam>,
+        where_clauses: Vec<WhereClause>,
+        alias: Type,
+        doc: Option<String>
+    },
+    /// A sum type, like `enum Shape { Empty, Circle(f64) }`.
+    Enum {
+        name: String,
+        variants: Vec<EnumVariant>,
+        generic_args: Vec<GenericParam>,
+        where_clauses: Vec<WhereClause>,
+        doc: Option<String>
     },
+    /// A constant at the top level of a program, like `const LIMIT: i32 = 10;`.
+    /// Inside a block, `const` declares an immutable variable instead.
+    Const {
+        name: String,
+        const_type: Type,
+        value: Box<Spanned<Expression>>
+    },
+    /// `import foo.bar;`, `import foo.bar as baz;`, or `import foo.{a, b};`.
     Import {
-        path: Vec<String>
+        path: Vec<String>,
+        /// The name the import is used by instead of the path's last part, from `as`.
+        alias: Option<String>,
+        /// The only names brought in from the path, if it was written like `foo.{a, b}`.
+        items: Option<Vec<String>>
+    }
+}
+
+impl Declaration {
+    /// The declaration's doc comment. Consts and imports can't have one.
+    pub fn doc(&self) -> Option<&str> {
+        match self {
+            Declaration::Function { doc, .. } | Declaration::Struct { doc, .. } |
+            Declaration::TypeDeclaration { doc, .. } | Declaration::Enum { doc, .. } => doc.as_deref(),
+            Declaration::Const { .. } | Declaration::Import { .. } => None
+        }
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EnumVariant {
+    pub name: String,
+    /// The types of the values this variant holds, like `(f64, f64)`. Empty for variants without any.
+    pub associated_types: Vec<Type>
+}
+
+/// A generic argument of a declaration, like the `T: Comparable` in `func largest<T: Comparable>`.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GenericParam {
+    pub name: String,
+    /// The bounds written after the name, like `Comparable + Copy`. Bounds from where clauses aren't included.
+    pub bounds: Vec<Type>
+}
+
+/// A `where T: A + B` bound on a generic argument: the argument's name and the names of its bounds.
+pub type WhereClause = (String, Vec<String>);
+
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum StructElement {
-    Declaration(Declaration),
+    Declaration {
+        declaration: Declaration,
+        visibility: Visibility
+    },
     Field {
         name: String,
-        field_type: Type
+        field_type: Type,
+        visibility: Visibility
+    }
+}
+
+/// Whether a struct member can be used from outside the struct.
+/// Members are private unless they're marked with `pub`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Visibility {
+    Public,
+    Private
+}
+
+impl std::fmt::Display for Visibility {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", match self {
+            Visibility::Public => "Public",
+            Visibility::Private => "Private"
+        })
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Statement {
     Declaration(Declaration),
+    /// An expression followed by a semicolon, run for its side effects
     Expression {
-        expression: Box<Expression>,
-        result: bool // true if this is a result value, false if it's just an expression statement
+        expression: Box<Spanned<Expression>>
     },
     VariableDeclaration {
         mutability: VariableMutability,
         name: String,
         variable_type: Type,
-        value: Box<Expression>
+        /// `None` for a declaration like `let x: i32;`, which is assigned later
+        value: Option<Box<Spanned<Expression>>>
     },
-    Break,
-    Continue,
-    Return(Option<Box<Expression>>)
+    /// The label of the loop to break out of, or `None` for the innermost one
+    Break(Option<String>),
+    /// The label of the loop to continue, or `None` for the innermost one
+    Continue(Option<String>),
+    Return(Option<Box<Spanned<Expression>>>),
+    /// An expression that runs when the enclosing block exits, including through `return`, `break`, or `continue`.
+    /// Deferred expressions in the same block run in the reverse of the order they were reached.
+    Defer(Box<Spanned<Expression>>)
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FunctionParameter {
     pub name: String,
-    pub param_type: Type
+    pub param_type: Type,
+    /// The value a parameter gets when a call leaves it out, like the `0` in `y: i32 = 0`.
+    /// It's evaluated where the function is declared, so it can't refer to the other parameters.
+    pub default: Option<Box<Spanned<Expression>>>
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Type {
     U8, U16, U32, U64,
     I8, I16, I32, I64,
@@ -206,13 +617,312 @@ pub enum Type {
         params: Vec<Type>,
         return_type: Box<Type>
     },
-    Array(Box<Type>),
+    /// An array, like `[i32]`. Fixed-size arrays, like `[i32; 4]`, have a `size`; other arrays only know their length at runtime.
+    Array {
+        element: Box<Type>,
+        size: Option<usize>
+    },
+    /// A reference to a value of another type, like `&i32` or `&mut [u8]`.
+    Reference {
+        mutable: bool,
+        inner: Box<Type>
+    },
+    /// A structural record type like `{ x: i32, y: i32 }`.
+    /// Unlike structs, records are compared by their fields instead of their name.
+    Record(Vec<(String, Type)>),
+    /// A tuple type like `(i32, bool)`. One-element tuples are written `(i32,)`, since `(i32)` is just `i32`.
+    Tuple(Vec<Type>),
+    /// A value that might be missing, like `i32?`.
+    Optional(Box<Type>),
+    /// A map from keys to values, written `Map<K, V>`.
+    Map {
+        key: Box<Type>,
+        value: Box<Type>
+    },
     /// Nil is the return type for functions that don't return a value.
     /// Nil can only have the value of `nil` (which, itself, is only valid for the type Nil), and is invalid in other contexts.
-    Nil
+    /// Nil is distinct from the empty tuple `()`: nil means there is no value at all, while `()` is an ordinary value
+    /// that happens to have no elements. Keeping them apart means a function returning nil can't be passed where one
+    /// returning `()` is expected (or the other way around), and code that's generic over tuples never has to special-case nil.
+    Nil,
+    /// The return type for functions that never return, like ones that loop forever or always panic.
+    /// Unlike nil, there's no value of this type at all, so code after a call to one of these functions can't run.
+    Never,
+    /// The type of a variable declared without one, like `let x = 5;`, or of an anonymous struct creation, like `new { x: 5 }`.
+    /// The type checker replaces it with the initializer's type or the type the struct is expected to have.
+    Inferred
 }
 
-#[derive(Debug, PartialEq)]
+impl Type {
+    /// An array without a fixed size.
+    pub fn array(element: Type) -> Self {
+        Type::Array { element: Box::new(element), size: None }
+    }
+
+    /// The same type with identifiers that name a built-in type replaced by that type, at any depth.
+    /// `I32` and `i32` both become `Type::I32`, and `Array<T>` and `Map<K, V>` become the built-in array and map types.
+    /// Other identifiers are left as they are, so two types mean the same thing when their normalized forms are equal.
+    #[allow(dead_code)] // Not used by the compiler's own passes yet
+    pub fn normalize(&self) -> Type {
+        let normalize_all = |types: &[Type]| types.iter().map(Type::normalize).collect::<Vec<_>>();
+        match self {
+            Type::Identifier { name, generics } => match (name.as_str(), &generics[..]) {
+                (name, []) => primitive_named(name).unwrap_or_else(|| self.clone()),
+                ("Array", [element_type]) => Type::array(element_type.normalize()),
+                ("Map", [key, value]) => Type::Map { key: Box::new(key.normalize()), value: Box::new(value.normalize()) },
+                _ => Type::Identifier { name: name.clone(), generics: normalize_all(generics) }
+            },
+            Type::Function { params, return_type } => Type::Function { params: normalize_all(params), return_type: Box::new(return_type.normalize()) },
+            Type::Array { element, size } => Type::Array { element: Box::new(element.normalize()), size: *size },
+            Type::Reference { mutable, inner } => Type::Reference { mutable: *mutable, inner: Box::new(inner.normalize()) },
+            Type::Record(fields) => Type::Record(fields.iter().map(|(name, field_type)| (name.clone(), field_type.normalize())).collect()),
+            Type::Tuple(elements) => Type::Tuple(normalize_all(elements)),
+            Type::Optional(inner) => Type::Optional(Box::new(inner.normalize())),
+            Type::Map { key, value } => Type::Map { key: Box::new(key.normalize()), value: Box::new(value.normalize()) },
+            Type::U8 | Type::U16 | Type::U32 | Type::U64 |
+            Type::I8 | Type::I16 | Type::I32 | Type::I64 |
+            Type::F32 | Type::F64 |
+            Type::Boolean | Type::Character | Type::Nil | Type::Never | Type::Inferred => self.clone()
+        }
+    }
+}
+
+/// The built-in type a name refers to, either how it's printed (like `Boolean`) or how it's written in source (like `bool`).
+fn primitive_named(name: &str) -> Option<Type> {
+    Some(match name {
+        "U8" | "u8" => Type::U8,
+        "U16" | "u16" => Type::U16,
+        "U32" | "u32" => Type::U32,
+        "U64" | "u64" => Type::U64,
+        "I8" | "i8" => Type::I8,
+        "I16" | "i16" => Type::I16,
+        "I32" | "i32" => Type::I32,
+        "I64" | "i64" => Type::I64,
+        "F32" | "f32" => Type::F32,
+        "F64" | "f64" => Type::F64,
+        "Boolean" | "bool" => Type::Boolean,
+        "Character" | "char" => Type::Character,
+        "Nil" | "nil" => Type::Nil,
+        "Never" | "never" => Type::Never,
+        _ => return None
+    })
+}
+
+/// A compact, single-line form of a type, like `Array<I32>` or `(I32, Boolean) -> Nil`.
+impl std::fmt::Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fn join(types: &[Type]) -> String {
+            types.iter().map(|ty| ty.to_string()).collect::<Vec<_>>().join(", ")
+        }
+
+        match self {
+            Type::U8 => write!(f, "U8"),
+            Type::U16 => write!(f, "U16"),
+            Type::U32 => write!(f, "U32"),
+            Type::U64 => write!(f, "U64"),
+            Type::I8 => write!(f, "I8"),
+            Type::I16 => write!(f, "I16"),
+            Type::I32 => write!(f, "I32"),
+            Type::I64 => write!(f, "I64"),
+            Type::F32 => write!(f, "F32"),
+            Type::F64 => write!(f, "F64"),
+            Type::Boolean => write!(f, "Boolean"),
+            Type::Character => write!(f, "Character"),
+            Type::Nil => write!(f, "Nil"),
+            Type::Never => write!(f, "Never"),
+            Type::Inferred => write!(f, "Inferred"),
+            Type::Identifier { name, generics } if generics.is_empty() => write!(f, "{}", name),
+            Type::Identifier { name, generics } => write!(f, "{}<{}>", name, join(generics)),
+            Type::Function { params, return_type } => write!(f, "({}) -> {}", join(params), return_type),
+            Type::Array { element, size: None } => write!(f, "Array<{}>", element),
+            Type::Array { element, size: Some(size) } => write!(f, "Array<{}; {}>", element, size),
+            Type::Reference { mutable: false, inner } => write!(f, "&{}", inner),
+            Type::Reference { mutable: true, inner } => write!(f, "&mut {}", inner),
+            Type::Record(fields) => {
+                let fields = fields.iter().map(|(name, field_type)| format!("{}: {}", name, field_type)).collect::<Vec<_>>();
+                write!(f, "{{ {} }}", fields.join(", "))
+            },
+            Type::Tuple(elements) if elements.len() == 1 => write!(f, "({},)", elements[0]),
+            Type::Tuple(elements) => write!(f, "({})", join(elements)),
+            Type::Optional(inner) => write!(f, "{}?", inner),
+            Type::Map { key, value } => write!(f, "Map<{}, {}>", key, value)
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Program {
-    pub declarations: Vec<Declaration>
-}
\ No newline at end of file
+    pub declarations: Vec<Spanned<Declaration>>
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser::Parser, tokenizer::Tokenizer};
+
+    #[test]
+    fn test_type_display() {
+        let nested = Type::array(Type::array(Type::I32));
+        assert_eq!(nested.to_string(), "Array<Array<I32>>");
+        assert_eq!(Type::Array { element: Box::new(Type::I32), size: Some(4) }.to_string(), "Array<I32; 4>");
+
+        let map = Type::Identifier { name: "Map".to_string(), generics: vec![
+            Type::Identifier { name: "String".to_string(), generics: vec![] },
+            Type::I32
+        ] };
+        assert_eq!(map.to_string(), "Map<String, I32>");
+
+        let callback = Type::Function { params: vec![Type::I32, Type::Boolean], return_type: Box::new(Type::Nil) };
+        assert_eq!(callback.to_string(), "(I32, Boolean) -> Nil");
+        let higher_order = Type::Function {
+            params: vec![callback.clone()],
+            return_type: Box::new(Type::array(callback))
+        };
+        assert_eq!(higher_order.to_string(), "((I32, Boolean) -> Nil) -> Array<(I32, Boolean) -> Nil>");
+
+        assert_eq!(Type::Tuple(vec![Type::U8]).to_string(), "(U8,)");
+
+        let reference = Type::Reference { mutable: false, inner: Box::new(Type::I32) };
+        assert_eq!(reference.to_string(), "&I32");
+        let mutable_bytes = Type::Reference { mutable: true, inner: Box::new(Type::array(Type::U8)) };
+        assert_eq!(mutable_bytes.to_string(), "&mut Array<U8>");
+        let callback = Type::Function { params: vec![reference, mutable_bytes], return_type: Box::new(Type::Nil) };
+        assert_eq!(callback.to_string(), "(&I32, &mut Array<U8>) -> Nil");
+        assert_eq!(Type::Record(vec![("x".to_string(), Type::F64)]).to_string(), "{ x: F64 }");
+
+        let optional = Type::Optional(Box::new(Type::I32));
+        assert_eq!(optional.to_string(), "I32?");
+        let string = Type::Identifier { name: "String".to_string(), generics: vec![] };
+        assert_eq!(Type::Map { key: Box::new(string), value: Box::new(optional) }.to_string(), "Map<String, I32?>");
+    }
+
+    #[test]
+    fn test_types_are_hashable() {
+        let named = |name: &str, generics: Vec<Type>| Type::Identifier { name: name.to_string(), generics };
+        let types = [
+            named("Pair", vec![Type::I32, Type::Boolean]),
+            named("Pair", vec![Type::I32, Type::Boolean]),
+            // Generic arguments are part of the hash, so these are all different
+            named("Pair", vec![Type::Boolean, Type::I32]),
+            named("Pair", vec![]),
+            Type::Function { params: vec![Type::array(Type::U8)], return_type: Box::new(Type::Nil) },
+            Type::Function { params: vec![Type::array(Type::U8)], return_type: Box::new(Type::Nil) },
+            Type::Record(vec![("x".to_string(), Type::F64)]),
+            Type::Record(vec![("x".to_string(), Type::F64)]),
+            Type::I32
+        ];
+        let unique = types.iter().cloned().collect::<std::collections::HashSet<_>>();
+        assert_eq!(unique.len(), 6);
+        assert!(unique.contains(&named("Pair", vec![Type::I32, Type::Boolean])));
+
+        let operators = [BinaryOperator::Add, BinaryOperator::Add, BinaryOperator::Multiply].into_iter().collect::<std::collections::HashSet<_>>();
+        assert_eq!(operators.len(), 2);
+        let operators = [UnaryOperator::Negate, UnaryOperator::Negate].into_iter().collect::<std::collections::HashSet<_>>();
+        assert_eq!(operators.len(), 1);
+    }
+
+    #[test]
+    fn test_normalize_types() {
+        let named = |name: &str, generics: Vec<Type>| Type::Identifier { name: name.to_string(), generics };
+        assert_eq!(named("U8", vec![]).normalize(), Type::U8);
+        assert_eq!(named("bool", vec![]).normalize(), Type::Boolean);
+
+        // Unknown identifiers are left alone, but their generic arguments are still normalized
+        assert_eq!(named("Point", vec![]).normalize(), named("Point", vec![]));
+        assert_eq!(named("Pair", vec![named("I32", vec![]), named("Point", vec![])]).normalize(), named("Pair", vec![Type::I32, named("Point", vec![])]));
+        // A primitive's name with generic arguments isn't that primitive
+        assert_eq!(named("I32", vec![Type::U8]).normalize(), named("I32", vec![Type::U8]));
+
+        let map = named("Map", vec![named("String", vec![]), named("Array", vec![named("F64", vec![])])]);
+        assert_eq!(map.normalize(), Type::Map {
+            key: Box::new(named("String", vec![])),
+            value: Box::new(Type::array(Type::F64))
+        });
+        let reference = Type::Reference { mutable: true, inner: Box::new(Type::Tuple(vec![named("Character", vec![]), Type::Nil])) };
+        assert_eq!(reference.normalize(), Type::Reference { mutable: true, inner: Box::new(Type::Tuple(vec![Type::Character, Type::Nil])) });
+    }
+
+    #[test]
+    fn test_deep_clone() {
+        let mut tokenizer = Tokenizer::new("(1 + x) * -(y - 2) / f(3)".to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let expression = Parser::new(tokens).parse_expression().unwrap();
+        assert!(matches!(expression, Expression::BinaryOperation { .. }));
+
+        let mut copy = expression.clone();
+        assert_eq!(copy, expression);
+
+        // The copy doesn't share any children with the original
+        let Expression::BinaryOperation { left, .. } = &mut copy else {
+            unreachable!();
+        };
+        left.node = Expression::number(0.0);
+        assert_ne!(copy, expression);
+    }
+
+    #[test]
+    fn test_operator_precedence() {
+        assert!(BinaryOperator::Multiply.precedence() > BinaryOperator::Add.precedence());
+        assert!(BinaryOperator::Add.precedence() > BinaryOperator::LessThan.precedence());
+        assert!(BinaryOperator::Equal.precedence() > BinaryOperator::And.precedence());
+        assert!(BinaryOperator::And.precedence() > BinaryOperator::Or.precedence());
+        assert_eq!(BinaryOperator::Subtract.associativity(), Associativity::Left);
+        assert_eq!(BinaryOperator::Divide.associativity(), Associativity::Left);
+    }
+
+    #[test]
+    fn test_nan_literals_are_equal() {
+        // NaN can't be written in source code, so the parsed literal is swapped for one
+        let parse_with_nan = || {
+            let mut tokenizer = Tokenizer::new("func main() -> nil { const nan: f64 = 0.0; print(nan); }".to_string());
+            let tokens = tokenizer.tokenize().unwrap();
+            let mut program = Parser::new(tokens).parse_program().unwrap();
+            let Declaration::Function { body, .. } = &mut program.declarations[0].node else {
+                unreachable!();
+            };
+            let Expression::Block { statements, .. } = &mut body.node else {
+                unreachable!();
+            };
+            let Statement::VariableDeclaration { value, .. } = &mut statements[0].node else {
+                unreachable!();
+            };
+            value.as_mut().unwrap().node = Expression::number(f64::NAN);
+            program
+        };
+        assert_eq!(parse_with_nan(), parse_with_nan());
+
+        assert_ne!(LiteralFloat(0.0), LiteralFloat(-0.0));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_json_round_trip() {
+        let mut tokenizer = Tokenizer::new(r#"
+            import std.io;
+
+            struct Point {
+                pub x: f64;
+                y: f64;
+            }
+
+            func main(scale: f64) -> nil {
+                const letter: char = 'a';
+                let point: Point = new Point { x: 1.5 * scale, y: -2 };
+                let count: i32 = 3;
+                loop (count > 0) {
+                    count = count - 1;
+                };
+                print(letter, true as i32, sizeof(u64));
+            }
+        "#.to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let program = Parser::new(tokens).parse_program().unwrap();
+
+        let json = serde_json::to_string(&program).unwrap();
+        assert!(json.contains(r#"{"NumberLiteral":{"value":1.5,"lexeme":null}}"#));
+        assert!(json.contains(r#"{"CharLiteral":"a"}"#));
+        let parsed: Program = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, program);
+    }
+}
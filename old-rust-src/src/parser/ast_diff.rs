@@ -0,0 +1,154 @@
+use super::{ast::{Declaration, Program, Spanned}, id_assigner::IdAssigner};
+
+// Compares two programs declaration by declaration, for seeing what a refactor changed beyond the text.
+// Declarations are matched up by name, so moving one around or changing only its spans and formatting isn't a change.
+// Expression ids depend on how many expressions come before a declaration, so each one is numbered on its own before they're compared.
+// Imports don't have names, so they aren't compared. For a tree-level view of the same change, see `ast_printer::diff_programs`.
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum AstChange {
+    Added(String),
+    Removed(String),
+    /// Anything about the declaration changed other than a function's body, like its parameters or a struct's fields.
+    Modified(String),
+    /// A function's body changed. If its signature changed too, it's also reported as `Modified`.
+    BodyChanged(String)
+}
+
+/// The changes from `old` to `new`, with changes to `old`'s declarations in their order, then new declarations in theirs.
+#[allow(dead_code)] // For tools that review changes
+pub fn diff_programs(old: &Program, new: &Program) -> Vec<AstChange> {
+    let old_declarations = named_declarations(old);
+    let new_declarations = named_declarations(new);
+
+    let mut changes = Vec::new();
+    for (name, old_declaration) in &old_declarations {
+        let Some(new_declaration) = find(&new_declarations, name) else {
+            changes.push(AstChange::Removed(name.to_string()));
+            continue;
+        };
+        match (old_declaration, new_declaration) {
+            (Declaration::Function { body: old_body, .. }, Declaration::Function { body: new_body, .. }) => {
+                if !same_signature(old_declaration, new_declaration) {
+                    changes.push(AstChange::Modified(name.to_string()));
+                }
+                if old_body != new_body {
+                    changes.push(AstChange::BodyChanged(name.to_string()));
+                }
+            },
+            _ if old_declaration != new_declaration => changes.push(AstChange::Modified(name.to_string())),
+            _ => {}
+        }
+    }
+    for (name, _) in &new_declarations {
+        if find(&old_declarations, name).is_none() {
+            changes.push(AstChange::Added(name.to_string()));
+        }
+    }
+    changes
+}
+
+/// The program's named declarations, each numbered starting from the first id.
+fn named_declarations(program: &Program) -> Vec<(&str, Declaration)> {
+    program.declarations.iter().filter_map(|declaration| match &declaration.node {
+        Declaration::Function { name, .. } | Declaration::Struct { name, .. } | Declaration::TypeDeclaration { name, .. } |
+        Declaration::Enum { name, .. } | Declaration::Const { name, .. } => {
+            let mut alone = Program { declarations: vec![Spanned::from(declaration.node.clone())] };
+            IdAssigner::new().assign_program(&mut alone);
+            Some((name.as_str(), alone.declarations.remove(0).node))
+        },
+        Declaration::Import { .. } => None
+    }).collect()
+}
+
+fn find<'a>(declarations: &'a [(&str, Declaration)], name: &str) -> Option<&'a Declaration> {
+    declarations.iter().find(|(other, _)| *other == name).map(|(_, declaration)| declaration)
+}
+
+/// If two functions are the same apart from their bodies.
+fn same_signature(old: &Declaration, new: &Declaration) -> bool {
+    let mut new = new.clone();
+    if let (Declaration::Function { body: old_body, .. }, Declaration::Function { body, .. }) = (old, &mut new) {
+        *body = old_body.clone();
+    }
+    old == &new
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser::Parser, tokenizer::Tokenizer};
+
+    fn parse(source: &str) -> Program {
+        let mut tokenizer = Tokenizer::new(source.to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        Parser::new(tokens).parse_program().unwrap()
+    }
+
+    const BEFORE: &str = r#"
+        struct Point {
+            x: i32;
+            y: i32;
+        }
+        struct Size {
+            width: i32;
+        }
+        func area(width: i32, height: i32) -> i32 {
+            width * height
+        }
+        func main() -> nil {
+            print(area(2, 3));
+        }
+    "#;
+
+    #[test]
+    fn test_added_and_removed() {
+        let after = parse(r#"
+            struct Point {
+                x: i32;
+                y: i32;
+            }
+            func main() -> nil {
+                print(area(2, 3));
+            }
+            func area(width: i32, height: i32) -> i32 {
+                width * height
+            }
+            func perimeter(width: i32, height: i32) -> i32 {
+                2 * (width + height)
+            }
+        "#);
+        // Moving `area` after `main` isn't a change
+        assert_eq!(diff_programs(&parse(BEFORE), &after), vec![
+            AstChange::Removed("Size".to_string()),
+            AstChange::Added("perimeter".to_string())
+        ]);
+        assert_eq!(diff_programs(&after, &after), vec![]);
+    }
+
+    #[test]
+    fn test_modified() {
+        let after = parse(r#"
+            struct Point {
+                x: i32;
+                y: i32;
+                z: i32;
+            }
+            struct Size {
+                width: i32;
+            }
+            func area(width: i32, height: i32) -> i32 {
+                height * width
+            }
+            func main(verbose: bool) -> nil {
+                print(area(3, 2));
+            }
+        "#);
+        assert_eq!(diff_programs(&parse(BEFORE), &after), vec![
+            AstChange::Modified("Point".to_string()),
+            AstChange::BodyChanged("area".to_string()),
+            AstChange::Modified("main".to_string()),
+            AstChange::BodyChanged("main".to_string())
+        ]);
+    }
+}
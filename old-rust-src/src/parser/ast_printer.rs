@@ -1,22 +1,62 @@
-use super::ast::{Declaration, Expression, LoopType, Program, Statement, StructElement, Type, VariableMutability};
+use crate::typechecker::InferredTypes;
+
+use super::arena::ArenaProgram;
+use super::source_printer::primitive_name;
+use super::ast::{BinaryOperator, Declaration, EnumVariant, Expression, GenericParam, LoopType, Program, Span, Spanned, Statement, StringPart, StructElement, Type, VariableMutability, WhereClause};
 
 pub struct ASTPrinter {
     indent: usize,
+    /// If set, each expression's type is shown after its node, like `Binary Operation: + : I32`.
+    inferred_types: Option<InferredTypes>,
+    /// If each expression and statement's source span is shown after its node, like `Binary Operation: + [12..19]`.
+    show_spans: bool,
+    /// If node names and indentation guides are styled with ANSI escape codes.
+    color: bool,
+    /// If arrays with a literal size and initial value are printed on one line, like `Array: [0; 16]`.
+    inline_constant_arrays: bool,
+    /// If assignments of a literal or variable are printed on one line, like `Assignment: x = 1`.
+    inline_leaves: bool,
+    /// If binary operations show their precedence level and associativity, like `Binary Operation: * (prec 10, left-assoc)`.
+    show_precedence: bool,
+    /// If primitive types are written the way they are in source code, like `i32` instead of `I32`.
+    source_types: bool,
+    array_style: ArrayStyle,
+    /// If set, nodes nested deeper than this are left out, with a placeholder saying how many there were.
+    max_depth: Option<usize>
+}
+
+/// How array types are written.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ArrayStyle {
+    /// Like in source code, `[I32]`
+    #[default]
+    Brackets,
+    /// Like a generic type, `Array<I32>`
+    Generic
 }
 
 const ANSI_GRAY: &str = "\x1b[90m";
 const ANSI_BOLD: &str = "\x1b[1m";
 const ANSI_RESET: &str = "\x1b[0m";
 
+// While the tree is being printed, each node's first line is marked with its span, like `\x01start:end\x01`, or with `\x01\x01`
+// for nodes without one, like types. The finished tree is cut off by node and mapped back to the source using these,
+// and they're removed before it's returned.
+const SPAN_MARKER: char = '\x01';
+
 // A replacement for format! that includes indentation
 macro_rules! fmt_indent {
     ($self:ident, $fmt:expr $(, $args:expr)*) => {{
         let mut output = String::new();
-        output.push_str(ANSI_GRAY);
+        if $self.color {
+            output.push_str(ANSI_GRAY);
+        }
         for _ in 0..$self.indent {
             output.push_str("|  ");
         }
-        output.push_str(ANSI_RESET);
+        if $self.color {
+            output.push_str(ANSI_RESET);
+        }
         let val = &format!($fmt $(, $args)*);
 
         // Colorize everything before a colon
@@ -24,9 +64,13 @@ macro_rules! fmt_indent {
         let first_part = parts.next().unwrap_or("");
         let second_part = parts.next();
         
-        output.push_str(ANSI_BOLD);
-        output.push_str(first_part);
-        output.push_str(ANSI_RESET);
+        if $self.color {
+            output.push_str(ANSI_BOLD);
+            output.push_str(first_part);
+            output.push_str(ANSI_RESET);
+        } else {
+            output.push_str(first_part);
+        }
 
         if let Some(second_part) = second_part {
             output.push_str(":");
@@ -39,73 +83,358 @@ macro_rules! fmt_indent {
 
 impl ASTPrinter {
     pub fn new() -> Self {
-        ASTPrinter { indent: 0 }
+        ASTPrinter { indent: 0, inferred_types: None, show_spans: false, color: true, inline_constant_arrays: false, inline_leaves: false, show_precedence: false, source_types: false, array_style: ArrayStyle::default(), max_depth: None }
+    }
+
+    /// Annotates expressions with the types from a type check of the program that's going to be printed.
+    pub fn with_inferred_types(mut self, types: InferredTypes) -> Self {
+        self.inferred_types = Some(types);
+        self
+    }
+
+    /// Turns ANSI colors on or off, like for output that isn't going to a terminal.
+    pub fn with_color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Prints arrays filled with a literal, with a literal size, on one line instead of expanding them.
+    pub fn with_inline_constant_arrays(mut self, inline_constant_arrays: bool) -> Self {
+        self.inline_constant_arrays = inline_constant_arrays;
+        self
+    }
+
+    /// Prints assignments whose value is a literal or a variable on one line instead of expanding the value.
+    pub fn with_inline_leaves(mut self, inline_leaves: bool) -> Self {
+        self.inline_leaves = inline_leaves;
+        self
+    }
+
+    /// Annotates expressions and statements with their source spans.
+    pub fn with_spans(mut self, show_spans: bool) -> Self {
+        self.show_spans = show_spans;
+        self
+    }
+
+    /// Annotates binary operations with how they group, which is useful for explaining how an expression was parsed.
+    pub fn with_precedence(mut self, show_precedence: bool) -> Self {
+        self.show_precedence = show_precedence;
+        self
+    }
+
+    /// Writes primitive types the way they're written in source code, like `i32` instead of `I32`.
+    pub fn with_source_types(mut self, source_types: bool) -> Self {
+        self.source_types = source_types;
+        self
+    }
+
+    /// Picks how array types are written, like `[I32]` or `Array<I32>`.
+    pub fn with_array_style(mut self, array_style: ArrayStyle) -> Self {
+        self.array_style = array_style;
+        self
+    }
+
+    /// Only prints nodes up to `max_depth` levels deep, for an overview of a large program.
+    /// At depth 0, only the first line of each top-level declaration is printed.
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    /// Adds an annotation to the end of a node's first line.
+    fn annotate(output: &mut String, annotation: &str) {
+        let end = output.find('\n').unwrap_or(output.len());
+        output.insert_str(end, annotation);
+    }
+
+    fn annotate_span(&self, output: &mut String, span: Span) {
+        if self.show_spans {
+            Self::annotate(output, &format!(" {}", span));
+        }
+        self.mark_span(output, span);
+    }
+
+    fn mark_span(&self, output: &mut String, span: Span) {
+        Self::annotate(output, &format!("{SPAN_MARKER}{}:{}{SPAN_MARKER}", span.start, span.end));
+    }
+
+    /// Prints a type on its own line after a label, like `Return Type: I32`. Types are nodes too, but without spans.
+    fn print_type_line(&mut self, label: &str, ty: &Type) -> String {
+        let printed = self.print_type(ty);
+        let mut output = fmt_indent!(self, "{}: {}\n", label, printed);
+        Self::annotate(&mut output, &format!("{SPAN_MARKER}{SPAN_MARKER}"));
+        output
+    }
+
+    fn precedence_hint(&self, operator: &BinaryOperator) -> String {
+        if self.show_precedence {
+            format!(" (prec {}, {})", operator.precedence(), operator.associativity())
+        } else {
+            String::new()
+        }
     }
 
     pub fn print_program(&mut self, program: &Program) -> String {
+        self.print_program_with_map(program).0
+    }
+
+    /// Prints the arena form of a program, which looks exactly like the boxed form printed by `print_program`.
+    pub fn print_arena_program(&mut self, program: &ArenaProgram) -> String {
+        self.print_program(&program.to_program())
+    }
+
+    /// Prints the program along with a map from printed lines to the source span of the node each one starts, for tools that work on the printed tree.
+    /// Lines are numbered from 0. Lines that only label a child, like `Left:`, aren't in the map.
+    pub fn print_program_with_map(&mut self, program: &Program) -> (String, Vec<(usize, Span)>) {
         self.indent = 0;
         let mut output = String::new();
         for declaration in &program.declarations {
-            output.push_str(&self.print_declaration(declaration));
+            // Marked on the node itself rather than its doc comment
+            let mut printed = self.print_declaration_node(declaration);
+            self.mark_span(&mut printed, declaration.span);
+            output.push_str(&self.print_doc(declaration.doc()));
+            output.push_str(&printed);
         }
+        let limited = self.limit_depth(output);
+        take_span_markers(&limited)
+    }
+
+    /// Replaces each run of lines nested deeper than `max_depth` with a placeholder like `… (12 more nodes)`.
+    /// This works on the printed tree so that every kind of node is cut off the same way.
+    fn limit_depth(&mut self, output: String) -> String {
+        let Some(max_depth) = self.max_depth else {
+            return output;
+        };
+        let mut limited = String::new();
+        let mut elided = 0;
+        for line in output.lines() {
+            let plain = strip_ansi(line);
+            let mut rest = plain.as_str();
+            let mut depth = 0;
+            while let Some(after) = rest.strip_prefix("|  ") {
+                rest = after;
+                depth += 1;
+            }
+            if depth > max_depth || (rest.is_empty() && elided > 0) {
+                // Only nodes are counted, not the labels between them. A line can start more than one, like `Body: |  Block:`.
+                elided += rest.matches(SPAN_MARKER).count() / 2;
+                continue;
+            }
+            limited.push_str(&self.elided_placeholder(max_depth, &mut elided));
+            limited.push_str(line);
+            limited.push('\n');
+        }
+        limited.push_str(&self.elided_placeholder(max_depth, &mut elided));
+        limited
+    }
+
+    fn elided_placeholder(&mut self, max_depth: usize, elided: &mut usize) -> String {
+        if *elided == 0 {
+            return String::new();
+        }
+        self.indent = max_depth + 1;
+        let output = fmt_indent!(self, "… ({} more nodes)\n", elided);
+        self.indent = 0;
+        *elided = 0;
         output
     }
 
     fn print_declaration(&mut self, declaration: &Declaration) -> String {
+        let mut output = self.print_doc(declaration.doc());
+        output.push_str(&self.print_declaration_node(declaration));
+        output
+    }
+
+    /// Doc comments are printed above the declaration they're for, like `Doc: "Adds two numbers"`.
+    fn print_doc(&mut self, doc: Option<&str>) -> String {
+        match doc {
+            Some(doc) => fmt_indent!(self, "Doc: {:?}\n", doc),
+            None => String::new()
+        }
+    }
+
+    fn print_declaration_node(&mut self, declaration: &Declaration) -> String {
         match declaration {
-            Declaration::Function { name, params, return_type, body, generic_args } => {
-                let mut output = fmt_indent!(self, "Function: {}\n", name);
+            Declaration::Function { name, params, return_type, body, generic_args, where_clauses, is_async, is_variadic, .. } => {
+                let mut output = fmt_indent!(self, "{}: {}\n", if *is_async { "Async Function" } else { "Function" }, name);
                 self.indent += 1;
-                output.push_str(&fmt_indent!(self, "Parameters:\n"));
+                output.push_str(&self.print_generic_args(generic_args));
+                output.push_str(&self.print_collection_label("Parameters", params.is_empty()));
                 for param in params {
                     output.push_str(&fmt_indent!(self, "- {}: {}\n", param.name, self.print_type(&param.param_type)));
+                    // On its own line, since both the type and the value can take up more than one
+                    if let Some(default) = &param.default {
+                        self.indent += 1;
+                        output.push_str(&fmt_indent!(self, "Default:\n"));
+                        output.push_str(&self.print_expression(default));
+                        self.indent -= 1;
+                    }
+                }
+                if *is_variadic {
+                    output.push_str(&fmt_indent!(self, "Variadic: true\n"));
                 }
-                output.push_str(&fmt_indent!(self, "Return Type: {}\n", self.print_type(return_type)));
+                output.push_str(&self.print_type_line("Return Type", return_type));
+                output.push_str(&self.print_where_clauses(where_clauses));
                 output.push_str(&fmt_indent!(self, "Body: "));
                 output.push_str(&self.print_expression(body));
                 self.indent -= 1;
                 output
             }
-            Declaration::Import { path } => {
-                fmt_indent!(self, "Import: {}\n", path.join("."))
+            Declaration::Const { name, const_type, value } => {
+                let mut output = fmt_indent!(self, "Const: {}\n", name);
+                self.indent += 1;
+                output.push_str(&self.print_type_line("Type", const_type));
+                output.push_str(&fmt_indent!(self, "Value:\n"));
+                output.push_str(&self.print_expression(value));
+                self.indent -= 1;
+                output
             }
-            Declaration::Struct { name, elements, generic_args } => {
+            Declaration::Import { path, alias, items } => self.print_import(path, alias, items),
+            Declaration::Struct { name, elements, generic_args, where_clauses, .. } => {
                 let mut output = fmt_indent!(self, "Struct: {}\n", name);
                 self.indent += 1;
-                output.push_str(&fmt_indent!(self, "Elements:\n"));
+                output.push_str(&self.print_generic_args(generic_args));
+                output.push_str(&self.print_where_clauses(where_clauses));
+                output.push_str(&self.print_collection_label("Elements", elements.is_empty()));
                 for element in elements {
                     match element {
-                        StructElement::Field { name, field_type } => {
-                            output.push_str(&fmt_indent!(self, "- {}: {}\n", name, self.print_type(field_type)));
+                        StructElement::Field { name, field_type, visibility } => {
+                            output.push_str(&fmt_indent!(self, "- {} {}: {}\n", visibility, name, self.print_type(field_type)));
                         },
-                        StructElement::Declaration(declaration) => {
+                        StructElement::Declaration { declaration, visibility } => {
+                            output.push_str(&fmt_indent!(self, "{}:\n", visibility));
+                            self.indent += 1;
                             output.push_str(&self.print_declaration(declaration));
+                            self.indent -= 1;
                         }
                     }
                 }
                 self.indent -= 1;
                 output
             },
-            Declaration::TypeDeclaration { name, alias, generic_args } => {
+            Declaration::TypeDeclaration { name, alias, generic_args, where_clauses, .. } => {
                 let mut output = fmt_indent!(self, "Type Declaration: {}\n", name);
                 self.indent += 1;
-                output.push_str(&fmt_indent!(self, "Alias: {}\n", self.print_type(alias)));
-                if !generic_args.is_empty() {
-                    output.push_str(&fmt_indent!(self, "Generic Arguments:\n"));
-                    for arg in generic_args {
-                        output.push_str(&fmt_indent!(self, "- {}\n", arg));
-                    }
-                }
+                output.push_str(&self.print_type_line("Alias", alias));
+                output.push_str(&self.print_generic_args(generic_args));
+                output.push_str(&self.print_where_clauses(where_clauses));
                 self.indent -= 1;
                 output
+            },
+            Declaration::Enum { name, variants, generic_args, where_clauses, .. } => {
+                let mut output = fmt_indent!(self, "Enum: {}\n", name);
+                self.indent += 1;
+                output.push_str(&self.print_generic_args(generic_args));
+                output.push_str(&self.print_where_clauses(where_clauses));
+                output.push_str(&self.print_enum_variants(variants));
+                self.indent -= 1;
+                output
+            }
+        }
+    }
+
+    fn print_generic_args(&mut self, generic_args: &[GenericParam]) -> String {
+        if generic_args.is_empty() {
+            return String::new();
+        }
+        let mut output = fmt_indent!(self, "Generic Arguments:\n");
+        for arg in generic_args {
+            if arg.bounds.is_empty() {
+                output.push_str(&fmt_indent!(self, "- {}\n", arg.name));
+            } else {
+                let bounds = arg.bounds.iter().map(|bound| self.print_type(bound)).collect::<Vec<_>>();
+                output.push_str(&fmt_indent!(self, "- {}: {}\n", arg.name, bounds.join(" + ")));
             }
         }
+        output
+    }
+
+    fn print_label(&mut self, label: &Option<String>) -> String {
+        match label {
+            Some(label) => fmt_indent!(self, "Label: '{}\n", label),
+            None => String::new()
+        }
     }
 
-    fn print_expression(&mut self, expression: &Expression) -> String {
+    fn print_enum_variants(&mut self, variants: &[EnumVariant]) -> String {
+        let mut output = fmt_indent!(self, "Variants:\n");
+        for variant in variants {
+            if variant.associated_types.is_empty() {
+                output.push_str(&fmt_indent!(self, "- {}\n", variant.name));
+            } else {
+                let types = variant.associated_types.iter().map(|ty| self.print_type(ty)).collect::<Vec<_>>();
+                output.push_str(&fmt_indent!(self, "- {}({})\n", variant.name, types.join(", ")));
+            }
+        }
+        output
+    }
+
+    fn print_import(&mut self, path: &[String], alias: &Option<String>, items: &Option<Vec<String>>) -> String {
+        let mut output = match alias {
+            Some(alias) => fmt_indent!(self, "Import: {} as {}\n", path.join("."), alias),
+            None => fmt_indent!(self, "Import: {}\n", path.join("."))
+        };
+        if let Some(items) = items {
+            self.indent += 1;
+            output.push_str(&fmt_indent!(self, "Items:\n"));
+            for item in items {
+                output.push_str(&fmt_indent!(self, "- {}\n", item));
+            }
+            self.indent -= 1;
+        }
+        output
+    }
+
+    /// The label above a list of children, which says so when there aren't any so the dump doesn't look cut off.
+    fn print_collection_label(&mut self, label: &str, is_empty: bool) -> String {
+        if is_empty {
+            fmt_indent!(self, "{}: (none)\n", label)
+        } else {
+            fmt_indent!(self, "{}:\n", label)
+        }
+    }
+
+    fn print_where_clauses(&mut self, where_clauses: &[WhereClause]) -> String {
+        if where_clauses.is_empty() {
+            return String::new();
+        }
+        let mut output = fmt_indent!(self, "Where Clauses:\n");
+        for (name, bounds) in where_clauses {
+            output.push_str(&fmt_indent!(self, "- {}: {}\n", name, bounds.join(" + ")));
+        }
+        output
+    }
+
+    fn print_expression(&mut self, expression: &Spanned<Expression>) -> String {
+        let mut output = self.print_expression_node(expression);
+        self.annotate_expression(&mut output, expression);
+        output
+    }
+
+    /// Adds an expression's span and type to the end of the first line of `output`, if they're being shown.
+    fn annotate_expression(&mut self, output: &mut String, expression: &Spanned<Expression>) {
+        self.annotate_span(output, expression.span);
+
+        let inferred_type = self.inferred_types.as_ref()
+            .and_then(|types| types.get(&(&expression.node as *const Expression)))
+            .cloned();
+        if let Some(ty) = inferred_type {
+            let annotation = format!(" : {}", self.print_type(&ty));
+            Self::annotate(output, &annotation);
+        }
+    }
+
+    fn print_expression_node(&mut self, expression: &Expression) -> String {
         match expression {
-            Expression::Assignment { name: variable, value, .. } => {
-                let mut output = fmt_indent!(self, "Assignment:\n");
+            Expression::Assignment { name: variable, operator, value, .. } => {
+                if self.inline_leaves && let Some(value) = inline_leaf(value) {
+                    let operator = operator.as_ref().map(|operator| operator.to_string()).unwrap_or_default();
+                    return fmt_indent!(self, "Assignment: {} {}= {}\n", variable, operator, value);
+                }
+                let mut output = match operator {
+                    Some(operator) => fmt_indent!(self, "Assignment ({}=):\n", operator),
+                    None => fmt_indent!(self, "Assignment:\n")
+                };
                 self.indent += 1;
                 output.push_str(&fmt_indent!(self, "Variable: {}\n", variable));
                 output.push_str(&fmt_indent!(self, "Value:\n"));
@@ -113,8 +442,37 @@ impl ASTPrinter {
                 self.indent -= 1;
                 output
             },
+            Expression::MemberAssignment { object, member, operator, value } => {
+                let mut output = match operator {
+                    Some(operator) => fmt_indent!(self, "Member Assignment ({}=):\n", operator),
+                    None => fmt_indent!(self, "Member Assignment:\n")
+                };
+                self.indent += 1;
+                output.push_str(&fmt_indent!(self, "Object:\n"));
+                output.push_str(&self.print_expression(object));
+                output.push_str(&fmt_indent!(self, "Member: {}\n", member));
+                output.push_str(&fmt_indent!(self, "Value:\n"));
+                output.push_str(&self.print_expression(value));
+                self.indent -= 1;
+                output
+            },
+            Expression::IndexAssignment { object, index, operator, value } => {
+                let mut output = match operator {
+                    Some(operator) => fmt_indent!(self, "Index Assignment ({}=):\n", operator),
+                    None => fmt_indent!(self, "Index Assignment:\n")
+                };
+                self.indent += 1;
+                output.push_str(&fmt_indent!(self, "Object:\n"));
+                output.push_str(&self.print_expression(object));
+                output.push_str(&fmt_indent!(self, "Index:\n"));
+                output.push_str(&self.print_expression(index));
+                output.push_str(&fmt_indent!(self, "Value:\n"));
+                output.push_str(&self.print_expression(value));
+                self.indent -= 1;
+                output
+            },
             Expression::BinaryOperation { left, operator, right } => {
-                let mut output = fmt_indent!(self, "Binary Operation: {}\n", operator);
+                let mut output = fmt_indent!(self, "Binary Operation: {}{}\n", operator, self.precedence_hint(operator));
                 self.indent += 1;
                 output.push_str(&fmt_indent!(self, "Left:\n"));
                 output.push_str(&self.print_expression(left));
@@ -131,26 +489,61 @@ impl ASTPrinter {
                 self.indent -= 1;
                 output
             },
-            Expression::Block(statements) => {
+            Expression::Block { statements, tail } => {
                 let mut output = fmt_indent!(self, "Block:\n");
                 self.indent += 1;
                 for statement in statements {
                     output.push_str(&self.print_statement(statement));
                 }
+                if let Some(tail) = tail {
+                    output.push_str(&fmt_indent!(self, "Expression (result):\n"));
+                    output.push_str(&self.print_expression(tail));
+                }
                 self.indent -= 1;
                 output
             },
             Expression::BooleanLiteral(value) => {
                 fmt_indent!(self, "Boolean Literal: {}\n", value)
             },
+            Expression::NilLiteral => {
+                fmt_indent!(self, "Nil Literal\n")
+            },
             Expression::CharLiteral(value) => {
-                fmt_indent!(self, "Character Literal: {}\n", value)
+                fmt_indent!(self, "Character Literal: {:?}\n", value)
+            },
+            // Literals are printed how they were written when the parser kept that, like `0xFF`
+            Expression::IntegerLiteral { lexeme: Some(lexeme), .. } => {
+                fmt_indent!(self, "Integer Literal: {}\n", lexeme)
             },
-            Expression::NumberLiteral(value) => {
+            Expression::IntegerLiteral { value, .. } => {
+                fmt_indent!(self, "Integer Literal: {}\n", value)
+            },
+            Expression::NumberLiteral { lexeme: Some(lexeme), .. } => {
+                fmt_indent!(self, "Number Literal: {}\n", lexeme)
+            },
+            Expression::NumberLiteral { value, .. } => {
                 fmt_indent!(self, "Number Literal: {}\n", value)
             },
-            Expression::StringLiteral(value) => {
-                fmt_indent!(self, "String Literal: {}\n", value)
+            Expression::StringLiteral { value, raw: false } => {
+                fmt_indent!(self, "String Literal: {:?}\n", value)
+            },
+            Expression::StringLiteral { value, raw: true } => {
+                fmt_indent!(self, "Raw String Literal: {:?}\n", value)
+            },
+            Expression::InterpolatedString { parts } => {
+                let mut output = self.print_collection_label("Interpolated String", parts.is_empty());
+                self.indent += 1;
+                for part in parts {
+                    match part {
+                        StringPart::Literal(text) => output.push_str(&fmt_indent!(self, "Literal: {:?}\n", text)),
+                        StringPart::Expr(expression) => {
+                            output.push_str(&fmt_indent!(self, "Expression:\n"));
+                            output.push_str(&self.print_expression(expression));
+                        }
+                    }
+                }
+                self.indent -= 1;
+                output
             },
             Expression::FunctionCall { callee, args } => {
                 let mut output = fmt_indent!(self, "Function Call\n");
@@ -158,7 +551,20 @@ impl ASTPrinter {
                 output.push_str(&fmt_indent!(self, "Callee:\n"));
                 output.push_str(&self.print_expression(callee));
 
-                output.push_str(&fmt_indent!(self, "Arguments:\n"));
+                output.push_str(&self.print_collection_label("Arguments", args.is_empty()));
+                for arg in args {
+                    output.push_str(&self.print_expression(arg));
+                }
+                self.indent -= 1;
+                output
+            },
+            Expression::MethodCall { receiver, method, args } => {
+                let mut output = fmt_indent!(self, "Method Call: {}\n", method);
+                self.indent += 1;
+                output.push_str(&fmt_indent!(self, "Receiver:\n"));
+                output.push_str(&self.print_expression(receiver));
+
+                output.push_str(&self.print_collection_label("Arguments", args.is_empty()));
                 for arg in args {
                     output.push_str(&self.print_expression(arg));
                 }
@@ -175,6 +581,35 @@ impl ASTPrinter {
                 output.push_str(&self.print_expression(condition));
                 output.push_str(&fmt_indent!(self, "Then Branch:\n"));
                 output.push_str(&self.print_expression(then_branch));
+
+                // Else-if chains are printed flat, instead of nesting each if in the previous one's else branch.
+                // Ifs wrapped in a block are left alone, since the block is part of the tree.
+                let mut else_branch = else_branch;
+                while let Some(branch) = else_branch {
+                    let Expression::If { condition, then_branch, else_branch: next } = &branch.node else {
+                        output.push_str(&fmt_indent!(self, "Else Branch:\n"));
+                        output.push_str(&self.print_expression(branch));
+                        break;
+                    };
+                    let mut header = fmt_indent!(self, "Else If:\n");
+                    self.annotate_expression(&mut header, branch);
+                    output.push_str(&header);
+                    output.push_str(&self.print_expression(condition));
+                    output.push_str(&fmt_indent!(self, "Then Branch:\n"));
+                    output.push_str(&self.print_expression(then_branch));
+                    else_branch = next;
+                }
+                self.indent -= 1;
+                output
+            },
+            Expression::IfLet { pattern, value, then_branch, else_branch } => {
+                let mut output = fmt_indent!(self, "If Let:\n");
+                self.indent += 1;
+                output.push_str(&fmt_indent!(self, "Pattern: {}\n", pattern));
+                output.push_str(&fmt_indent!(self, "Value:\n"));
+                output.push_str(&self.print_expression(value));
+                output.push_str(&fmt_indent!(self, "Then Branch:\n"));
+                output.push_str(&self.print_expression(then_branch));
                 if let Some(else_branch) = else_branch {
                     output.push_str(&fmt_indent!(self, "Else Branch:\n"));
                     output.push_str(&self.print_expression(else_branch));
@@ -182,16 +617,18 @@ impl ASTPrinter {
                 self.indent -= 1;
                 output
             },
-            Expression::Loop(LoopType::Infinite { body }) => {
+            Expression::Loop(LoopType::Infinite { body, label }) => {
                 let mut output = fmt_indent!(self, "Infinite Loop:\n");
                 self.indent += 1;
+                output.push_str(&self.print_label(label));
                 output.push_str(&self.print_expression(body));
                 self.indent -= 1;
                 output
             },
-            Expression::Loop(LoopType::While { condition, body }) => {
+            Expression::Loop(LoopType::While { condition, body, label }) => {
                 let mut output = fmt_indent!(self, "While Loop:\n");
                 self.indent += 1;
+                output.push_str(&self.print_label(label));
                 output.push_str(&fmt_indent!(self, "Condition:\n"));
                 output.push_str(&self.print_expression(condition));
                 output.push_str(&fmt_indent!(self, "Body: "));
@@ -199,9 +636,10 @@ impl ASTPrinter {
                 self.indent -= 1;
                 output
             },
-            Expression::Loop(LoopType::Iterator { mutability, iterator, iterable, body }) => {
+            Expression::Loop(LoopType::Iterator { mutability, iterator, iterable, body, label }) => {
                 let mut output = fmt_indent!(self, "Iterator Loop:\n");
                 self.indent += 1;
+                output.push_str(&self.print_label(label));
                 output.push_str(&fmt_indent!(self, "Mutability: {}\n", match mutability {
                     VariableMutability::Mutable => "Mutable",
                     VariableMutability::Immutable => "Immutable",
@@ -223,10 +661,24 @@ impl ASTPrinter {
                 self.indent -= 1;
                 output
             }
+            Expression::Index { object, index } => {
+                let mut output = fmt_indent!(self, "Index Access:\n");
+                self.indent += 1;
+                output.push_str(&fmt_indent!(self, "Object:\n"));
+                output.push_str(&self.print_expression(object));
+                output.push_str(&fmt_indent!(self, "Index:\n"));
+                output.push_str(&self.print_expression(index));
+                self.indent -= 1;
+                output
+            }
             Expression::Array { array_type, size, initial_value } => {
+                if self.inline_constant_arrays
+                    && let (Some(size), Some(initial_value)) = (inline_literal(size), inline_literal(initial_value)) {
+                    return fmt_indent!(self, "Array: [{}; {}]\n", initial_value, size);
+                }
                 let mut output = fmt_indent!(self, "Array:\n");
                 self.indent += 1;
-                output.push_str(&fmt_indent!(self, "Type: {}\n", self.print_type(array_type)));
+                output.push_str(&self.print_type_line("Type", array_type));
                 output.push_str(&fmt_indent!(self, "Size:\n"));
                 output.push_str(&self.print_expression(size));
                 output.push_str(&fmt_indent!(self, "Initial Value:\n"));
@@ -237,8 +689,8 @@ impl ASTPrinter {
             Expression::StructCreation { struct_type, fields } => {
                 let mut output = fmt_indent!(self, "Struct Creation:\n");
                 self.indent += 1;
-                output.push_str(&fmt_indent!(self, "Type: {}\n", self.print_type(struct_type)));
-                output.push_str(&fmt_indent!(self, "Fields:\n"));
+                output.push_str(&self.print_type_line("Type", struct_type));
+                output.push_str(&self.print_collection_label("Fields", fields.is_empty()));
                 for (name, value) in fields {
                     output.push_str(&fmt_indent!(self, "{}:\n", name));
                     self.indent += 1;
@@ -247,28 +699,121 @@ impl ASTPrinter {
                 }
                 self.indent -= 1;
                 output
+            },
+            Expression::ArrayLiteral(elements) => {
+                let mut output = fmt_indent!(self, "Array Literal:\n");
+                self.indent += 1;
+                for element in elements {
+                    output.push_str(&self.print_expression(element));
+                }
+                self.indent -= 1;
+                output
+            },
+            Expression::Tuple(elements) => {
+                let mut output = fmt_indent!(self, "Tuple:\n");
+                self.indent += 1;
+                for element in elements {
+                    output.push_str(&self.print_expression(element));
+                }
+                self.indent -= 1;
+                output
+            },
+            Expression::SizeOf(ty) => {
+                fmt_indent!(self, "Size Of: {}\n", self.print_type(ty))
+            },
+            Expression::TypeOf(operand) => {
+                let mut output = fmt_indent!(self, "Type Of:\n");
+                self.indent += 1;
+                output.push_str(&self.print_expression(operand));
+                self.indent -= 1;
+                output
+            },
+            Expression::Cast { value, target_type } => {
+                let mut output = fmt_indent!(self, "Cast:\n");
+                self.indent += 1;
+                output.push_str(&fmt_indent!(self, "Value:\n"));
+                output.push_str(&self.print_expression(value));
+                output.push_str(&self.print_type_line("Target Type", target_type));
+                self.indent -= 1;
+                output
+            },
+            Expression::Await(operand) => {
+                let mut output = fmt_indent!(self, "Await:\n");
+                self.indent += 1;
+                output.push_str(&self.print_expression(operand));
+                self.indent -= 1;
+                output
+            },
+            Expression::Grouping(inner) => {
+                let mut output = fmt_indent!(self, "Grouping:\n");
+                self.indent += 1;
+                output.push_str(&self.print_expression(inner));
+                self.indent -= 1;
+                output
+            },
+            Expression::Try { operand } => {
+                let mut output = fmt_indent!(self, "Try:\n");
+                self.indent += 1;
+                output.push_str(&fmt_indent!(self, "Operand:\n"));
+                output.push_str(&self.print_expression(operand));
+                self.indent -= 1;
+                output
+            },
+            Expression::Match { scrutinee, arms } => {
+                let mut output = fmt_indent!(self, "Match:\n");
+                self.indent += 1;
+                output.push_str(&fmt_indent!(self, "Scrutinee:\n"));
+                output.push_str(&self.print_expression(scrutinee));
+                for arm in arms {
+                    output.push_str(&fmt_indent!(self, "Arm: {}\n", arm.pattern));
+                    self.indent += 1;
+                    output.push_str(&self.print_expression(&arm.body));
+                    self.indent -= 1;
+                }
+                self.indent -= 1;
+                output
+            },
+            Expression::Range { start, end, inclusive } => {
+                let mut output = fmt_indent!(self, "Range ({}):\n", if *inclusive { "inclusive" } else { "exclusive" });
+                self.indent += 1;
+                output.push_str(&fmt_indent!(self, "Start:\n"));
+                output.push_str(&self.print_expression(start));
+                output.push_str(&fmt_indent!(self, "End:\n"));
+                output.push_str(&self.print_expression(end));
+                self.indent -= 1;
+                output
             }
         }
     }
 
-    fn print_statement(&mut self, statement: &Statement) -> String {
+    fn print_statement(&mut self, statement: &Spanned<Statement>) -> String {
+        let mut output = self.print_statement_node(statement);
+        self.annotate_span(&mut output, statement.span);
+        output
+    }
+
+    fn print_statement_node(&mut self, statement: &Statement) -> String {
         match statement {
             Statement::Declaration(declaration) => {
                 self.print_declaration(declaration)
             },
-            Statement::Break => {
-                fmt_indent!(self, "Break\n")
+            Statement::Break(label) => {
+                fmt_indent!(self, "Break{}\n", jump_label(label))
             },
-            Statement::Continue => {
-                fmt_indent!(self, "Continue\n")
+            Statement::Continue(label) => {
+                fmt_indent!(self, "Continue{}\n", jump_label(label))
             },
-            Statement::Expression { expression, result } => {
-                let mut output = fmt_indent!(self, "Expression:\n");
+            Statement::Defer(value) => {
+                let mut output = fmt_indent!(self, "Defer:\n");
+                self.indent += 1;
+                output.push_str(&self.print_expression(value));
+                self.indent -= 1;
+                output
+            },
+            Statement::Expression { expression } => {
+                let mut output = fmt_indent!(self, "Expression (discarded):\n");
                 self.indent += 1;
                 output.push_str(&self.print_expression(expression));
-                if *result {
-                    output.push_str(&fmt_indent!(self, "Result: true\n"));
-                }
                 self.indent -= 1;
                 output
             },
@@ -290,9 +835,11 @@ impl ASTPrinter {
                     VariableMutability::Mutable => "Mutable",
                     VariableMutability::Immutable => "Immutable",
                 }));
-                output.push_str(&fmt_indent!(self, "Type: {}\n", self.print_type(variable_type)));
-                output.push_str(&fmt_indent!(self, "Value:\n"));
-                output.push_str(&self.print_expression(value));
+                output.push_str(&self.print_type_line("Type", variable_type));
+                if let Some(value) = value {
+                    output.push_str(&fmt_indent!(self, "Value:\n"));
+                    output.push_str(&self.print_expression(value));
+                }
                 self.indent -= 1;
                 output
             }
@@ -300,6 +847,9 @@ impl ASTPrinter {
     }
 
     fn print_type(&mut self, ty: &Type) -> String {
+        if self.source_types && let Some(name) = primitive_name(ty) {
+            return name.to_string();
+        }
         match ty {
             Type::Boolean => "Boolean".to_string(),
             Type::Character => "Character".to_string(),
@@ -314,6 +864,8 @@ impl ASTPrinter {
             Type::U32 => "U32".to_string(),
             Type::U64 => "U64".to_string(),
             Type::Nil => "Nil".to_string(),
+            Type::Never => "Never".to_string(),
+            Type::Inferred => "<inferred>".to_string(),
             Type::Identifier { name, generics: generic_args } => {
                 let mut output = name.clone();
                 if !generic_args.is_empty() {
@@ -323,25 +875,1400 @@ impl ASTPrinter {
                 }
                 output
             },
-            Type::Array(of) => {
-                let mut output = "\n".to_string();
-                output.push_str(&fmt_indent!(self, "Array of "));
+            Type::Array { element, size } => {
+                let element = self.print_type(element);
+                let size = size.map(|size| format!("; {}", size)).unwrap_or_default();
+                match self.array_style {
+                    ArrayStyle::Brackets => format!("[{}{}]", element, size),
+                    ArrayStyle::Generic => format!("Array<{}{}>", element, size)
+                }
+            },
+            Type::Reference { mutable, inner } => {
                 self.indent += 1;
-                output.push_str(&self.print_type(of));
+                let inner = self.print_type(inner);
                 self.indent -= 1;
-                output
-            }
-            Type::Function { params, return_type } => {
-                let mut output = fmt_indent!(self, "Function:\n");
-                self.indent += 1;
-                output.push_str(&fmt_indent!(self, "Parameters:\n"));
-                for param in params {
-                    output.push_str(&fmt_indent!(self, "- {}\n", self.print_type(&param)));
+                if !inner.contains('\n') {
+                    return format!("&{}{}", if *mutable { "mut " } else { "" }, inner);
                 }
-                output.push_str(&fmt_indent!(self, "Return Type: {}\n", self.print_type(return_type)));
-                self.indent -= 1;
+                // Types that take up multiple lines, like function types, get a block
+                let mut output = "\n".to_string();
+                output.push_str(&fmt_indent!(self, "{} ", if *mutable { "Mutable Reference to" } else { "Reference to" }));
+                output.push_str(&inner);
                 output
+            },
+            Type::Record(fields) => {
+                let fields = fields.iter().map(|(name, field_type)| format!("{}: {}", name, self.print_type(field_type))).collect::<Vec<_>>();
+                format!("{{ {} }}", fields.join(", "))
+            },
+            Type::Tuple(elements) => {
+                let elements = elements.iter().map(|element| self.print_type(element)).collect::<Vec<_>>();
+                if elements.len() == 1 {
+                    format!("({},)", elements[0])
+                } else {
+                    format!("({})", elements.join(", "))
+                }
+            },
+            Type::Optional(inner) => format!("{}?", self.print_type(inner)),
+            Type::Map { key, value } => format!("Map<{}, {}>", self.print_type(key), self.print_type(value)),
+            Type::Function { params, return_type } => {
+                self.print_function_type(params, return_type, None)
+            }
+        }
+    }
+
+    /// Prints a type, naming the parameters of function types.
+    /// Function types don't store their parameter names, so this is for when the caller knows them (e.g. from a declaration).
+    pub fn print_type_with_param_names(&mut self, ty: &Type, param_names: &[String]) -> String {
+        match ty {
+            Type::Function { params, return_type } => self.print_function_type(params, return_type, Some(param_names)),
+            _ => self.print_type(ty)
+        }
+    }
+
+    fn print_function_type(&mut self, params: &[Type], return_type: &Type, param_names: Option<&[String]>) -> String {
+        let mut output = fmt_indent!(self, "Function:\n");
+        self.indent += 1;
+        output.push_str(&self.print_collection_label("Parameters", params.is_empty()));
+        for (i, param) in params.iter().enumerate() {
+            match param_names.and_then(|names| names.get(i)) {
+                Some(name) => output.push_str(&fmt_indent!(self, "- {}: {}\n", name, self.print_type(param))),
+                None => output.push_str(&fmt_indent!(self, "- {}\n", self.print_type(param)))
+            }
+        }
+        output.push_str(&fmt_indent!(self, "Return Type: {}\n", self.print_type(return_type)));
+        self.indent -= 1;
+        output
+    }
+}
+
+/// How a literal is written when it's printed inline, or `None` if the expression isn't a literal.
+fn inline_literal(expression: &Expression) -> Option<String> {
+    match expression {
+        Expression::IntegerLiteral { value, .. } => Some(value.to_string()),
+        Expression::NumberLiteral { value, .. } => Some(value.to_string()),
+        Expression::BooleanLiteral(value) => Some(value.to_string()),
+        Expression::NilLiteral => Some("nil".to_string()),
+        Expression::CharLiteral(value) => Some(format!("{:?}", value)),
+        Expression::StringLiteral { value, raw: false } => Some(format!("{:?}", value)),
+        Expression::StringLiteral { value, raw: true } => Some(format!("r{:?}", value)),
+        _ => None
+    }
+}
+
+/// The end of a `break` or `continue` line, naming the loop it jumps to if it has a label.
+fn jump_label(label: &Option<String>) -> String {
+    match label {
+        Some(label) => format!(": '{}", label),
+        None => String::new()
+    }
+}
+
+/// How a literal or variable is written when it's printed inline, or `None` if the expression has children.
+fn inline_leaf(expression: &Expression) -> Option<String> {
+    match expression {
+        Expression::Variable { name, .. } => Some(name.clone()),
+        _ => inline_literal(expression)
+    }
+}
+
+/// Produces a unified diff of two programs' printed trees (without colors), for reviewing how a change affects the AST.
+pub fn diff_programs(a: &Program, b: &Program) -> String {
+    let old = ASTPrinter::new().with_color(false).print_program(a);
+    let new = ASTPrinter::new().with_color(false).print_program(b);
+    unified_diff(&old.lines().collect::<Vec<_>>(), &new.lines().collect::<Vec<_>>(), 3)
+}
+
+enum DiffLine<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str)
+}
+
+/// Finds a minimal set of line changes that turns `old` into `new`, using the longest common subsequence.
+fn diff_lines<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<DiffLine<'a>> {
+    // lengths[i][j] is the length of the longest common subsequence of old[i..] and new[j..]
+    let mut lengths = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i][j] = if old[i] == new[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let mut lines = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            lines.push(DiffLine::Same(old[i]));
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            lines.push(DiffLine::Removed(old[i]));
+            i += 1;
+        } else {
+            lines.push(DiffLine::Added(new[j]));
+            j += 1;
+        }
+    }
+    lines.extend(old[i..].iter().map(|line| DiffLine::Removed(line)));
+    lines.extend(new[j..].iter().map(|line| DiffLine::Added(line)));
+    lines
+}
+
+fn unified_diff(old: &[&str], new: &[&str], context: usize) -> String {
+    let lines = diff_lines(old, new);
+
+    // Group changes that are close enough to share their context lines into hunks
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for (index, line) in lines.iter().enumerate() {
+        if let DiffLine::Same(_) = line {
+            continue;
+        }
+        let start = index.saturating_sub(context);
+        let end = (index + context).min(lines.len() - 1);
+        match hunks.last_mut() {
+            Some((_, last_end)) if start <= *last_end + 1 => *last_end = end,
+            _ => hunks.push((start, end))
+        }
+    }
+
+    if hunks.is_empty() {
+        return String::new();
+    }
+
+    let mut output = "--- a\n+++ b\n".to_string();
+    for (start, end) in hunks {
+        let old_start = lines[..start].iter().filter(|line| !matches!(line, DiffLine::Added(_))).count();
+        let new_start = lines[..start].iter().filter(|line| !matches!(line, DiffLine::Removed(_))).count();
+        let old_length = lines[start..=end].iter().filter(|line| !matches!(line, DiffLine::Added(_))).count();
+        let new_length = lines[start..=end].iter().filter(|line| !matches!(line, DiffLine::Removed(_))).count();
+
+        // Line numbers are 1-based, except that an empty range names the line before it
+        output.push_str(&format!("@@ -{},{} +{},{} @@\n",
+            if old_length == 0 { old_start } else { old_start + 1 }, old_length,
+            if new_length == 0 { new_start } else { new_start + 1 }, new_length
+        ));
+        for line in &lines[start..=end] {
+            match line {
+                DiffLine::Same(line) => output.push_str(&format!(" {}\n", line)),
+                DiffLine::Removed(line) => output.push_str(&format!("-{}\n", line)),
+                DiffLine::Added(line) => output.push_str(&format!("+{}\n", line))
+            }
+        }
+    }
+    output
+}
+
+/// Takes the span markers out of a printed tree, returning the plain tree and where each node in it came from.
+fn take_span_markers(marked: &str) -> (String, Vec<(usize, Span)>) {
+    let mut output = String::new();
+    let mut map = Vec::new();
+    for (line_number, line) in marked.lines().enumerate() {
+        let mut span = None;
+        // Markers alternate with the printed text. When a line starts more than one node, like `Body: |  Block:`,
+        // the outermost node is annotated last.
+        for (i, part) in line.split(SPAN_MARKER).enumerate() {
+            if i % 2 == 0 {
+                output.push_str(part);
+            } else if let Some((start, end)) = part.split_once(':') {
+                span = Some(Span { start: start.parse().unwrap(), end: end.parse().unwrap() });
+            }
+        }
+        output.push('\n');
+        if let Some(span) = span {
+            map.push((line_number, span));
+        }
+    }
+    (output, map)
+}
+
+/// Removes the ANSI escape codes the printer adds.
+fn strip_ansi(input: &str) -> String {
+    let mut output = String::new();
+    let mut chars = input.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // Skip until the end of the escape sequence
+            while chars.next().is_some_and(|c| c != 'm') {}
+        } else {
+            output.push(c);
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser::{ast::ExpressionId, Parser}, tokenizer::Tokenizer};
+
+    #[test]
+    fn test_plain_output() {
+        let mut tokenizer = Tokenizer::new("func main() -> i32 { if (true) { 1 } else { -2 } }".to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let program = Parser::new(tokens).parse_program().unwrap();
+
+        let colored = ASTPrinter::new().print_program(&program);
+        assert!(colored.contains('\x1b'));
+        let plain = ASTPrinter::new().with_color(false).print_program(&program);
+        assert!(!plain.contains('\x1b'));
+        assert_eq!(plain, strip_ansi(&colored));
+    }
+
+    #[test]
+    fn test_escaped_literals() {
+        // A newline and a tab written straight into the literals, since the tokenizer doesn't turn `\n` into one yet
+        let mut tokenizer = Tokenizer::new("func main() -> nil { print(\"say \\\"hi\\\"\n\tthere\"); print('\n'); }".to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let program = Parser::new(tokens).parse_program().unwrap();
+
+        let output = ASTPrinter::new().with_color(false).print_program(&program);
+        assert!(output.contains("|  |  |  |  String Literal: \"say \\\"hi\\\"\\n\\tthere\"\n"));
+        assert!(output.contains("|  |  |  |  Character Literal: '\\n'\n"));
+        // Every line is still part of the tree
+        assert!(output.lines().all(|line| line.is_empty() || line.starts_with('|') || line.starts_with("Function")));
+    }
+
+    #[test]
+    fn test_doc_comments() {
+        let mut tokenizer = Tokenizer::new(r#"
+            /// Adds two numbers.
+            /// Overflow wraps around.
+            func add(a: i32, b: i32) -> i32 { a + b }
+
+            // Not a doc comment
+            struct Counter {
+                /// Bumps the count.
+                pub func bump(counter: Counter) -> nil {}
+            }
+        "#.to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let program = Parser::new(tokens).parse_program().unwrap();
+
+        let output = ASTPrinter::new().with_color(false).print_program(&program);
+        assert!(output.starts_with("Doc: \"Adds two numbers.\\nOverflow wraps around.\"\nFunction: add\n"), "{}", output);
+        assert!(output.contains("\nStruct: Counter\n|  Elements:\n|  Public:\n|  |  Doc: \"Bumps the count.\"\n|  |  Function: bump\n"), "{}", output);
+        assert_eq!(output.matches("Doc:").count(), 2);
+        assert_eq!(output, ASTPrinter::new().with_color(false).print_arena_program(&ArenaProgram::from_program(program)));
+    }
+
+    #[test]
+    fn test_default_parameters() {
+        let mut tokenizer = Tokenizer::new("func scale(value: f64, factor: f64 = 2.5) -> f64 { value * factor }".to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut program = Parser::new(tokens).parse_program().unwrap();
+
+        let output = ASTPrinter::new().with_color(false).print_program(&program);
+        assert!(output.starts_with(&[
+            "Function: scale",
+            "|  Parameters:",
+            "|  - value: F64",
+            "|  - factor: F64",
+            "|  |  Default:",
+            "|  |  Number Literal: 2.5",
+            "|  Return Type: F64\n"
+        ].join("\n")), "{}", output);
+
+        // There's no syntax for function types yet, but a parameter with one can still have a default
+        let Declaration::Function { params, .. } = &mut program.declarations[0].node else { unreachable!() };
+        params[1].param_type = Type::Function { params: vec![Type::F64], return_type: Box::new(Type::F64) };
+        params[1].default = Some(Box::new(Expression::Variable { name: "double".to_string(), expression_id: ExpressionId(0) }.into()));
+        let output = ASTPrinter::new().with_color(false).print_program(&program);
+        assert!(output.contains("|  |  Return Type: F64\n\n|  |  Default:\n|  |  Variable: double\n|  Return Type: F64\n"), "{}", output);
+    }
+
+    #[test]
+    fn test_variadic_functions() {
+        let mut tokenizer = Tokenizer::new(r#"
+            func printf(format: String, ...values: i32) -> nil {}
+            func print_one(value: i32) -> nil {}
+        "#.to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let program = Parser::new(tokens).parse_program().unwrap();
+
+        let output = ASTPrinter::new().with_color(false).print_program(&program);
+        assert!(output.starts_with(&[
+            "Function: printf",
+            "|  Parameters:",
+            "|  - format: String",
+            "|  - values: I32",
+            "|  Variadic: true",
+            "|  Return Type: Nil\n"
+        ].join("\n")), "{}", output);
+        assert_eq!(output.matches("Variadic").count(), 1);
+        let output = ASTPrinter::new().with_color(false).print_arena_program(&ArenaProgram::from_program(program));
+        assert_eq!(output.matches("|  Variadic: true\n").count(), 1);
+    }
+
+    #[test]
+    fn test_generic_bounds() {
+        let mut tokenizer = Tokenizer::new(r#"
+            func largest<T: Comparable + Copy, U>(a: T, b: U) -> T { a }
+            struct Cell<T: Comparable> {
+                value: T;
+            }
+        "#.to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let program = Parser::new(tokens).parse_program().unwrap();
+
+        let output = ASTPrinter::new().with_color(false).print_program(&program);
+        assert!(output.starts_with(&[
+            "Function: largest",
+            "|  Generic Arguments:",
+            "|  - T: Comparable + Copy",
+            "|  - U",
+            "|  Parameters:\n"
+        ].join("\n")), "{}", output);
+        assert!(output.contains(&[
+            "Struct: Cell",
+            "|  Generic Arguments:",
+            "|  - T: Comparable",
+            "|  Elements:\n"
+        ].join("\n")), "{}", output);
+        assert_eq!(output, ASTPrinter::new().with_color(false).print_arena_program(&ArenaProgram::from_program(program)));
+    }
+
+    #[test]
+    fn test_max_depth() {
+        let mut tokenizer = Tokenizer::new(r#"
+            func main(limit: i32) -> nil {
+                loop (const i: 0..limit) {
+                    print(i * 2);
+                };
+            }
+            const LIMIT: i32 = 10;
+        "#.to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let program = Parser::new(tokens).parse_program().unwrap();
+
+        let full = ASTPrinter::new().with_color(false).print_program(&program);
+        let depth_0 = ASTPrinter::new().with_color(false).with_max_depth(0).print_program(&program);
+        let depth_2 = ASTPrinter::new().with_color(false).with_max_depth(2).print_program(&program);
+        // Labels like `Value:` and parameter lines aren't nodes, so the constant only has its type and value left out
+        assert_eq!(depth_0, "Function: main\n|  … (14 more nodes)\nConst: LIMIT\n|  … (2 more nodes)\n");
+        assert!(depth_2.starts_with(&[
+            "Function: main",
+            "|  Parameters:",
+            "|  - limit: I32",
+            "|  Return Type: Nil",
+            "|  Body: |  Block:",
+            "|  |  Expression (discarded):",
+            "|  |  |  … (11 more nodes)",
+            "Const: LIMIT\n"
+        ].join("\n")), "{}", depth_2);
+        // Every line that's kept is the same as in the full output
+        let kept: Vec<&str> = depth_2.lines().filter(|line| !line.contains('…')).collect();
+        assert_eq!(kept, full.lines().filter(|line| kept.contains(line)).collect::<Vec<_>>());
+        assert!(full.lines().count() > depth_2.lines().count());
+        // A depth past the deepest node changes nothing
+        assert_eq!(ASTPrinter::new().with_color(false).with_max_depth(20).print_program(&program), full);
+    }
+
+    #[test]
+    fn test_casts() {
+        let mut tokenizer = Tokenizer::new("func main(a: i32, b: i32) -> nil { print(a as f32); print(a + b as f32); print((a + b) as f32); }".to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let program = Parser::new(tokens).parse_program().unwrap();
+
+        let output = ASTPrinter::new().with_color(false).print_program(&program);
+        let calls = output.split("Function Call").skip(1).collect::<Vec<_>>();
+        assert!(calls[0].contains(&[
+            "|  |  |  |  Cast:",
+            "|  |  |  |  |  Value:",
+            "|  |  |  |  |  Variable: a",
+            "|  |  |  |  |  Target Type: F32\n"
+        ].join("\n")), "{}", output);
+        // Casts bind tighter than arithmetic, so only `b` is cast here
+        assert!(calls[1].contains(&[
+            "|  |  |  |  Binary Operation: +",
+            "|  |  |  |  |  Left:",
+            "|  |  |  |  |  Variable: a",
+            "|  |  |  |  |  Right:",
+            "|  |  |  |  |  Cast:",
+            "|  |  |  |  |  |  Value:",
+            "|  |  |  |  |  |  Variable: b",
+            "|  |  |  |  |  |  Target Type: F32\n"
+        ].join("\n")), "{}", output);
+        assert!(calls[2].contains(&[
+            "|  |  |  |  Cast:",
+            "|  |  |  |  |  Value:",
+            "|  |  |  |  |  Grouping:",
+            "|  |  |  |  |  |  Binary Operation: +",
+            "|  |  |  |  |  |  |  Left:",
+            "|  |  |  |  |  |  |  Variable: a",
+            "|  |  |  |  |  |  |  Right:",
+            "|  |  |  |  |  |  |  Variable: b",
+            "|  |  |  |  |  Target Type: F32\n"
+        ].join("\n")), "{}", output);
+    }
+
+    #[test]
+    fn test_raw_strings() {
+        let mut tokenizer = Tokenizer::new(r#"func main() -> nil { print(r"C:/files"); print("C:/files"); }"#.to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let program = Parser::new(tokens).parse_program().unwrap();
+
+        let output = ASTPrinter::new().with_color(false).print_program(&program);
+        assert!(output.contains("|  |  |  |  Raw String Literal: \"C:/files\"\n"));
+        assert!(output.contains("|  |  |  |  String Literal: \"C:/files\"\n"));
+        let output = ASTPrinter::new().with_color(false).print_arena_program(&ArenaProgram::from_program(program));
+        assert!(output.contains("Raw String Literal: \"C:/files\"\n"));
+    }
+
+    #[test]
+    fn test_function_type_param_names() {
+        let function_type = Type::Function {
+            params: vec![Type::I32, Type::I32],
+            return_type: Box::new(Type::F64)
+        };
+
+        let mut printer = ASTPrinter::new();
+        assert_eq!(
+            strip_ansi(&printer.print_type(&function_type)),
+            "Function:\n|  Parameters:\n|  - I32\n|  - I32\n|  Return Type: F64\n"
+        );
+        assert_eq!(
+            strip_ansi(&printer.print_type_with_param_names(&function_type, &["x".to_string(), "y".to_string()])),
+            "Function:\n|  Parameters:\n|  - x: I32\n|  - y: I32\n|  Return Type: F64\n"
+        );
+    }
+
+    #[test]
+    fn test_references() {
+        let mut tokenizer = Tokenizer::new("func fill(values: &mut [u8], n: &i32) -> nil {}".to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let program = Parser::new(tokens).parse_program().unwrap();
+
+        let output = ASTPrinter::new().with_color(false).print_program(&program);
+        assert!(output.contains("|  - values: &mut [U8]\n"), "{}", output);
+        assert!(output.contains("|  - n: &I32\n"), "{}", output);
+
+        let mut printer = ASTPrinter::new().with_color(false);
+        let reference = Type::Reference { mutable: true, inner: Box::new(Type::I32) };
+        assert_eq!(printer.print_type(&reference), "&mut I32");
+    }
+
+    #[test]
+    fn test_optional_and_map_types() {
+        let string = Type::Identifier { name: "String".to_string(), generics: vec![] };
+        let optional = Type::Optional(Box::new(Type::I32));
+        let map = Type::Map { key: Box::new(string.clone()), value: Box::new(Type::I32) };
+        let nested = Type::Map { key: Box::new(string), value: Box::new(optional.clone()) };
+
+        let mut printer = ASTPrinter::new().with_color(false);
+        assert_eq!(printer.print_type(&optional), "I32?");
+        assert_eq!(printer.print_type(&map), "Map<String, I32>");
+        assert_eq!(printer.print_type(&nested), "Map<String, I32?>");
+
+        let mut tokenizer = Tokenizer::new("func lookup(scores: Map<String, i32?>) -> i32? { nil }".to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let program = Parser::new(tokens).parse_program().unwrap();
+        let Declaration::Function { params, return_type, .. } = &program.declarations[0].node else { unreachable!() };
+        assert_eq!(params[0].param_type, nested);
+        assert_eq!(*return_type, optional);
+    }
+
+    #[test]
+    fn test_nil_literals() {
+        let mut tokenizer = Tokenizer::new("func nothing() -> nil { return nil; } func value() -> nil { nil }".to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let program = Parser::new(tokens).parse_program().unwrap();
+        let output = ASTPrinter::new().with_color(false).print_program(&program);
+        assert_eq!(output, [
+            "Function: nothing\n",
+            "|  Parameters: (none)\n",
+            "|  Return Type: Nil\n",
+            "|  Body: |  Block:\n",
+            "|  |  Return:\n",
+            "|  |  |  Nil Literal\n",
+            "Function: value\n",
+            "|  Parameters: (none)\n",
+            "|  Return Type: Nil\n",
+            "|  Body: |  Block:\n",
+            "|  |  Expression (result):\n",
+            "|  |  Nil Literal\n"
+        ].concat());
+    }
+
+    #[test]
+    fn test_empty_collections() {
+        let mut tokenizer = Tokenizer::new("struct Empty {} func main() -> nil { print(); new Empty {}; }".to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let program = Parser::new(tokens).parse_program().unwrap();
+        let output = ASTPrinter::new().with_color(false).print_program(&program);
+        assert_eq!(output, [
+            "Struct: Empty\n",
+            "|  Elements: (none)\n",
+            "Function: main\n",
+            "|  Parameters: (none)\n",
+            "|  Return Type: Nil\n",
+            "|  Body: |  Block:\n",
+            "|  |  Expression (discarded):\n",
+            "|  |  |  Function Call\n",
+            "|  |  |  |  Callee:\n",
+            "|  |  |  |  Variable: print\n",
+            "|  |  |  |  Arguments: (none)\n",
+            "|  |  Expression (discarded):\n",
+            "|  |  |  Struct Creation:\n",
+            "|  |  |  |  Type: Empty\n",
+            "|  |  |  |  Fields: (none)\n"
+        ].concat());
+        assert_eq!(ASTPrinter::new().with_color(false).print_arena_program(&ArenaProgram::from_program(program)), output);
+    }
+
+    #[test]
+    fn test_if_let() {
+        let source = r#"
+            func main(maybe_value: i32?) -> i32 {
+                if (let value = maybe_value) {
+                    print(value);
+                };;
+                if (let 0 = maybe_value) 1 else 2
+            }
+        "#;
+        let mut tokenizer = Tokenizer::new(source.to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let program = Parser::new(tokens).parse_program().unwrap();
+        let output = ASTPrinter::new().with_color(false).print_program(&program);
+        assert_eq!(output, [
+            "Function: main\n",
+            "|  Parameters:\n",
+            "|  - maybe_value: I32?\n",
+            "|  Return Type: I32\n",
+            "|  Body: |  Block:\n",
+            "|  |  Expression (discarded):\n",
+            "|  |  |  If Let:\n",
+            "|  |  |  |  Pattern: value\n",
+            "|  |  |  |  Value:\n",
+            "|  |  |  |  Variable: maybe_value\n",
+            "|  |  |  |  Then Branch:\n",
+            "|  |  |  |  Block:\n",
+            "|  |  |  |  |  Expression (discarded):\n",
+            "|  |  |  |  |  |  Function Call\n",
+            "|  |  |  |  |  |  |  Callee:\n",
+            "|  |  |  |  |  |  |  Variable: print\n",
+            "|  |  |  |  |  |  |  Arguments:\n",
+            "|  |  |  |  |  |  |  Variable: value\n",
+            "|  |  Expression (result):\n",
+            "|  |  If Let:\n",
+            "|  |  |  Pattern: 0\n",
+            "|  |  |  Value:\n",
+            "|  |  |  Variable: maybe_value\n",
+            "|  |  |  Then Branch:\n",
+            "|  |  |  Integer Literal: 1\n",
+            "|  |  |  Else Branch:\n",
+            "|  |  |  Integer Literal: 2\n"
+        ].concat());
+        assert_eq!(ASTPrinter::new().with_color(false).print_arena_program(&ArenaProgram::from_program(program)), output);
+    }
+
+    #[test]
+    fn test_array_literals() {
+        let source = "func main() -> nil { const values: [i32] = [1, 2, 3]; const empty: [i32] = []; const filled: [i32] = [i32, 3] { 0 }; }";
+        let mut tokenizer = Tokenizer::new(source.to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let program = Parser::new(tokens).parse_program().unwrap();
+        let output = ASTPrinter::new().with_color(false).print_program(&program);
+        assert!(output.contains(&[
+            "|  |  |  Value:",
+            "|  |  |  Array Literal:",
+            "|  |  |  |  Integer Literal: 1",
+            "|  |  |  |  Integer Literal: 2",
+            "|  |  |  |  Integer Literal: 3",
+            "|  |  Variable Declaration: empty",
+            "|  |  |  Mutability: Immutable",
+            "|  |  |  Type: [I32]",
+            "|  |  |  Value:",
+            "|  |  |  Array Literal:",
+            "|  |  Variable Declaration: filled",
+            ""
+        ].join("\n")), "{}", output);
+        // Brackets followed by a brace still create a filled array
+        assert!(output.contains("|  |  |  Array:\n|  |  |  |  Type: I32\n"), "{}", output);
+    }
+
+    #[test]
+    fn test_interpolated_strings() {
+        let mut tokenizer = Tokenizer::new(r#"func main() -> nil { print("a${x}b${y}"); print("${x}${y}"); }"#.to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut program = Parser::new(tokens).parse_program().unwrap();
+        let output = ASTPrinter::new().with_color(false).print_program(&program);
+        assert!(output.contains(&[
+            "|  |  |  |  Arguments:",
+            "|  |  |  |  Interpolated String:",
+            "|  |  |  |  |  Literal: \"a\"",
+            "|  |  |  |  |  Expression:",
+            "|  |  |  |  |  Variable: x",
+            "|  |  |  |  |  Literal: \"b\"",
+            "|  |  |  |  |  Expression:",
+            "|  |  |  |  |  Variable: y",
+            "|  |  Expression (discarded):",
+            ""
+        ].join("\n")), "{}", output);
+        // Back-to-back expressions don't have an empty literal between them
+        assert!(output.contains(&[
+            "|  |  |  |  Interpolated String:",
+            "|  |  |  |  |  Expression:",
+            "|  |  |  |  |  Variable: x",
+            "|  |  |  |  |  Expression:",
+            "|  |  |  |  |  Variable: y",
+            ""
+        ].join("\n")), "{}", output);
+
+        // The parser never makes an interpolated string without parts, but other passes could
+        let Declaration::Function { body, .. } = &mut program.declarations[0].node else { unreachable!() };
+        let Expression::Block { statements, .. } = &mut body.node else { unreachable!() };
+        let Statement::Expression { expression, .. } = &mut statements[1].node else { unreachable!() };
+        let Expression::FunctionCall { args, .. } = &mut expression.node else { unreachable!() };
+        args[0].node = Expression::InterpolatedString { parts: vec![] };
+        let output = ASTPrinter::new().with_color(false).print_program(&program);
+        assert!(output.ends_with("|  |  |  |  Arguments:\n|  |  |  |  Interpolated String: (none)\n"), "{}", output);
+        assert_eq!(output, ASTPrinter::new().with_color(false).print_arena_program(&ArenaProgram::from_program(program)));
+    }
+
+    #[test]
+    fn test_grouping() {
+        let mut tokenizer = Tokenizer::new("func main() -> i32 { (a + b) * c }".to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let program = Parser::new(tokens).parse_program().unwrap();
+        let output = ASTPrinter::new().with_color(false).print_program(&program);
+        assert!(output.ends_with(&[
+            "|  |  Expression (result):",
+            "|  |  Binary Operation: *",
+            "|  |  |  Left:",
+            "|  |  |  Grouping:",
+            "|  |  |  |  Binary Operation: +",
+            "|  |  |  |  |  Left:",
+            "|  |  |  |  |  Variable: a",
+            "|  |  |  |  |  Right:",
+            "|  |  |  |  |  Variable: b",
+            "|  |  |  Right:",
+            "|  |  |  Variable: c",
+            ""
+        ].join("\n")), "{}", output);
+        assert_eq!(output, ASTPrinter::new().with_color(false).print_arena_program(&ArenaProgram::from_program(program)));
+    }
+
+    #[test]
+    fn test_try() {
+        let mut tokenizer = Tokenizer::new("func main() -> i32? { foo()?; a?.b }".to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let program = Parser::new(tokens).parse_program().unwrap();
+        let output = ASTPrinter::new().with_color(false).print_program(&program);
+        assert_eq!(output, [
+            "Function: main",
+            "|  Parameters: (none)",
+            "|  Return Type: I32?",
+            "|  Body: |  Block:",
+            "|  |  Expression (discarded):",
+            "|  |  |  Try:",
+            "|  |  |  |  Operand:",
+            "|  |  |  |  Function Call",
+            "|  |  |  |  |  Callee:",
+            "|  |  |  |  |  Variable: foo",
+            "|  |  |  |  |  Arguments: (none)",
+            // The member access is on the result of the try
+            "|  |  Expression (result):",
+            "|  |  Member Access:",
+            "|  |  |  Object:",
+            "|  |  |  Try:",
+            "|  |  |  |  Operand:",
+            "|  |  |  |  Variable: a",
+            "|  |  |  Member: b",
+            ""
+        ].join("\n"));
+        assert_eq!(output, ASTPrinter::new().with_color(false).print_arena_program(&ArenaProgram::from_program(program)));
+    }
+
+    #[test]
+    fn test_method_calls() {
+        let mut tokenizer = Tokenizer::new("func main() -> nil { list.push(1, 2); (list.clear)(); }".to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let program = Parser::new(tokens).parse_program().unwrap();
+        let output = ASTPrinter::new().with_color(false).print_program(&program);
+        // Calling a member access in parentheses is a plain function call
+        assert_eq!(output, [
+            "Function: main\n",
+            "|  Parameters: (none)\n",
+            "|  Return Type: Nil\n",
+            "|  Body: |  Block:\n",
+            "|  |  Expression (discarded):\n",
+            "|  |  |  Method Call: push\n",
+            "|  |  |  |  Receiver:\n",
+            "|  |  |  |  Variable: list\n",
+            "|  |  |  |  Arguments:\n",
+            "|  |  |  |  Integer Literal: 1\n",
+            "|  |  |  |  Integer Literal: 2\n",
+            "|  |  Expression (discarded):\n",
+            "|  |  |  Function Call\n",
+            "|  |  |  |  Callee:\n",
+            "|  |  |  |  Grouping:\n",
+            "|  |  |  |  |  Member Access:\n",
+            "|  |  |  |  |  |  Object:\n",
+            "|  |  |  |  |  |  Variable: list\n",
+            "|  |  |  |  |  |  Member: clear\n",
+            "|  |  |  |  Arguments: (none)\n"
+        ].concat());
+        assert_eq!(ASTPrinter::new().with_color(false).print_arena_program(&ArenaProgram::from_program(program)), output);
+    }
+
+    #[test]
+    fn test_array_styles() {
+        let array = Type::array(Type::I32);
+        let nested = Type::array(array.clone());
+        let fixed = Type::Array { element: Box::new(Type::I32), size: Some(4) };
+
+        let mut printer = ASTPrinter::new().with_color(false);
+        assert_eq!(printer.print_type(&array), "[I32]");
+        assert_eq!(printer.print_type(&nested), "[[I32]]");
+        assert_eq!(printer.print_type(&fixed), "[I32; 4]");
+
+        let mut printer = ASTPrinter::new().with_color(false).with_array_style(ArrayStyle::Generic);
+        assert_eq!(printer.print_type(&array), "Array<I32>");
+        assert_eq!(printer.print_type(&nested), "Array<Array<I32>>");
+        assert_eq!(printer.print_type(&fixed), "Array<I32; 4>");
+    }
+
+    #[test]
+    fn test_inferred_types() {
+        let mut tokenizer = Tokenizer::new(r#"
+            func add(a: i32, b: i32) -> i32 {
+                a + b
             }
+        "#.to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let program = Parser::new(tokens).parse_program().unwrap();
+        let types = crate::typechecker::TypeChecker::new().check_program(&program).unwrap();
+
+        let mut printer = ASTPrinter::new().with_inferred_types(types);
+        let output = strip_ansi(&printer.print_program(&program));
+        assert!(output.contains("Binary Operation: + : I32\n"));
+        assert!(output.contains("Variable: a : I32\n"));
+    }
+
+    #[test]
+    fn test_spans() {
+        let mut tokenizer = Tokenizer::new("func main() -> nil { 1 + 23; }".to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let program = Parser::new(tokens).parse_program().unwrap();
+
+        let output = strip_ansi(&ASTPrinter::new().with_spans(true).print_program(&program));
+        assert!(output.contains("Block: [19..30]\n"));
+        // Statements include their semicolons
+        assert!(output.contains("Expression (discarded): [21..28]\n"));
+        assert!(output.contains("Binary Operation: + [21..27]\n"));
+        assert!(output.contains("Integer Literal: 23 [25..27]\n"));
+
+        // Spans are off by default
+        let output = strip_ansi(&ASTPrinter::new().print_program(&program));
+        assert!(output.contains("Binary Operation: +\n"));
+        assert!(!output.contains("[21..27]"));
+    }
+
+    #[test]
+    fn test_source_map() {
+        let source = "/// Entry point\nfunc main() -> i32 { 1 + 23 }";
+        let mut tokenizer = Tokenizer::new(source.to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let program = Parser::new(tokens).parse_program().unwrap();
+
+        let (output, map) = ASTPrinter::new().with_color(false).print_program_with_map(&program);
+        // The map doesn't change what's printed
+        assert_eq!(output, ASTPrinter::new().with_color(false).print_program(&program));
+
+        let lines: Vec<&str> = output.lines().collect();
+        let line_of = |text: &str| lines.iter().position(|line| line.trim_start_matches("|  ") == text).unwrap();
+        let span_of = |line: usize| map.iter().find(|(mapped, _)| *mapped == line).map(|(_, span)| *span);
+
+        let start = source.find("1 + 23").unwrap();
+        assert_eq!(span_of(line_of("Binary Operation: +")), Some(Span { start, end: start + 6 }));
+        assert_eq!(span_of(line_of("Integer Literal: 23")), Some(Span { start: start + 4, end: start + 6 }));
+        // The declaration's line is mapped, not its doc comment's
+        assert_eq!(span_of(line_of("Function: main")), Some(program.declarations[0].span));
+        assert_eq!(span_of(line_of("Doc: \"Entry point\"")), None);
+        // Labels aren't nodes
+        assert_eq!(span_of(line_of("Left:")), None);
+    }
+
+    #[test]
+    fn test_integer_and_float_literals() {
+        let mut tokenizer = Tokenizer::new("func main() -> u64 { const x: f64 = 5.0; 18446744073709551615 }".to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let program = Parser::new(tokens).parse_program().unwrap();
+        crate::typechecker::TypeChecker::new().check_program(&program).unwrap();
+
+        let output = ASTPrinter::new().with_color(false).print_program(&program);
+        assert!(output.contains("Number Literal: 5\n"));
+        // Larger than 2^53, so this would be rounded if it were stored as an f64
+        assert!(output.contains("Integer Literal: 18446744073709551615\n"));
+    }
+
+    #[test]
+    fn test_literal_spellings() {
+        let input = "func main() -> nil { print(0xFF + 1_000.50 + 7); }";
+        let mut tokenizer = Tokenizer::new(input.to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let program = Parser::new(tokens).with_literal_spellings(input).parse_program().unwrap();
+        let Declaration::Function { body, .. } = &program.declarations[0].node else { unreachable!() };
+        let Expression::Block { statements, .. } = &body.node else { unreachable!() };
+        let Statement::Expression { expression } = &statements[0].node else { unreachable!() };
+        let Expression::FunctionCall { args, .. } = &expression.node else { unreachable!() };
+        let Expression::BinaryOperation { left, .. } = &args[0].node else { unreachable!() };
+        let Expression::BinaryOperation { left: hex, .. } = &left.node else { unreachable!() };
+        assert_eq!(hex.node, Expression::IntegerLiteral { value: 255, lexeme: Some("0xFF".to_string()) });
+
+        let output = ASTPrinter::new().with_color(false).print_program(&program);
+        assert!(output.contains("Integer Literal: 0xFF\n"));
+        assert!(output.contains("Number Literal: 1_000.50\n"));
+        assert!(output.contains("Integer Literal: 7\n"));
+        let output = ASTPrinter::new().with_color(false).print_arena_program(&ArenaProgram::from_program(program));
+        assert!(output.contains("Integer Literal: 0xFF\n"));
+    }
+
+    #[test]
+    fn test_source_type_syntax() {
+        let mut tokenizer = Tokenizer::new(r#"
+            func main(a: u8, b: u16, c: u32, d: u64, e: i8, f: i16, g: i32, h: i64, i: f32, j: f64, k: bool, l: char) -> nil {}
+        "#.to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let program = Parser::new(tokens).parse_program().unwrap();
+
+        let verbose = ASTPrinter::new().with_color(false).print_program(&program);
+        let source = ASTPrinter::new().with_color(false).with_source_types(true).print_program(&program);
+        let expected = [
+            ("a", "U8", "u8"), ("b", "U16", "u16"), ("c", "U32", "u32"), ("d", "U64", "u64"),
+            ("e", "I8", "i8"), ("f", "I16", "i16"), ("g", "I32", "i32"), ("h", "I64", "i64"),
+            ("i", "F32", "f32"), ("j", "F64", "f64"), ("k", "Boolean", "bool"), ("l", "Character", "char")
+        ];
+        for (param, verbose_name, source_name) in expected {
+            assert!(verbose.contains(&format!("- {}: {}\n", param, verbose_name)));
+            assert!(source.contains(&format!("- {}: {}\n", param, source_name)));
         }
+        assert!(verbose.contains("Return Type: Nil\n"));
+        assert!(source.contains("Return Type: nil\n"));
+    }
+
+    #[test]
+    fn test_block_tails() {
+        let mut tokenizer = Tokenizer::new("func main() -> i32 { print(1); 2 } func other() -> nil { print(1); }".to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let program = Parser::new(tokens).parse_program().unwrap();
+
+        let output = ASTPrinter::new().with_color(false).print_program(&program);
+        let (with_tail, without_tail) = output.split_once("Function: other").unwrap();
+        assert!(with_tail.ends_with(&[
+            "|  Body: |  Block:",
+            "|  |  Expression (discarded):",
+            "|  |  |  Function Call",
+            "|  |  |  |  Callee:",
+            "|  |  |  |  Variable: print",
+            "|  |  |  |  Arguments:",
+            "|  |  |  |  Integer Literal: 1",
+            "|  |  Expression (result):",
+            "|  |  Integer Literal: 2",
+            ""
+        ].join("\n")), "{}", with_tail);
+        assert!(!without_tail.contains("Expression (result):"));
+    }
+
+    #[test]
+    fn test_discarded_expression() {
+        let mut tokenizer = Tokenizer::new("func main() -> nil { x + 1; }".to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let program = Parser::new(tokens).parse_program().unwrap();
+
+        let output = ASTPrinter::new().with_color(false).print_program(&program);
+        assert!(output.ends_with(&[
+            "|  Body: |  Block:",
+            "|  |  Expression (discarded):",
+            "|  |  |  Binary Operation: +",
+            "|  |  |  |  Left:",
+            "|  |  |  |  Variable: x",
+            "|  |  |  |  Right:",
+            "|  |  |  |  Integer Literal: 1",
+            ""
+        ].join("\n")), "{}", output);
+    }
+
+    #[test]
+    fn test_result_expression() {
+        let mut tokenizer = Tokenizer::new("func main() -> i32 { x + 1 }".to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let program = Parser::new(tokens).parse_program().unwrap();
+
+        let output = ASTPrinter::new().with_color(false).print_program(&program);
+        assert!(output.ends_with(&[
+            "|  Body: |  Block:",
+            "|  |  Expression (result):",
+            "|  |  Binary Operation: +",
+            "|  |  |  Left:",
+            "|  |  |  Variable: x",
+            "|  |  |  Right:",
+            "|  |  |  Integer Literal: 1",
+            ""
+        ].join("\n")), "{}", output);
+    }
+
+    #[test]
+    fn test_else_if_chain() {
+        let mut tokenizer = Tokenizer::new(r#"
+            func sign(x: i32) -> i32 {
+                if (x < 0) -1 else if (x == 0) 0 else 1
+            }
+        "#.to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let program = Parser::new(tokens).parse_program().unwrap();
+
+        let expected = [
+            "|  |  If Statement:",
+            "|  |  |  Condition:",
+            "|  |  |  Binary Operation: <",
+            "|  |  |  |  Left:",
+            "|  |  |  |  Variable: x",
+            "|  |  |  |  Right:",
+            "|  |  |  |  Integer Literal: 0",
+            "|  |  |  Then Branch:",
+            "|  |  |  Unary Operation: -",
+            "|  |  |  |  Operand:",
+            "|  |  |  |  Integer Literal: 1",
+            "|  |  |  Else If:",
+            "|  |  |  Binary Operation: ==",
+            "|  |  |  |  Left:",
+            "|  |  |  |  Variable: x",
+            "|  |  |  |  Right:",
+            "|  |  |  |  Integer Literal: 0",
+            "|  |  |  Then Branch:",
+            "|  |  |  Integer Literal: 0",
+            "|  |  |  Else Branch:",
+            "|  |  |  Integer Literal: 1",
+        ].join("\n");
+        let output = ASTPrinter::new().with_color(false).print_program(&program);
+        assert!(output.contains(&expected), "{}", output);
+        assert_eq!(output.matches("If Statement:").count(), 1);
+
+        // An if inside a block is still nested
+        let mut tokenizer = Tokenizer::new("func f(x: bool) -> i32 { if (x) 1 else { if (x) 2 else 3 } }".to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let program = Parser::new(tokens).parse_program().unwrap();
+        let output = ASTPrinter::new().with_color(false).print_program(&program);
+        assert_eq!(output.matches("If Statement:").count(), 2);
+        assert!(!output.contains("Else If:"));
+    }
+
+    #[test]
+    fn test_precedence_hints() {
+        let mut tokenizer = Tokenizer::new("func main(a: i32) -> bool { a * 2 < 10 }".to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let program = Parser::new(tokens).parse_program().unwrap();
+
+        let output = ASTPrinter::new().with_color(false).with_precedence(true).print_program(&program);
+        assert!(output.contains("Binary Operation: * (prec 10, left-assoc)\n"));
+        assert!(output.contains("Binary Operation: < (prec 4, left-assoc)\n"));
+
+        let output = ASTPrinter::new().with_color(false).print_program(&program);
+        assert!(output.contains("Binary Operation: *\n"));
+    }
+
+    #[test]
+    fn test_ranges() {
+        let mut tokenizer = Tokenizer::new("func main() -> nil { print(0..10); print(0..=10); }".to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let program = Parser::new(tokens).parse_program().unwrap();
+
+        let output = ASTPrinter::new().with_color(false).print_program(&program);
+        assert!(output.contains(&[
+            "|  |  |  |  Range (exclusive):",
+            "|  |  |  |  |  Start:",
+            "|  |  |  |  |  Integer Literal: 0",
+            "|  |  |  |  |  End:",
+            "|  |  |  |  |  Integer Literal: 10",
+        ].join("\n")));
+        assert!(output.contains("Range (inclusive):\n"));
+    }
+
+    #[test]
+    fn test_index() {
+        let mut tokenizer = Tokenizer::new("func main(values: [i32]) -> nil { print(values[1 + 1]); }".to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let program = Parser::new(tokens).parse_program().unwrap();
+
+        let output = ASTPrinter::new().with_color(false).print_program(&program);
+        assert!(output.contains(&[
+            "|  |  |  |  Index Access:",
+            "|  |  |  |  |  Object:",
+            "|  |  |  |  |  Variable: values",
+        ].join("\n")), "{}", output);
+        assert!(output.contains("|  |  |  |  |  Index:\n|  |  |  |  |  Binary Operation:"), "{}", output);
+    }
+
+    #[test]
+    fn test_tuples() {
+        let mut tokenizer = Tokenizer::new(r#"
+            func main(point: (i32, bool, char)) -> nil {
+                const empty = ();
+                const single = (1,);
+                const triple = (1, true, 'c');
+            }
+        "#.to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let program = Parser::new(tokens).parse_program().unwrap();
+
+        let output = ASTPrinter::new().with_color(false).print_program(&program);
+        assert!(output.contains("|  - point: (I32, Boolean, Character)\n"), "{}", output);
+        // The empty tuple has no elements, but it's still a tuple rather than nil
+        assert!(output.contains("|  |  |  Value:\n|  |  |  Tuple:\n|  |  Variable Declaration: single\n"), "{}", output);
+        assert!(output.contains("|  |  |  Tuple:\n|  |  |  |  Integer Literal: 1\n|  |  Variable Declaration: triple\n"), "{}", output);
+        assert!(output.contains(&[
+            "|  |  |  Tuple:",
+            "|  |  |  |  Integer Literal: 1",
+            "|  |  |  |  Boolean Literal: true",
+            "|  |  |  |  Character Literal: 'c'",
+        ].join("\n")), "{}", output);
+    }
+
+    #[test]
+    fn test_labeled_jumps() {
+        let mut tokenizer = Tokenizer::new(r#"
+            func main(a: bool) -> nil {
+                'outer: loop (a) {
+                    loop {
+                        break 'outer;
+                    };
+                };
+                loop {
+                    continue;
+                    break;
+                };
+            }
+        "#.to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let program = Parser::new(tokens).parse_program().unwrap();
+
+        let output = ASTPrinter::new().with_color(false).print_program(&program);
+        assert!(output.contains("|  |  |  While Loop:\n|  |  |  |  Label: 'outer\n|  |  |  |  Condition:\n"), "{}", output);
+        assert!(output.contains("|  |  |  |  |  |  |  |  Break: 'outer\n"), "{}", output);
+        // Unlabeled loops and jumps don't mention labels
+        assert!(output.contains("|  |  |  Infinite Loop:\n|  |  |  |  Block:\n|  |  |  |  |  Continue\n|  |  |  |  |  Break\n"), "{}", output);
+        assert_eq!(output.matches("Label:").count(), 1);
+    }
+
+    #[test]
+    fn test_inline_constant_arrays() {
+        let mut tokenizer = Tokenizer::new(r#"
+            func main(n: u64) -> nil {
+                const zeroes: [i32] = [i32, 16] { 0 };
+                const computed: [i32] = [i32, n] { f() };
+            }
+        "#.to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let program = Parser::new(tokens).parse_program().unwrap();
+
+        let output = ASTPrinter::new().with_color(false).with_inline_constant_arrays(true).print_program(&program);
+        assert!(output.contains("|  |  |  Array: [0; 16]\n"));
+        assert_eq!(output.matches("Array:\n").count(), 1);
+        assert!(output.contains("Function Call\n"));
+
+        // Arrays are expanded by default
+        let output = ASTPrinter::new().with_color(false).print_program(&program);
+        assert_eq!(output.matches("Array:\n").count(), 2);
+    }
+
+    #[test]
+    fn test_match() {
+        let mut tokenizer = Tokenizer::new(r#"
+            func name(digit: i32) -> String {
+                match (digit) {
+                    0 => "zero",
+                    1 => "one",
+                    other => "many"
+                }
+            }
+        "#.to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let program = Parser::new(tokens).parse_program().unwrap();
+
+        let output = ASTPrinter::new().with_color(false).print_program(&program);
+        assert!(output.contains(concat!(
+            "|  |  Match:\n",
+            "|  |  |  Scrutinee:\n",
+            "|  |  |  Variable: digit\n",
+            "|  |  |  Arm: 0\n",
+            "|  |  |  |  String Literal: \"zero\"\n",
+            "|  |  |  Arm: 1\n",
+            "|  |  |  |  String Literal: \"one\"\n",
+            "|  |  |  Arm: other\n",
+            "|  |  |  |  String Literal: \"many\"\n"
+        )));
+    }
+
+    #[test]
+    fn test_compound_assignment() {
+        let mut tokenizer = Tokenizer::new("func main() -> nil { let count: i32 = 0; count = 1; count += 2; count %= 3; }".to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let program = Parser::new(tokens).parse_program().unwrap();
+
+        let output = ASTPrinter::new().with_color(false).print_program(&program);
+        assert!(output.contains(&[
+            "|  |  |  Assignment:",
+            "|  |  |  |  Variable: count",
+            "|  |  |  |  Value:",
+            "|  |  |  |  Integer Literal: 1",
+            "|  |  Expression (discarded):",
+            "|  |  |  Assignment (+=):",
+            "|  |  |  |  Variable: count",
+            "|  |  |  |  Value:",
+            "|  |  |  |  Integer Literal: 2",
+            "|  |  Expression (discarded):",
+            "|  |  |  Assignment (%=):",
+        ].join("\n")));
+    }
+
+    #[test]
+    fn test_anonymous_struct_creation() {
+        let mut tokenizer = Tokenizer::new("func main() -> nil { const a: Point = new { x: 1 }; const b: Point = new Point { x: 2 }; }".to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let program = Parser::new(tokens).parse_program().unwrap();
+
+        let output = ASTPrinter::new().with_color(false).print_program(&program);
+        assert!(output.contains(&[
+            "|  |  |  Struct Creation:",
+            "|  |  |  |  Type: <inferred>",
+            "|  |  |  |  Fields:",
+            "|  |  |  |  x:",
+            "|  |  |  |  |  Integer Literal: 1",
+        ].join("\n")), "{}", output);
+        assert!(output.contains("|  |  |  |  Type: Point\n"), "{}", output);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_inline_leaves() {
+        let mut tokenizer = Tokenizer::new("func main(a: i32, b: i32) -> nil { let x: i32 = 0; x = 1; x -= a; x = a + b; }".to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let program = Parser::new(tokens).parse_program().unwrap();
+
+        let output = ASTPrinter::new().with_color(false).with_inline_leaves(true).print_program(&program);
+        assert!(output.contains("|  |  |  Assignment: x = 1\n"));
+        assert!(output.contains("|  |  |  Assignment: x -= a\n"));
+        assert!(output.contains(&[
+            "|  |  |  Assignment:",
+            "|  |  |  |  Variable: x",
+            "|  |  |  |  Value:",
+            "|  |  |  |  Binary Operation: +",
+        ].join("\n")));
+        assert_eq!(output.matches("Assignment").count(), 3);
+    }
+
+    #[test]
+    fn test_enum() {
+        let mut tokenizer = Tokenizer::new(r#"
+            enum Shape<T> {
+                Empty,
+                Circle(f64),
+                Polygon(T, u32),
+            }
+        "#.to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let program = Parser::new(tokens).parse_program().unwrap();
+
+        let output = ASTPrinter::new().with_color(false).print_program(&program);
+        assert_eq!(output, [
+            "Enum: Shape",
+            "|  Generic Arguments:",
+            "|  - T",
+            "|  Variants:",
+            "|  - Empty",
+            "|  - Circle(F64)",
+            "|  - Polygon(T, U32)",
+            ""
+        ].join("\n"));
+    }
+
+    #[test]
+    fn test_const() {
+        let mut tokenizer = Tokenizer::new("const LIMIT: i32 = 10; func main() -> i32 { LIMIT }".to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let program = Parser::new(tokens).parse_program().unwrap();
+
+        let output = ASTPrinter::new().with_color(false).print_program(&program);
+        // The constant comes first, before the function that follows it
+        assert!(output.starts_with(&[
+            "Const: LIMIT",
+            "|  Type: I32",
+            "|  Value:",
+            "|  Integer Literal: 10",
+            "Function: main",
+            ""
+        ].join("\n")));
+    }
+
+    fn print_plain(source: &str) -> String {
+        let mut tokenizer = Tokenizer::new(source.to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let program = Parser::new(tokens).parse_program().unwrap();
+        ASTPrinter::new().with_color(false).print_program(&program)
+    }
+
+    #[test]
+    fn test_whole_path_import() {
+        assert_eq!(print_plain("import std.io;"), "Import: std.io\n");
+    }
+
+    #[test]
+    fn test_aliased_import() {
+        assert_eq!(print_plain("import foo.bar as baz;"), "Import: foo.bar as baz\n");
+    }
+
+    #[test]
+    fn test_selective_import() {
+        assert_eq!(print_plain("import foo.{a, b};"), [
+            "Import: foo",
+            "|  Items:",
+            "|  - a",
+            "|  - b",
+            ""
+        ].join("\n"));
+    }
+
+    #[test]
+    fn test_arena_prints_like_boxed() {
+        let input = r#"
+            struct Point {
+                x: i32;
+                y: i32;
+                func length(self: Point) -> i32 {
+                    self.x * self.x + self.y * self.y
+                }
+            }
+
+            func main() -> nil {
+                let total: i32 = 0;
+                loop(const i: [i32, 10] { 1 }) {
+                    total = total + i;
+                    total *= 2;
+                };
+                if (total > 5 && !false) {
+                    print(new Point { x: total, y: -1 });
+                } else {
+                    return;
+                }
+            }
+        "#;
+        let mut tokenizer = Tokenizer::new(input.to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let boxed = Parser::new(tokens).parse_program().unwrap();
+        let arena = Parser::new(tokens).parse_program_arena().unwrap();
+        assert!(!arena.arena.is_empty());
+
+        let mut printer = ASTPrinter::new();
+        assert_eq!(printer.print_program(&boxed), printer.print_arena_program(&arena));
+    }
+
+    #[test]
+    fn test_diff_programs() {
+        let parse = |input: &str| {
+            let mut tokenizer = Tokenizer::new(input.to_string());
+            let tokens = tokenizer.tokenize().unwrap().clone();
+            Parser::new(&tokens).parse_program().unwrap()
+        };
+
+        let before = parse(r#"
+            func first() -> i32 { let a: i32 = 1; let b: i32 = 2; a + b }
+            func second() -> i32 { let e: i32 = 10; let f: i32 = 6; e + f }
+            func third() -> i32 { let c: i32 = 3; let d: i32 = 4; c + d }
+        "#);
+        let after = parse(r#"
+            func first() -> i32 { let a: i32 = 1; let b: i32 = 2; a + b }
+            func second() -> i32 { let e: i32 = 20; let f: i32 = 6; e + f }
+            func third() -> i32 { let c: i32 = 3; let d: i32 = 4; c + d }
+        "#);
+
+        let diff = diff_programs(&before, &after);
+        assert!(diff.starts_with("--- a\n+++ b\n"));
+        assert_eq!(diff.matches("@@ -").count(), 1);
+
+        let changes: Vec<&str> = diff.lines()
+            .skip(2)
+            .filter(|line| line.starts_with('-') || line.starts_with('+'))
+            .collect();
+        assert_eq!(changes, vec![
+            "-|  |  |  Integer Literal: 10",
+            "+|  |  |  Integer Literal: 20"
+        ]);
+        assert!(!diff.contains("Function: first"));
+        assert!(!diff.contains("Function: third"));
+
+        assert_eq!(diff_programs(&before, &before), "");
+    }
+
+    #[test]
+    fn test_bitwise_operators() {
+        let mut tokenizer = Tokenizer::new("func main(masks: Map<String, Array<u8>>) -> u8 { a & b | c ^ d << 2 }".to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let program = Parser::new(tokens).parse_program().unwrap();
+        let output = strip_ansi(&ASTPrinter::new().print_program(&program));
+        // The `>>` closing the generics is still two angle brackets
+        assert_eq!(output, [
+            "Function: main",
+            "|  Parameters:",
+            "|  - masks: Map<String, Array<U8>>",
+            "|  Return Type: U8",
+            "|  Body: |  Block:",
+            "|  |  Expression (result):",
+            "|  |  Binary Operation: |",
+            "|  |  |  Left:",
+            "|  |  |  Binary Operation: &",
+            "|  |  |  |  Left:",
+            "|  |  |  |  Variable: a",
+            "|  |  |  |  Right:",
+            "|  |  |  |  Variable: b",
+            "|  |  |  Right:",
+            "|  |  |  Binary Operation: ^",
+            "|  |  |  |  Left:",
+            "|  |  |  |  Variable: c",
+            "|  |  |  |  Right:",
+            "|  |  |  |  Binary Operation: <<",
+            "|  |  |  |  |  Left:",
+            "|  |  |  |  |  Variable: d",
+            "|  |  |  |  |  Right:",
+            "|  |  |  |  |  Integer Literal: 2",
+            ""
+        ].join("\n"));
+    }
+
+    #[test]
+    fn test_nil_and_empty_tuple_print_differently() {
+        let mut printer = ASTPrinter::new();
+        assert_eq!(printer.print_type(&Type::Nil), "Nil");
+        assert_eq!(printer.print_type(&Type::Tuple(vec![])), "()");
+        assert_eq!(printer.print_type(&Type::Tuple(vec![Type::I32])), "(I32,)");
+        assert_eq!(printer.print_type(&Type::Tuple(vec![Type::I32, Type::Boolean])), "(I32, Boolean)");
+    }
+
+    #[test]
+    fn test_never_returns() {
+        let mut tokenizer = Tokenizer::new("func serve() -> never { loop { poll(); }; }".to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let program = Parser::new(tokens).parse_program().unwrap();
+        let output = ASTPrinter::new().with_color(false).print_program(&program);
+        assert_eq!(output, [
+            "Function: serve",
+            "|  Parameters: (none)",
+            "|  Return Type: Never",
+            "|  Body: |  Block:",
+            "|  |  Expression (discarded):",
+            "|  |  |  Infinite Loop:",
+            "|  |  |  |  Block:",
+            "|  |  |  |  |  Expression (discarded):",
+            "|  |  |  |  |  |  Function Call",
+            "|  |  |  |  |  |  |  Callee:",
+            "|  |  |  |  |  |  |  Variable: poll",
+            "|  |  |  |  |  |  |  Arguments: (none)",
+            ""
+        ].join("\n"));
+        assert_ne!(Type::Never, Type::Nil);
+        assert_eq!(ASTPrinter::new().with_source_types(true).print_type(&Type::Never), "never");
+    }
+}
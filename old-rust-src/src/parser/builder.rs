@@ -0,0 +1,149 @@
+use super::ast::{BinaryOperator, Declaration, Expression, ExpressionId, FunctionParameter, Program, Spanned, Statement, Type};
+
+// Short constructors for building ASTs by hand, like in tests, without writing out every `Box` and `Spanned`.
+// Like other nodes built outside the parser, everything here has an empty span.
+// Every variable gets id 0, since the builder doesn't know what else is in the program; `IdAssigner` can number them afterwards.
+
+pub fn int(value: i128) -> Spanned<Expression> {
+    Expression::integer(value).into()
+}
+
+pub fn num(value: f64) -> Spanned<Expression> {
+    Expression::number(value).into()
+}
+
+pub fn string(value: &str) -> Spanned<Expression> {
+    Expression::string(value.to_string()).into()
+}
+
+pub fn var(name: &str) -> Spanned<Expression> {
+    Expression::Variable { name: name.to_string(), expression_id: ExpressionId(0) }.into()
+}
+
+pub fn bin(left: Spanned<Expression>, operator: BinaryOperator, right: Spanned<Expression>) -> Spanned<Expression> {
+    Expression::BinaryOperation { left: Box::new(left), operator, right: Box::new(right) }.into()
+}
+
+pub fn call(callee: Spanned<Expression>, args: Vec<Spanned<Expression>>) -> Spanned<Expression> {
+    Expression::FunctionCall { callee: Box::new(callee), args }.into()
+}
+
+/// A block without a tail, so it evaluates to `nil`.
+pub fn block(statements: Vec<Spanned<Statement>>) -> Spanned<Expression> {
+    Expression::Block { statements, tail: None }.into()
+}
+
+pub fn block_with_tail(statements: Vec<Spanned<Statement>>, tail: Spanned<Expression>) -> Spanned<Expression> {
+    Expression::Block { statements, tail: Some(Box::new(tail)) }.into()
+}
+
+/// An expression statement, like `print(x);`.
+pub fn stmt(expression: Spanned<Expression>) -> Spanned<Statement> {
+    Statement::Expression { expression: Box::new(expression) }.into()
+}
+
+/// A parameter without a default value.
+pub fn param(name: &str, param_type: Type) -> FunctionParameter {
+    FunctionParameter { name: name.to_string(), param_type, default: None }
+}
+
+/// A function that isn't generic, async, variadic, or documented.
+pub fn function(name: &str, params: Vec<FunctionParameter>, return_type: Type, body: Spanned<Expression>) -> Spanned<Declaration> {
+    Declaration::Function {
+        name: name.to_string(),
+        params,
+        generic_args: vec![],
+        where_clauses: vec![],
+        return_type,
+        body: Box::new(body),
+        is_async: false,
+        is_variadic: false,
+        doc: None
+    }.into()
+}
+
+pub fn program(declarations: Vec<Spanned<Declaration>>) -> Program {
+    Program { declarations }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser::{id_assigner::IdAssigner, Parser}, tokenizer::Tokenizer};
+
+    fn built() -> Program {
+        program(vec![
+            function("area", vec![param("width", Type::F64)], Type::F64, block_with_tail(vec![
+                stmt(call(var("print"), vec![string("measuring")])),
+            ], bin(var("width"), BinaryOperator::Multiply, num(2.5)))),
+            function("main", vec![], Type::Nil, block(vec![
+                stmt(call(var("area"), vec![bin(int(1), BinaryOperator::Add, int(2))]))
+            ]))
+        ])
+    }
+
+    #[test]
+    fn test_matches_hand_built() {
+        let variable = |name: &str| Box::new(Spanned::from(Expression::Variable { name: name.to_string(), expression_id: ExpressionId(0) }));
+        let function = |name: &str, params, return_type, body| Spanned::from(Declaration::Function {
+            name: name.to_string(),
+            params,
+            generic_args: vec![],
+            where_clauses: vec![],
+            return_type,
+            body: Box::new(Spanned::from(body)),
+            is_async: false,
+            is_variadic: false,
+            doc: None
+        });
+        let hand_built = Program { declarations: vec![
+            function("area", vec![FunctionParameter { name: "width".to_string(), param_type: Type::F64, default: None }], Type::F64, Expression::Block {
+                statements: vec![Spanned::from(Statement::Expression {
+                    expression: Box::new(Spanned::from(Expression::FunctionCall {
+                        callee: variable("print"),
+                        args: vec![Spanned::from(Expression::string("measuring".to_string()))]
+                    }))
+                })],
+                tail: Some(Box::new(Spanned::from(Expression::BinaryOperation {
+                    left: variable("width"),
+                    operator: BinaryOperator::Multiply,
+                    right: Box::new(Spanned::from(Expression::number(2.5)))
+                })))
+            }),
+            function("main", vec![], Type::Nil, Expression::Block {
+                statements: vec![Spanned::from(Statement::Expression {
+                    expression: Box::new(Spanned::from(Expression::FunctionCall {
+                        callee: variable("area"),
+                        args: vec![Spanned::from(Expression::BinaryOperation {
+                            left: Box::new(Spanned::from(Expression::integer(1))),
+                            operator: BinaryOperator::Add,
+                            right: Box::new(Spanned::from(Expression::integer(2)))
+                        })]
+                    }))
+                })],
+                tail: None
+            })
+        ] };
+        assert_eq!(built(), hand_built);
+    }
+
+    #[test]
+    fn test_matches_parsed() {
+        let mut tokenizer = Tokenizer::new(r#"
+            func area(width: f64) -> f64 {
+                print("measuring");
+                width * 2.5
+            }
+            func main() -> nil {
+                area(1 + 2);
+            }
+        "#.to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parsed = Parser::new(tokens).parse_program().unwrap();
+        // Spans are never compared, but the variables have to be numbered the same way
+        let mut built = built();
+        IdAssigner::new().assign_program(&mut built);
+        IdAssigner::new().assign_program(&mut parsed);
+        assert_eq!(built, parsed);
+    }
+}
@@ -0,0 +1,74 @@
+use super::{ast::{Declaration, Expression, Program, Spanned}, visitor::{walk_declaration, walk_expression, Visitor}};
+
+// Finds which functions each top-level function calls, for building a call graph.
+// Only calls whose callee is a plain name, like `foo(1)`, are collected. Method calls and calls through other expressions,
+// like `(list.clear)()`, could call anything, so they're skipped.
+// Calls inside functions declared in a function's body count as calls from the outer function.
+
+impl Program {
+    /// Each top-level function's name, with the names it calls in the order they're first called.
+    #[allow(dead_code)] // For tools that build call graphs
+    pub fn call_targets(&self) -> Vec<(String, Vec<String>)> {
+        self.declarations.iter().filter_map(|declaration| {
+            let Declaration::Function { name, .. } = &declaration.node else {
+                return None;
+            };
+            let mut collector = CallCollector::default();
+            walk_declaration(&mut collector, declaration);
+            Some((name.clone(), collector.targets))
+        }).collect()
+    }
+}
+
+#[derive(Default)]
+struct CallCollector {
+    targets: Vec<String>
+}
+
+impl Visitor for CallCollector {
+    fn visit_expression(&mut self, expression: &Spanned<Expression>) {
+        if let Expression::FunctionCall { callee, .. } = &expression.node
+            && let Expression::Variable { name, .. } = &callee.node
+            && !self.targets.contains(name) {
+            self.targets.push(name.clone());
+        }
+        walk_expression(self, expression);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{parser::Parser, tokenizer::Tokenizer};
+
+    fn call_targets(source: &str) -> Vec<(String, Vec<String>)> {
+        let mut tokenizer = Tokenizer::new(source.to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        Parser::new(tokens).parse_program().unwrap().call_targets()
+    }
+
+    #[test]
+    fn test_call_targets() {
+        let targets = |names: &[&str]| names.iter().map(|name| name.to_string()).collect::<Vec<_>>();
+        assert_eq!(call_targets(r#"
+            func square(x: i32) -> i32 {
+                x * x
+            }
+            const LIMIT: i32 = 10;
+            func main(values: [i32]) -> nil {
+                loop (const value: values) {
+                    if (value > LIMIT) {
+                        print(square(value));
+                    } else {
+                        print(match (value) { 0 => 0, other => square(other) });
+                    };
+                };
+                values.push(square(2));
+                (values.clear)();
+            }
+        "#), vec![
+            ("square".to_string(), vec![]),
+            // The method call and the call through a member access are skipped, but the call in the method's arguments isn't
+            ("main".to_string(), targets(&["print", "square"]))
+        ]);
+    }
+}
@@ -0,0 +1,177 @@
+use std::collections::HashSet;
+
+use super::{ast::{Declaration, Expression, LoopType, Pattern, Spanned, Statement}, visitor::{walk_declaration, walk_expression_node, walk_statement, Visitor}};
+
+// Finds the variables an expression refers to without declaring them itself, like the ones a closure would capture.
+// Blocks, iterator loops, match arms, and if lets bind names for part of the expression, so those names aren't free there.
+// Assigning to a variable counts as referring to it. Functions, including built-ins like `print`, are referred to by name
+// like any other variable, so calling one makes its name free unless it's declared inside the expression.
+
+impl Expression {
+    /// The names this expression refers to that aren't bound inside it.
+    #[allow(dead_code)] // For closure captures, which the interpreter doesn't have yet
+    pub fn free_variables(&self) -> HashSet<String> {
+        let mut collector = FreeVariableCollector::default();
+        collector.visit_node(self);
+        collector.free
+    }
+}
+
+#[derive(Default)]
+struct FreeVariableCollector {
+    /// The names bound in each scope we're in.
+    scopes: Vec<Vec<String>>,
+    free: HashSet<String>
+}
+
+impl FreeVariableCollector {
+    fn refer(&mut self, name: &str) {
+        if !self.scopes.iter().flatten().any(|bound| bound == name) {
+            self.free.insert(name.to_string());
+        }
+    }
+
+    fn bind(&mut self, name: &str) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.push(name.to_string());
+        }
+    }
+
+    fn in_scope(&mut self, bound: Vec<String>, walk: impl FnOnce(&mut Self)) {
+        self.scopes.push(bound);
+        walk(self);
+        self.scopes.pop();
+    }
+
+    fn visit_node(&mut self, expression: &Expression) {
+        match expression {
+            Expression::Variable { name, .. } => self.refer(name),
+            Expression::Assignment { name, value, .. } => {
+                self.refer(name);
+                self.visit_expression(value);
+            },
+            Expression::Block { statements, tail } => self.in_scope(Vec::new(), |collector| {
+                for statement in statements {
+                    match &statement.node {
+                        // Functions can call themselves, so they're bound before their body is visited
+                        Statement::Declaration(Declaration::Function { name, .. }) => {
+                            collector.bind(name);
+                            walk_statement(collector, statement);
+                        },
+                        // Variables are bound after their value, since the value can't see them
+                        Statement::VariableDeclaration { name, .. } | Statement::Declaration(Declaration::Const { name, .. }) => {
+                            walk_statement(collector, statement);
+                            collector.bind(name);
+                        },
+                        _ => walk_statement(collector, statement)
+                    }
+                }
+                if let Some(tail) = tail {
+                    collector.visit_expression(tail);
+                }
+            }),
+            // The iterator is only bound in the body, so it's still free if the iterable refers to it
+            Expression::Loop(LoopType::Iterator { iterator, iterable, body, .. }) => {
+                self.visit_expression(iterable);
+                self.in_scope(vec![iterator.clone()], |collector| collector.visit_expression(body));
+            },
+            Expression::Match { scrutinee, arms } => {
+                self.visit_expression(scrutinee);
+                for arm in arms {
+                    let bound = match &arm.pattern {
+                        Pattern::Binding(name) => vec![name.clone()],
+                        _ => Vec::new()
+                    };
+                    self.in_scope(bound, |collector| collector.visit_expression(&arm.body));
+                }
+            },
+            // Like in a match arm, the binding is only visible in the then branch
+            Expression::IfLet { pattern, value, then_branch, else_branch } => {
+                self.visit_expression(value);
+                let bound = match pattern {
+                    Pattern::Binding(name) => vec![name.clone()],
+                    _ => Vec::new()
+                };
+                self.in_scope(bound, |collector| collector.visit_expression(then_branch));
+                if let Some(else_branch) = else_branch {
+                    self.visit_expression(else_branch);
+                }
+            },
+            _ => walk_expression_node(self, expression)
+        }
+    }
+}
+
+impl Visitor for FreeVariableCollector {
+    fn visit_declaration(&mut self, declaration: &Declaration) {
+        let Declaration::Function { params, body, .. } = declaration else {
+            walk_declaration(self, declaration);
+            return;
+        };
+        // Defaults are evaluated where the function is declared, so the parameters aren't bound in them
+        for default in params.iter().filter_map(|param| param.default.as_ref()) {
+            self.visit_expression(default);
+        }
+        self.in_scope(params.iter().map(|param| param.name.clone()).collect(), |collector| collector.visit_expression(body));
+    }
+
+    fn visit_expression(&mut self, expression: &Spanned<Expression>) {
+        self.visit_node(&expression.node);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser::Parser, tokenizer::Tokenizer};
+
+    /// The free variables of the body of the only function in `source`.
+    fn free_variables(source: &str) -> Vec<String> {
+        let mut tokenizer = Tokenizer::new(source.to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let program = Parser::new(tokens).parse_program().unwrap();
+        let Declaration::Function { body, .. } = &program.declarations[0].node else { unreachable!() };
+        let mut names: Vec<String> = body.free_variables().into_iter().collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_declared_variables_are_bound() {
+        assert_eq!(free_variables(r#"
+            func main() -> i32 {
+                let total: i32 = 1;
+                total += 2;
+                total
+            }
+        "#), Vec::<String>::new());
+        // The value of a declaration can't see the variable it declares
+        assert_eq!(free_variables("func main() -> i32 { const total: i32 = total + 1; total }"), vec!["total"]);
+    }
+
+    #[test]
+    fn test_outer_variables_are_free() {
+        assert_eq!(free_variables(r#"
+            func main() -> nil {
+                {
+                    const inner: i32 = outer * 2;
+                };
+                print(inner);
+            }
+        "#), vec!["inner", "outer", "print"]);
+    }
+
+    #[test]
+    fn test_iterator_loops() {
+        // `i` is bound in the body, but not in the iterable
+        assert_eq!(free_variables(r#"
+            func main() -> nil {
+                loop (const i: 0..i) {
+                    total += i;
+                };
+                print(match (total) { 0 => 1, other => other });
+            }
+        "#), vec!["i", "print", "total"]);
+        assert_eq!(free_variables("func main() -> nil { loop (const i: 0..limit) { total += i; }; }"), vec!["limit", "total"]);
+    }
+}
@@ -0,0 +1,138 @@
+use super::{ast::Program, ast_printer::ASTPrinter};
+
+// Prints the AST as an HTML fragment, for showing it on a web page.
+// This is built on the `ASTPrinter`'s plain output, so the two always show the same tree.
+// Each line becomes a `<div class="ast-node">`, with a node's children nested inside it instead of indented with pipes.
+// Like the ANSI colors, everything before the first colon on a line is styled as the node's kind.
+
+pub struct HtmlPrinter {
+    printer: ASTPrinter
+}
+
+impl HtmlPrinter {
+    pub fn new() -> Self {
+        HtmlPrinter { printer: ASTPrinter::new().with_color(false) }
+    }
+
+    pub fn print_program(&mut self, program: &Program) -> String {
+        let text = self.printer.print_program(program);
+        let mut output = "<div class=\"ast\">".to_string();
+        let mut open = 0;
+        for line in text.lines() {
+            let (mut depth, mut rest) = strip_indentation(line);
+            // Some labels have their child on the same line, like `Body: |  Block:`
+            while let Some((label, child)) = rest.split_once(':') && child.starts_with(" |  ") {
+                open_node(&mut output, &mut open, depth, &print_line(&format!("{}:", label)));
+                (depth, rest) = strip_indentation(&child[1..]);
+            }
+            if !rest.is_empty() {
+                open_node(&mut output, &mut open, depth, &print_line(rest));
+            }
+        }
+        for _ in 0..open {
+            output.push_str("</div>");
+        }
+        output.push_str("</div>");
+        output
+    }
+}
+
+/// How deeply a line of the `ASTPrinter`'s output is indented, and the line without its indentation.
+fn strip_indentation(mut line: &str) -> (usize, &str) {
+    let mut depth = 0;
+    while let Some(rest) = line.strip_prefix("|  ") {
+        line = rest;
+        depth += 1;
+    }
+    (depth, line)
+}
+
+/// Starts a node at `depth`, first closing the nodes that aren't its parents.
+/// `open` is how many nodes are open.
+fn open_node(output: &mut String, open: &mut usize, depth: usize, content: &str) {
+    while *open > depth {
+        output.push_str("</div>");
+        *open -= 1;
+    }
+    // Every line is indented at most one more than the line before it, but this keeps the tags balanced if one isn't
+    while *open < depth {
+        output.push_str("<div class=\"ast-node\">");
+        *open += 1;
+    }
+    output.push_str("<div class=\"ast-node\">");
+    output.push_str(content);
+    *open += 1;
+}
+
+fn print_line(line: &str) -> String {
+    let Some((kind, value)) = line.split_once(':') else {
+        return format!("<span class=\"ast-keyword\">{}</span>", escape(line));
+    };
+    let kind_html = format!("<span class=\"ast-keyword\">{}</span>:", escape(kind));
+    match value.strip_prefix(' ') {
+        Some(value) if kind.ends_with("Literal") => format!("{} <span class=\"ast-literal\">{}</span>", kind_html, escape(value)),
+        _ => format!("{}{}", kind_html, escape(value))
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser::Parser, tokenizer::Tokenizer};
+
+    fn print(source: &str) -> String {
+        let mut tokenizer = Tokenizer::new(source.to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        HtmlPrinter::new().print_program(&Parser::new(tokens).parse_program().unwrap())
+    }
+
+    /// Checks that every tag is closed, and in the right order.
+    fn assert_balanced(html: &str) {
+        let mut open = Vec::new();
+        for tag in html.split('<').skip(1) {
+            let name = tag.split(['>', ' ']).next().unwrap();
+            match name.strip_prefix('/') {
+                Some(name) => assert_eq!(open.pop(), Some(name), "Unbalanced tags in {}", html),
+                None => open.push(name)
+            }
+        }
+        assert!(open.is_empty(), "Unclosed tags in {}", html);
+    }
+
+    #[test]
+    fn test_tree_structure() {
+        let html = print("func main() -> i32 { 1 }");
+        assert_balanced(&html);
+        assert_eq!(html, [
+            "<div class=\"ast\">",
+            "<div class=\"ast-node\"><span class=\"ast-keyword\">Function</span>: main",
+            "<div class=\"ast-node\"><span class=\"ast-keyword\">Parameters</span>: (none)</div>",
+            "<div class=\"ast-node\"><span class=\"ast-keyword\">Return Type</span>: I32</div>",
+            "<div class=\"ast-node\"><span class=\"ast-keyword\">Body</span>:</div>",
+            "<div class=\"ast-node\"><span class=\"ast-keyword\">Block</span>:",
+            "<div class=\"ast-node\"><span class=\"ast-keyword\">Expression (result)</span>:</div>",
+            "<div class=\"ast-node\"><span class=\"ast-keyword\">Integer Literal</span>: <span class=\"ast-literal\">1</span></div>",
+            "</div></div></div>"
+        ].concat());
+    }
+
+    #[test]
+    fn test_escaped_literals() {
+        let html = print(r#"
+            func main() -> nil {
+                print("a < b && c > d");
+                loop (const i: 0..10) {
+                    print(i > 5);
+                };
+            }
+        "#);
+        assert_balanced(&html);
+        assert!(html.contains("<span class=\"ast-literal\">\"a &lt; b &amp;&amp; c &gt; d\"</span>"), "{}", html);
+        assert!(html.contains("<span class=\"ast-keyword\">Binary Operation</span>: &gt;"), "{}", html);
+        assert!(!html.contains(" < ") && !html.contains("&&"));
+    }
+}
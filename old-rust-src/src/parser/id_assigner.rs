@@ -0,0 +1,197 @@
+use super::ast::{Declaration, Expression, ExpressionId, LoopType, Program, Spanned, Statement, StringPart, StructElement};
+
+/// Numbers every `Variable` and `Assignment` in a program in the order they appear, so each one has its own `ExpressionId`.
+/// The parser already numbers expressions as it goes, but programs built or changed after parsing need to be numbered again.
+pub struct IdAssigner {
+    next: u32
+}
+
+impl IdAssigner {
+    pub fn new() -> Self {
+        IdAssigner { next: 0 }
+    }
+
+    /// Continues numbering from `next`, like after another program that was numbered up to it.
+    pub fn starting_at(next: ExpressionId) -> Self {
+        IdAssigner { next: next.0 }
+    }
+
+    /// Numbers the program's expressions and returns the next id that hasn't been used.
+    pub fn assign_program(&mut self, program: &mut Program) -> ExpressionId {
+        for declaration in &mut program.declarations {
+            self.assign_declaration(declaration);
+        }
+        ExpressionId(self.next)
+    }
+
+    fn next_id(&mut self) -> ExpressionId {
+        let id = ExpressionId(self.next);
+        self.next += 1;
+        id
+    }
+
+    fn assign_declaration(&mut self, declaration: &mut Declaration) {
+        match declaration {
+            Declaration::Function { params, body, .. } => {
+                for default in params.iter_mut().filter_map(|param| param.default.as_mut()) {
+                    self.assign_expression(default);
+                }
+                self.assign_expression(body);
+            },
+            Declaration::Const { value, .. } => self.assign_expression(value),
+            Declaration::Struct { elements, .. } => {
+                for element in elements {
+                    if let StructElement::Declaration { declaration, .. } = element {
+                        self.assign_declaration(declaration);
+                    }
+                }
+            },
+            Declaration::TypeDeclaration { .. } | Declaration::Enum { .. } | Declaration::Import { .. } => {}
+        }
+    }
+
+    fn assign_statement(&mut self, statement: &mut Statement) {
+        match statement {
+            Statement::Declaration(declaration) => self.assign_declaration(declaration),
+            Statement::Expression { expression: value, .. } | Statement::VariableDeclaration { value: Some(value), .. } |
+            Statement::Return(Some(value)) | Statement::Defer(value) => self.assign_expression(value),
+            Statement::VariableDeclaration { value: None, .. } | Statement::Return(None) | Statement::Break(_) | Statement::Continue(_) => {}
+        }
+    }
+
+    fn assign_expression(&mut self, expression: &mut Spanned<Expression>) {
+        match &mut expression.node {
+            Expression::Block { statements, tail } => {
+                for statement in statements {
+                    self.assign_statement(statement);
+                }
+                if let Some(tail) = tail {
+                    self.assign_expression(tail);
+                }
+            },
+            Expression::Variable { expression_id, .. } => *expression_id = self.next_id(),
+            // The assignment is numbered before its value, since that's the order they're written in
+            Expression::Assignment { value, expression_id, .. } => {
+                *expression_id = self.next_id();
+                self.assign_expression(value);
+            },
+            Expression::IntegerLiteral { .. } | Expression::NumberLiteral { .. } | Expression::StringLiteral { .. } |
+            Expression::CharLiteral(_) | Expression::BooleanLiteral(_) | Expression::NilLiteral | Expression::SizeOf(_) => {},
+            Expression::FunctionCall { callee, args } | Expression::MethodCall { receiver: callee, args, .. } => {
+                self.assign_expression(callee);
+                for arg in args {
+                    self.assign_expression(arg);
+                }
+            },
+            Expression::BinaryOperation { left, right, .. } | Expression::Range { start: left, end: right, .. } |
+            Expression::MemberAssignment { object: left, value: right, .. } | Expression::Index { object: left, index: right } | Expression::Array { size: left, initial_value: right, .. } => {
+                self.assign_expression(left);
+                self.assign_expression(right);
+            },
+            Expression::UnaryOperation { operand, .. } | Expression::TypeOf(operand) | Expression::Await(operand) | Expression::Try { operand } | Expression::Grouping(operand) |
+            Expression::MemberAccess { object: operand, .. } | Expression::Cast { value: operand, .. } => {
+                self.assign_expression(operand);
+            },
+            Expression::IndexAssignment { object, index, value, .. } => {
+                self.assign_expression(object);
+                self.assign_expression(index);
+                self.assign_expression(value);
+            },
+            Expression::Tuple(elements) | Expression::ArrayLiteral(elements) => {
+                for element in elements {
+                    self.assign_expression(element);
+                }
+            },
+            Expression::InterpolatedString { parts } => {
+                for part in parts {
+                    if let StringPart::Expr(expression) = part {
+                        self.assign_expression(expression);
+                    }
+                }
+            },
+            Expression::StructCreation { fields, .. } => {
+                for (_, value) in fields {
+                    self.assign_expression(value);
+                }
+            },
+            Expression::If { condition, then_branch, else_branch } | Expression::IfLet { value: condition, then_branch, else_branch, .. } => {
+                self.assign_expression(condition);
+                self.assign_expression(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.assign_expression(else_branch);
+                }
+            },
+            Expression::Loop(LoopType::Infinite { body, .. }) => self.assign_expression(body),
+            Expression::Loop(LoopType::While { condition: other, body, .. } | LoopType::Iterator { iterable: other, body, .. }) => {
+                self.assign_expression(other);
+                self.assign_expression(body);
+            },
+            Expression::Match { scrutinee, arms } => {
+                self.assign_expression(scrutinee);
+                for arm in arms {
+                    self.assign_expression(&mut arm.body);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser::{visitor::{self, Visitor}, Parser}, tokenizer::Tokenizer};
+
+    #[derive(Default)]
+    struct IdCollector(Vec<u32>);
+
+    impl Visitor for IdCollector {
+        fn visit_expression(&mut self, expression: &Spanned<Expression>) {
+            if let Expression::Variable { expression_id, .. } | Expression::Assignment { expression_id, .. } = &expression.node {
+                self.0.push(expression_id.0);
+            }
+            visitor::walk_expression(self, expression);
+        }
+    }
+
+    fn parse(source: &str) -> Program {
+        let mut tokenizer = Tokenizer::new(source.to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        Parser::new(tokens).parse_program().unwrap()
+    }
+
+    #[test]
+    fn test_ids_are_unique_and_contiguous() {
+        let mut program = parse(r#"
+            struct Counter {
+                count: i32;
+                func bump(counter: Counter) -> i32 {
+                    counter.count + 1
+                }
+            }
+
+            func main(limit: i32) -> nil {
+                let total: i32 = 0;
+                loop (const i: 0..limit) {
+                    total += i * limit;
+                };
+                if (total > 10) {
+                    total = limit;
+                };;
+                print(match (total) { 11 => limit, other => other });
+                print((total, bump(new Counter { count: total })));
+            }
+        "#);
+
+        let next = IdAssigner::new().assign_program(&mut program);
+        let mut collector = IdCollector::default();
+        collector.visit_program(&program);
+        let mut ids = collector.0;
+        ids.sort();
+        assert_eq!(ids, (0..next.0).collect::<Vec<_>>());
+        assert_eq!(next, ExpressionId(16));
+
+        // Numbering again from where we stopped doesn't reuse any ids
+        let mut other = parse("func other(x: i32) -> i32 { x }");
+        assert_eq!(IdAssigner::starting_at(next).assign_program(&mut other), ExpressionId(17));
+    }
+}
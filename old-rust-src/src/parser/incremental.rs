@@ -0,0 +1,309 @@
+use crate::tokenizer::{Token, Tokenizer};
+
+use super::{ast::{Declaration, Expression, ExpressionId, LoopType, Program, Span, Spanned, Statement, StringPart, StructElement}, id_assigner::IdAssigner, visitor::{self, Visitor}, Parser};
+
+// Reparsing after an edit, for tools like editors that parse the same file over and over.
+// Top-level declarations entirely before the edit are reused as they are, and ones entirely after it are reused with their spans moved
+// by however much the edit grew or shrank the source. Only the declarations in between are parsed again.
+
+/// Parses `source`, the new version of the code `old` was parsed from, after the part of the old code at `edit` was replaced.
+/// Everything outside `edit` has to be unchanged. Like `Parser::parse_program`, this returns `None` if the new code doesn't parse.
+#[allow(dead_code)] // Nothing in the compiler itself edits source, so this is only for tools
+pub fn incremental_reparse(old: &Program, source: &str, edit: Span) -> Option<Program> {
+    let mut tokenizer = Tokenizer::new(source.to_string());
+    let tokens = tokenizer.tokenize().ok()?;
+
+    let reused_before = old.declarations.iter().take_while(|declaration| declaration.span.end < edit.start).count();
+    // A declaration's doc comment isn't part of its span, so it counts as starting where the declaration before it ends.
+    // That way, an edit to the comment (or one that adds a comment) reparses the declaration.
+    let reused_after = (reused_before..old.declarations.len()).rev().take_while(|&i| {
+        i > 0 && old.declarations[i - 1].span.end > edit.end
+    }).count();
+    let reparse_start = old.declarations[..reused_before].last().map(|declaration| declaration.span.end).unwrap_or(0);
+
+    // The code after the edit only moved, so the last token still ends where the last declaration does
+    let (delta, reparse_end) = match (&old.declarations[old.declarations.len() - reused_after..], tokens.last()) {
+        ([], _) => (0, source.len()),
+        ([first, .., last] | [first @ last], Some(token)) => {
+            let delta = token.span.end as isize - last.span.end as isize;
+            let Some(reparse_end) = first.span.start.checked_add_signed(delta) else {
+                return Parser::new(tokens).parse_program();
+            };
+            (delta, reparse_end)
+        },
+        (_, None) => return Parser::new(tokens).parse_program()
+    };
+
+    // If the declarations we'd reuse don't start on a token in the new code, the edit wasn't the one we were told about
+    if reused_after > 0 && !starts_token(tokens, reparse_end) {
+        return Parser::new(tokens).parse_program();
+    }
+
+    let region: Vec<Token> = tokens.iter().filter(|token| token.span.start >= reparse_start && token.span.end <= reparse_end).cloned().collect();
+    let mut reparsed = Parser::new(&region).parse_program()?;
+    // Keep numbering after the expressions we're reusing, so ids stay unique
+    IdAssigner::starting_at(ExpressionId(highest_expression_id(old) + 1)).assign_program(&mut reparsed);
+
+    let mut declarations = old.declarations[..reused_before].to_vec();
+    declarations.extend(reparsed.declarations);
+    for declaration in &old.declarations[old.declarations.len() - reused_after..] {
+        let mut declaration = declaration.clone();
+        declaration.span = shift(declaration.span, delta);
+        shift_declaration(&mut declaration.node, delta);
+        declarations.push(declaration);
+    }
+    Some(Program { declarations })
+}
+
+fn starts_token(tokens: &[Token], offset: usize) -> bool {
+    tokens.iter().any(|token| token.span.start == offset)
+}
+
+#[derive(Default)]
+struct HighestId(u32);
+
+impl Visitor for HighestId {
+    fn visit_expression(&mut self, expression: &Spanned<Expression>) {
+        if let Expression::Variable { expression_id: ExpressionId(id), .. } | Expression::Assignment { expression_id: ExpressionId(id), .. } = expression.node {
+            self.0 = self.0.max(id);
+        }
+        visitor::walk_expression(self, expression);
+    }
+}
+
+fn highest_expression_id(program: &Program) -> u32 {
+    let mut highest = HighestId::default();
+    highest.visit_program(program);
+    highest.0
+}
+
+fn shift(span: Span, delta: isize) -> Span {
+    Span { start: span.start.saturating_add_signed(delta), end: span.end.saturating_add_signed(delta) }
+}
+
+fn shift_declaration(declaration: &mut Declaration, delta: isize) {
+    match declaration {
+        Declaration::Function { params, body, .. } => {
+            for default in params.iter_mut().filter_map(|param| param.default.as_mut()) {
+                shift_expression(default, delta);
+            }
+            shift_expression(body, delta);
+        },
+        Declaration::Const { value, .. } => shift_expression(value, delta),
+        Declaration::Struct { elements, .. } => {
+            for element in elements {
+                if let StructElement::Declaration { declaration, .. } = element {
+                    shift_declaration(declaration, delta);
+                }
+            }
+        },
+        Declaration::TypeDeclaration { .. } | Declaration::Enum { .. } | Declaration::Import { .. } => {}
+    }
+}
+
+fn shift_statement(statement: &mut Spanned<Statement>, delta: isize) {
+    statement.span = shift(statement.span, delta);
+    match &mut statement.node {
+        Statement::Declaration(declaration) => shift_declaration(declaration, delta),
+        Statement::Expression { expression: value, .. } | Statement::VariableDeclaration { value: Some(value), .. } |
+        Statement::Return(Some(value)) | Statement::Defer(value) => shift_expression(value, delta),
+        Statement::VariableDeclaration { value: None, .. } | Statement::Return(None) | Statement::Break(_) | Statement::Continue(_) => {}
+    }
+}
+
+fn shift_expression(expression: &mut Spanned<Expression>, delta: isize) {
+    expression.span = shift(expression.span, delta);
+    match &mut expression.node {
+        Expression::Block { statements, tail } => {
+            for statement in statements {
+                shift_statement(statement, delta);
+            }
+            if let Some(tail) = tail {
+                shift_expression(tail, delta);
+            }
+        },
+        Expression::IntegerLiteral { .. } | Expression::NumberLiteral { .. } | Expression::StringLiteral { .. } |
+        Expression::CharLiteral(_) | Expression::BooleanLiteral(_) | Expression::NilLiteral | Expression::Variable { .. } | Expression::SizeOf(_) => {},
+        Expression::FunctionCall { callee, args } | Expression::MethodCall { receiver: callee, args, .. } => {
+            shift_expression(callee, delta);
+            for arg in args {
+                shift_expression(arg, delta);
+            }
+        },
+        Expression::BinaryOperation { left, right, .. } | Expression::Range { start: left, end: right, .. } |
+        Expression::MemberAssignment { object: left, value: right, .. } | Expression::Index { object: left, index: right } | Expression::Array { size: left, initial_value: right, .. } => {
+            shift_expression(left, delta);
+            shift_expression(right, delta);
+        },
+        Expression::UnaryOperation { operand, .. } | Expression::TypeOf(operand) | Expression::Await(operand) | Expression::Try { operand } | Expression::Grouping(operand) |
+        Expression::Assignment { value: operand, .. } | Expression::MemberAccess { object: operand, .. } | Expression::Cast { value: operand, .. } => {
+            shift_expression(operand, delta);
+        },
+        Expression::IndexAssignment { object, index, value, .. } => {
+            shift_expression(object, delta);
+            shift_expression(index, delta);
+            shift_expression(value, delta);
+        },
+        Expression::Tuple(elements) | Expression::ArrayLiteral(elements) => {
+            for element in elements {
+                shift_expression(element, delta);
+            }
+        },
+        Expression::InterpolatedString { parts } => {
+            for part in parts {
+                if let StringPart::Expr(expression) = part {
+                    shift_expression(expression, delta);
+                }
+            }
+        },
+        Expression::StructCreation { fields, .. } => {
+            for (_, value) in fields {
+                shift_expression(value, delta);
+            }
+        },
+        Expression::If { condition, then_branch, else_branch } | Expression::IfLet { value: condition, then_branch, else_branch, .. } => {
+            shift_expression(condition, delta);
+            shift_expression(then_branch, delta);
+            if let Some(else_branch) = else_branch {
+                shift_expression(else_branch, delta);
+            }
+        },
+        Expression::Loop(LoopType::Infinite { body, .. }) => shift_expression(body, delta),
+        Expression::Loop(LoopType::While { condition: other, body, .. } | LoopType::Iterator { iterable: other, body, .. }) => {
+            shift_expression(other, delta);
+            shift_expression(body, delta);
+        },
+        Expression::Match { scrutinee, arms } => {
+            shift_expression(scrutinee, delta);
+            for arm in arms {
+                shift_expression(&mut arm.body, delta);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::source_printer::SourcePrinter;
+
+    const SOURCE: &str = r#"
+        func first() -> i32 {
+            1
+        }
+
+        func second(x: i32) -> i32 {
+            x + 1
+        }
+
+        func third(y: i32) -> i32 {
+            second(y)
+        }
+    "#;
+
+    fn parse(source: &str) -> Program {
+        let mut tokenizer = Tokenizer::new(source.to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        Parser::new(tokens).parse_program().unwrap()
+    }
+
+    // Reparsed expressions are numbered differently than a full parse would, so programs are compared by how they print
+    fn print(program: &Program) -> String {
+        SourcePrinter::new().print_program(program)
+    }
+
+    fn body_span(declaration: &Spanned<Declaration>) -> Span {
+        match &declaration.node {
+            Declaration::Function { body, .. } => body.span,
+            _ => panic!("Expected a function")
+        }
+    }
+
+    #[test]
+    fn test_reuses_unedited_functions() {
+        let old = parse(SOURCE);
+        let start = SOURCE.find("x + 1").unwrap();
+        let edit = Span { start, end: start + "x + 1".len() };
+        let source = format!("{}{}{}", &SOURCE[..edit.start], "x * 20 + 1", &SOURCE[edit.end..]);
+
+        let new = incremental_reparse(&old, &source, edit).unwrap();
+        assert_eq!(print(&new), print(&parse(&source)));
+        assert_ne!(new.declarations[1], old.declarations[1]);
+
+        // The function before the edit is untouched, and the one after it moved over by the five bytes we added
+        assert_eq!(new.declarations[0].span, old.declarations[0].span);
+        assert_eq!(body_span(&new.declarations[0]), body_span(&old.declarations[0]));
+        assert_eq!(new.declarations[2], old.declarations[2]);
+        assert_eq!(new.declarations[2].span, shift(old.declarations[2].span, 5));
+        assert_eq!(body_span(&new.declarations[2]), shift(body_span(&old.declarations[2]), 5));
+        assert_eq!(&source[new.declarations[2].span.start..new.declarations[2].span.end], &SOURCE[old.declarations[2].span.start..old.declarations[2].span.end]);
+    }
+
+    #[test]
+    fn test_new_ids_are_unique() {
+        let old = parse(SOURCE);
+        let start = SOURCE.find("x + 1").unwrap();
+        let edit = Span { start, end: start + 1 };
+        let source = format!("{}{}{}", &SOURCE[..edit.start], "x + x", &SOURCE[edit.end..]);
+
+        let new = incremental_reparse(&old, &source, edit).unwrap();
+        let mut ids = Vec::new();
+        for declaration in &new.declarations {
+            let Declaration::Function { body, .. } = &declaration.node else { unreachable!() };
+            collect_ids(body, &mut ids);
+        }
+        let count = ids.len();
+        ids.sort();
+        ids.dedup();
+        assert_eq!(ids.len(), count);
+    }
+
+    fn collect_ids(expression: &Spanned<Expression>, ids: &mut Vec<u32>) {
+        struct Ids<'a>(&'a mut Vec<u32>);
+        impl Visitor for Ids<'_> {
+            fn visit_expression(&mut self, expression: &Spanned<Expression>) {
+                if let Expression::Variable { expression_id: ExpressionId(id), .. } = expression.node {
+                    self.0.push(id);
+                }
+                visitor::walk_expression(self, expression);
+            }
+        }
+        Ids(ids).visit_expression(expression);
+    }
+
+    #[test]
+    fn test_edit_across_declarations() {
+        let old = parse(SOURCE);
+        // Deleting from the middle of one function to the middle of the next leaves one function
+        let start = SOURCE.find("1\n").unwrap();
+        let end = SOURCE.find("x + 1").unwrap();
+        let source = format!("{}{}{}", &SOURCE[..start], "2 }\n        func joined() -> i32 {\n            3", &SOURCE[end + 1..]);
+
+        let new = incremental_reparse(&old, &source, Span { start, end: end + 1 }).unwrap();
+        assert_eq!(print(&new), print(&parse(&source)));
+        assert_eq!(new.declarations.len(), 3);
+        assert!(incremental_reparse(&old, "func 1() -> i32 { 1 }", Span { start: 0, end: SOURCE.len() }).is_none());
+    }
+
+    #[test]
+    fn test_edit_to_doc_comment() {
+        let old_source = "func first() -> i32 { 1 }\n\n/// Old\nfunc second() -> i32 { 2 }\n\nfunc third() -> i32 { 3 }\n";
+        let old = parse(old_source);
+        let start = old_source.find("Old").unwrap();
+        let edit = Span { start, end: start + "Old".len() };
+        let source = format!("{}{}{}", &old_source[..edit.start], "Brand new", &old_source[edit.end..]);
+
+        let new = incremental_reparse(&old, &source, edit).unwrap();
+        assert_eq!(new.declarations[1].doc(), Some("Brand new"));
+        assert_eq!(new.declarations[1].doc(), parse(&source).declarations[1].doc());
+        // The function before the comment is still reused
+        assert_eq!(new.declarations[0].span, old.declarations[0].span);
+
+        // Adding a comment where there wasn't one counts too, even with a blank line between it and the declaration
+        let start = old_source.find("{ 2 }\n").unwrap() + "{ 2 }\n".len();
+        let source = format!("{}{}{}", &old_source[..start], "/// Added\n", &old_source[start..]);
+        let new = incremental_reparse(&old, &source, Span { start, end: start }).unwrap();
+        assert_eq!(new.declarations[2].doc(), Some("Added"));
+        assert_eq!(print(&new), print(&parse(&source)));
+    }
+}
@@ -0,0 +1,231 @@
+use std::cell::RefCell;
+
+use serde::{de::{self, value::{Error, U32Deserializer}, DeserializeSeed, EnumAccess, IntoDeserializer, VariantAccess, Visitor}, forward_to_deserialize_any, Deserialize};
+use serde_json::{json, Map, Value};
+
+use super::ast::{BinaryOperator, Declaration, EnumVariant, Expression, FunctionParameter, GenericParam, LoopType, MatchArm, Pattern, Program, Span, Spanned, Statement, StringPart, StructElement, Type, UnaryOperator, VariableMutability, Visibility};
+
+// Generates a JSON Schema for the AST's JSON form (the output of `--only-print-ast-json`).
+// The shapes come from the serde derives themselves: deserializing a node from `ShapeProbe` makes serde hand over
+// the enum's variant names or the struct's field names, so new variants show up in the schema without any changes here.
+// Only tags and field names are described; field values can be any JSON.
+
+/// A JSON Schema describing every AST node, as pretty-printed JSON.
+pub fn ast_json_schema() -> String {
+    let mut definitions = Map::new();
+    definitions.insert("Program".to_string(), shape_of::<Program>().to_schema());
+    definitions.insert("Declaration".to_string(), shape_of::<Declaration>().to_schema());
+    definitions.insert("StructElement".to_string(), shape_of::<StructElement>().to_schema());
+    definitions.insert("EnumVariant".to_string(), shape_of::<EnumVariant>().to_schema());
+    definitions.insert("FunctionParameter".to_string(), shape_of::<FunctionParameter>().to_schema());
+    definitions.insert("GenericParam".to_string(), shape_of::<GenericParam>().to_schema());
+    definitions.insert("Statement".to_string(), shape_of::<Statement>().to_schema());
+    definitions.insert("Expression".to_string(), shape_of::<Expression>().to_schema());
+    definitions.insert("LoopType".to_string(), shape_of::<LoopType>().to_schema());
+    definitions.insert("StringPart".to_string(), shape_of::<StringPart>().to_schema());
+    definitions.insert("MatchArm".to_string(), shape_of::<MatchArm>().to_schema());
+    definitions.insert("Pattern".to_string(), shape_of::<Pattern>().to_schema());
+    definitions.insert("BinaryOperator".to_string(), shape_of::<BinaryOperator>().to_schema());
+    definitions.insert("UnaryOperator".to_string(), shape_of::<UnaryOperator>().to_schema());
+    definitions.insert("VariableMutability".to_string(), shape_of::<VariableMutability>().to_schema());
+    definitions.insert("Visibility".to_string(), shape_of::<Visibility>().to_schema());
+    definitions.insert("Type".to_string(), shape_of::<Type>().to_schema());
+    // Every spanned node has the same wrapper, so one definition covers them all
+    definitions.insert("Spanned".to_string(), shape_of::<Spanned<Expression>>().to_schema());
+    definitions.insert("Span".to_string(), shape_of::<Span>().to_schema());
+
+    let schema = json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "Vixen AST",
+        "$ref": "#/$defs/Program",
+        "$defs": definitions
+    });
+    serde_json::to_string_pretty(&schema).unwrap()
+}
+
+enum Shape {
+    Struct(&'static [&'static str]),
+    Enum(Vec<(&'static str, VariantShape)>)
+}
+
+enum VariantShape {
+    Unit,
+    Newtype,
+    Tuple(usize),
+    Struct(&'static [&'static str])
+}
+
+impl Shape {
+    fn to_schema(&self) -> Value {
+        match self {
+            Shape::Struct(fields) => object_schema(fields),
+            // Enums are externally tagged: unit variants are just their name, and others are `{ "Name": payload }`
+            Shape::Enum(variants) => {
+                let variants = variants.iter().map(|(name, variant)| {
+                    let payload = match variant {
+                        VariantShape::Unit => return json!({ "const": name }),
+                        VariantShape::Newtype => json!({}),
+                        VariantShape::Tuple(length) => json!({ "type": "array", "minItems": length, "maxItems": length }),
+                        VariantShape::Struct(fields) => object_schema(fields)
+                    };
+                    json!({
+                        "type": "object",
+                        "properties": { *name: payload },
+                        "required": [name],
+                        "additionalProperties": false
+                    })
+                }).collect::<Vec<_>>();
+                json!({ "oneOf": variants })
+            }
+        }
+    }
+}
+
+fn object_schema(fields: &[&str]) -> Value {
+    let properties = fields.iter().map(|field| (field.to_string(), json!({}))).collect::<Map<_, _>>();
+    json!({
+        "type": "object",
+        "properties": properties,
+        "required": fields,
+        "additionalProperties": false
+    })
+}
+
+/// Finds the shape of a type by partially deserializing it.
+fn shape_of<'de, T: Deserialize<'de>>() -> Shape {
+    let found = RefCell::new(None);
+    let _ = T::deserialize(ShapeProbe { variant: 0, found: &found });
+    match found.into_inner() {
+        Some(Found::Struct(fields)) => Shape::Struct(fields),
+        Some(Found::Enum(names, first)) => {
+            // The first probe only picks variant 0, so probe again for each of the others
+            let mut variants = vec![(names[0], first)];
+            for (index, name) in names.iter().enumerate().skip(1) {
+                let found = RefCell::new(None);
+                let _ = T::deserialize(ShapeProbe { variant: index as u32, found: &found });
+                let Some(Found::Enum(_, variant)) = found.into_inner() else {
+                    unreachable!("The same type was deserialized differently");
+                };
+                variants.push((*name, variant));
+            }
+            Shape::Enum(variants)
+        },
+        None => panic!("{} isn't a struct or an enum", std::any::type_name::<T>())
+    }
+}
+
+enum Found {
+    Struct(&'static [&'static str]),
+    Enum(&'static [&'static str], VariantShape)
+}
+
+/// A deserializer that records the shape serde asks for, then stops with an error.
+/// For enums, it pretends the input is variant number `variant`.
+struct ShapeProbe<'a> {
+    variant: u32,
+    found: &'a RefCell<Option<Found>>
+}
+
+fn stop<T>() -> Result<T, Error> {
+    Err(de::Error::custom("Stopped after finding the shape"))
+}
+
+impl<'de> de::Deserializer<'de> for ShapeProbe<'_> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Error> {
+        stop()
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(self, _name: &'static str, fields: &'static [&'static str], _visitor: V) -> Result<V::Value, Error> {
+        *self.found.borrow_mut() = Some(Found::Struct(fields));
+        stop()
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(self, _name: &'static str, variants: &'static [&'static str], visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_enum(VariantProbe { index: self.variant, variants, found: self.found })
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map identifier ignored_any
+    }
+}
+
+struct VariantProbe<'a> {
+    index: u32,
+    variants: &'static [&'static str],
+    found: &'a RefCell<Option<Found>>
+}
+
+impl<'de, 'a> EnumAccess<'de> for VariantProbe<'a> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<S: DeserializeSeed<'de>>(self, seed: S) -> Result<(S::Value, Self), Error> {
+        let deserializer: U32Deserializer<Error> = self.index.into_deserializer();
+        Ok((seed.deserialize(deserializer)?, self))
+    }
+}
+
+impl<'de> VariantAccess<'de> for VariantProbe<'_> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        self.record(VariantShape::Unit);
+        Ok(())
+    }
+
+    fn newtype_variant_seed<S: DeserializeSeed<'de>>(self, _seed: S) -> Result<S::Value, Error> {
+        self.record(VariantShape::Newtype);
+        stop()
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, length: usize, _visitor: V) -> Result<V::Value, Error> {
+        self.record(VariantShape::Tuple(length));
+        stop()
+    }
+
+    fn struct_variant<V: Visitor<'de>>(self, fields: &'static [&'static str], _visitor: V) -> Result<V::Value, Error> {
+        self.record(VariantShape::Struct(fields));
+        stop()
+    }
+}
+
+impl VariantProbe<'_> {
+    fn record(&self, variant: VariantShape) {
+        *self.found.borrow_mut() = Some(Found::Enum(self.variants, variant));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schema_covers_expressions() {
+        let schema: Value = serde_json::from_str(&ast_json_schema()).unwrap();
+        let expression_tags = schema["$defs"]["Expression"]["oneOf"].as_array().unwrap().iter()
+            .map(|variant| match &variant["const"] {
+                Value::String(name) => name.clone(),
+                _ => variant["required"][0].as_str().unwrap().to_string()
+            })
+            .collect::<Vec<_>>();
+        for kind in [
+            "Block", "IntegerLiteral", "NumberLiteral", "StringLiteral", "CharLiteral", "InterpolatedString", "BooleanLiteral", "NilLiteral", "Variable",
+            "FunctionCall", "MethodCall", "BinaryOperation", "UnaryOperation", "Assignment", "MemberAccess", "Array", "StructCreation",
+            "If", "IfLet", "Loop", "SizeOf", "TypeOf", "Cast", "Await", "Try", "Grouping", "Match", "Range", "Index", "Tuple", "ArrayLiteral"
+        ] {
+            assert!(expression_tags.iter().any(|tag| tag == kind), "The schema is missing {}", kind);
+        }
+
+        let binary = &schema["$defs"]["Expression"]["oneOf"].as_array().unwrap().iter()
+            .find(|variant| variant["required"][0] == "BinaryOperation")
+            .unwrap()["properties"]["BinaryOperation"];
+        assert_eq!(binary["required"], json!(["left", "operator", "right"]));
+        // Unit variants are plain strings
+        assert!(schema["$defs"]["VariableMutability"]["oneOf"].as_array().unwrap().contains(&json!({ "const": "Mutable" })));
+        assert_eq!(schema["$defs"]["Span"]["required"], json!(["start", "end"]));
+    }
+}
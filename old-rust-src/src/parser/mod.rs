@@ -1,15 +1,29 @@
-use ast::{BinaryOperator, Declaration, Expression, ExpressionId, FunctionParameter, LoopType, Program, Statement, StructElement, Type, UnaryOperator, VariableMutability};
+use ast::{BinaryOperator, Declaration, EnumVariant, Expression, ExpressionId, FunctionParameter, GenericParam, LiteralFloat, LoopType, MatchArm, Pattern, Program, Span, Spanned, Statement, StringPart, StructElement, Type, UnaryOperator, VariableMutability, Visibility, WhereClause};
 
 use crate::tokenizer::{Token, TokenType};
 
 pub mod ast;
+pub mod ast_diff;
 pub mod ast_printer;
+#[allow(dead_code)] // For building ASTs by hand in tests and tools
+pub mod builder;
+pub mod call_graph;
+pub mod free_variables;
+pub mod html_printer;
+pub mod arena;
+pub mod id_assigner;
+pub mod incremental;
+#[cfg(feature = "serde")]
+pub mod json_schema;
+pub mod source_printer;
+pub mod stats;
+pub mod visitor;
 
 #[derive(Debug, PartialEq)]
 pub enum ParseError {
     UnexpectedToken {
         expected: Option<TokenType>,
-        found: Token,
+        found: Box<Token>,
         message: Option<String>
     },
     UnexpectedEndOfInput
@@ -34,18 +48,28 @@ impl std::fmt::Display for ParseError {
     }
 }
 
+/// Something in a block: most things are statements, but an expression at the end without a semicolon is the block's value.
+enum BlockItem {
+    Statement(Statement),
+    Tail(Spanned<Expression>)
+}
+
 pub struct Parser<'a> {
     tokens: &'a [Token],
     current: usize,
     errors: Vec<ParseError>,
     /// The current expression ID. This is used to uniquely identify expressions in the AST.
     /// It is incremented each time a new expression is created.
-    current_expr_id: u32
+    current_expr_id: u32,
+    /// The source code the tokens came from, if number literals should keep how they were written.
+    /// Tools like formatters need this to reproduce spellings like `0xFF` or `1_000`.
+    source: Option<&'a str>
 }
 
 macro_rules! parse_precedence_binary {
     ($self:ident, $next_level:ident, $( ($token_type:path, $operator:expr) ),+ $(,)?) => {
         {
+            let start = $self.start_offset();
             let mut expr = $self.$next_level()?;
             while !$self.is_eof() && let Some(operator) = match $self.peek().token_type.clone() {
                 $(
@@ -53,11 +77,12 @@ macro_rules! parse_precedence_binary {
                 )+
                 _ => None
             } {
+                let left_span = $self.span_from(start);
                 $self.advance(); // Consume the operator
 
-                let right = Box::new($self.$next_level()?);
+                let right = Box::new($self.spanned(Self::$next_level)?);
                 expr = Expression::BinaryOperation {
-                    left: Box::new(expr),
+                    left: Box::new(Spanned::new(expr, left_span)),
                     operator,
                     right
                 };
@@ -79,7 +104,7 @@ macro_rules! parse_precedence_unary {
             } {
                 $self.advance(); // Consume the operator
 
-                let right = Box::new($self.$next_level()?);
+                let right = Box::new($self.spanned(Self::$next_level)?);
                 expr = Some(Expression::UnaryOperation {
                     operator,
                     operand: right
@@ -100,15 +125,51 @@ impl<'a> Parser<'a> {
             tokens,
             current: 0,
             errors: Vec::new(),
-            current_expr_id: 0
+            current_expr_id: 0,
+            source: None
         }
     }
 
+    /// Keeps the original spelling of number literals, like `0xFF` or `1_000`, so they can be printed back the same way.
+    /// `source` has to be the code the tokens were made from, since spellings are read from it using the tokens' spans.
+    pub fn with_literal_spellings(mut self, source: &'a str) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    /// The spelling of the number literal we're about to consume, if we're keeping spellings.
+    fn literal_lexeme(&self) -> Option<String> {
+        let span = self.peek().span;
+        self.source.map(|source| source[span.start..span.end].to_string())
+    }
+
     pub fn get_id(&mut self) -> ExpressionId {
         self.current_expr_id += 1;
         return ExpressionId(self.current_expr_id);
     }
 
+    /// The byte offset where the next token starts.
+    fn start_offset(&self) -> usize {
+        if self.is_eof() {
+            self.tokens.last().map(|token| token.span.end).unwrap_or(0)
+        } else {
+            self.peek().span.start
+        }
+    }
+
+    /// The span from `start` to the end of the last token we consumed.
+    fn span_from(&self, start: usize) -> Span {
+        let end = self.tokens[..self.current.min(self.tokens.len())].last().map(|token| token.span.end).unwrap_or(start);
+        Span { start, end: end.max(start) }
+    }
+
+    /// Runs a parse function and records the span of the tokens it consumed.
+    fn spanned<T>(&mut self, parse: impl FnOnce(&mut Self) -> Result<T, ParseError>) -> Result<Spanned<T>, ParseError> {
+        let start = self.start_offset();
+        let node = parse(self)?;
+        Ok(Spanned::new(node, self.span_from(start)))
+    }
+
     fn is_eof(&self) -> bool {
         self.current >= self.tokens.len()
     }
@@ -146,17 +207,21 @@ impl<'a> Parser<'a> {
             
             match self.peek().token_type {
                 TokenType::FunctionKeyword |
+                TokenType::AsyncKeyword |
                 TokenType::ImportKeyword | 
                 TokenType::StructKeyword |
+                TokenType::EnumKeyword |
                 TokenType::TypeKeyword |
                 TokenType::LetKeyword |
                 TokenType::ConstKeyword |
                 TokenType::LoopKeyword |
                 TokenType::IfKeyword |
                 TokenType::ElseKeyword |
+                TokenType::MatchKeyword |
                 TokenType::ReturnKeyword |
                 TokenType::BreakKeyword |
                 TokenType::ContinueKeyword |
+                TokenType::DeferKeyword |
                 TokenType::OpenCurlyBracket
                 => {
                     break; // Stop at the next function or import keyword
@@ -168,13 +233,29 @@ impl<'a> Parser<'a> {
         }
     }
 
+    /// Parses a program into the arena form of the AST.
+    /// Each declaration is moved into the arena as soon as it's parsed, so the boxed form of the whole program is never built.
+    pub fn parse_program_arena(&mut self) -> Option<arena::ArenaProgram> {
+        let mut arena = arena::ExpressionArena::new();
+        let mut declarations = Vec::new();
+        self.parse_declarations(|decl| declarations.push(Spanned::new(arena.lower_declaration(decl.node), decl.span)))?;
+        Some(arena::ArenaProgram { arena, declarations })
+    }
+
     /// Parses the entire program and returns a Program object. If parsing fails, it returns None.
     pub fn parse_program(&mut self) -> Option<Program> {
         let mut declarations = Vec::new();
+        self.parse_declarations(|decl| declarations.push(decl))?;
+        Some(Program { declarations })
+    }
+
+    /// Parses declarations until the end of the input, passing each one to `add`.
+    /// If any of them fail to parse, the errors are reported and it returns None.
+    fn parse_declarations(&mut self, mut add: impl FnMut(Spanned<Declaration>)) -> Option<()> {
         self.errors.clear(); // Clear previous errors
 
         while !self.is_eof() {
-            let decl = match self.parse_declaration() {
+            let decl = match self.spanned(Self::parse_declaration) {
                 Ok(decl) => decl,
                 Err(e) => {
                     self.errors.push(e); // Store the error
@@ -182,7 +263,7 @@ impl<'a> Parser<'a> {
                     continue; // Try to parse the next declaration
                 }
             };
-            declarations.push(decl);
+            add(decl);
         }
 
         debug_assert!(self.is_eof(), "Unexpected end of input");
@@ -194,7 +275,7 @@ impl<'a> Parser<'a> {
             return None; // Return None if there were errors
         }
 
-        Some(Program { declarations })
+        Some(())
     }
 
     fn expect_identifier(&mut self) -> Result<String, ParseError> {
@@ -205,7 +286,7 @@ impl<'a> Parser<'a> {
             },
             _ => Err(ParseError::UnexpectedToken {
                 expected: Some(TokenType::Identifier("".to_string())),
-                found: self.peek().clone(),
+                found: Box::new(self.peek().clone()),
                 message: Some("Expected an identifier".to_string())
             })
         }
@@ -217,21 +298,51 @@ impl<'a> Parser<'a> {
         } else {
             Err(ParseError::UnexpectedToken {
                 expected: Some(token_type),
-                found: self.peek().clone(),
+                found: Box::new(self.peek().clone()),
                 message: Some(message.to_string())
             })
         }
     }
 
-    fn parse_function_parameters(&mut self) -> Result<Vec<FunctionParameter>, ParseError> {
+    /// Parses a function's parameters, and whether the last one is variadic.
+    fn parse_function_parameters(&mut self) -> Result<(Vec<FunctionParameter>, bool), ParseError> {
         self.expect(TokenType::OpenParenthesis, "Expected open parentheses after function name")?; // Expect an open parenthesis
         
         let mut params = Vec::new();
+        let mut is_variadic = false;
         while !self.is_eof() && self.peek().token_type != TokenType::CloseParenthesis {
+            if is_variadic {
+                return Err(ParseError::UnexpectedToken {
+                    expected: Some(TokenType::CloseParenthesis),
+                    found: Box::new(self.peek().clone()),
+                    message: Some("Only the last parameter can be variadic".to_string())
+                });
+            }
+            is_variadic = self.advance_if(TokenType::Ellipsis);
             let name = self.expect_identifier()?;
             self.expect(TokenType::Colon, "Expected colon after function parameter for type")?; // Expect a colon after the name
             let param_type = self.parse_type()?;
-            params.push(FunctionParameter { name, param_type });
+            if is_variadic && self.is_match(TokenType::AssignmentOperator) {
+                return Err(ParseError::UnexpectedToken {
+                    expected: None,
+                    found: Box::new(self.peek().clone()),
+                    message: Some("Variadic parameters can't have default values".to_string())
+                });
+            }
+            let default = if self.advance_if(TokenType::AssignmentOperator) {
+                Some(Box::new(self.spanned(Self::parse_expression)?))
+            } else {
+                None
+            };
+            // Arguments are matched to parameters in order, so the ones that can be left out have to be at the end
+            if default.is_none() && !is_variadic && params.last().is_some_and(|param: &FunctionParameter| param.default.is_some()) {
+                return Err(ParseError::UnexpectedToken {
+                    expected: Some(TokenType::AssignmentOperator),
+                    found: Box::new(self.peek().clone()),
+                    message: Some("Parameters without default values must come before ones with them".to_string())
+                });
+            }
+            params.push(FunctionParameter { name, param_type, default });
 
             if self.is_match(TokenType::Comma) {
                 self.advance(); // Consume the comma
@@ -242,27 +353,45 @@ impl<'a> Parser<'a> {
 
         self.expect(TokenType::CloseParenthesis, "Unmatched open parentheses")?; // Expect a close parenthesis
 
-        Ok(params)
+        Ok((params, is_variadic))
     }
 
     pub(crate) fn parse_declaration(&mut self) -> Result<Declaration, ParseError> {
+        // Constants are only declarations at the top level. In a block, `const` starts an immutable variable instead.
+        if self.advance_if(TokenType::ConstKeyword) {
+            let name = self.expect_identifier()?;
+            self.expect(TokenType::Colon, "Expected colon after constant name for type")?;
+            let const_type = self.parse_type()?;
+            self.expect(TokenType::AssignmentOperator, "Expected assignment operator after constant type")?;
+            let value = Box::new(self.spanned(Self::parse_expression)?);
+            self.expect(TokenType::Semicolon, "Expected semicolon after constant declaration")?;
+            return Ok(Declaration::Const { name, const_type, value });
+        }
+
         if let Some(decl) = self.try_parse_declaration()? {
             Ok(decl)
         } else {
             Err(ParseError::UnexpectedToken {
                 expected: None,
-                found: self.peek().clone(),
-                message: Some("Expected a function, struct, type, or import declaration".to_string())
+                found: Box::new(self.peek().clone()),
+                message: Some("Expected a function, struct, type, const, or import declaration".to_string())
             })
         }
     }
 
-    fn parse_generic_args(&mut self) -> Result<Vec<String>, ParseError> {
+    fn parse_generic_args(&mut self) -> Result<Vec<GenericParam>, ParseError> {
         if self.advance_if(TokenType::OpenAngleBracket) {
             let mut args = Vec::new();
             while !self.is_eof() && self.peek().token_type != TokenType::CloseAngleBracket {
-                let arg = self.expect_identifier()?;
-                args.push(arg);
+                let name = self.expect_identifier()?;
+                let mut bounds = Vec::new();
+                if self.advance_if(TokenType::Colon) {
+                    bounds.push(self.parse_type()?);
+                    while self.advance_if(TokenType::AddOperator) {
+                        bounds.push(self.parse_type()?);
+                    }
+                }
+                args.push(GenericParam { name, bounds });
                 if self.is_match(TokenType::Comma) {
                     self.advance(); // Consume the comma
                 } else {
@@ -276,6 +405,29 @@ impl<'a> Parser<'a> {
         }
     }
 
+    /// Parses an optional `where T: A + B, U: C` list of generic bounds.
+    fn parse_where_clauses(&mut self) -> Result<Vec<WhereClause>, ParseError> {
+        let mut clauses = Vec::new();
+        if !self.advance_if(TokenType::WhereKeyword) {
+            return Ok(clauses);
+        }
+
+        loop {
+            let name = self.expect_identifier()?;
+            self.expect(TokenType::Colon, "Expected colon after generic argument name in where clause")?;
+            let mut bounds = vec![self.expect_identifier()?];
+            while self.advance_if(TokenType::AddOperator) {
+                bounds.push(self.expect_identifier()?);
+            }
+            clauses.push((name, bounds));
+
+            if !self.advance_if(TokenType::Comma) {
+                break; // No more clauses
+            }
+        }
+        Ok(clauses)
+    }
+
     fn parse_generics(&mut self) -> Result<Vec<Type>, ParseError> {
         if self.advance_if(TokenType::OpenAngleBracket) {
             let mut generics = Vec::new();
@@ -296,33 +448,71 @@ impl<'a> Parser<'a> {
     }
 
     fn try_parse_declaration(&mut self) -> Result<Option<Declaration>, ParseError> {
+        // Doc comments are kept on the token after them, which is the `pub` for a public struct member
+        let doc = match self.tokens[..self.current].last() {
+            Some(token) if token.token_type == TokenType::PubKeyword => token.doc.clone(),
+            _ if !self.is_eof() => self.peek().doc.clone(),
+            _ => None
+        };
+        let is_async = self.advance_if(TokenType::AsyncKeyword);
+        if is_async && !self.is_match(TokenType::FunctionKeyword) {
+            return Err(ParseError::UnexpectedToken {
+                expected: Some(TokenType::FunctionKeyword),
+                found: Box::new(self.peek().clone()),
+                message: Some("Expected func after async".to_string())
+            });
+        }
+
         if self.advance_if(TokenType::FunctionKeyword) {
             let name = self.expect_identifier()?;
             let generic_args = self.parse_generic_args()?;
-            let params = self.parse_function_parameters()?;
+            let (params, is_variadic) = self.parse_function_parameters()?;
             self.expect(TokenType::Arrow, "Expected arrow after function parameters for type")?;
             let return_type = self.parse_type()?;
-            let body = self.parse_block()?;
-            Ok(Some(Declaration::Function { name, params, return_type, generic_args, body: Box::new(body) }))
+            let where_clauses = self.parse_where_clauses()?;
+            let body = self.spanned(Self::parse_block)?;
+            Ok(Some(Declaration::Function { name, params, return_type, generic_args, where_clauses, body: Box::new(body), is_async, is_variadic, doc }))
         } else if self.advance_if(TokenType::ImportKeyword) {
             let mut path = vec![
                 self.expect_identifier()? // Expect the first part of the path
             ];
 
+            let mut items = None;
             while !self.is_eof() {
                 if self.advance_if(TokenType::Dot) {
+                    // A list of names, like `foo.{a, b}`, ends the path
+                    if self.advance_if(TokenType::OpenCurlyBracket) {
+                        let mut names = Vec::new();
+                        while !self.is_eof() && self.peek().token_type != TokenType::CloseCurlyBracket {
+                            names.push(self.expect_identifier()?);
+                            if !self.advance_if(TokenType::Comma) {
+                                break; // No more names
+                            }
+                        }
+                        self.expect(TokenType::CloseCurlyBracket, "Unmatched open brace in import list")?;
+                        items = Some(names);
+                        break;
+                    }
                     path.push(self.expect_identifier()?); // Expect the next part of the path
                 } else {
                     break; // No more parts of the path
                 }
             }
 
+            // Only a whole path can be renamed, since a list already names what it imports
+            let alias = if items.is_none() && self.advance_if(TokenType::AsKeyword) {
+                Some(self.expect_identifier()?)
+            } else {
+                None
+            };
+
             self.expect(TokenType::Semicolon, "Expected semicolon after import path")?; // Expect a semicolon
 
-            Ok(Some(Declaration::Import { path }))
+            Ok(Some(Declaration::Import { path, alias, items }))
         } else if self.advance_if(TokenType::StructKeyword) {
             let name = self.expect_identifier()?;
             let generic_args = self.parse_generic_args()?;
+            let where_clauses = self.parse_where_clauses()?;
             self.expect(TokenType::OpenCurlyBracket, "Expected open brace after struct name")?;
             let mut declarations = Vec::new();
             while !self.is_eof() && self.peek().token_type != TokenType::CloseCurlyBracket {
@@ -330,22 +520,58 @@ impl<'a> Parser<'a> {
                 declarations.push(decl);
             }
             self.expect(TokenType::CloseCurlyBracket, "Unmatched open brace")?;
-            Ok(Some(Declaration::Struct { name, elements: declarations, generic_args }))
+            Ok(Some(Declaration::Struct { name, elements: declarations, generic_args, where_clauses, doc }))
+        } else if self.advance_if(TokenType::EnumKeyword) {
+            let name = self.expect_identifier()?;
+            let generic_args = self.parse_generic_args()?;
+            let where_clauses = self.parse_where_clauses()?;
+            self.expect(TokenType::OpenCurlyBracket, "Expected open brace after enum name")?;
+            let mut variants = Vec::new();
+            while !self.is_eof() && self.peek().token_type != TokenType::CloseCurlyBracket {
+                variants.push(self.parse_enum_variant()?);
+                if !self.advance_if(TokenType::Comma) {
+                    break; // No more variants
+                }
+            }
+            self.expect(TokenType::CloseCurlyBracket, "Unmatched open brace")?;
+            Ok(Some(Declaration::Enum { name, variants, generic_args, where_clauses, doc }))
         } else if self.advance_if(TokenType::TypeKeyword) {
             let name = self.expect_identifier()?;
             let generic_args = self.parse_generic_args()?;
+            let where_clauses = self.parse_where_clauses()?;
             self.expect(TokenType::AssignmentOperator, "Expected assignment operator after type name")?; // Expect an assignment operator
             let alias = self.parse_type()?;
             self.expect(TokenType::Semicolon, "Expected semicolon after type declaration")?; // Expect a semicolon
-            Ok(Some(Declaration::TypeDeclaration { name, alias, generic_args }))
+            Ok(Some(Declaration::TypeDeclaration { name, alias, generic_args, where_clauses, doc }))
         } else {
             Ok(None)
         }
     }
 
+    fn parse_enum_variant(&mut self) -> Result<EnumVariant, ParseError> {
+        let name = self.expect_identifier()?;
+        let mut associated_types = Vec::new();
+        if self.advance_if(TokenType::OpenParenthesis) {
+            while !self.is_eof() && self.peek().token_type != TokenType::CloseParenthesis {
+                associated_types.push(self.parse_type()?);
+                if !self.advance_if(TokenType::Comma) {
+                    break; // No more types
+                }
+            }
+            self.expect(TokenType::CloseParenthesis, "Unmatched open parenthesis")?;
+        }
+        Ok(EnumVariant { name, associated_types })
+    }
+
     fn parse_struct_element(&mut self) -> Result<StructElement, ParseError> {
-        if let Some(decl) = self.try_parse_declaration()? {
-            return Ok(StructElement::Declaration(decl)); // Parse a declaration
+        let visibility = if self.advance_if(TokenType::PubKeyword) {
+            Visibility::Public
+        } else {
+            Visibility::Private
+        };
+
+        if let Some(declaration) = self.try_parse_declaration()? {
+            return Ok(StructElement::Declaration { declaration, visibility }); // Parse a declaration
         }
 
         let name = self.expect_identifier()?;
@@ -353,10 +579,19 @@ impl<'a> Parser<'a> {
         let field_type = self.parse_type()?;
         self.expect(TokenType::Semicolon, "Expected semicolon after struct field declaration")?; // Expect a semicolon
 
-        Ok(StructElement::Field { name, field_type })
+        Ok(StructElement::Field { name, field_type, visibility })
     }
 
     fn parse_type(&mut self) -> Result<Type, ParseError> {
+        let mut ty = self.parse_type_without_suffix()?;
+        // Optionals, like `i32?`. These can nest, so `i32??` is an optional optional.
+        while self.advance_if(TokenType::QuestionMark) {
+            ty = Type::Optional(Box::new(ty));
+        }
+        Ok(ty)
+    }
+
+    fn parse_type_without_suffix(&mut self) -> Result<Type, ParseError> {
         match self.peek().token_type.clone() {
             TokenType::Identifier(ref name) => {
                 self.advance(); // Consume the identifier
@@ -373,35 +608,128 @@ impl<'a> Parser<'a> {
                     "f64" => Ok(Type::F64),
                     "bool" => Ok(Type::Boolean),
                     "char" => Ok(Type::Character),
+                    "nil" => Ok(Type::Nil),
+                    "never" => Ok(Type::Never),
                     _ => {
                         // Custom types (structs, enums, etc.)
-                        // We can't use parse_generic_args because it expects identifiers, while we need types.
-                        let generics = self.parse_generics()?;
-                        Ok(Type::Identifier { name: name.clone(), generics })
+                        // We can't use parse_generic_args because it expects names with bounds, while we need types.
+                        let mut generics = self.parse_generics()?;
+                        // Maps are built in, so later stages can recognize them without comparing names
+                        match (name.as_str(), generics.len()) {
+                            ("Map", 2) => {
+                                let value = generics.pop().unwrap();
+                                let key = generics.pop().unwrap();
+                                Ok(Type::Map { key: Box::new(key), value: Box::new(value) })
+                            },
+                            _ => Ok(Type::Identifier { name: name.clone(), generics })
+                        }
                     }
                 }
             },
+            TokenType::Ampersand => {
+                // References, like `&i32` or `&mut i32`. `mut` isn't a keyword, so it's only special here.
+                self.advance();
+                let mutable = matches!(&self.peek().token_type, TokenType::Identifier(name) if name == "mut");
+                if mutable {
+                    self.advance(); // Consume 'mut'
+                }
+                let inner = self.parse_type()?;
+                Ok(Type::Reference { mutable, inner: Box::new(inner) })
+            },
+            TokenType::AndOperator => {
+                // `&&i32` is a reference to a reference, but it's tokenized as `&&`
+                self.advance();
+                let inner = self.parse_type()?;
+                Ok(Type::Reference { mutable: false, inner: Box::new(Type::Reference { mutable: false, inner: Box::new(inner) }) })
+            },
             TokenType::OpenSquareBracket => {
-                // Arrays
+                // Arrays, like `[i32]`, or `[i32; 4]` if they have a fixed size
                 self.advance();
                 let element_type = self.parse_type()?;
+                let size = if self.advance_if(TokenType::Semicolon) {
+                    match self.peek().token_type {
+                        TokenType::IntegerLiteral(size) if usize::try_from(size).is_ok() => {
+                            self.advance();
+                            Some(size as usize)
+                        },
+                        _ => return Err(ParseError::UnexpectedToken {
+                            expected: None,
+                            found: Box::new(self.peek().clone()),
+                            message: Some("Expected a non-negative integer for the array size".to_string())
+                        })
+                    }
+                } else {
+                    None
+                };
                 self.expect(TokenType::CloseSquareBracket, "Unmatched open square bracket")?;
-                Ok(Type::Array(Box::new(element_type)))
+                Ok(Type::Array { element: Box::new(element_type), size })
+            },
+            TokenType::OpenParenthesis => {
+                // Tuples
+                self.advance();
+                let mut elements = Vec::new();
+                let mut trailing_comma = false;
+                while !self.is_eof() && self.peek().token_type != TokenType::CloseParenthesis {
+                    elements.push(self.parse_type()?);
+                    trailing_comma = self.advance_if(TokenType::Comma);
+                    if !trailing_comma {
+                        break; // No more elements
+                    }
+                }
+                self.expect(TokenType::CloseParenthesis, "Unmatched open parentheses")?;
+
+                // A single type in parentheses without a trailing comma is just that type
+                if elements.len() == 1 && !trailing_comma {
+                    return Ok(elements.remove(0));
+                }
+                Ok(Type::Tuple(elements))
+            },
+            TokenType::OpenCurlyBracket => {
+                // Records
+                self.advance();
+                let mut fields = Vec::new();
+                while !self.is_eof() && self.peek().token_type != TokenType::CloseCurlyBracket {
+                    let name = self.expect_identifier()?;
+                    self.expect(TokenType::Colon, "Expected colon after record field name")?; // Expect a colon after the name
+                    let field_type = self.parse_type()?;
+                    fields.push((name, field_type));
+                    if !self.advance_if(TokenType::Comma) {
+                        break; // No more fields
+                    }
+                }
+                self.expect(TokenType::CloseCurlyBracket, "Unmatched open brace")?;
+                Ok(Type::Record(fields))
             },
             _ => Err(ParseError::UnexpectedToken {
                 expected: Some(TokenType::Identifier("".to_string())),
-                found: self.peek().clone(),
+                found: Box::new(self.peek().clone()),
                 message: Some("Expected a type identifier".to_string())
             })
         }
     }
 
+    /// Loop bodies have to be blocks, so it's always clear which loop a `break` or `continue` belongs to.
+    fn parse_loop_body(&mut self) -> Result<Expression, ParseError> {
+        if self.is_eof() {
+            return Err(ParseError::UnexpectedEndOfInput);
+        }
+        if !self.is_match(TokenType::OpenCurlyBracket) {
+            return Err(ParseError::UnexpectedToken {
+                expected: Some(TokenType::OpenCurlyBracket),
+                found: Box::new(self.peek().clone()),
+                message: Some("Loop bodies must be blocks; try wrapping the body in braces".to_string())
+            });
+        }
+        self.parse_block()
+    }
+
     pub(crate) fn parse_block(&mut self) -> Result<Expression, ParseError> {
         self.expect(TokenType::OpenCurlyBracket, "Expected open brace")?;
         let mut statements = Vec::new();
+        let mut tail = None;
         while !self.is_eof() && self.peek().token_type != TokenType::CloseCurlyBracket {
-            let stmt = match self.parse_statement() {
-                Ok(stmt) => stmt,
+            let item = match self.spanned(Self::parse_block_item) {
+                Ok(item) => item,
                 Err(e) => {
                     self.errors.push(e); // Store the error
                     self.synchronize(); // Skip to the next statement
@@ -409,22 +737,32 @@ impl<'a> Parser<'a> {
                 }
             };
 
-            let is_result_expression = match stmt {
-                Statement::Expression { result: true, .. } => true,
-                _ => false
-            };
-            statements.push(stmt);
-            if is_result_expression {
-                break;
+            match item.node {
+                BlockItem::Statement(statement) => statements.push(Spanned::new(statement, item.span)),
+                BlockItem::Tail(expression) => {
+                    // Nothing can come after the tail
+                    tail = Some(Box::new(expression));
+                    break;
+                }
             }
         }
         self.expect(TokenType::CloseCurlyBracket, "Unmatched open brace")?;
-        Ok(Expression::Block(statements))
+        Ok(Expression::Block { statements, tail })
     }
 
-    pub(crate) fn parse_statement(&mut self) -> Result<Statement, ParseError> {
+    /// Parses the optional label after `break` or `continue`, like `break 'outer;`.
+    fn parse_jump_label(&mut self) -> Option<String> {
+        let TokenType::Label(label) = self.peek().token_type.clone() else {
+            return None;
+        };
+        self.advance(); // Consume the label
+        Some(label)
+    }
+
+    /// Parses a statement, or the expression at the end of a block that gives it its value.
+    fn parse_block_item(&mut self) -> Result<BlockItem, ParseError> {
         if let Some(decl) = self.try_parse_declaration()? {
-            return Ok(Statement::Declaration(decl)); // Parse a declaration
+            return Ok(BlockItem::Statement(Statement::Declaration(decl))); // Parse a declaration
         }
 
         match self.peek().token_type.clone() {
@@ -432,13 +770,15 @@ impl<'a> Parser<'a> {
             TokenType::BreakKeyword => {
                 // TODO: Breaking with values
                 self.advance(); // Consume 'break'
+                let label = self.parse_jump_label();
                 self.expect(TokenType::Semicolon, "Expected semicolon after break")?; // Expect a semicolon
-                Ok(Statement::Break)
+                Ok(BlockItem::Statement(Statement::Break(label)))
             },
             TokenType::ContinueKeyword => {
                 self.advance(); // Consume 'continue'
+                let label = self.parse_jump_label();
                 self.expect(TokenType::Semicolon, "Expected semicolon after continue")?; // Expect a semicolon
-                Ok(Statement::Continue)
+                Ok(BlockItem::Statement(Statement::Continue(label)))
             },
 
             // Variable declaration
@@ -450,12 +790,21 @@ impl<'a> Parser<'a> {
                 };
                 self.advance(); // Consume 'let' or 'const'
                 let name = self.expect_identifier()?;
-                self.expect(TokenType::Colon, "Expected colon after variable name")?; // Expect a colon after the name
-                let variable_type = self.parse_type()?;
-                self.expect(TokenType::AssignmentOperator, "Expected assignment operator after variable type")?; // Expect an assignment operator
-                let value = Box::new(self.parse_expression()?);
+                // The type can be left out, like `let x = 5;`, to infer it from the value
+                let variable_type = if self.advance_if(TokenType::Colon) {
+                    self.parse_type()?
+                } else {
+                    Type::Inferred
+                };
+                // A mutable variable with a type can be declared without a value, like `let x: i32;`
+                let value = if self.is_match(TokenType::Semicolon) && mutability == VariableMutability::Mutable && variable_type != Type::Inferred {
+                    None
+                } else {
+                    self.expect(TokenType::AssignmentOperator, "Expected assignment operator after variable name or type")?; // Expect an assignment operator
+                    Some(Box::new(self.spanned(Self::parse_expression)?))
+                };
                 self.expect(TokenType::Semicolon, "Expected semicolon after variable declaration")?; // Expect a semicolon
-                Ok(Statement::VariableDeclaration { mutability, name, variable_type, value })
+                Ok(BlockItem::Statement(Statement::VariableDeclaration { mutability, name, variable_type, value }))
             },
 
             // Return
@@ -464,26 +813,29 @@ impl<'a> Parser<'a> {
                 let value = if self.is_match(TokenType::Semicolon) {
                     None
                 } else {
-                    Some(Box::new(self.parse_expression()?))
+                    Some(Box::new(self.spanned(Self::parse_expression)?))
                 };
                 self.expect(TokenType::Semicolon, "Expected semicolon after return")?; // Expect a semicolon
-                Ok(Statement::Return(value))
+                Ok(BlockItem::Statement(Statement::Return(value)))
+            },
+
+            // Defer
+            TokenType::DeferKeyword => {
+                self.advance(); // Consume 'defer'
+                let value = Box::new(self.spanned(Self::parse_expression)?);
+                self.expect(TokenType::Semicolon, "Expected semicolon after deferred expression")?; // Expect a semicolon
+                Ok(BlockItem::Statement(Statement::Defer(value)))
             },
 
             _ => {
                 // Try to parse as an expression statement
-                let expr = self.parse_expression()?;
-                // If there's a semicolon, this is an expression. Otherwise, it's a result value.
-                let result = if self.is_match(TokenType::Semicolon) {
-                    self.advance(); // Consume the semicolon
-                    false // This is just an expression statement
+                let expr = self.spanned(Self::parse_expression)?;
+                // If there's a semicolon, this is an expression statement. Otherwise, it's the block's value.
+                if self.advance_if(TokenType::Semicolon) {
+                    Ok(BlockItem::Statement(Statement::Expression { expression: Box::new(expr) }))
                 } else {
-                    true // This is a result value
-                };
-                Ok(Statement::Expression {
-                    expression: Box::new(expr),
-                    result
-                })
+                    Ok(BlockItem::Tail(expr))
+                }
             }
         }
     }
@@ -494,6 +846,22 @@ impl<'a> Parser<'a> {
             return self.parse_block(); // Parse a block
         }
 
+        // Loops can be labeled, like `'outer: loop { ... }`, so nested loops can break out of them
+        let label = if let TokenType::Label(label) = self.peek().token_type.clone() {
+            self.advance(); // Consume the label
+            self.expect(TokenType::Colon, "Expected colon after loop label")?; // Expect a colon after the label
+            if !self.is_match(TokenType::LoopKeyword) {
+                return Err(ParseError::UnexpectedToken {
+                    expected: Some(TokenType::LoopKeyword),
+                    found: Box::new(self.peek().clone()),
+                    message: Some("Expected a loop after a label".to_string())
+                });
+            }
+            Some(label)
+        } else {
+            None
+        };
+
         // Try to parse loop statements
         if self.advance_if(TokenType::LoopKeyword) {
             // If there's a set of parentheses, this is a while loop or iterator loop
@@ -507,30 +875,33 @@ impl<'a> Parser<'a> {
                     self.advance(); // Consume 'let' or 'const'
                     let iterator = self.expect_identifier()?;
                     self.expect(TokenType::Colon, "Expected colon after variable name")?; // Expect a colon after the name
-                    let iterable = Box::new(self.parse_expression()?);
+                    let iterable = Box::new(self.spanned(Self::parse_expression)?);
                     self.expect(TokenType::CloseParenthesis, "Unmatched open parentheses")?; // Expect a close parenthesis
-                    let body = Box::new(self.parse_block()?);
+                    let body = Box::new(self.spanned(Self::parse_loop_body)?);
                     return Ok(Expression::Loop(LoopType::Iterator {
                         body,
                         mutability,
                         iterator,
-                        iterable
+                        iterable,
+                        label
                     }));
                 }
                 
-                let condition = Box::new(self.parse_expression()?);
+                let condition = Box::new(self.spanned(Self::parse_expression)?);
 
                 self.expect(TokenType::CloseParenthesis, "Unmatched open parentheses")?; // Expect a close parenthesis
-                let body = Box::new(self.parse_block()?);
+                let body = Box::new(self.spanned(Self::parse_loop_body)?);
                 return Ok(Expression::Loop(LoopType::While {
                     condition,
-                    body
+                    body,
+                    label
                 }));
             } else {
                 // Otherwise, this is an infinite loop
-                let body = Box::new(self.parse_block()?);
+                let body = Box::new(self.spanned(Self::parse_loop_body)?);
                 return Ok(Expression::Loop(LoopType::Infinite {
-                    body
+                    body,
+                    label
                 }));
             }
         }
@@ -538,19 +909,35 @@ impl<'a> Parser<'a> {
         // Try to parse if statements
         if self.advance_if(TokenType::IfKeyword) {
             self.expect(TokenType::OpenParenthesis, "Expected open parentheses after if")?; // Expect an open parenthesis
-            let condition = Box::new(self.parse_expression()?);
+            // Conditional bindings, like `if (let value = maybe_value)`
+            let binding = if self.advance_if(TokenType::LetKeyword) {
+                let pattern = self.parse_pattern()?;
+                self.expect(TokenType::AssignmentOperator, "Expected = after if let pattern")?;
+                Some(pattern)
+            } else {
+                None
+            };
+            let condition = Box::new(self.spanned(Self::parse_expression)?);
             self.expect(TokenType::CloseParenthesis, "Unmatched open parentheses")?; // Expect a close parenthesis
-            let body = Box::new(self.parse_expression()?);
+            let body = Box::new(self.spanned(Self::parse_expression)?);
 
             // Optional semicolon after the if statement
             self.advance_if(TokenType::Semicolon);
 
             let else_branch = if self.advance_if(TokenType::ElseKeyword) {
-                Some(Box::new(self.parse_expression()?)) // Parse the else branch
+                Some(Box::new(self.spanned(Self::parse_expression)?)) // Parse the else branch
             } else {
                 None // No else branch
             };
 
+            if let Some(pattern) = binding {
+                return Ok(Expression::IfLet {
+                    pattern,
+                    value: condition,
+                    then_branch: body,
+                    else_branch
+                });
+            }
             return Ok(Expression::If {
                 condition,
                 then_branch: body,
@@ -558,14 +945,40 @@ impl<'a> Parser<'a> {
             });
         }
 
-        if self.advance_if(TokenType::OpenSquareBracket) {
-            // Array creation
-            let element_type = self.parse_type()?;
-            self.expect(TokenType::Comma, "Expected comma after array type")?;
-            let size = Box::new(self.parse_expression()?);
-            self.expect(TokenType::CloseSquareBracket, "Unmatched open square bracket")?;
-            self.expect(TokenType::OpenCurlyBracket, "Expected open brace after array size")?;
-            let initial_value = Box::new(self.parse_expression()?);
+        // Try to parse match expressions
+        if self.advance_if(TokenType::MatchKeyword) {
+            self.expect(TokenType::OpenParenthesis, "Expected open parentheses after match")?;
+            let scrutinee = Box::new(self.spanned(Self::parse_expression)?);
+            self.expect(TokenType::CloseParenthesis, "Unmatched open parentheses")?;
+            self.expect(TokenType::OpenCurlyBracket, "Expected open brace after match scrutinee")?;
+
+            let mut arms = Vec::new();
+            while !self.is_eof() && self.peek().token_type != TokenType::CloseCurlyBracket {
+                let pattern = self.parse_pattern()?;
+                self.expect(TokenType::FatArrow, "Expected => after match pattern")?;
+                let body = Box::new(self.spanned(Self::parse_expression)?);
+                // Arms are separated by commas, but they're optional after blocks
+                let needs_comma = !matches!(body.node, Expression::Block { .. });
+                arms.push(MatchArm { pattern, body });
+                if !self.advance_if(TokenType::Comma) && needs_comma {
+                    break; // No more arms
+                }
+            }
+            self.expect(TokenType::CloseCurlyBracket, "Unmatched open brace")?;
+
+            return Ok(Expression::Match { scrutinee, arms });
+        }
+
+        if self.is_match(TokenType::OpenSquareBracket) {
+            // Array creation, like `[i32, 3] { 0 }`, starts the same way as an array literal like `[a, 3]`.
+            // It's only array creation if there's a brace after the brackets, so we go back to the bracket if there isn't.
+            let checkpoint = (self.current, self.current_expr_id, self.errors.len());
+            let Ok((element_type, size)) = self.parse_array_creation_header() else {
+                (self.current, self.current_expr_id) = (checkpoint.0, checkpoint.1);
+                self.errors.truncate(checkpoint.2);
+                return self.parse_array_literal();
+            };
+            let initial_value = Box::new(self.spanned(Self::parse_expression)?);
             self.expect(TokenType::CloseCurlyBracket, "Unmatched open brace")?;
 
             return Ok(Expression::Array {
@@ -576,14 +989,18 @@ impl<'a> Parser<'a> {
         }
 
         if self.advance_if(TokenType::NewKeyword) {
-            // Struct creation
-            let struct_type = self.parse_type()?;
+            // Struct creation, where the type can be left out to infer it, like `new { x: 1 }`
+            let struct_type = if self.is_match(TokenType::OpenCurlyBracket) {
+                Type::Inferred
+            } else {
+                self.parse_type()?
+            };
             self.expect(TokenType::OpenCurlyBracket, "Expected open brace after struct name")?;
             let mut elements = Vec::new();
             while !self.is_eof() && self.peek().token_type != TokenType::CloseCurlyBracket {
                 let name = self.expect_identifier()?;
                 self.expect(TokenType::Colon, "Expected colon after struct field name")?; // Expect a colon after the name
-                let value = Box::new(self.parse_expression()?);
+                let value = Box::new(self.spanned(Self::parse_expression)?);
                 elements.push((name, value)); // Store the field name and value
                 if !self.advance_if(TokenType::Comma) {
                     break; // No more fields
@@ -599,29 +1016,152 @@ impl<'a> Parser<'a> {
         self.parse_assignment_or_lower()
     }
 
+    /// Parses the `[i32, 3] {` at the start of an array creation.
+    fn parse_array_creation_header(&mut self) -> Result<(Type, Box<Spanned<Expression>>), ParseError> {
+        self.expect(TokenType::OpenSquareBracket, "Expected open square bracket")?;
+        let element_type = self.parse_type()?;
+        self.expect(TokenType::Comma, "Expected comma after array type")?;
+        let size = Box::new(self.spanned(Self::parse_expression)?);
+        self.expect(TokenType::CloseSquareBracket, "Unmatched open square bracket")?;
+        self.expect(TokenType::OpenCurlyBracket, "Expected open brace after array size")?;
+        Ok((element_type, size))
+    }
+
+    fn parse_array_literal(&mut self) -> Result<Expression, ParseError> {
+        self.expect(TokenType::OpenSquareBracket, "Expected open square bracket")?;
+        let mut elements = Vec::new();
+        while !self.is_eof() && self.peek().token_type != TokenType::CloseSquareBracket {
+            elements.push(self.spanned(Self::parse_expression)?);
+            if !self.advance_if(TokenType::Comma) {
+                break; // No more elements
+            }
+        }
+        self.expect(TokenType::CloseSquareBracket, "Unmatched open square bracket")?;
+        Ok(Expression::ArrayLiteral(elements))
+    }
+
+    /// Parses the rest of an interpolated string, after its `InterpolationStart` token.
+    /// `text` is the part of the string before the first interpolation.
+    fn parse_interpolated_string(&mut self, mut text: String) -> Result<Expression, ParseError> {
+        let mut parts = Vec::new();
+        loop {
+            if !text.is_empty() {
+                parts.push(StringPart::Literal(text));
+            }
+            parts.push(StringPart::Expr(Box::new(self.spanned(Self::parse_expression)?)));
+            match self.peek().token_type.clone() {
+                TokenType::InterpolationMiddle(next) => {
+                    self.advance(); // Consume the text between the interpolations
+                    text = next;
+                },
+                TokenType::InterpolationEnd(end) => {
+                    self.advance(); // Consume the end of the string
+                    if !end.is_empty() {
+                        parts.push(StringPart::Literal(end));
+                    }
+                    return Ok(Expression::InterpolatedString { parts });
+                },
+                _ => return Err(ParseError::UnexpectedToken {
+                    expected: None,
+                    found: Box::new(self.peek().clone()),
+                    message: Some("Expected a closing curly bracket after the interpolated expression".to_string())
+                })
+            }
+        }
+    }
+
+    fn parse_pattern(&mut self) -> Result<Pattern, ParseError> {
+        // Negative number patterns
+        let negate = self.advance_if(TokenType::SubtractOperator);
+        let pattern = match self.peek().token_type.clone() {
+            TokenType::IntegerLiteral(value) => Pattern::IntegerLiteral(value),
+            TokenType::FloatLiteral(value) => Pattern::NumberLiteral(LiteralFloat(value)),
+            _ if negate => {
+                return Err(ParseError::UnexpectedToken {
+                    expected: None,
+                    found: Box::new(self.peek().clone()),
+                    message: Some("Expected a number after - in pattern".to_string())
+                });
+            },
+            TokenType::StringLiteral(value) | TokenType::RawStringLiteral(value) => Pattern::StringLiteral(value),
+            TokenType::CharLiteral(value) => Pattern::CharLiteral(value),
+            TokenType::TrueValue => Pattern::BooleanLiteral(true),
+            TokenType::FalseValue => Pattern::BooleanLiteral(false),
+            TokenType::Identifier(name) => Pattern::Binding(name),
+            _ => {
+                return Err(ParseError::UnexpectedToken {
+                    expected: None,
+                    found: Box::new(self.peek().clone()),
+                    message: Some("Expected a pattern".to_string())
+                });
+            }
+        };
+        self.advance(); // Consume the pattern
+
+        Ok(match pattern {
+            Pattern::IntegerLiteral(value) if negate => Pattern::IntegerLiteral(-value),
+            Pattern::NumberLiteral(value) if negate => Pattern::NumberLiteral(LiteralFloat(-value.0)),
+            pattern => pattern
+        })
+    }
+
     fn parse_assignment_or_lower(&mut self) -> Result<Expression, ParseError> {
         // Assignment is right-associative, so we recursively parse instead of looping.
-        let expr = self.parse_logical_or_or_lower()?;
-        if self.advance_if(TokenType::AssignmentOperator) {
-            let value = Box::new(self.parse_logical_or_or_lower()?); // Parse the right-hand side
-            // TODO: member access assignment
+        let expr = self.parse_range_or_lower()?;
+        let operator = if self.is_eof() {
+            None
+        } else {
+            match self.peek().token_type {
+                TokenType::AssignmentOperator => Some(None),
+                TokenType::AddAssignmentOperator => Some(Some(BinaryOperator::Add)),
+                TokenType::SubtractAssignmentOperator => Some(Some(BinaryOperator::Subtract)),
+                TokenType::MultiplyAssignmentOperator => Some(Some(BinaryOperator::Multiply)),
+                TokenType::DivideAssignmentOperator => Some(Some(BinaryOperator::Divide)),
+                TokenType::ModuloAssignmentOperator => Some(Some(BinaryOperator::Modulus)),
+                _ => None
+            }
+        };
+        if let Some(operator) = operator {
+            self.advance(); // Consume the assignment operator
+            let value = Box::new(self.spanned(Self::parse_range_or_lower)?); // Parse the right-hand side
             if let Expression::Variable { name, expression_id } = expr {
                 return Ok(Expression::Assignment {
                     name,
+                    operator,
                     value,
                     expression_id
                 });
+            } else if let Expression::MemberAccess { object, member } = expr {
+                return Ok(Expression::MemberAssignment { object, member, operator, value });
+            } else if let Expression::Index { object, index } = expr {
+                return Ok(Expression::IndexAssignment { object, index, operator, value });
             } else {
                 return Err(ParseError::UnexpectedToken {
                     expected: Some(TokenType::Identifier("".to_string())),
-                    found: self.peek().clone(),
-                    message: Some("Expected an identifier for assignment".to_string())
+                    found: Box::new(self.peek().clone()),
+                    message: Some("Expected a variable, member access, or index for assignment".to_string())
                 });
             }
         }
         Ok(expr)
     }
 
+    /// Ranges don't chain, so `a..b..c` is an error instead of a range of ranges.
+    fn parse_range_or_lower(&mut self) -> Result<Expression, ParseError> {
+        let start = self.start_offset();
+        let expr = self.parse_logical_or_or_lower()?;
+        let span = self.span_from(start);
+        let inclusive = if self.advance_if(TokenType::RangeOperator) {
+            false
+        } else if self.advance_if(TokenType::InclusiveRangeOperator) {
+            true
+        } else {
+            return Ok(expr);
+        };
+        let end = Box::new(self.spanned(Self::parse_logical_or_or_lower)?);
+        Ok(Expression::Range { start: Box::new(Spanned::new(expr, span)), end, inclusive })
+    }
+
     fn parse_logical_or_or_lower(&mut self) -> Result<Expression, ParseError> {
         parse_precedence_binary!(
             self,
@@ -650,7 +1190,7 @@ impl<'a> Parser<'a> {
     fn parse_comparison_or_lower(&mut self) -> Result<Expression, ParseError> {
         parse_precedence_binary!(
             self,
-            parse_term_or_lower,
+            parse_bitwise_or_or_lower,
             (TokenType::OpenAngleBracket, BinaryOperator::LessThan),
             (TokenType::CloseAngleBracket, BinaryOperator::GreaterThan),
             (TokenType::LessThanEqualOperator, BinaryOperator::LessThanOrEqual),
@@ -658,6 +1198,57 @@ impl<'a> Parser<'a> {
         )
     }
 
+    fn parse_bitwise_or_or_lower(&mut self) -> Result<Expression, ParseError> {
+        parse_precedence_binary!(
+            self,
+            parse_bitwise_xor_or_lower,
+            (TokenType::BitwiseOrOperator, BinaryOperator::BitwiseOr)
+        )
+    }
+
+    fn parse_bitwise_xor_or_lower(&mut self) -> Result<Expression, ParseError> {
+        parse_precedence_binary!(
+            self,
+            parse_bitwise_and_or_lower,
+            (TokenType::BitwiseXorOperator, BinaryOperator::BitwiseXor)
+        )
+    }
+
+    fn parse_bitwise_and_or_lower(&mut self) -> Result<Expression, ParseError> {
+        parse_precedence_binary!(
+            self,
+            parse_shift_or_lower,
+            (TokenType::Ampersand, BinaryOperator::BitwiseAnd)
+        )
+    }
+
+    /// Shifts are written as two angle brackets with nothing between them, since `>>` is also the end of nested generics like `Array<Array<i32>>`.
+    fn parse_shift_or_lower(&mut self) -> Result<Expression, ParseError> {
+        let start = self.start_offset();
+        let mut expr = self.parse_term_or_lower()?;
+        while let Some(operator) = self.peek_shift() {
+            let left_span = self.span_from(start);
+            self.advance(); // Consume the first angle bracket
+            self.advance(); // Consume the second angle bracket
+            let right = Box::new(self.spanned(Self::parse_term_or_lower)?);
+            expr = Expression::BinaryOperation { left: Box::new(Spanned::new(expr, left_span)), operator, right };
+        }
+        Ok(expr)
+    }
+
+    /// The shift operator at the current token, if the next two tokens are the same angle bracket right next to each other.
+    fn peek_shift(&self) -> Option<BinaryOperator> {
+        let (first, second) = (self.tokens.get(self.current)?, self.tokens.get(self.current + 1)?);
+        if first.token_type != second.token_type || first.span.end != second.span.start {
+            return None;
+        }
+        match first.token_type {
+            TokenType::OpenAngleBracket => Some(BinaryOperator::ShiftLeft),
+            TokenType::CloseAngleBracket => Some(BinaryOperator::ShiftRight),
+            _ => None
+        }
+    }
+
     fn parse_term_or_lower(&mut self) -> Result<Expression, ParseError> {
         parse_precedence_binary!(
             self,
@@ -670,14 +1261,36 @@ impl<'a> Parser<'a> {
     fn parse_factor_or_lower(&mut self) -> Result<Expression, ParseError> {
         parse_precedence_binary!(
             self,
-            parse_unary_or_lower,
+            parse_cast_or_lower,
             (TokenType::MultiplyOperator, BinaryOperator::Multiply),
             (TokenType::DivideOperator, BinaryOperator::Divide),
             (TokenType::ModuloOperator, BinaryOperator::Modulus),
         )
     }
 
+    /// Casts bind tighter than binary operators but looser than unary ones, so `-x as u8` casts `-x`.
+    fn parse_cast_or_lower(&mut self) -> Result<Expression, ParseError> {
+        let start = self.start_offset();
+        let mut expr = self.parse_unary_or_lower()?;
+        while self.is_match(TokenType::AsKeyword) {
+            let span = self.span_from(start);
+            self.advance(); // Consume 'as'
+            let target_type = self.parse_type()?;
+            expr = Expression::Cast { value: Box::new(Spanned::new(expr, span)), target_type };
+        }
+        Ok(expr)
+    }
+
     fn parse_unary_or_lower(&mut self) -> Result<Expression, ParseError> {
+        // References, like `&point` or `&mut point`. As in types, `mut` is only special here.
+        if self.advance_if(TokenType::Ampersand) {
+            let mutable = matches!(&self.peek().token_type, TokenType::Identifier(name) if name == "mut");
+            if mutable {
+                self.advance(); // Consume 'mut'
+            }
+            let operand = Box::new(self.spanned(Self::parse_call_or_lower)?);
+            return Ok(Expression::UnaryOperation { operator: UnaryOperator::Reference { mutable }, operand });
+        }
         parse_precedence_unary!(
             self,
             parse_call_or_lower,
@@ -687,27 +1300,48 @@ impl<'a> Parser<'a> {
     }
 
     fn parse_call_or_lower(&mut self) -> Result<Expression, ParseError> {
+        let start = self.start_offset();
         let mut expr = self.parse_primary_or_lower()?;
 
-        // TODO: Array access and assignment
         while !self.is_eof() {
+            let span = self.span_from(start);
             if self.advance_if(TokenType::OpenParenthesis) {
-                expr = self.parse_function_call_after_paren(expr)?; // Parse function call
+                expr = self.parse_function_call_after_paren(Spanned::new(expr, span))?; // Parse function call
             } else if self.advance_if(TokenType::Dot) {
                 let name = self.expect_identifier()?; // Expect an identifier after the dot
-                expr = Expression::MemberAccess { object: Box::new(expr), member: name };
+                if self.advance_if(TokenType::OpenParenthesis) {
+                    let args = self.parse_arguments_after_paren()?;
+                    expr = Expression::MethodCall { receiver: Box::new(Spanned::new(expr, span)), method: name, args };
+                } else {
+                    expr = Expression::MemberAccess { object: Box::new(Spanned::new(expr, span)), member: name };
+                }
+            } else if self.advance_if(TokenType::OpenSquareBracket) {
+                let index = Box::new(self.spanned(Self::parse_expression)?);
+                self.expect(TokenType::CloseSquareBracket, "Expected closing square bracket after index")?;
+                expr = Expression::Index { object: Box::new(Spanned::new(expr, span)), index };
+            } else if self.advance_if(TokenType::QuestionMark) {
+                // Parsed with the other postfix operators, so `a?.b` and `a.b?` both chain from left to right
+                expr = Expression::Try { operand: Box::new(Spanned::new(expr, span)) };
             } else {
-                break; // No more function calls or member accesses
+                break; // No more function calls, member accesses, indexes, or tries
             }
         }
 
         Ok(expr)
     }
 
-    fn parse_function_call_after_paren(&mut self, callee: Expression) -> Result<Expression, ParseError> {
+    fn parse_function_call_after_paren(&mut self, callee: Spanned<Expression>) -> Result<Expression, ParseError> {
+        let args = self.parse_arguments_after_paren()?;
+        Ok(Expression::FunctionCall {
+            callee: Box::new(callee),
+            args
+        })
+    }
+
+    fn parse_arguments_after_paren(&mut self) -> Result<Vec<Spanned<Expression>>, ParseError> {
         let mut args = Vec::new();
         while !self.is_eof() && self.peek().token_type != TokenType::CloseParenthesis {
-            args.push(self.parse_expression()?);
+            args.push(self.spanned(Self::parse_expression)?);
             if self.is_match(TokenType::Comma) {
                 self.advance(); // Consume the comma
             } else {
@@ -715,26 +1349,34 @@ impl<'a> Parser<'a> {
             }
         }
         self.expect(TokenType::CloseParenthesis, "Unmatched open parentheses")?;
-        Ok(Expression::FunctionCall {
-            callee: Box::new(callee),
-            args
-        })
+        Ok(args)
     }
 
     fn parse_primary_or_lower(&mut self) -> Result<Expression, ParseError> {
         match self.peek().token_type.clone() {
             // Simple literals
-            TokenType::IntegerLiteral(ref value) => {
+            TokenType::IntegerLiteral(value) => {
+                let lexeme = self.literal_lexeme();
                 self.advance(); // Consume the number
-                Ok(Expression::NumberLiteral(*value as f64)) // Convert to f64
+                Ok(Expression::IntegerLiteral { value, lexeme })
             },
-            TokenType::FloatLiteral(ref value) => {
+            TokenType::FloatLiteral(value) => {
+                let lexeme = self.literal_lexeme();
                 self.advance(); // Consume the number
-                Ok(Expression::NumberLiteral(*value)) // Already f64
+                Ok(Expression::NumberLiteral { value: LiteralFloat(value), lexeme }) // Already f64
             },
             TokenType::StringLiteral(ref value) => {
                 self.advance(); // Consume the string
-                Ok(Expression::StringLiteral(value.clone()))
+                Ok(Expression::string(value.clone()))
+            },
+            TokenType::RawStringLiteral(ref value) => {
+                self.advance(); // Consume the string
+                Ok(Expression::StringLiteral { value: value.clone(), raw: true })
+            },
+            TokenType::InterpolationStart(ref value) => {
+                let value = value.clone();
+                self.advance(); // Consume the start of the string
+                self.parse_interpolated_string(value)
             },
             TokenType::CharLiteral(ref value) => {
                 self.advance(); // Consume the char
@@ -748,23 +1390,61 @@ impl<'a> Parser<'a> {
                 self.advance(); // Consume 'false'
                 Ok(Expression::BooleanLiteral(false))
             },
+            // `nil` is an identifier so it can also be used as a type
+            TokenType::Identifier(ref name) if name == "nil" => {
+                self.advance(); // Consume 'nil'
+                Ok(Expression::NilLiteral)
+            },
 
             TokenType::Identifier(ref name) => {
                 self.advance(); // Consume the identifier
                 Ok(Expression::Variable { name: name.clone(), expression_id: self.get_id() })
             },
 
+            TokenType::SizeofKeyword => {
+                self.advance(); // Consume 'sizeof'
+                self.expect(TokenType::OpenParenthesis, "Expected open parentheses after sizeof")?;
+                let ty = self.parse_type()?;
+                self.expect(TokenType::CloseParenthesis, "Unmatched open parentheses")?;
+                Ok(Expression::SizeOf(ty))
+            },
+            TokenType::AwaitKeyword => {
+                self.advance(); // Consume 'await'
+                let operand = self.spanned(Self::parse_unary_or_lower)?;
+                Ok(Expression::Await(Box::new(operand)))
+            },
+            TokenType::TypeofKeyword => {
+                self.advance(); // Consume 'typeof'
+                self.expect(TokenType::OpenParenthesis, "Expected open parentheses after typeof")?;
+                let operand = self.spanned(Self::parse_expression)?;
+                self.expect(TokenType::CloseParenthesis, "Unmatched open parentheses")?;
+                Ok(Expression::TypeOf(Box::new(operand)))
+            },
+
             TokenType::OpenParenthesis => {
+                // Parenthesized expressions and tuples, which are told apart like tuple types are
                 self.advance(); // Consume the open parenthesis
-                let expr = self.parse_expression()?;
+                let mut elements = Vec::new();
+                let mut trailing_comma = false;
+                while !self.is_eof() && self.peek().token_type != TokenType::CloseParenthesis {
+                    elements.push(self.spanned(Self::parse_expression)?);
+                    trailing_comma = self.advance_if(TokenType::Comma);
+                    if !trailing_comma {
+                        break; // No more elements
+                    }
+                }
                 self.expect(TokenType::CloseParenthesis, "Unmatched open parentheses")?; // Expect a close parenthesis
-                Ok(expr)
+
+                if elements.len() == 1 && !trailing_comma {
+                    return Ok(Expression::Grouping(Box::new(elements.remove(0))));
+                }
+                Ok(Expression::Tuple(elements))
             },
 
             _ => {
                 Err(ParseError::UnexpectedToken {
                     expected: None,
-                    found: self.peek().clone(),
+                    found: Box::new(self.peek().clone()),
                     message: Some("Expected an expression".to_string())
                 })
             }
@@ -782,39 +1462,171 @@ mod tests {
             {
                 let mut tokenizer = Tokenizer::new($input.to_string());
                 let tokens = tokenizer.tokenize().unwrap();
-                let mut parser = Parser::new(&tokens);
+                let mut parser = Parser::new(tokens);
                 let expression = parser.$parse_fn().unwrap();
                 expression
             }
         };
     }
 
+    fn boxed(expression: Expression) -> Box<Spanned<Expression>> {
+        Box::new(expression.into())
+    }
+
     #[test]
     fn test_associativity() {
         assert_eq!(parse!(r#"
             1 + 2 * 3 - 4 / 5 % 6;
         "#, parse_expression), 
             Expression::BinaryOperation {
-                left: Box::new(Expression::BinaryOperation {
-                    left: Box::new(Expression::NumberLiteral(1.0)),
+                left: boxed(Expression::BinaryOperation {
+                    left: boxed(Expression::integer(1)),
                     operator: BinaryOperator::Add,
-                    right: Box::new(Expression::BinaryOperation {
-                        left: Box::new(Expression::NumberLiteral(2.0)),
+                    right: boxed(Expression::BinaryOperation {
+                        left: boxed(Expression::integer(2)),
                         operator: BinaryOperator::Multiply,
-                        right: Box::new(Expression::NumberLiteral(3.0))
+                        right: boxed(Expression::integer(3))
                     })
                 }),
                 operator: BinaryOperator::Subtract,
-                right: Box::new(Expression::BinaryOperation {
-                    left: Box::new(Expression::BinaryOperation {
-                        left: Box::new(Expression::NumberLiteral(4.0)),
+                right: boxed(Expression::BinaryOperation {
+                    left: boxed(Expression::BinaryOperation {
+                        left: boxed(Expression::integer(4)),
                         operator: BinaryOperator::Divide,
-                        right: Box::new(Expression::NumberLiteral(5.0))
+                        right: boxed(Expression::integer(5))
                     }),
                     operator: BinaryOperator::Modulus,
-                    right: Box::new(Expression::NumberLiteral(6.0))
+                    right: boxed(Expression::integer(6))
                 })
             }
         );
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_integer_literals_keep_precision() {
+        assert_eq!(parse!("18446744073709551615", parse_expression), Expression::integer(u64::MAX as i128));
+        assert_eq!(parse!("5", parse_expression), Expression::integer(5));
+        assert_eq!(parse!("5.0", parse_expression), Expression::number(5.0));
+    }
+
+    #[test]
+    fn test_where_clauses() {
+        let declaration = parse!(r#"
+            func show<T, U>(a: T, b: U) -> nil where T: Ord + Display, U: Display {}
+        "#, parse_declaration);
+
+        let Declaration::Function { where_clauses, .. } = declaration else {
+            panic!("Expected a function declaration");
+        };
+        assert_eq!(where_clauses, vec![
+            ("T".to_string(), vec!["Ord".to_string(), "Display".to_string()]),
+            ("U".to_string(), vec!["Display".to_string()])
+        ]);
+
+        let declaration = parse!("enum Tree<T> where T: Ord { Leaf, Node(T) }", parse_declaration);
+        let Declaration::Enum { where_clauses, .. } = declaration else {
+            panic!("Expected an enum declaration");
+        };
+        assert_eq!(where_clauses, vec![("T".to_string(), vec!["Ord".to_string()])]);
+    }
+
+    #[test]
+    fn test_loop_body_must_be_block() {
+        let expression = parse!("loop (x) { foo(); }", parse_expression);
+        assert!(matches!(expression, Expression::Loop(LoopType::While { body, .. }) if matches!(body.node, Expression::Block { .. })));
+
+        let mut tokenizer = Tokenizer::new("loop (x) foo();".to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let Err(ParseError::UnexpectedToken { expected, message, .. }) = parser.parse_expression() else {
+            panic!("Expected a non-block loop body to be rejected");
+        };
+        assert_eq!(expected, Some(TokenType::OpenCurlyBracket));
+        assert_eq!(message, Some("Loop bodies must be blocks; try wrapping the body in braces".to_string()));
+    }
+
+    #[test]
+    fn test_default_parameters_come_last() {
+        let declaration = parse!("func pad(text: String, width: u32 = 8, fill: char = ' ') -> String { text }", parse_declaration);
+        let Declaration::Function { params, .. } = declaration else {
+            panic!("Expected a function");
+        };
+        assert_eq!(params.iter().map(|param| param.default.as_ref().map(|default| default.node.clone())).collect::<Vec<_>>(), vec![
+            None,
+            Some(Expression::integer(8)),
+            Some(Expression::CharLiteral(' '))
+        ]);
+
+        let mut tokenizer = Tokenizer::new("func pad(width: u32 = 8, text: String) -> String { text }".to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let Err(ParseError::UnexpectedToken { message, .. }) = Parser::new(tokens).parse_declaration() else {
+            panic!("Expected a parameter without a default after one with a default to be rejected");
+        };
+        assert_eq!(message, Some("Parameters without default values must come before ones with them".to_string()));
+    }
+
+    #[test]
+    fn test_variadic_parameters() {
+        let declaration = parse!("func sum(...values: i32) -> i32 { 0 }", parse_declaration);
+        assert!(matches!(declaration, Declaration::Function { is_variadic: true, .. }));
+        let declaration = parse!("func sum(values: [i32]) -> i32 { 0 }", parse_declaration);
+        assert!(matches!(declaration, Declaration::Function { is_variadic: false, .. }));
+
+        for (source, error) in [
+            ("func sum(...values: i32, last: i32) -> i32 { 0 }", "Only the last parameter can be variadic"),
+            ("func sum(...values: i32 = 0) -> i32 { 0 }", "Variadic parameters can't have default values")
+        ] {
+            let mut tokenizer = Tokenizer::new(source.to_string());
+            let tokens = tokenizer.tokenize().unwrap();
+            let Err(ParseError::UnexpectedToken { message, .. }) = Parser::new(tokens).parse_declaration() else {
+                panic!("Expected {} to be rejected", source);
+            };
+            assert_eq!(message, Some(error.to_string()));
+        }
+    }
+
+    #[test]
+    fn test_async_and_await() {
+        let declaration = parse!("async func fetch(id: i32) -> i32 { await load(id) + 1 }", parse_declaration);
+        let Declaration::Function { is_async, body, .. } = declaration else {
+            panic!("Expected a function");
+        };
+        assert!(is_async);
+        let Expression::Block { tail: Some(expression), .. } = &body.node else {
+            panic!("Expected a block body with a tail");
+        };
+        // `await` binds tighter than binary operators
+        let Expression::BinaryOperation { left, .. } = &expression.node else {
+            panic!("Expected a binary operation");
+        };
+        assert!(matches!(&left.node, Expression::Await(operand) if matches!(operand.node, Expression::FunctionCall { .. })));
+
+        let declaration = parse!("func fetch() -> nil {}", parse_declaration);
+        assert!(matches!(declaration, Declaration::Function { is_async: false, .. }));
+    }
+
+    #[test]
+    fn test_match() {
+        let expression = parse!(r#"match (x) { -1 => "negative", 0 => { zero(); } other => other }"#, parse_expression);
+        let Expression::Match { scrutinee, arms } = expression else {
+            panic!("Expected a match expression");
+        };
+        assert!(matches!(scrutinee.node, Expression::Variable { ref name, .. } if name == "x"));
+        let patterns: Vec<Pattern> = arms.iter().map(|arm| arm.pattern.clone()).collect();
+        assert_eq!(patterns, vec![Pattern::IntegerLiteral(-1), Pattern::IntegerLiteral(0), Pattern::Binding("other".to_string())]);
+        assert_eq!(arms[0].body.node, Expression::string("negative".to_string()));
+    }
+
+    #[test]
+    fn test_fixed_size_arrays() {
+        assert_eq!(parse!("[i32; 4]", parse_type), Type::Array { element: Box::new(Type::I32), size: Some(4) });
+        assert_eq!(parse!("[[u8; 2]]", parse_type), Type::array(Type::Array { element: Box::new(Type::U8), size: Some(2) }));
+
+        let mut tokenizer = Tokenizer::new("[i32; n]".to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let Err(ParseError::UnexpectedToken { message, .. }) = Parser::new(tokens).parse_type() else {
+            panic!("Expected an array size that isn't a literal to be rejected");
+        };
+        assert_eq!(message, Some("Expected a non-negative integer for the array size".to_string()));
+    }
+}
@@ -0,0 +1,742 @@
+use super::ast::{BinaryOperator, Declaration, Expression, FunctionParameter, GenericParam, LoopType, Pattern, Program, Statement, StringPart, StructElement, Type, VariableMutability, Visibility, WhereClause};
+
+// Turns an AST back into Vixen source code; the inverse of the parser.
+// Parsing the output gives back an equal AST, although comments and the original formatting are lost.
+// Parentheses from the source are kept as groupings, so they're printed where they were written.
+// Trees built without groupings, like by the optimizer, only get parentheses where the parser needs them,
+// so `(a + b) * c` keeps its parentheses but `a + (b * c)` loses them.
+
+/// How tightly an expression binds when it's printed, where higher levels bind tighter.
+/// Binary operations use their own precedence (1 to 10) on top of `RANGE`, which fits between `RANGE` and `CAST`.
+mod level {
+    use super::BinaryOperator;
+
+    /// Expressions that are only parsed by `parse_expression`, like blocks, loops, and assignments
+    pub const ANY: u8 = 0;
+    pub const RANGE: u8 = 1;
+    pub const CAST: u8 = 12;
+    pub const UNARY: u8 = 13;
+    pub const CALL: u8 = 14;
+    pub const PRIMARY: u8 = 15;
+
+    pub fn binary(operator: &BinaryOperator) -> u8 {
+        RANGE + operator.precedence()
+    }
+}
+
+pub struct SourcePrinter {
+    indent: usize
+}
+
+impl SourcePrinter {
+    pub fn new() -> Self {
+        SourcePrinter { indent: 0 }
+    }
+
+    pub fn print_program(&mut self, program: &Program) -> String {
+        self.indent = 0;
+        program.declarations.iter()
+            .map(|declaration| format!("{}{}", self.print_doc(declaration), self.print_declaration(declaration)))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn indentation(&self) -> String {
+        "    ".repeat(self.indent)
+    }
+
+    /// The declaration's doc comment as `///` lines, each ending with a newline.
+    fn print_doc(&self, declaration: &Declaration) -> String {
+        let Some(doc) = declaration.doc() else {
+            return String::new();
+        };
+        doc.split('\n').map(|line| if line.is_empty() {
+            format!("{}///\n", self.indentation())
+        } else {
+            format!("{}/// {}\n", self.indentation(), line)
+        }).collect()
+    }
+
+    /// Prints a declaration that starts after the current indentation, ending with a newline.
+    fn print_declaration(&mut self, declaration: &Declaration) -> String {
+        match declaration {
+            Declaration::Function { name, params, generic_args, where_clauses, return_type, body, is_async, is_variadic, .. } => {
+                format!(
+                    "{}func {}{}({}) -> {}{} {}\n",
+                    if *is_async { "async " } else { "" },
+                    name,
+                    print_generic_args(generic_args),
+                    self.print_params(params, *is_variadic),
+                    print_type(return_type),
+                    print_where_clauses(where_clauses),
+                    self.print_expression(body, level::ANY)
+                )
+            },
+            Declaration::Struct { name, elements, generic_args, where_clauses, .. } => {
+                let mut output = format!(
+                    "struct {}{}{} {{\n",
+                    name,
+                    print_generic_args(generic_args),
+                    print_where_clauses(where_clauses)
+                );
+                self.indent += 1;
+                for element in elements {
+                    output.push_str(&self.print_struct_element(element));
+                }
+                self.indent -= 1;
+                output.push_str(&format!("{}}}\n", self.indentation()));
+                output
+            },
+            Declaration::TypeDeclaration { name, generic_args, where_clauses, alias, .. } => {
+                format!(
+                    "type {}{}{} = {};\n",
+                    name,
+                    print_generic_args(generic_args),
+                    print_where_clauses(where_clauses),
+                    print_type(alias)
+                )
+            },
+            Declaration::Enum { name, variants, generic_args, where_clauses, .. } => {
+                let mut output = format!("enum {}{}{} {{\n", name, print_generic_args(generic_args), print_where_clauses(where_clauses));
+                self.indent += 1;
+                for variant in variants {
+                    output.push_str(&format!("{}{}", self.indentation(), variant.name));
+                    if !variant.associated_types.is_empty() {
+                        let types = variant.associated_types.iter().map(print_type).collect::<Vec<_>>();
+                        output.push_str(&format!("({})", types.join(", ")));
+                    }
+                    output.push_str(",\n");
+                }
+                self.indent -= 1;
+                output.push_str(&format!("{}}}\n", self.indentation()));
+                output
+            },
+            Declaration::Const { name, const_type, value } => {
+                format!("const {}: {} = {}{}\n", name, print_type(const_type), self.print_expression(value, level::ANY), terminator(value))
+            },
+            Declaration::Import { path, alias, items } => format!("import {};\n", print_import(path, alias, items))
+        }
+    }
+
+    fn print_params(&mut self, params: &[FunctionParameter], is_variadic: bool) -> String {
+        params.iter().enumerate()
+            .map(|(i, param)| match &param.default {
+                Some(default) => format!("{}: {} = {}", param.name, print_type(&param.param_type), self.print_expression(default, level::ANY)),
+                None if is_variadic && i == params.len() - 1 => format!("...{}: {}", param.name, print_type(&param.param_type)),
+                None => format!("{}: {}", param.name, print_type(&param.param_type))
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    fn print_struct_element(&mut self, element: &StructElement) -> String {
+        match element {
+            StructElement::Field { name, field_type, visibility } => {
+                format!("{}{}{}: {};\n", self.indentation(), print_visibility(visibility), name, print_type(field_type))
+            },
+            StructElement::Declaration { declaration, visibility } => {
+                format!("{}{}{}{}", self.print_doc(declaration), self.indentation(), print_visibility(visibility), self.print_declaration(declaration))
+            }
+        }
+    }
+
+    fn print_statement(&mut self, statement: &Statement) -> String {
+        let output = match statement {
+            Statement::Declaration(declaration) => {
+                return format!("{}{}{}", self.print_doc(declaration), self.indentation(), self.print_declaration(declaration));
+            },
+            Statement::Expression { expression } => {
+                format!("{}{}", self.print_expression(expression, level::ANY), terminator(expression))
+            },
+            Statement::VariableDeclaration { mutability, name, variable_type, value } => {
+                format!(
+                    "{} {}{}{}",
+                    match mutability {
+                        VariableMutability::Mutable => "let",
+                        VariableMutability::Immutable => "const"
+                    },
+                    name,
+                    match variable_type {
+                        Type::Inferred => String::new(),
+                        _ => format!(": {}", print_type(variable_type))
+                    },
+                    match value {
+                        Some(value) => format!(" = {}{}", self.print_expression(value, level::ANY), terminator(value)),
+                        None => ";".to_string()
+                    }
+                )
+            },
+            Statement::Break(None) => "break;".to_string(),
+            Statement::Break(Some(label)) => format!("break '{};", label),
+            Statement::Continue(None) => "continue;".to_string(),
+            Statement::Continue(Some(label)) => format!("continue '{};", label),
+            Statement::Return(None) => "return;".to_string(),
+            Statement::Return(Some(value)) => {
+                format!("return {}{}", self.print_expression(value, level::ANY), terminator(value))
+            },
+            Statement::Defer(value) => {
+                format!("defer {}{}", self.print_expression(value, level::ANY), terminator(value))
+            }
+        };
+        format!("{}{}\n", self.indentation(), output)
+    }
+
+    /// Prints an expression in a position where the parser expects at least `min_level`, adding parentheses if it binds more loosely.
+    fn print_expression(&mut self, expression: &Expression, min_level: u8) -> String {
+        let output = self.print_expression_unparenthesized(expression);
+        if expression_level(expression) < min_level {
+            format!("({})", output)
+        } else {
+            output
+        }
+    }
+
+    fn print_expression_unparenthesized(&mut self, expression: &Expression) -> String {
+        match expression {
+            Expression::Block { statements, tail } => {
+                if statements.is_empty() && tail.is_none() {
+                    return "{}".to_string();
+                }
+                let mut output = "{\n".to_string();
+                self.indent += 1;
+                for statement in statements {
+                    output.push_str(&self.print_statement(statement));
+                }
+                if let Some(tail) = tail {
+                    output.push_str(&format!("{}{}\n", self.indentation(), self.print_expression(tail, level::ANY)));
+                }
+                self.indent -= 1;
+                output.push_str(&format!("{}}}", self.indentation()));
+                output
+            },
+            // Keep the original spelling when the parser kept it, like `0xFF` or `1_000`
+            Expression::IntegerLiteral { lexeme: Some(lexeme), .. } | Expression::NumberLiteral { lexeme: Some(lexeme), .. } => lexeme.clone(),
+            Expression::IntegerLiteral { value, .. } => value.to_string(),
+            // Whole floats still need a decimal point, or they'd be parsed back as integers
+            Expression::NumberLiteral { value, .. } if value.0.fract() == 0.0 => format!("{}.0", value),
+            Expression::NumberLiteral { value, .. } => value.to_string(),
+            Expression::StringLiteral { value, raw: false } => print_string(value),
+            Expression::StringLiteral { value, raw: true } => format!("r\"{}\"", value),
+            Expression::InterpolatedString { parts } => {
+                let parts = parts.iter().map(|part| match part {
+                    StringPart::Literal(text) => escape_string(text),
+                    StringPart::Expr(expression) => format!("${{{}}}", self.print_expression(expression, level::ANY))
+                }).collect::<Vec<_>>();
+                format!("\"{}\"", parts.concat())
+            },
+            Expression::CharLiteral(value) => format!("'{}'", value),
+            Expression::BooleanLiteral(value) => format!("{}", value),
+            Expression::NilLiteral => "nil".to_string(),
+            Expression::Variable { name, .. } => name.to_string(),
+            Expression::FunctionCall { callee, args } => {
+                let args = args.iter().map(|arg| self.print_expression(arg, level::ANY)).collect::<Vec<_>>();
+                // Without parentheses, calling a member access would be parsed as a method call
+                let callee = match callee.node {
+                    Expression::MemberAccess { .. } => format!("({})", self.print_expression(callee, level::ANY)),
+                    _ => self.print_expression(callee, level::CALL)
+                };
+                format!("{}({})", callee, args.join(", "))
+            },
+            Expression::MethodCall { receiver, method, args } => {
+                let args = args.iter().map(|arg| self.print_expression(arg, level::ANY)).collect::<Vec<_>>();
+                format!("{}.{}({})", self.print_expression(receiver, level::CALL), method, args.join(", "))
+            },
+            Expression::BinaryOperation { left, operator, right } => {
+                // Every operator groups to the left, so the left side can hold the same operator without parentheses, but the right side can't
+                let precedence = level::binary(operator);
+                let (left_level, right_level) = (precedence, precedence + 1);
+                format!(
+                    "{} {} {}",
+                    self.print_expression(left, left_level),
+                    operator,
+                    self.print_expression(right, right_level)
+                )
+            },
+            Expression::UnaryOperation { operator, operand } => {
+                // The parser doesn't allow stacking unary operators, so `--x` has to be written `-(-x)`
+                format!("{}{}", operator, self.print_expression(operand, level::CALL))
+            },
+            Expression::Assignment { name, operator, value, .. } => {
+                let operator = operator.as_ref().map(|operator| operator.to_string()).unwrap_or_default();
+                format!("{} {}= {}", name, operator, self.print_expression(value, level::RANGE))
+            },
+            Expression::MemberAssignment { object, member, operator, value } => {
+                let operator = operator.as_ref().map(|operator| operator.to_string()).unwrap_or_default();
+                format!("{}.{} {}= {}", self.print_expression(object, level::CALL), member, operator, self.print_expression(value, level::RANGE))
+            },
+            Expression::MemberAccess { object, member } => {
+                format!("{}.{}", self.print_expression(object, level::CALL), member)
+            },
+            Expression::Index { object, index } => {
+                format!("{}[{}]", self.print_expression(object, level::CALL), self.print_expression(index, level::ANY))
+            },
+            Expression::IndexAssignment { object, index, operator, value } => {
+                let operator = operator.as_ref().map(|operator| operator.to_string()).unwrap_or_default();
+                format!(
+                    "{}[{}] {}= {}",
+                    self.print_expression(object, level::CALL),
+                    self.print_expression(index, level::ANY),
+                    operator,
+                    self.print_expression(value, level::RANGE)
+                )
+            },
+            Expression::Array { array_type, size, initial_value } => {
+                format!(
+                    "[{}, {}] {{ {} }}",
+                    print_type(array_type),
+                    self.print_expression(size, level::ANY),
+                    self.print_expression(initial_value, level::ANY)
+                )
+            },
+            Expression::StructCreation { struct_type, fields } => {
+                let struct_type = match struct_type {
+                    Type::Inferred => String::new(),
+                    _ => format!("{} ", print_type(struct_type))
+                };
+                if fields.is_empty() {
+                    return format!("new {}{{}}", struct_type);
+                }
+                let fields = fields.iter()
+                    .map(|(name, value)| format!("{}: {}", name, self.print_expression(value, level::ANY)))
+                    .collect::<Vec<_>>();
+                format!("new {}{{ {} }}", struct_type, fields.join(", "))
+            },
+            Expression::ArrayLiteral(elements) => {
+                let elements = elements.iter().map(|element| self.print_expression(element, level::ANY)).collect::<Vec<_>>();
+                format!("[{}]", elements.join(", "))
+            },
+            Expression::Tuple(elements) => {
+                let elements = elements.iter().map(|element| self.print_expression(element, level::ANY)).collect::<Vec<_>>();
+                if elements.len() == 1 {
+                    format!("({},)", elements[0])
+                } else {
+                    format!("({})", elements.join(", "))
+                }
+            },
+            Expression::If { condition, then_branch, else_branch } => {
+                let condition = self.print_expression(condition, level::ANY);
+                let Some(else_branch) = else_branch else {
+                    return format!("if ({}) {}", condition, self.print_expression(then_branch, level::ANY));
+                };
+
+                // An `else` after an if without one would belong to the inner if instead
+                let then_branch = if ends_with_open_if(then_branch) {
+                    format!("({})", self.print_expression(then_branch, level::ANY))
+                } else {
+                    self.print_expression(then_branch, level::ANY)
+                };
+                format!("if ({}) {} else {}", condition, then_branch, self.print_expression(else_branch, level::ANY))
+            },
+            Expression::IfLet { pattern, value, then_branch, else_branch } => {
+                let header = format!("if (let {} = {})", print_pattern(pattern), self.print_expression(value, level::ANY));
+                let Some(else_branch) = else_branch else {
+                    return format!("{} {}", header, self.print_expression(then_branch, level::ANY));
+                };
+                let then_branch = if ends_with_open_if(then_branch) {
+                    format!("({})", self.print_expression(then_branch, level::ANY))
+                } else {
+                    self.print_expression(then_branch, level::ANY)
+                };
+                format!("{} {} else {}", header, then_branch, self.print_expression(else_branch, level::ANY))
+            },
+            Expression::Loop(LoopType::Infinite { body, label }) => {
+                format!("{}loop {}", print_loop_label(label), self.print_expression(body, level::ANY))
+            },
+            Expression::Loop(LoopType::While { condition, body, label }) => {
+                format!(
+                    "{}loop ({}) {}",
+                    print_loop_label(label),
+                    self.print_expression(condition, level::ANY),
+                    self.print_expression(body, level::ANY)
+                )
+            },
+            Expression::Loop(LoopType::Iterator { mutability, iterator, iterable, body, label }) => {
+                format!(
+                    "{}loop ({} {}: {}) {}",
+                    print_loop_label(label),
+                    match mutability {
+                        VariableMutability::Mutable => "let",
+                        VariableMutability::Immutable => "const"
+                    },
+                    iterator,
+                    self.print_expression(iterable, level::ANY),
+                    self.print_expression(body, level::ANY)
+                )
+            },
+            Expression::SizeOf(ty) => format!("sizeof({})", print_type(ty)),
+            Expression::TypeOf(operand) => format!("typeof({})", self.print_expression(operand, level::ANY)),
+            Expression::Cast { value, target_type } => {
+                format!("{} as {}", self.print_expression(value, level::CAST), print_type(target_type))
+            },
+            Expression::Await(operand) => format!("await {}", self.print_expression(operand, level::UNARY)),
+            Expression::Try { operand } => format!("{}?", self.print_expression(operand, level::CALL)),
+            Expression::Grouping(inner) => format!("({})", self.print_expression(inner, level::ANY)),
+            Expression::Match { scrutinee, arms } => {
+                let mut output = format!("match ({}) {{\n", self.print_expression(scrutinee, level::ANY));
+                self.indent += 1;
+                for arm in arms {
+                    let body = self.print_expression(&arm.body, level::ANY);
+                    output.push_str(&format!("{}{} => {},\n", self.indentation(), print_pattern(&arm.pattern), body));
+                }
+                self.indent -= 1;
+                output.push_str(&format!("{}}}", self.indentation()));
+                output
+            },
+            Expression::Range { start, end, inclusive } => {
+                // Ranges don't chain, so both bounds have to bind tighter than a range
+                let bound_level = level::binary(&BinaryOperator::Or);
+                format!(
+                    "{}{}{}",
+                    self.print_expression(start, bound_level),
+                    if *inclusive { "..=" } else { ".." },
+                    self.print_expression(end, bound_level)
+                )
+            }
+        }
+    }
+}
+
+/// The level an expression binds at; see `level`.
+fn expression_level(expression: &Expression) -> u8 {
+    match expression {
+        Expression::Block { .. } | Expression::Assignment { .. } | Expression::MemberAssignment { .. } | Expression::IndexAssignment { .. } |
+        Expression::Array { .. } | Expression::StructCreation { .. } |
+        Expression::If { .. } | Expression::IfLet { .. } | Expression::Loop(_) | Expression::Match { .. } => level::ANY,
+        Expression::Range { .. } => level::RANGE,
+        Expression::BinaryOperation { operator, .. } => level::binary(operator),
+        Expression::Cast { .. } => level::CAST,
+        // `await` is parsed like a primary expression, but it takes everything after it up to a binary operator or cast
+        Expression::UnaryOperation { .. } | Expression::Await(_) => level::UNARY,
+        Expression::FunctionCall { .. } | Expression::MethodCall { .. } | Expression::MemberAccess { .. } | Expression::Index { .. } |
+        Expression::Try { .. } => level::CALL,
+        Expression::IntegerLiteral { .. } | Expression::NumberLiteral { .. } | Expression::StringLiteral { .. } | Expression::InterpolatedString { .. } | Expression::CharLiteral(_) |
+        Expression::BooleanLiteral(_) | Expression::NilLiteral | Expression::Variable { .. } | Expression::SizeOf(_) | Expression::TypeOf(_) | Expression::Tuple(_) | Expression::ArrayLiteral(_) |
+        Expression::Grouping(_) => level::PRIMARY
+    }
+}
+
+/// If an expression ends with an if that doesn't have an else branch, like `if (a) b else if (c) d`.
+fn ends_with_open_if(expression: &Expression) -> bool {
+    match expression {
+        Expression::If { else_branch: None, .. } | Expression::IfLet { else_branch: None, .. } => true,
+        Expression::If { else_branch: Some(else_branch), .. } | Expression::IfLet { else_branch: Some(else_branch), .. } => {
+            ends_with_open_if(else_branch)
+        },
+        _ => false
+    }
+}
+
+/// The semicolon after an expression.
+/// An if without an else consumes the semicolon after it (so `if (a) b; else c` works), so those need a second one.
+fn terminator(expression: &Expression) -> &'static str {
+    if ends_with_open_if(expression) { ";;" } else { ";" }
+}
+
+/// An import's path, with its alias or selected items.
+fn print_import(path: &[String], alias: &Option<String>, items: &Option<Vec<String>>) -> String {
+    let mut output = path.join(".");
+    if let Some(items) = items {
+        output.push_str(&format!(".{{{}}}", items.join(", ")));
+    }
+    if let Some(alias) = alias {
+        output.push_str(&format!(" as {}", alias));
+    }
+    output
+}
+
+fn print_loop_label(label: &Option<String>) -> String {
+    match label {
+        Some(label) => format!("'{}: ", label),
+        None => String::new()
+    }
+}
+
+fn print_string(value: &str) -> String {
+    format!("\"{}\"", escape_string(value))
+}
+
+/// Escapes the characters that would end a string or start an interpolation.
+fn escape_string(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace("${", "\\${")
+}
+
+fn print_pattern(pattern: &Pattern) -> String {
+    match pattern {
+        Pattern::StringLiteral(value) => print_string(value),
+        Pattern::CharLiteral(value) => format!("'{}'", value),
+        _ => pattern.to_string()
+    }
+}
+
+fn print_visibility(visibility: &Visibility) -> &'static str {
+    match visibility {
+        Visibility::Public => "pub ",
+        Visibility::Private => ""
+    }
+}
+
+fn print_generic_args(generic_args: &[GenericParam]) -> String {
+    if generic_args.is_empty() {
+        return String::new();
+    }
+    let args = generic_args.iter().map(|arg| {
+        if arg.bounds.is_empty() {
+            arg.name.clone()
+        } else {
+            format!("{}: {}", arg.name, arg.bounds.iter().map(print_type).collect::<Vec<_>>().join(" + "))
+        }
+    }).collect::<Vec<_>>();
+    format!("<{}>", args.join(", "))
+}
+
+fn print_where_clauses(where_clauses: &[WhereClause]) -> String {
+    if where_clauses.is_empty() {
+        return String::new();
+    }
+    let clauses = where_clauses.iter()
+        .map(|(name, bounds)| format!("{}: {}", name, bounds.join(" + ")))
+        .collect::<Vec<_>>();
+    format!(" where {}", clauses.join(", "))
+}
+
+/// The name a primitive type is written with in source code, like `i32` or `bool`.
+pub fn primitive_name(ty: &Type) -> Option<&'static str> {
+    Some(match ty {
+        Type::U8 => "u8",
+        Type::U16 => "u16",
+        Type::U32 => "u32",
+        Type::U64 => "u64",
+        Type::I8 => "i8",
+        Type::I16 => "i16",
+        Type::I32 => "i32",
+        Type::I64 => "i64",
+        Type::F32 => "f32",
+        Type::F64 => "f64",
+        Type::Boolean => "bool",
+        Type::Character => "char",
+        Type::Nil => "nil",
+        Type::Never => "never",
+        _ => return None
+    })
+}
+
+fn print_type(ty: &Type) -> String {
+    if let Some(name) = primitive_name(ty) {
+        return name.to_string();
+    }
+    match ty {
+        Type::U8 | Type::U16 | Type::U32 | Type::U64 |
+        Type::I8 | Type::I16 | Type::I32 | Type::I64 |
+        Type::F32 | Type::F64 |
+        Type::Boolean | Type::Character | Type::Nil | Type::Never => unreachable!("Primitive types are handled above"),
+        Type::Inferred => unreachable!("Inferred types are left out of variable declarations and struct creations"),
+        Type::Identifier { name, generics } => {
+            if generics.is_empty() {
+                name.to_string()
+            } else {
+                format!("{}<{}>", name, generics.iter().map(print_type).collect::<Vec<_>>().join(", "))
+            }
+        },
+        // TODO: There isn't syntax for function types yet, so this can't be parsed back
+        Type::Function { params, return_type } => {
+            format!("func({}) -> {}", params.iter().map(print_type).collect::<Vec<_>>().join(", "), print_type(return_type))
+        },
+        Type::Array { element, size: None } => format!("[{}]", print_type(element)),
+        Type::Array { element, size: Some(size) } => format!("[{}; {}]", print_type(element), size),
+        Type::Reference { mutable: false, inner } => format!("&{}", print_type(inner)),
+        Type::Reference { mutable: true, inner } => format!("&mut {}", print_type(inner)),
+        Type::Record(fields) => {
+            let fields = fields.iter()
+                .map(|(name, field_type)| format!("{}: {}", name, print_type(field_type)))
+                .collect::<Vec<_>>();
+            format!("{{ {} }}", fields.join(", "))
+        },
+        Type::Tuple(elements) => {
+            let elements = elements.iter().map(print_type).collect::<Vec<_>>();
+            if elements.len() == 1 {
+                format!("({},)", elements[0])
+            } else {
+                format!("({})", elements.join(", "))
+            }
+        },
+        Type::Optional(inner) => format!("{}?", print_type(inner)),
+        Type::Map { key, value } => format!("Map<{}, {}>", print_type(key), print_type(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser::{ast::Spanned, builder::{bin, var}, Parser}, tokenizer::Tokenizer};
+
+    fn parse(input: &str) -> Program {
+        let mut tokenizer = Tokenizer::new(input.to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        Parser::new(tokens).parse_program().unwrap()
+    }
+
+    /// Checks that printing the program and parsing it again gives the same AST, then returns the printed source.
+    fn round_trip(input: &str) -> String {
+        let program = parse(input);
+        let output = SourcePrinter::new().print_program(&program);
+        assert_eq!(parse(&output), program, "The printed source was:\n{}", output);
+        output
+    }
+
+    #[test]
+    fn test_literal_spellings() {
+        let input = "func main() -> nil {\n    print(0xFF + 1_000 + 2.50);\n}\n";
+        let mut tokenizer = Tokenizer::new(input.to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let program = Parser::new(tokens).with_literal_spellings(input).parse_program().unwrap();
+        assert_eq!(SourcePrinter::new().print_program(&program), input);
+
+        // Without spellings, literals are printed from their values
+        assert!(SourcePrinter::new().print_program(&parse(input)).contains("print(255 + 1000 + 2.5);"));
+    }
+
+    #[test]
+    fn test_precedence() {
+        let output = round_trip(r#"
+            func main(a: i32, b: i32, c: i32) -> nil {
+                print(a + b * c);
+                print((a + b) * c);
+                print((a - b) - c);
+                print(a - (b - c));
+                print(!(a < b) || a == b && b != c);
+                print(-(a as f64) as u8);
+                print((await f(a)).x);
+                print(a..=b + c);
+                print((a..b).x);
+                print(a & b | c ^ a);
+                print(a & (b | c));
+                print(a << 1 >> b + c);
+                print(a & 1 == 0);
+            }
+        "#);
+        assert!(output.contains("print(a + b * c);\n"));
+        assert!(output.contains("print((a + b) * c);\n"));
+        assert!(output.contains("print((a - b) - c);\n"));
+        assert!(output.contains("print(a - (b - c));\n"));
+        assert!(output.contains("print(!(a < b) || a == b && b != c);\n"));
+        assert!(output.contains("print(-(a as f64) as u8);\n"));
+        assert!(output.contains("print((await f(a)).x);\n"));
+        assert!(output.contains("print(a..=b + c);\n"));
+        assert!(output.contains("print((a..b).x);\n"));
+        assert!(output.contains("print(a & b | c ^ a);\n"));
+        assert!(output.contains("print(a & (b | c));\n"));
+        assert!(output.contains("print(a << 1 >> b + c);\n"));
+        assert!(output.contains("print(a & 1 == 0);\n"));
+    }
+
+    #[test]
+    fn test_parentheses_without_groupings() {
+        let print = |expression: Spanned<Expression>| SourcePrinter::new().print_expression(&expression, level::ANY);
+        assert_eq!(print(bin(bin(var("a"), BinaryOperator::Add, var("b")), BinaryOperator::Multiply, var("c"))), "(a + b) * c");
+        assert_eq!(print(bin(var("a"), BinaryOperator::Add, bin(var("b"), BinaryOperator::Multiply, var("c")))), "a + b * c");
+        assert_eq!(print(bin(bin(var("a"), BinaryOperator::Subtract, var("b")), BinaryOperator::Subtract, var("c"))), "a - b - c");
+        assert_eq!(print(bin(var("a"), BinaryOperator::Subtract, bin(var("b"), BinaryOperator::Subtract, var("c")))), "a - (b - c)");
+    }
+
+    #[test]
+    fn test_declarations() {
+        let output = round_trip(r#"
+            import std.io;
+            import std.fs as files;
+            import std.collections.{Map, Set};
+            type Pair<T> = (T, T);
+            /// An optional value.
+            ///
+            /// Either `None` or `Some`.
+            enum Option<T> { None, Some(T) }
+            func largest<T: Comparable + Copy, U>(a: T, b: U) -> T {
+                a
+            }
+            enum Tree<T> where T: Ord { Leaf, Node(T) }
+            struct Grid<T> where T: Add + Copy {
+                pub cells: [[T]];
+                size: { width: u32, height: u32 };
+                /// The number of cells.
+                pub func area(grid: Grid<T>) -> u32 {
+                    grid.size.width * grid.size.height
+                }
+            }
+            func fill(values: &mut [u8], count: &&i32) -> nil {}
+            func fail(message: String) -> never {}
+            func lookup(scores: Map<String, i32?>, key: String) -> [u8]?? {}
+            func printf(format: String, width: u32 = 0, ...values: i32) -> nil {}
+            func scale(value: f64, factor: f64 = 2.0 * 1.5, name: String = "scale") -> f64 {
+                value * factor
+            }
+            const LIMIT: u32 = 10 * 2;
+            async func fetch() -> (i32,) {
+                return await other();
+            }
+        "#);
+        assert!(output.starts_with("import std.io;\n\nimport std.fs as files;\n\nimport std.collections.{Map, Set};\n\ntype Pair<T> = (T, T);\n\n/// An optional value.\n///\n/// Either `None` or `Some`.\nenum Option<T> {\n    None,\n    Some(T),\n}\n"));
+        assert!(output.contains("func largest<T: Comparable + Copy, U>(a: T, b: U) -> T {\n"));
+        assert!(output.contains("enum Tree<T> where T: Ord {\n    Leaf,\n    Node(T),\n}\n"));
+        assert!(output.contains("struct Grid<T> where T: Add + Copy {\n    pub cells: [[T]];\n"));
+        assert!(output.contains("    /// The number of cells.\n    pub func area(grid: Grid<T>) -> u32 {\n        grid.size.width * grid.size.height\n    }\n"));
+        assert!(output.contains("func scale(value: f64, factor: f64 = 2.0 * 1.5, name: String = \"scale\") -> f64 {\n"));
+        assert!(output.contains("func fail(message: String) -> never {}\n"));
+        assert!(output.contains("func printf(format: String, width: u32 = 0, ...values: i32) -> nil {}\n"));
+        assert!(output.contains("func lookup(scores: Map<String, i32?>, key: String) -> [u8]?? {}\n"));
+        assert!(output.contains("\nconst LIMIT: u32 = 10 * 2;\n"));
+        assert!(output.contains("async func fetch() -> (i32,) {\n"));
+    }
+
+    #[test]
+    fn test_statements() {
+        round_trip(r#"
+            func main(n: u32) -> nil {
+                let total: u32 = 0;
+                const half = 0.5;
+                let later: bool;
+                const pair: (i32, (bool,), ()) = (1, (true,), ());
+                defer print(total);
+                const values: [u32] = [u32, n] { 1 };
+                const corners: [[i32; 2]; 4] = [[0, 0], [0, 1], [1, 0], [1, 1]];
+                loop (const value: values) {
+                    total = total + value + values[0];
+                    total /= 2;
+                };
+                if (total > 10) {
+                    print("big");
+                    print(r"C:\files");
+                };;
+                if (total > 5) print("medium") else if (total > 0) print("\"small\"");;
+                if (let 0 = total) print("zero") else if (let other = total) print(other);;
+                print([total, 2, 3]);
+                print("total: ${total + 1}, ${"\${"}${ { half } }");
+                print(lookup(total)?.count?, -values[0]?, (await fetch())?);
+                values.push(total.abs(), (values.clear)());
+                loop (total > 0) {
+                    total = total - 1;
+                    continue;
+                };
+                loop {
+                    break;
+                };
+                'outer: loop (const row: values) {
+                    loop {
+                        continue 'outer;
+                    };
+                    break 'outer;
+                };
+                const point: Point = new Point { x: 1.5, y: -2 };
+                const origin: Point = new { x: 0.0, y: 0.0 };
+                point.x += 1.5;
+                (point).y = origin.y;
+                reset(&mut point, &origin);
+                values[total - 1] *= 2;
+                print(match (point.x) {
+                    0 => 'z',
+                    -1 => { 'n' }
+                    other => 'p',
+                });
+                typeof(point) == sizeof(Point)
+            }
+        "#);
+    }
+}
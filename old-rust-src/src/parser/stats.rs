@@ -0,0 +1,89 @@
+use super::{ast::{Declaration, Expression, Program, Spanned, Statement}, visitor::{self, Visitor}};
+
+/// How big a program is, for profiling the stages that run on it.
+#[derive(Debug, PartialEq, Default)]
+pub struct ProgramStats {
+    pub expressions: usize,
+    pub statements: usize,
+    /// Includes declarations inside structs and blocks, not just top-level ones.
+    pub declarations: usize,
+    /// How many expressions deep the most nested expression is, where a function's body is at depth 1.
+    pub max_depth: usize
+}
+
+/// Counts the nodes in a program and finds how deeply its expressions nest.
+#[allow(dead_code)] // Only used when profiling
+pub fn program_stats(program: &Program) -> ProgramStats {
+    let mut counter = StatsCounter::default();
+    counter.visit_program(program);
+    counter.stats
+}
+
+#[derive(Default)]
+struct StatsCounter {
+    stats: ProgramStats,
+    depth: usize
+}
+
+impl Visitor for StatsCounter {
+    fn visit_declaration(&mut self, declaration: &Declaration) {
+        self.stats.declarations += 1;
+        visitor::walk_declaration(self, declaration);
+    }
+
+    fn visit_statement(&mut self, statement: &Statement) {
+        self.stats.statements += 1;
+        visitor::walk_statement(self, statement);
+    }
+
+    fn visit_expression(&mut self, expression: &Spanned<Expression>) {
+        self.stats.expressions += 1;
+        self.depth += 1;
+        self.stats.max_depth = self.stats.max_depth.max(self.depth);
+        visitor::walk_expression(self, expression);
+        self.depth -= 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser::Parser, tokenizer::Tokenizer};
+
+    fn stats(source: &str) -> ProgramStats {
+        let mut tokenizer = Tokenizer::new(source.to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        program_stats(&Parser::new(tokens).parse_program().unwrap())
+    }
+
+    #[test]
+    fn test_program_stats() {
+        let source = r#"
+            struct Point {
+                x: i32;
+                func origin() -> i32 {
+                    0
+                }
+            }
+
+            func main(limit: i32) -> nil {
+                let total: i32 = 0;
+                loop (const i: 0..limit) {
+                    if (i > 2) {
+                        total += i;
+                    };;
+                };
+                print(total);
+            }
+        "#;
+        // main's body, the loop, its body, the if, its then branch, the assignment, and finally `i` are 7 deep
+        assert_eq!(stats(source), ProgramStats { expressions: 19, statements: 5, declarations: 3, max_depth: 7 });
+    }
+
+    #[test]
+    fn test_depth_of_siblings() {
+        // Expressions next to each other don't add to the depth
+        assert_eq!(stats("func main() -> nil { 1; 2; 3; }").max_depth, 2);
+        assert_eq!(stats("func main() -> i32 { { { 1 } } }").max_depth, 4);
+    }
+}
@@ -0,0 +1,283 @@
+use super::ast::{Declaration, Expression, LoopType, Program, Spanned, Statement, StringPart, StructElement, Type};
+
+// A read-only traversal of the AST.
+// Every method recurses into the node's children by default, so a visitor only needs to override the nodes it cares about.
+// Overriding methods can call the matching `walk_` function to keep descending.
+// Expressions are visited with their spans, since every expression in the AST has one.
+
+pub trait Visitor {
+    fn visit_program(&mut self, program: &Program) {
+        walk_program(self, program);
+    }
+
+    fn visit_declaration(&mut self, declaration: &Declaration) {
+        walk_declaration(self, declaration);
+    }
+
+    fn visit_statement(&mut self, statement: &Statement) {
+        walk_statement(self, statement);
+    }
+
+    fn visit_expression(&mut self, expression: &Spanned<Expression>) {
+        walk_expression(self, expression);
+    }
+
+    fn visit_type(&mut self, ty: &Type) {
+        walk_type(self, ty);
+    }
+}
+
+pub fn walk_program<V: Visitor + ?Sized>(visitor: &mut V, program: &Program) {
+    for declaration in &program.declarations {
+        visitor.visit_declaration(declaration);
+    }
+}
+
+pub fn walk_declaration<V: Visitor + ?Sized>(visitor: &mut V, declaration: &Declaration) {
+    match declaration {
+        Declaration::Function { params, return_type, body, .. } => {
+            for param in params {
+                visitor.visit_type(&param.param_type);
+                if let Some(default) = &param.default {
+                    visitor.visit_expression(default);
+                }
+            }
+            visitor.visit_type(return_type);
+            visitor.visit_expression(body);
+        },
+        Declaration::Struct { elements, .. } => {
+            for element in elements {
+                match element {
+                    StructElement::Field { field_type, .. } => visitor.visit_type(field_type),
+                    StructElement::Declaration { declaration, .. } => visitor.visit_declaration(declaration)
+                }
+            }
+        },
+        Declaration::TypeDeclaration { alias, .. } => {
+            visitor.visit_type(alias);
+        },
+        Declaration::Enum { variants, .. } => {
+            for variant in variants {
+                for associated_type in &variant.associated_types {
+                    visitor.visit_type(associated_type);
+                }
+            }
+        },
+        Declaration::Const { const_type, value, .. } => {
+            visitor.visit_type(const_type);
+            visitor.visit_expression(value);
+        },
+        Declaration::Import { .. } => {}
+    }
+}
+
+pub fn walk_statement<V: Visitor + ?Sized>(visitor: &mut V, statement: &Statement) {
+    match statement {
+        Statement::Declaration(declaration) => visitor.visit_declaration(declaration),
+        Statement::Expression { expression, .. } => visitor.visit_expression(expression),
+        Statement::VariableDeclaration { variable_type, value, .. } => {
+            visitor.visit_type(variable_type);
+            if let Some(value) = value {
+                visitor.visit_expression(value);
+            }
+        },
+        Statement::Return(Some(value)) | Statement::Defer(value) => visitor.visit_expression(value),
+        Statement::Return(None) | Statement::Break(_) | Statement::Continue(_) => {}
+    }
+}
+
+pub fn walk_expression<V: Visitor + ?Sized>(visitor: &mut V, expression: &Spanned<Expression>) {
+    walk_expression_node(visitor, &expression.node);
+}
+
+/// Like `walk_expression`, for an expression that isn't wrapped in its span.
+pub fn walk_expression_node<V: Visitor + ?Sized>(visitor: &mut V, expression: &Expression) {
+    match expression {
+        Expression::Block { statements, tail } => {
+            for statement in statements {
+                visitor.visit_statement(statement);
+            }
+            if let Some(tail) = tail {
+                visitor.visit_expression(tail);
+            }
+        },
+        Expression::IntegerLiteral { .. } | Expression::NumberLiteral { .. } | Expression::StringLiteral { .. } |
+        Expression::CharLiteral(_) | Expression::BooleanLiteral(_) | Expression::NilLiteral | Expression::Variable { .. } => {},
+        Expression::FunctionCall { callee, args } | Expression::MethodCall { receiver: callee, args, .. } => {
+            visitor.visit_expression(callee);
+            for arg in args {
+                visitor.visit_expression(arg);
+            }
+        },
+        Expression::BinaryOperation { left, right, .. } | Expression::Range { start: left, end: right, .. } |
+        Expression::MemberAssignment { object: left, value: right, .. } => {
+            visitor.visit_expression(left);
+            visitor.visit_expression(right);
+        },
+        Expression::UnaryOperation { operand, .. } | Expression::TypeOf(operand) | Expression::Await(operand) | Expression::Try { operand } |
+        Expression::Grouping(operand) => {
+            visitor.visit_expression(operand);
+        },
+        Expression::Assignment { value, .. } => visitor.visit_expression(value),
+        Expression::MemberAccess { object, .. } => visitor.visit_expression(object),
+        Expression::Index { object, index } => {
+            visitor.visit_expression(object);
+            visitor.visit_expression(index);
+        },
+        Expression::IndexAssignment { object, index, value, .. } => {
+            visitor.visit_expression(object);
+            visitor.visit_expression(index);
+            visitor.visit_expression(value);
+        },
+        Expression::Array { array_type, size, initial_value } => {
+            visitor.visit_type(array_type);
+            visitor.visit_expression(size);
+            visitor.visit_expression(initial_value);
+        },
+        Expression::Tuple(elements) | Expression::ArrayLiteral(elements) => {
+            for element in elements {
+                visitor.visit_expression(element);
+            }
+        },
+        Expression::InterpolatedString { parts } => {
+            for part in parts {
+                if let StringPart::Expr(expression) = part {
+                    visitor.visit_expression(expression);
+                }
+            }
+        },
+        Expression::StructCreation { struct_type, fields } => {
+            visitor.visit_type(struct_type);
+            for (_, value) in fields {
+                visitor.visit_expression(value);
+            }
+        },
+        Expression::If { condition, then_branch, else_branch } => {
+            visitor.visit_expression(condition);
+            visitor.visit_expression(then_branch);
+            if let Some(else_branch) = else_branch {
+                visitor.visit_expression(else_branch);
+            }
+        },
+        Expression::IfLet { value, then_branch, else_branch, .. } => {
+            visitor.visit_expression(value);
+            visitor.visit_expression(then_branch);
+            if let Some(else_branch) = else_branch {
+                visitor.visit_expression(else_branch);
+            }
+        },
+        Expression::Loop(LoopType::Infinite { body, .. }) => visitor.visit_expression(body),
+        Expression::Loop(LoopType::While { condition, body, .. }) => {
+            visitor.visit_expression(condition);
+            visitor.visit_expression(body);
+        },
+        Expression::Loop(LoopType::Iterator { iterable, body, .. }) => {
+            visitor.visit_expression(iterable);
+            visitor.visit_expression(body);
+        },
+        Expression::SizeOf(ty) => visitor.visit_type(ty),
+        Expression::Cast { value, target_type } => {
+            visitor.visit_expression(value);
+            visitor.visit_type(target_type);
+        },
+        Expression::Match { scrutinee, arms } => {
+            visitor.visit_expression(scrutinee);
+            for arm in arms {
+                visitor.visit_expression(&arm.body);
+            }
+        }
+    }
+}
+
+pub fn walk_type<V: Visitor + ?Sized>(visitor: &mut V, ty: &Type) {
+    match ty {
+        Type::Identifier { generics, .. } => {
+            for generic in generics {
+                visitor.visit_type(generic);
+            }
+        },
+        Type::Function { params, return_type } => {
+            for param in params {
+                visitor.visit_type(param);
+            }
+            visitor.visit_type(return_type);
+        },
+        Type::Array { element: element_type, .. } | Type::Reference { inner: element_type, .. } | Type::Optional(element_type) => visitor.visit_type(element_type),
+        Type::Map { key, value } => {
+            visitor.visit_type(key);
+            visitor.visit_type(value);
+        },
+        Type::Record(fields) => {
+            for (_, field_type) in fields {
+                visitor.visit_type(field_type);
+            }
+        },
+        Type::Tuple(elements) => {
+            for element in elements {
+                visitor.visit_type(element);
+            }
+        },
+        Type::U8 | Type::U16 | Type::U32 | Type::U64 |
+        Type::I8 | Type::I16 | Type::I32 | Type::I64 |
+        Type::F32 | Type::F64 |
+        Type::Boolean | Type::Character | Type::Nil | Type::Never | Type::Inferred => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser::Parser, tokenizer::Tokenizer};
+
+    #[derive(Default)]
+    struct NumberCounter {
+        numbers: usize,
+        // Checks that overriding one method doesn't stop types from being visited
+        arrays: usize
+    }
+
+    impl Visitor for NumberCounter {
+        fn visit_expression(&mut self, expression: &Spanned<Expression>) {
+            if let Expression::IntegerLiteral { .. } | Expression::NumberLiteral { .. } = expression.node {
+                self.numbers += 1;
+            }
+            walk_expression(self, expression);
+        }
+
+        fn visit_type(&mut self, ty: &Type) {
+            if let Type::Array { .. } = ty {
+                self.arrays += 1;
+            }
+            walk_type(self, ty);
+        }
+    }
+
+    #[test]
+    fn test_count_number_literals() {
+        let mut tokenizer = Tokenizer::new(r#"
+            struct Grid {
+                cells: [[i32]];
+                func area(grid: Grid) -> i32 {
+                    10 * 10
+                }
+            }
+
+            func main(scale: f64) -> nil {
+                const values: [i32] = [i32, 3] { 0 };
+                if (scale > 1.5) {
+                    print(match (2) { 1 => 1, other => scale * 4 });
+                } else {
+                    return;
+                }
+            }
+        "#.to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let program = Parser::new(tokens).parse_program().unwrap();
+
+        let mut counter = NumberCounter::default();
+        counter.visit_program(&program);
+        assert_eq!(counter.numbers, 8);
+        // `[[i32]]` is two arrays, and the array expression's type is its element type
+        assert_eq!(counter.arrays, 3);
+    }
+}
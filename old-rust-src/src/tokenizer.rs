@@ -1,10 +1,17 @@
 use std::{collections::{HashMap, VecDeque}, sync::LazyLock};
 
+use crate::parser::ast::Span;
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct Token {
     pub token_type: TokenType,
     pub line: usize,
-    pub column: usize
+    pub column: usize,
+    /// The byte range of the token in the source
+    pub span: Span,
+    /// The `///` comments right before the token, joined with newlines.
+    /// They're kept on a token instead of being tokens themselves, so they don't get in the way anywhere they aren't used.
+    pub doc: Option<String>
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -13,8 +20,17 @@ pub enum TokenType {
     ImportKeyword, // import
     FunctionKeyword, // func
     StructKeyword, // struct
+    EnumKeyword, // enum
     TypeKeyword, // type
     NewKeyword, // new
+    WhereKeyword, // where
+    PubKeyword, // pub
+    SizeofKeyword, // sizeof
+    TypeofKeyword, // typeof
+    AsKeyword, // as
+    MatchKeyword, // match
+    AsyncKeyword, // async
+    AwaitKeyword, // await
 
     ReturnKeyword, // return
     IfKeyword, // if
@@ -24,17 +40,25 @@ pub enum TokenType {
     LetKeyword, // let
     BreakKeyword, // break
     ContinueKeyword, // continue
+    DeferKeyword, // defer
     
     // values
     TrueValue, // true
     FalseValue, // false
 
     StringLiteral(String), // "hello", "world", etc.
-    IntegerLiteral(i64), // 0, 1, 2, etc.
+    RawStringLiteral(String), // r"C:\files", where backslashes aren't escapes
+    // A string with `${}` in it is split around the expressions, which are tokenized as usual in between.
+    // `"a${x}b${y}c"` is `InterpolationStart("a")`, `x`, `InterpolationMiddle("b")`, `y`, `InterpolationEnd("c")`.
+    InterpolationStart(String), // "a${
+    InterpolationMiddle(String), // }b${
+    InterpolationEnd(String), // }c"
+    IntegerLiteral(i128), // 0, 1, 2, etc.
     FloatLiteral(f64), // 0.0, 0.1, 0.2, etc.
     CharLiteral(char), // 'a', 'b', 'c', etc.
 
     Identifier(String), // variable names, function names, etc.
+    Label(String), // 'outer, for naming loops
 
     // operators
     AddOperator, // +
@@ -43,19 +67,33 @@ pub enum TokenType {
     DivideOperator, // /
     ModuloOperator, // %
     AssignmentOperator, // =
+    AddAssignmentOperator, // +=
+    SubtractAssignmentOperator, // -=
+    MultiplyAssignmentOperator, // *=
+    DivideAssignmentOperator, // /=
+    ModuloAssignmentOperator, // %=
 
     AndOperator, // &&
     OrOperator, // ||
     NotOperator, // !
 
-    // TODO: Bitwise operators
+    // bitwise operators
+    // Bitwise and is `Ampersand`, since it's also used for references. Shifts are two angle brackets, so `>>` can still close nested generics.
+    BitwiseOrOperator, // |
+    BitwiseXorOperator, // ^
     
     Semicolon, // ;
     Comma, // ,
     Dot, // .
     Colon, // :
     Arrow, // ->
+    FatArrow, // =>
+    Ampersand, // &
+    QuestionMark, // ?
     Pipeline, // |>
+    RangeOperator, // ..
+    InclusiveRangeOperator, // ..=
+    Ellipsis, // ...
 
     // comparison
     EqualOperator, // ==
@@ -86,9 +124,19 @@ impl TokenType {
             TokenType::LoopKeyword => "loop".to_string(),
             TokenType::BreakKeyword => "break".to_string(),
             TokenType::ContinueKeyword => "continue".to_string(),
+            TokenType::DeferKeyword => "defer".to_string(),
             TokenType::StructKeyword => "struct".to_string(),
+            TokenType::EnumKeyword => "enum".to_string(),
             TokenType::TypeKeyword => "type".to_string(),
             TokenType::NewKeyword => "new".to_string(),
+            TokenType::WhereKeyword => "where".to_string(),
+            TokenType::PubKeyword => "pub".to_string(),
+            TokenType::SizeofKeyword => "sizeof".to_string(),
+            TokenType::TypeofKeyword => "typeof".to_string(),
+            TokenType::AsKeyword => "as".to_string(),
+            TokenType::MatchKeyword => "match".to_string(),
+            TokenType::AsyncKeyword => "async".to_string(),
+            TokenType::AwaitKeyword => "await".to_string(),
 
             TokenType::TrueValue => "true".to_string(),
             TokenType::FalseValue => "false".to_string(),
@@ -97,11 +145,16 @@ impl TokenType {
             TokenType::LetKeyword => "let".to_string(),
 
             TokenType::StringLiteral(value) => format!("\"{}\"", value),
+            TokenType::RawStringLiteral(value) => format!("r\"{}\"", value),
+            TokenType::InterpolationStart(value) => format!("\"{}${{", value),
+            TokenType::InterpolationMiddle(value) => format!("}}{}${{", value),
+            TokenType::InterpolationEnd(value) => format!("}}{}\"", value),
             TokenType::IntegerLiteral(value) => value.to_string(),
             TokenType::FloatLiteral(value) => value.to_string(),
             TokenType::CharLiteral(value) => format!("'{}'", value),
 
             TokenType::Identifier(value) => value.clone(),
+            TokenType::Label(value) => format!("'{}", value),
 
             _ => {
                 if let Some(symbol) = SYMBOLS.iter().find(|(_, v)| v == &self) {
@@ -122,8 +175,17 @@ static KEYWORDS: LazyLock<HashMap<&str, TokenType>> = LazyLock::new(|| {
     keywords.insert("import", TokenType::ImportKeyword);
     keywords.insert("func", TokenType::FunctionKeyword);
     keywords.insert("struct", TokenType::StructKeyword);
+    keywords.insert("enum", TokenType::EnumKeyword);
     keywords.insert("type", TokenType::TypeKeyword);
     keywords.insert("new", TokenType::NewKeyword);
+    keywords.insert("where", TokenType::WhereKeyword);
+    keywords.insert("pub", TokenType::PubKeyword);
+    keywords.insert("sizeof", TokenType::SizeofKeyword);
+    keywords.insert("typeof", TokenType::TypeofKeyword);
+    keywords.insert("as", TokenType::AsKeyword);
+    keywords.insert("match", TokenType::MatchKeyword);
+    keywords.insert("async", TokenType::AsyncKeyword);
+    keywords.insert("await", TokenType::AwaitKeyword);
     
     keywords.insert("return", TokenType::ReturnKeyword);
     keywords.insert("if", TokenType::IfKeyword);
@@ -131,6 +193,7 @@ static KEYWORDS: LazyLock<HashMap<&str, TokenType>> = LazyLock::new(|| {
     keywords.insert("loop", TokenType::LoopKeyword);
     keywords.insert("break", TokenType::BreakKeyword);
     keywords.insert("continue", TokenType::ContinueKeyword);
+    keywords.insert("defer", TokenType::DeferKeyword);
 
     keywords.insert("true", TokenType::TrueValue);
     keywords.insert("false", TokenType::FalseValue);
@@ -150,6 +213,11 @@ static SYMBOLS: LazyLock<HashMap<&str, TokenType>> = LazyLock::new(|| {
     symbols.insert("/", TokenType::DivideOperator);
     symbols.insert("%", TokenType::ModuloOperator);
     symbols.insert("=", TokenType::AssignmentOperator);
+    symbols.insert("+=", TokenType::AddAssignmentOperator);
+    symbols.insert("-=", TokenType::SubtractAssignmentOperator);
+    symbols.insert("*=", TokenType::MultiplyAssignmentOperator);
+    symbols.insert("/=", TokenType::DivideAssignmentOperator);
+    symbols.insert("%=", TokenType::ModuloAssignmentOperator);
 
     symbols.insert(">=", TokenType::GreaterThanEqualOperator);
     symbols.insert("<=", TokenType::LessThanEqualOperator);
@@ -159,13 +227,21 @@ static SYMBOLS: LazyLock<HashMap<&str, TokenType>> = LazyLock::new(|| {
     symbols.insert("&&", TokenType::AndOperator);
     symbols.insert("||", TokenType::OrOperator);
     symbols.insert("!", TokenType::NotOperator);
+    symbols.insert("|", TokenType::BitwiseOrOperator);
+    symbols.insert("^", TokenType::BitwiseXorOperator);
     
     symbols.insert(";", TokenType::Semicolon);
     symbols.insert(",", TokenType::Comma);
     symbols.insert(".", TokenType::Dot);
     symbols.insert(":", TokenType::Colon);
     symbols.insert("->", TokenType::Arrow);
+    symbols.insert("=>", TokenType::FatArrow);
+    symbols.insert("&", TokenType::Ampersand);
+    symbols.insert("?", TokenType::QuestionMark);
     symbols.insert("|>", TokenType::Pipeline);
+    symbols.insert("..", TokenType::RangeOperator);
+    symbols.insert("..=", TokenType::InclusiveRangeOperator);
+    symbols.insert("...", TokenType::Ellipsis);
 
     symbols.insert("(", TokenType::OpenParenthesis);
     symbols.insert(")", TokenType::CloseParenthesis);
@@ -183,6 +259,15 @@ pub struct Tokenizer {
     characters: VecDeque<char>,
     current_line: usize,
     current_column: usize,
+    /// The byte offset of the next character
+    current_offset: usize,
+    /// The byte offset where the token we're reading started
+    token_start: usize,
+    /// How many curly brackets are open in each string interpolation we're in, innermost last.
+    /// A closing bracket when none are open ends the interpolation instead.
+    interpolations: Vec<usize>,
+    /// The doc comments we've read since the last token.
+    doc: Option<String>,
 
     tokens: Vec<Token>
 }
@@ -194,6 +279,10 @@ impl Tokenizer {
             characters,
             current_line: 1,
             current_column: 1,
+            current_offset: 0,
+            token_start: 0,
+            interpolations: Vec::new(),
+            doc: None,
             tokens: Vec::<Token>::new()
         }
     }
@@ -214,6 +303,7 @@ impl Tokenizer {
     fn next(&mut self) -> Option<char> {
         if let Some(c) = self.characters.pop_front() {
             self.current_column += 1;
+            self.current_offset += c.len_utf8();
             if c == '\n' {
                 self.current_line += 1;
                 self.current_column = 1;
@@ -232,17 +322,33 @@ impl Tokenizer {
         self.tokens.push(Token {
             token_type,
             line: self.current_line,
-            column: self.current_column
+            column: self.current_column,
+            span: Span { start: self.token_start, end: self.current_offset },
+            doc: self.doc.take()
         });
     }
 
     pub fn tokenize(&mut self) -> Result<&Vec<Token>, String> {
         while self.peek().is_some() {
             self.skip_whitespace();
+            self.token_start = self.current_offset;
 
             match self.next() {
                 None => break,
 
+                // Raw strings, like `r"C:\files"`. Everything up to the next quote is kept as it's written.
+                Some('r') if self.peek() == Some(&'"') => {
+                    self.next(); // Consume the opening quote
+                    let mut string_value = String::new();
+                    while let Some(c) = self.next() {
+                        if c == '"' {
+                            break;
+                        }
+                        string_value.push(c);
+                    }
+                    self.add_token(TokenType::RawStringLiteral(string_value));
+                },
+
                 // Keywords and identifiers
                 Some(c) if c.is_alphabetic() || c == '_' => {
                     let mut identifier = String::new();
@@ -264,12 +370,37 @@ impl Tokenizer {
                     }
                 },
 
+                // Integers with a base prefix, like `0xFF`, `0o17`, or `0b1010`
+                Some('0') if self.peek().is_some_and(|&c| c == 'x' || c == 'o' || c == 'b') => {
+                    let prefix = self.next().unwrap();
+                    let radix = match prefix {
+                        'x' => 16,
+                        'o' => 8,
+                        _ => 2
+                    };
+                    let mut lexeme = format!("0{}", prefix);
+                    while let Some(next_char) = self.next_if(|c| c.is_alphanumeric() || c == '_') {
+                        lexeme.push(next_char);
+                    }
+
+                    let digits = lexeme[2..].replace('_', "");
+                    match i128::from_str_radix(&digits, radix) {
+                        Ok(value) if !digits.is_empty() => self.add_token(TokenType::IntegerLiteral(value)),
+                        _ => return Err(format!("Invalid integer value: {}", lexeme))
+                    }
+                },
+
                 Some(c) if c.is_numeric() => {
                     let mut number = String::new();
                     number.push(c);
 
                     while let Some(&next_char) = self.peek() {
-                        if next_char.is_numeric() || next_char == '.' {
+                        // A dot followed by another dot is a range, like `0..10`, instead of a decimal point
+                        let starts_range = next_char == '.' && self.characters.get(1) == Some(&'.');
+                        if next_char == '_' {
+                            // Underscores can separate digits, like `1_000`
+                            self.next();
+                        } else if next_char.is_numeric() || (next_char == '.' && !starts_range) {
                             number.push(self.next().unwrap());
                         } else {
                             break;
@@ -292,12 +423,16 @@ impl Tokenizer {
 
                     if number.contains('.') {
                         if let Ok(value) = number.parse::<f64>() {
+                            // Float literals are always finite, so infinity (and NaN) can't end up in the AST
+                            if !value.is_finite() {
+                                return Err(format!("Float value out of range: {}", number));
+                            }
                             self.add_token(TokenType::FloatLiteral(value));
                         } else {
                             return Err(format!("Invalid float value: {}", number));
                         }
                     } else {
-                        if let Ok(value) = number.parse::<i64>() {
+                        if let Ok(value) = number.parse::<i128>() {
                             self.add_token(TokenType::IntegerLiteral(value));
                         } else {
                             return Err(format!("Invalid integer value: {}", number));
@@ -325,6 +460,24 @@ impl Tokenizer {
                     }
                 },
 
+                // Doc comments, like `/// Adds two numbers`. A comment that starts with more slashes is a normal one.
+                Some('/') if self.peek() == Some(&'/') && self.characters.get(1) == Some(&'/') && self.characters.get(2) != Some(&'/') => {
+                    self.next(); // Consume the second '/'
+                    self.next(); // Consume the third '/'
+                    self.next_if(|c| c == ' ');
+                    let mut line = String::new();
+                    while let Some(c) = self.next_if(|c| c != '\n') {
+                        line.push(c);
+                    }
+                    match &mut self.doc {
+                        Some(doc) => {
+                            doc.push('\n');
+                            doc.push_str(line.trim_end());
+                        },
+                        None => self.doc = Some(line.trim_end().to_string())
+                    }
+                },
+
                 // Handle comments
                 Some('/') if self.peek().is_some_and(|&c| c == '/') => {
                     // Skip the rest of the line
@@ -347,42 +500,63 @@ impl Tokenizer {
                 },
 
                 // Strings
-                Some('"') => {
-                    // TODO: Escape sequences
-                    let mut string_value = String::new();
-                    while let Some(&c) = self.peek() {
-                        if c == '"' {
-                            self.next(); // Consume the closing quote
-                            break;
-                        } else if c == '\\' {
-                            self.next(); // Consume the backslash
-                            if let Some(&escaped_char) = self.peek() {
-                                string_value.push(escaped_char);
-                                self.next(); // Consume the escaped character
-                            }
+                Some('"') => self.read_string(false),
+
+                // Curly brackets inside a string interpolation, where the one that closes it goes back to reading the string
+                Some('{') if !self.interpolations.is_empty() => {
+                    *self.interpolations.last_mut().unwrap() += 1;
+                    self.add_token(TokenType::OpenCurlyBracket);
+                },
+                Some('}') if self.interpolations.last() == Some(&0) => {
+                    self.interpolations.pop();
+                    self.read_string(true);
+                },
+                Some('}') if !self.interpolations.is_empty() => {
+                    *self.interpolations.last_mut().unwrap() -= 1;
+                    self.add_token(TokenType::CloseCurlyBracket);
+                },
+
+                // Loop labels, like `'outer`. A single character followed by a quote is a character literal instead.
+                Some('\'') if self.peek().is_some_and(|&c| c.is_alphabetic() || c == '_') && self.characters.get(1) != Some(&'\'') => {
+                    let mut label = String::new();
+                    while let Some(&next_char) = self.peek() {
+                        if next_char.is_alphanumeric() || next_char == '_' {
+                            label.push(self.next().unwrap());
                         } else {
-                            string_value.push(c);
-                            self.next(); // Consume the character
+                            break;
                         }
                     }
-                    self.add_token(TokenType::StringLiteral(string_value));
+                    self.add_token(TokenType::Label(label));
                 },
 
                 // Handle character literals
                 Some('\'') => {
-                    if let Some(&next_char) = self.peek() {
-                        if next_char != '\'' {
-                            self.add_token(TokenType::CharLiteral(next_char));
+                    match self.peek() {
+                        Some(&'\'') => return Err("Empty character literal".to_string()),
+                        Some(&next_char) => {
                             self.next(); // Consume the character
-                        } else {
-                            return Err("Empty character literal".to_string());
-                        }
+                            self.next(); // Consume the closing quote
+                            // Added after the closing quote so the span covers the whole literal
+                            self.add_token(TokenType::CharLiteral(next_char));
+                        },
+                        None => {}
                     }
-                    self.next(); // Consume the closing quote
                 }
 
                 // Handle symbols and operators
                 Some(c) => {
+                    if let (Some(&next_char), Some(&third_char)) = (self.peek(), self.characters.get(1)) {
+                        // Check for 3-character symbols
+                        let three_char_symbol = format!("{}{}{}", c, next_char, third_char);
+                        if let Some(tok) = SYMBOLS.get(three_char_symbol.as_str()) {
+                            let token: TokenType = tok.clone();
+                            self.add_token(token);
+                            self.next(); // Consume the second character
+                            self.next(); // Consume the third character
+                            continue;
+                        }
+                    }
+
                     if let Some(&next_char) = self.peek() {
                         // Check for 2-character symbols
                         let two_char_symbol = format!("{}{}", c, next_char);
@@ -406,8 +580,48 @@ impl Tokenizer {
             }
         }
 
+        if !self.interpolations.is_empty() {
+            return Err("Unterminated string interpolation".to_string());
+        }
         Ok(&self.tokens)
     }
+
+    /// Reads the rest of a string, up to its closing quote or the next `${`.
+    /// `interpolated` is if we're continuing a string after one of its interpolations.
+    fn read_string(&mut self, interpolated: bool) {
+        // TODO: Escape sequences
+        let mut string_value = String::new();
+        while let Some(&c) = self.peek() {
+            if c == '"' {
+                self.next(); // Consume the closing quote
+                break;
+            } else if c == '$' && self.characters.get(1) == Some(&'{') {
+                self.next(); // Consume the dollar sign
+                self.next(); // Consume the curly bracket
+                self.interpolations.push(0);
+                self.add_token(if interpolated {
+                    TokenType::InterpolationMiddle(string_value)
+                } else {
+                    TokenType::InterpolationStart(string_value)
+                });
+                return;
+            } else if c == '\\' {
+                self.next(); // Consume the backslash
+                if let Some(&escaped_char) = self.peek() {
+                    string_value.push(escaped_char);
+                    self.next(); // Consume the escaped character
+                }
+            } else {
+                string_value.push(c);
+                self.next(); // Consume the character
+            }
+        }
+        self.add_token(if interpolated {
+            TokenType::InterpolationEnd(string_value)
+        } else {
+            TokenType::StringLiteral(string_value)
+        });
+    }
 }
 
 
@@ -457,6 +671,47 @@ mod tests {
 
         assert_eq!(tokens.len(), 1);
         assert_eq!(tokens[0].token_type, TokenType::StringLiteral("hello world".to_string()));
+
+        let mut tokenizer = Tokenizer::new(r#"r"C:\files" "C:\files" r"#.to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        assert_eq!(tokens[0].token_type, TokenType::RawStringLiteral(r"C:\files".to_string()));
+        assert_eq!(tokens[1].token_type, TokenType::StringLiteral("C:files".to_string()));
+        // Just an `r` is still an identifier
+        assert_eq!(tokens[2].token_type, TokenType::Identifier("r".to_string()));
+    }
+
+    #[test]
+    fn test_string_interpolation() {
+        let mut tokenizer = Tokenizer::new(r#""a${x}b${ { y } }" "${x}${y}" "\${x}""#.to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let types: Vec<TokenType> = tokens.iter().map(|token| token.token_type.clone()).collect();
+        assert_eq!(types, vec![
+            TokenType::InterpolationStart("a".to_string()),
+            TokenType::Identifier("x".to_string()),
+            TokenType::InterpolationMiddle("b".to_string()),
+            TokenType::OpenCurlyBracket,
+            TokenType::Identifier("y".to_string()),
+            TokenType::CloseCurlyBracket,
+            TokenType::InterpolationEnd("".to_string()),
+            TokenType::InterpolationStart("".to_string()),
+            TokenType::Identifier("x".to_string()),
+            TokenType::InterpolationMiddle("".to_string()),
+            TokenType::Identifier("y".to_string()),
+            TokenType::InterpolationEnd("".to_string()),
+            // An escaped dollar sign doesn't start an interpolation
+            TokenType::StringLiteral("${x}".to_string())
+        ]);
+
+        assert!(Tokenizer::new(r#""a${x"#.to_string()).tokenize().is_err());
+    }
+
+    #[test]
+    fn test_doc_comments() {
+        let mut tokenizer = Tokenizer::new("/// First line\n///\n///   Indented  \n//// Not a doc comment\nfunc // Nor this\nmain".to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        assert_eq!(tokens.len(), 2);
+        assert_eq!(tokens[0].doc, Some("First line\n\n  Indented".to_string()));
+        assert_eq!(tokens[1].doc, None);
     }
 
     #[test]
@@ -469,6 +724,19 @@ mod tests {
         assert_eq!(tokens[0].token_type, TokenType::CharLiteral('a'));
     }
 
+    #[test]
+    fn test_labels() {
+        let input = r#"'outer: 'a' 'b"#;
+        let mut tokenizer = Tokenizer::new(input.to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+
+        assert_eq!(tokens.len(), 4);
+        assert_eq!(tokens[0].token_type, TokenType::Label("outer".to_string()));
+        assert_eq!(tokens[1].token_type, TokenType::Colon);
+        assert_eq!(tokens[2].token_type, TokenType::CharLiteral('a'));
+        assert_eq!(tokens[3].token_type, TokenType::Label("b".to_string()));
+    }
+
     #[test]
     fn test_float_literals() {
         let input = r#"3.14"#;
@@ -503,6 +771,27 @@ mod tests {
         assert_eq!(tokens[0].token_type, TokenType::IntegerLiteral(42));
     }
 
+    #[test]
+    fn test_number_spellings() {
+        let input = r#"0xFF 0b1010 0o17 1_000 1_000.5"#;
+        let mut tokenizer = Tokenizer::new(input.to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+
+        let values = tokens.iter().map(|token| token.token_type.clone()).collect::<Vec<_>>();
+        assert_eq!(values, vec![
+            TokenType::IntegerLiteral(255),
+            TokenType::IntegerLiteral(10),
+            TokenType::IntegerLiteral(15),
+            TokenType::IntegerLiteral(1000),
+            TokenType::FloatLiteral(1000.5),
+        ]);
+        assert_eq!(&input[tokens[0].span.start..tokens[0].span.end], "0xFF");
+        assert_eq!(&input[tokens[3].span.start..tokens[3].span.end], "1_000");
+
+        assert!(Tokenizer::new("0x".to_string()).tokenize().is_err());
+        assert!(Tokenizer::new("0b102".to_string()).tokenize().is_err());
+    }
+
     #[test]
     fn test_keywords() {
         let input = r#"import func return if else loop const let break continue"#;
@@ -582,6 +871,30 @@ mod tests {
         assert_eq!(result.unwrap_err(), "Invalid float value: 3.14.15".to_string());
     }
 
+    #[test]
+    fn test_float_out_of_range() {
+        let input = format!("{}.0", "9".repeat(400));
+        let mut tokenizer = Tokenizer::new(input.clone());
+        let result = tokenizer.tokenize();
+
+        assert!(result.is_err());
+        assert_eq!(result.unwrap_err(), format!("Float value out of range: {}", input));
+    }
+
+    #[test]
+    fn test_ranges() {
+        let input = r#"0..10 0..=n"#;
+        let mut tokenizer = Tokenizer::new(input.to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+
+        assert_eq!(tokens[0].token_type, TokenType::IntegerLiteral(0));
+        assert_eq!(tokens[1].token_type, TokenType::RangeOperator);
+        assert_eq!(tokens[2].token_type, TokenType::IntegerLiteral(10));
+        assert_eq!(tokens[3].token_type, TokenType::IntegerLiteral(0));
+        assert_eq!(tokens[4].token_type, TokenType::InclusiveRangeOperator);
+        assert_eq!(tokens[5].token_type, TokenType::Identifier("n".to_string()));
+    }
+
     #[test]
     fn test_invalid_suffix() {
         let input = r#"42abc"#;
@@ -0,0 +1,1944 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::analysis::definite_assignment::check_definite_assignment;
+use crate::parser::ast::{BinaryOperator, Declaration, Expression, FunctionParameter, GenericParam, LoopType, Pattern, Program, Spanned, Statement, StringPart, StructElement, Type, UnaryOperator, VariableMutability, Visibility, WhereClause};
+use crate::parser::visitor::{walk_expression, walk_statement, Visitor};
+
+pub type TypeResult<T = Type> = Result<T, String>;
+
+/// The type inferred for each expression in a checked program, keyed by the expression's address.
+/// The keys are only meaningful while the program they came from is alive and unmodified.
+pub type InferredTypes = HashMap<*const Expression, Type>;
+
+/// Controls how record types relate to each other.
+/// Nominal types (primitives, structs, and anything else referenced by name) only ever match themselves.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct SubtypingRules {
+    /// Width subtyping: a record with extra fields can be used where a record with a subset of those fields is expected.
+    pub width: bool,
+    /// Depth subtyping: a record's field types can be subtypes of the expected field types instead of matching them exactly.
+    pub depth: bool
+}
+
+pub struct TypeChecker {
+    rules: SubtypingRules,
+    /// The fields of every struct we've seen, by struct name.
+    structs: HashMap<String, Vec<(String, Type)>>,
+    /// The types of the functions declared inside every struct we've seen, by struct name.
+    methods: HashMap<String, HashMap<String, Type>>,
+    /// The names of the members of each struct that aren't `pub`.
+    private_members: HashMap<String, HashSet<String>>,
+    /// The structs whose members we're currently checking, innermost last. Private members are usable in here.
+    current_structs: Vec<String>,
+    /// Non-generic type aliases, by name.
+    aliases: HashMap<String, Type>,
+    /// The variables and functions in scope, innermost scope last.
+    scopes: Vec<Scope>,
+    /// The declared return types of the functions we're currently inside, innermost last.
+    return_types: Vec<Type>,
+    /// Whether each function we're currently inside is async, innermost last.
+    async_functions: Vec<bool>,
+    /// Every expression type we've found so far.
+    types: InferredTypes
+}
+
+impl TypeChecker {
+    pub fn new() -> Self {
+        TypeChecker {
+            rules: SubtypingRules::default(),
+            structs: HashMap::new(),
+            methods: HashMap::new(),
+            private_members: HashMap::new(),
+            current_structs: Vec::new(),
+            aliases: HashMap::new(),
+            scopes: Vec::new(),
+            return_types: Vec::new(),
+            async_functions: Vec::new(),
+            types: HashMap::new()
+        }
+    }
+
+    /// Opts into structural subtyping for records.
+    /// Without this, a record is only compatible with a record that has exactly the same fields (in any order).
+    pub fn with_subtyping(mut self, rules: SubtypingRules) -> Self {
+        self.rules = rules;
+        self
+    }
+
+    fn begin_scope(&mut self) {
+        self.scopes.push(Scope::default());
+    }
+
+    fn end_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    fn declare(&mut self, name: String, ty: Type) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.integer_constants.remove(&name);
+            scope.arities.remove(&name);
+            scope.types.insert(name, ty);
+        }
+    }
+
+    fn declare_integer_constant(&mut self, name: String, ty: Type) {
+        self.declare(name.clone(), ty);
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.integer_constants.insert(name);
+        }
+    }
+
+    fn declare_function(&mut self, name: String, ty: Type, arity: Arity) {
+        self.declare(name.clone(), ty);
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.arities.insert(name, arity);
+        }
+    }
+
+    /// The innermost scope that declares `name`.
+    fn scope_of(&self, name: &str) -> Option<&Scope> {
+        self.scopes.iter().rev().find(|scope| scope.types.contains_key(name))
+    }
+
+    fn lookup(&self, name: &str) -> Option<&Type> {
+        self.scope_of(name).and_then(|scope| scope.types.get(name))
+    }
+
+    /// Whether the innermost variable called `name` is an integer constant made of literals.
+    fn is_integer_constant(&self, name: &str) -> bool {
+        self.scope_of(name).is_some_and(|scope| scope.integer_constants.contains(name))
+    }
+
+    /// How many arguments a callee can be passed, if it's a function called by name.
+    fn arity_of(&self, callee: &Expression) -> Option<Arity> {
+        let Expression::Variable { name, .. } = callee else {
+            return None;
+        };
+        self.scope_of(name).and_then(|scope| scope.arities.get(name)).copied()
+    }
+
+    /// Checks a whole program, returning the type of every expression in it.
+    pub fn check_program(&mut self, program: &Program) -> TypeResult<InferredTypes> {
+        self.begin_scope();
+
+        // Collect every top-level signature first so functions can be used before they're declared
+        self.collect_program(program);
+        for declaration in &program.declarations {
+            self.check_declaration(declaration)?;
+        }
+
+        self.end_scope();
+        Ok(std::mem::take(&mut self.types))
+    }
+
+    /// Learns the top-level declarations of a program without checking them, like the fields of its structs.
+    pub fn collect_program(&mut self, program: &Program) {
+        for declaration in &program.declarations {
+            self.collect_declaration(declaration);
+        }
+    }
+
+    /// Makes a declaration's name visible in the current scope without checking its body.
+    fn collect_declaration(&mut self, declaration: &Declaration) {
+        match declaration {
+            Declaration::Function { name, params, return_type, is_variadic, .. } => {
+                self.declare_function(name.clone(), function_type(params, return_type), arity(params, *is_variadic));
+            },
+            Declaration::Struct { name, elements, .. } => {
+                let fields = elements.iter().filter_map(|element| match element {
+                    StructElement::Field { name, field_type, .. } => Some((name.clone(), field_type.clone())),
+                    StructElement::Declaration { .. } => None
+                }).collect();
+                self.structs.insert(name.clone(), fields);
+
+                let methods = elements.iter().filter_map(|element| match element {
+                    StructElement::Declaration { declaration: Declaration::Function { name, params, return_type, .. }, .. } => {
+                        Some((name.clone(), function_type(params, return_type)))
+                    },
+                    _ => None
+                }).collect();
+                self.methods.insert(name.clone(), methods);
+
+                let private_members = elements.iter().filter_map(|element| match element {
+                    StructElement::Field { name, visibility: Visibility::Private, .. } => Some(name.clone()),
+                    StructElement::Declaration { declaration, visibility: Visibility::Private } => declaration_name(declaration),
+                    _ => None
+                }).collect();
+                self.private_members.insert(name.clone(), private_members);
+            },
+            Declaration::TypeDeclaration { name, generic_args, alias, .. } => {
+                // TODO: Generic aliases
+                if generic_args.is_empty() {
+                    self.aliases.insert(name.clone(), alias.clone());
+                }
+            },
+            Declaration::Enum { .. } => {
+                // TODO: Enum values, so the type can be used for something
+            },
+            Declaration::Const { name, const_type, .. } => {
+                self.declare(name.clone(), const_type.clone());
+            },
+            Declaration::Import { .. } => {
+                // TODO: Imports
+            }
+        }
+    }
+
+    fn check_declaration(&mut self, declaration: &Declaration) -> TypeResult<()> {
+        match declaration {
+            Declaration::Function { name, params, return_type, body, generic_args, where_clauses, is_async, is_variadic, .. } => {
+                // TODO: Enforce bounds once there's something to check them against
+                generic_bounds(generic_args, where_clauses)?;
+
+                for param in params {
+                    if let Some(default) = &param.default {
+                        let default_type = self.check_expression(default, Some(&param.param_type))?;
+                        self.expect_assignable(&default_type, &param.param_type, &format!("Mismatched default value for parameter {}", param.name))?;
+                    }
+                }
+
+                self.begin_scope();
+                for (i, param) in params.iter().enumerate() {
+                    // A variadic parameter is an array of the rest of the arguments
+                    if *is_variadic && i == params.len() - 1 {
+                        self.declare(param.name.clone(), Type::array(param.param_type.clone()));
+                    } else {
+                        self.declare(param.name.clone(), param.param_type.clone());
+                    }
+                }
+                self.return_types.push(return_type.clone());
+                self.async_functions.push(*is_async);
+
+                let body_type = self.check_expression(body, Some(return_type))?;
+                // Bodies without a result value are checked through their return statements instead
+                // TODO: Detect missing returns
+                if has_result(body) {
+                    self.expect_assignable(&body_type, return_type, &format!("Function {} returns the wrong type", name))?;
+                }
+                check_definite_assignment(body)?;
+
+                self.async_functions.pop();
+                self.return_types.pop();
+                self.end_scope();
+            },
+            Declaration::Struct { name, elements, generic_args, where_clauses, .. } => {
+                generic_bounds(generic_args, where_clauses)?;
+
+                // Nested declarations can see each other, but not outside the struct
+                self.begin_scope();
+                self.current_structs.push(name.clone());
+                for element in elements {
+                    if let StructElement::Declaration { declaration, .. } = element {
+                        self.collect_declaration(declaration);
+                    }
+                }
+                for element in elements {
+                    if let StructElement::Declaration { declaration, .. } = element {
+                        self.check_declaration(declaration)?;
+                    }
+                }
+                self.current_structs.pop();
+                self.end_scope();
+            },
+            Declaration::TypeDeclaration { generic_args, where_clauses, .. } => {
+                generic_bounds(generic_args, where_clauses)?;
+            },
+            Declaration::Enum { name, variants, generic_args, where_clauses, .. } => {
+                generic_bounds(generic_args, where_clauses)?;
+                for (i, variant) in variants.iter().enumerate() {
+                    if variants[..i].iter().any(|other| other.name == variant.name) {
+                        return Err(format!("Enum {} has two variants named {}", name, variant.name));
+                    }
+                }
+            },
+            Declaration::Const { name, const_type, value } => {
+                let value_type = self.check_expression(value, Some(const_type))?;
+                self.expect_assignable(&value_type, const_type, &format!("Mismatched types in declaration of {}", name))?;
+            },
+            Declaration::Import { .. } => {
+                // Nothing to check
+            }
+        }
+        Ok(())
+    }
+
+    fn check_statement(&mut self, statement: &Statement) -> TypeResult<()> {
+        match statement {
+            Statement::Declaration(declaration) => {
+                self.collect_declaration(declaration);
+                self.check_declaration(declaration)?;
+            },
+            Statement::Break(_) | Statement::Continue(_) => {
+                // Nothing to check
+            },
+            Statement::Expression { expression, .. } | Statement::Defer(expression) => {
+                self.check_expression(expression, None)?;
+            },
+            Statement::VariableDeclaration { mutability, name, variable_type: Type::Inferred, value } => {
+                // The parser only allows leaving out the value when there's a type
+                let value = value.as_ref().expect("Variables without a type have a value");
+                let value_type = self.check_expression(value, None)?;
+                if *mutability == VariableMutability::Immutable && is_integer_literal(value) {
+                    self.declare_integer_constant(name.clone(), value_type);
+                } else {
+                    self.declare(name.clone(), value_type);
+                }
+            },
+            Statement::VariableDeclaration { name, variable_type, value: None, .. } => {
+                self.declare(name.clone(), variable_type.clone());
+            },
+            Statement::VariableDeclaration { name, variable_type, value: Some(value), .. } => {
+                let value_type = self.check_expression(value, Some(variable_type))?;
+                self.expect_assignable(&value_type, variable_type, &format!("Mismatched types in declaration of {}", name))?;
+                self.declare(name.clone(), variable_type.clone());
+            },
+            Statement::Return(value) => {
+                let Some(return_type) = self.return_types.last().cloned() else {
+                    return Err("Tried to return outside of a function".to_string());
+                };
+                let value_type = match value {
+                    Some(value) => self.check_expression(value, Some(&return_type))?,
+                    None => Type::Nil
+                };
+                self.expect_assignable(&value_type, &return_type, "Mismatched return type")?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Finds the type of an expression and records it.
+    /// `expected` is the type the surrounding context wants, if it knows; it's used to pick a type for number literals.
+    fn check_expression(&mut self, expression: &Expression, expected: Option<&Type>) -> TypeResult {
+        let ty = self.infer_expression(expression, expected)?;
+        self.types.insert(expression as *const Expression, ty.clone());
+        Ok(ty)
+    }
+
+    fn infer_expression(&mut self, expression: &Expression, expected: Option<&Type>) -> TypeResult {
+        match expression {
+            Expression::IntegerLiteral { .. } => {
+                // Integer literals take on whatever numeric type is wanted, falling back to I64
+                match expected.map(|ty| self.resolve(ty)) {
+                    Some(ty) if is_numeric(&ty) => Ok(ty),
+                    _ => Ok(Type::I64)
+                }
+            },
+            Expression::NumberLiteral { .. } => {
+                // Float literals can only be floats, so `const n: i32 = 1.5` is a mismatch
+                match expected.map(|ty| self.resolve(ty)) {
+                    Some(ty) if is_float(&ty) => Ok(ty),
+                    _ => Ok(Type::F64)
+                }
+            },
+            Expression::StringLiteral { .. } => {
+                // TODO: A built-in string type
+                Ok(Type::Identifier { name: "String".to_string(), generics: vec![] })
+            },
+            // Any value can be written into a string, so the parts only need to check on their own
+            Expression::InterpolatedString { parts } => {
+                for part in parts {
+                    if let StringPart::Expr(expression) = part {
+                        self.check_expression(expression, None)?;
+                    }
+                }
+                Ok(Type::Identifier { name: "String".to_string(), generics: vec![] })
+            },
+            Expression::CharLiteral(_) => Ok(Type::Character),
+            Expression::BooleanLiteral(_) => Ok(Type::Boolean),
+            Expression::NilLiteral => Ok(Type::Nil),
+
+            Expression::Variable { name, .. } => {
+                let ty = self.lookup(name).cloned().ok_or_else(|| format!("Undefined variable: {}", name))?;
+                match expected.map(|ty| self.resolve(ty)) {
+                    Some(expected) if is_numeric(&expected) && self.is_integer_constant(name) => Ok(expected),
+                    _ => Ok(ty)
+                }
+            },
+
+            Expression::FunctionCall { callee, args } => {
+                let callee_type = self.check_expression(callee, None)?;
+                let arity = self.arity_of(callee);
+                self.check_call(&callee_type, arity, args)
+            },
+            // Methods are called through the member with their name, so this is the same as calling a member access
+            Expression::MethodCall { receiver, method, args } => {
+                let callee_type = self.check_member_access(receiver, method)?;
+                self.check_call(&callee_type, None, args)
+            },
+
+            Expression::BinaryOperation { left, operator, right } => {
+                match operator {
+                    BinaryOperator::Add | BinaryOperator::Subtract | BinaryOperator::Multiply |
+                    BinaryOperator::Divide | BinaryOperator::Modulus => {
+                        let operand_type = self.check_operands(left, right, expected)?;
+                        let is_string_concatenation = *operator == BinaryOperator::Add
+                            && matches!(&operand_type, Type::Identifier { name, .. } if name == "String");
+                        if !is_numeric(&operand_type) && !is_string_concatenation {
+                            return Err(format!("Operator {} can't be applied to {:?}", operator, operand_type));
+                        }
+                        Ok(operand_type)
+                    },
+                    // Shifts take the same type on both sides too, so `flags << 2` shifts by a literal of the flags' type
+                    BinaryOperator::BitwiseAnd | BinaryOperator::BitwiseOr | BinaryOperator::BitwiseXor |
+                    BinaryOperator::ShiftLeft | BinaryOperator::ShiftRight => {
+                        let operand_type = self.check_operands(left, right, expected)?;
+                        if !is_integer(&operand_type) {
+                            return Err(format!("Operator {} can't be applied to {:?}", operator, operand_type));
+                        }
+                        Ok(operand_type)
+                    },
+                    BinaryOperator::LessThan | BinaryOperator::GreaterThan |
+                    BinaryOperator::LessThanOrEqual | BinaryOperator::GreaterThanOrEqual => {
+                        let operand_type = self.check_operands(left, right, None)?;
+                        if !is_numeric(&operand_type) {
+                            return Err(format!("Operator {} can't be applied to {:?}", operator, operand_type));
+                        }
+                        Ok(Type::Boolean)
+                    },
+                    BinaryOperator::Equal | BinaryOperator::NotEqual => {
+                        self.check_operands(left, right, None)?;
+                        Ok(Type::Boolean)
+                    },
+                    BinaryOperator::And | BinaryOperator::Or => {
+                        let left_type = self.check_expression(left, Some(&Type::Boolean))?;
+                        self.expect_assignable(&left_type, &Type::Boolean, &format!("Operator {} expects booleans", operator))?;
+                        let right_type = self.check_expression(right, Some(&Type::Boolean))?;
+                        self.expect_assignable(&right_type, &Type::Boolean, &format!("Operator {} expects booleans", operator))?;
+                        Ok(Type::Boolean)
+                    }
+                }
+            },
+            Expression::UnaryOperation { operator, operand } => {
+                match operator {
+                    UnaryOperator::Negate => {
+                        let operand_type = self.check_expression(operand, expected)?;
+                        if !is_numeric(&operand_type) {
+                            return Err(format!("Operator {} can't be applied to {:?}", operator, operand_type));
+                        }
+                        Ok(operand_type)
+                    },
+                    UnaryOperator::Not => {
+                        let operand_type = self.check_expression(operand, Some(&Type::Boolean))?;
+                        self.expect_assignable(&operand_type, &Type::Boolean, "Operator ! expects a boolean")?;
+                        Ok(Type::Boolean)
+                    },
+                    UnaryOperator::Reference { mutable } => {
+                        let expected_inner = match expected.map(|expected| self.resolve(expected)) {
+                            Some(Type::Reference { inner, .. }) => Some(*inner),
+                            _ => None
+                        };
+                        let operand_type = self.check_expression(operand, expected_inner.as_ref())?;
+                        Ok(Type::Reference { mutable: *mutable, inner: Box::new(operand_type) })
+                    }
+                }
+            },
+
+            Expression::Assignment { name, operator, value, .. } => {
+                let variable_type = self.lookup(name).cloned().ok_or_else(|| format!("Undefined variable: {}", name))?;
+                let value_type = self.check_expression(value, Some(&variable_type))?;
+                self.expect_assignable(&value_type, &variable_type, &format!("Mismatched types in assignment to {}", name))?;
+                if let Some(operator) = operator {
+                    self.check_compound_assignment(operator, &variable_type)?;
+                }
+                Ok(variable_type)
+            },
+            Expression::MemberAssignment { object, member, operator, value } => {
+                let object_type = self.check_expression(object, None)?;
+                // Shared references can be copied freely, so changing a struct through one would change it for every copy
+                if let Type::Reference { mutable: false, .. } = self.resolve(&object_type) {
+                    return Err(format!("Can't assign to member {} through a shared reference", member));
+                }
+                let member_type = self.member_type(&object_type, member)?;
+                let value_type = self.check_expression(value, Some(&member_type))?;
+                self.expect_assignable(&value_type, &member_type, &format!("Mismatched types in assignment to member {}", member))?;
+                if let Some(operator) = operator {
+                    self.check_compound_assignment(operator, &member_type)?;
+                }
+                Ok(member_type)
+            },
+            Expression::MemberAccess { object, member } => self.check_member_access(object, member),
+            Expression::Index { object, index } => {
+                let object_type = self.check_expression(object, None)?;
+                self.check_index(&object_type, index)
+            },
+            Expression::IndexAssignment { object, index, operator, value } => {
+                let object_type = self.check_expression(object, None)?;
+                if let Type::Reference { mutable: false, .. } = self.resolve(&object_type) {
+                    return Err("Can't assign to an element through a shared reference".to_string());
+                }
+                let element_type = self.check_index(&object_type, index)?;
+                let value_type = self.check_expression(value, Some(&element_type))?;
+                self.expect_assignable(&value_type, &element_type, "Mismatched types in assignment to an element")?;
+                if let Some(operator) = operator {
+                    self.check_compound_assignment(operator, &element_type)?;
+                }
+                Ok(element_type)
+            },
+
+            Expression::Array { array_type, size, initial_value } => {
+                let size_type = self.check_expression(size, Some(&Type::U64))?;
+                if !is_numeric(&size_type) {
+                    return Err(format!("Array sizes must be numbers, found {:?}", size_type));
+                }
+                let value_type = self.check_expression(initial_value, Some(array_type))?;
+                self.expect_assignable(&value_type, array_type, "Mismatched array initial value")?;
+                // The size is only known at runtime
+                Ok(Type::array(array_type.clone()))
+            },
+            Expression::ArrayLiteral(elements) => {
+                let (hint, size) = match expected.map(|ty| self.resolve(ty)) {
+                    Some(Type::Array { element, size }) => (Some(*element), size),
+                    _ => (None, None)
+                };
+                // Literals are only fixed-size when they're expected to be, so they can still be used as arrays of any size
+                if let Some(size) = size && size != elements.len() {
+                    return Err(format!("Expected an array literal with {} elements, found {}", size, elements.len()));
+                }
+                let Some((first, rest)) = elements.split_first() else {
+                    return match hint {
+                        Some(element_type) => Ok(Type::Array { element: Box::new(element_type), size }),
+                        None => Err("Can't infer the type of an empty array literal without a type".to_string())
+                    };
+                };
+                // The other elements have to fit the first one's type
+                let element_type = self.check_expression(first, hint.as_ref())?;
+                for element in rest {
+                    let other_type = self.check_expression(element, Some(&element_type))?;
+                    self.expect_assignable(&other_type, &element_type, "Mismatched array element")?;
+                }
+                Ok(Type::Array { element: Box::new(element_type), size })
+            },
+            Expression::StructCreation { struct_type, fields } => {
+                let struct_type = match (struct_type, expected) {
+                    (Type::Inferred, Some(expected)) => expected,
+                    (Type::Inferred, None) => return Err("Can't infer the type of a struct creation without a type".to_string()),
+                    _ => struct_type
+                };
+                let Some(expected_fields) = self.fields_of(struct_type) else {
+                    return Err(format!("Type {:?} can't be created with fields", struct_type));
+                };
+                for (name, value) in fields {
+                    let Some((_, field_type)) = expected_fields.iter().find(|(field_name, _)| field_name == name) else {
+                        return Err(format!("Type {:?} has no field {}", struct_type, name));
+                    };
+                    let value_type = self.check_expression(value, Some(field_type))?;
+                    self.expect_assignable(&value_type, field_type, &format!("Mismatched types in field {}", name))?;
+                }
+                if let Some((missing, _)) = expected_fields.iter().find(|(field_name, _)| !fields.iter().any(|(name, _)| name == field_name)) {
+                    return Err(format!("Missing field {} in creation of {:?}", missing, struct_type));
+                }
+                Ok(struct_type.clone())
+            },
+            Expression::Tuple(elements) => {
+                // Each element takes its hint from the matching element of the expected tuple, if there is one
+                let hints = match expected.map(|ty| self.resolve(ty)) {
+                    Some(Type::Tuple(hints)) if hints.len() == elements.len() => hints.into_iter().map(Some).collect(),
+                    _ => vec![None; elements.len()]
+                };
+                let mut element_types = Vec::new();
+                for (element, hint) in elements.iter().zip(hints) {
+                    element_types.push(self.check_expression(element, hint.as_ref())?);
+                }
+                Ok(Type::Tuple(element_types))
+            },
+
+            Expression::Block { statements, tail } => {
+                self.begin_scope();
+                let mut diverges = false;
+                for statement in statements {
+                    self.check_statement(statement)?;
+                    diverges |= self.diverges(statement);
+                }
+                // A block that always jumps away never finishes, so it can stand in for any value
+                let result = match tail {
+                    Some(tail) => self.check_expression(tail, expected)?,
+                    None if diverges => Type::Never,
+                    None => Type::Nil
+                };
+                self.end_scope();
+                Ok(result)
+            },
+            Expression::If { condition, then_branch, else_branch } => {
+                self.check_condition(condition, "If")?;
+                let then_type = self.check_expression(then_branch, expected)?;
+                let Some(else_branch) = else_branch else {
+                    return Ok(Type::Nil);
+                };
+                let else_type = self.check_expression(else_branch, Some(expected.unwrap_or(&then_type)))?;
+                self.branch_type(then_type, else_type)
+            },
+            Expression::IfLet { pattern, value, then_branch, else_branch } => {
+                let value_type = self.check_expression(value, None)?;
+                // The pattern matches the value inside an optional
+                let pattern_type = match self.resolve(&value_type) {
+                    Type::Optional(inner) => *inner,
+                    _ => value_type
+                };
+                self.begin_scope();
+                self.check_pattern(pattern, &pattern_type)?;
+                let then_type = self.check_expression(then_branch, expected)?;
+                self.end_scope();
+                let Some(else_branch) = else_branch else {
+                    return Ok(Type::Nil);
+                };
+                let else_type = self.check_expression(else_branch, Some(expected.unwrap_or(&then_type)))?;
+                self.branch_type(then_type, else_type)
+            },
+            Expression::Loop(LoopType::Infinite { body, label }) => {
+                self.check_expression(body, None)?;
+                // Without a break, the only way out is to return from the function
+                if breaks_out(body, label) {
+                    Ok(Type::Nil)
+                } else {
+                    Ok(Type::Never)
+                }
+            },
+            Expression::Loop(LoopType::While { condition, body, .. }) => {
+                self.check_condition(condition, "While loop")?;
+                self.check_expression(body, None)?;
+                Ok(Type::Nil)
+            },
+            Expression::Loop(LoopType::Iterator { iterator, iterable, body, .. }) => {
+                let iterable_type = self.check_expression(iterable, None)?;
+                let element_type = match self.resolve(&iterable_type) {
+                    Type::Array { element, .. } => *element,
+                    Type::Identifier { name, mut generics } if name == "Range" && generics.len() == 1 => generics.remove(0),
+                    _ => return Err(format!("Can only iterate over arrays and ranges, found {:?}", iterable_type))
+                };
+                self.begin_scope();
+                self.declare(iterator.clone(), element_type);
+                self.check_expression(body, None)?;
+                self.end_scope();
+                Ok(Type::Nil)
+            },
+
+            Expression::SizeOf(ty) => {
+                self.size_of(ty)?;
+                Ok(Type::U64)
+            },
+            Expression::TypeOf(operand) => {
+                self.check_expression(operand, None)?;
+                // TODO: A built-in type for type descriptors
+                Ok(Type::Identifier { name: "Type".to_string(), generics: vec![] })
+            },
+            Expression::Await(operand) => {
+                if self.async_functions.last() != Some(&true) {
+                    return Err("await can only be used inside async functions".to_string());
+                }
+                // TODO: A future type to unwrap once async functions can run
+                self.check_expression(operand, expected)
+            },
+            Expression::Grouping(inner) => self.check_expression(inner, expected),
+            Expression::Try { operand } => {
+                let Some(return_type) = self.return_types.last().cloned() else {
+                    return Err("? can only be used inside functions".to_string());
+                };
+                let operand_type = self.check_expression(operand, None)?;
+                let Type::Optional(inner) = self.resolve(&operand_type) else {
+                    return Err(format!("? can only be used on optionals, found {:?}", operand_type));
+                };
+                // An empty optional returns nil, so the function has to return an optional or nil itself
+                if !matches!(self.resolve(&return_type), Type::Optional(_) | Type::Nil) {
+                    return Err(format!("? can only be used in functions that return optionals, found {:?}", return_type));
+                }
+                Ok(*inner)
+            },
+            Expression::Match { scrutinee, arms } => {
+                let scrutinee_type = self.check_expression(scrutinee, None)?;
+                let mut result_type: Option<Type> = None;
+                for arm in arms {
+                    self.begin_scope();
+                    self.check_pattern(&arm.pattern, &scrutinee_type)?;
+                    let arm_type = self.check_expression(&arm.body, result_type.as_ref().or(expected))?;
+                    self.end_scope();
+
+                    // Like if branches, the result is whichever arm type the others fit into
+                    result_type = Some(match result_type {
+                        None => arm_type,
+                        Some(result_type) if self.is_subtype(&arm_type, &result_type) => result_type,
+                        Some(result_type) if self.is_subtype(&result_type, &arm_type) => arm_type,
+                        Some(result_type) => {
+                            return Err(format!("Match arms have incompatible types {:?} and {:?}", result_type, arm_type));
+                        }
+                    });
+                }
+                Ok(result_type.unwrap_or(Type::Nil))
+            },
+            Expression::Cast { value, target_type } => {
+                let target = self.resolve(target_type);
+                // A number literal cast to a boolean is an integer
+                let value_expected = if target == Type::Boolean { Type::I64 } else { target.clone() };
+                let value_type = self.check_expression(value, Some(&value_expected))?;
+                if !is_valid_cast(&self.resolve(&value_type), &target) {
+                    return Err(format!("Can't cast {:?} to {:?}", value_type, target_type));
+                }
+                Ok(target_type.clone())
+            },
+            Expression::Range { start, end, .. } => {
+                let element_hint = match expected.map(|ty| self.resolve(ty)) {
+                    Some(Type::Identifier { name, mut generics }) if name == "Range" && generics.len() == 1 => generics.remove(0),
+                    _ => Type::I64
+                };
+                // A literal bound takes the other bound's type, so `0..n` works for any integer `n`
+                let (start_type, end_type) = if is_number_literal(start) {
+                    let end_type = self.check_expression(end, Some(&element_hint))?;
+                    (self.check_expression(start, Some(&end_type))?, end_type)
+                } else {
+                    let start_type = self.check_expression(start, Some(&element_hint))?;
+                    (start_type.clone(), self.check_expression(end, Some(&start_type))?)
+                };
+                if !is_integer(&self.resolve(&start_type)) {
+                    return Err(format!("Range bounds must be integers, found {:?}", start_type));
+                }
+                self.expect_assignable(&end_type, &start_type, "Mismatched range bounds")?;
+                Ok(Type::Identifier { name: "Range".to_string(), generics: vec![start_type] })
+            }
+        }
+    }
+
+    /// Finds the type of a field of `object`, checking that it's visible from here.
+    fn check_member_access(&mut self, object: &Expression, member: &str) -> TypeResult {
+        let object_type = self.check_expression(object, None)?;
+        self.member_type(&object_type, member)
+    }
+
+    /// The type of a field of a value of `object_type`, if it's visible from here.
+    /// Fields are reached through references, so `point.x` means the same for a `Point` and a `&Point`.
+    fn member_type(&self, object_type: &Type, member: &str) -> TypeResult {
+        let object_type = self.referent(object_type);
+        if let Type::Identifier { name, .. } = self.resolve(&object_type)
+            && !self.current_structs.contains(&name)
+            && self.private_members.get(&name).is_some_and(|private| private.contains(member)) {
+            return Err(format!("Member {} of {} is private", member, name));
+        }
+        self.fields_of(&object_type)
+            .and_then(|fields| fields.into_iter().find(|(name, _)| name == member))
+            .map(|(_, field_type)| field_type)
+            .ok_or_else(|| format!("Type {:?} has no member {}", object_type, member))
+    }
+
+    /// Checks an index into a value of `object_type`, returning the type of the element. Like fields, elements are reached through references.
+    fn check_index(&mut self, object_type: &Type, index: &Expression) -> TypeResult {
+        let Type::Array { element: element_type, .. } = self.resolve(&self.referent(object_type)) else {
+            return Err(format!("Only arrays can be indexed, found {:?}", object_type));
+        };
+        let index_type = self.check_expression(index, Some(&Type::U64))?;
+        if !is_integer(&index_type) {
+            return Err(format!("Array indexes must be integers, found {:?}", index_type));
+        }
+        Ok(*element_type)
+    }
+
+    /// The type a reference refers to, or the type itself if it isn't a reference.
+    fn referent(&self, ty: &Type) -> Type {
+        match self.resolve(ty) {
+            Type::Reference { inner, .. } => *inner,
+            _ => ty.clone()
+        }
+    }
+
+    /// Checks that a compound assignment like `target += value` works on the target's type,
+    /// since it means `target = target + value`.
+    fn check_compound_assignment(&self, operator: &BinaryOperator, target_type: &Type) -> TypeResult<()> {
+        let resolved = self.resolve(target_type);
+        let is_string_concatenation = *operator == BinaryOperator::Add
+            && matches!(&resolved, Type::Identifier { name, .. } if name == "String");
+        if !is_numeric(&resolved) && !is_string_concatenation {
+            return Err(format!("Operator {}= can't be applied to {:?}", operator, target_type));
+        }
+        Ok(())
+    }
+
+    /// Checks the arguments of a call, returning the type it results in.
+    /// Without an arity, like for a function that was passed around as a value, every parameter has to be passed.
+    fn check_call(&mut self, callee_type: &Type, arity: Option<Arity>, args: &[Spanned<Expression>]) -> TypeResult {
+        let Some((params, return_type)) = self.call_signature(callee_type)? else {
+            return Err(format!("Tried to call a value of type {:?}, which isn't a function or a struct with a call method", callee_type));
+        };
+        // Parameters that are left out get their defaults, which were checked with the function
+        let required = arity.map_or(params.len(), |arity| arity.required);
+        let is_variadic = arity.is_some_and(|arity| arity.is_variadic);
+        if is_variadic && args.len() < required {
+            return Err(format!("Expected at least {} arguments, found {}", required, args.len()));
+        }
+        if !is_variadic && (args.len() < required || args.len() > params.len()) {
+            if required == params.len() {
+                return Err(format!("Expected {} arguments, found {}", params.len(), args.len()));
+            }
+            return Err(format!("Expected {} to {} arguments, found {}", required, params.len(), args.len()));
+        }
+        for (i, arg) in args.iter().enumerate() {
+            // A variadic parameter's type is the type of each argument it collects
+            let param = &params[i.min(params.len() - 1)];
+            let arg_type = self.check_expression(arg, Some(param))?;
+            self.expect_assignable(&arg_type, param, "Mismatched argument type")?;
+        }
+        Ok(return_type)
+    }
+
+    /// Whether a statement that's already been checked always jumps away, so nothing after it in its block runs.
+    fn diverges(&self, statement: &Statement) -> bool {
+        match statement {
+            Statement::Return(_) | Statement::Break(_) | Statement::Continue(_) => true,
+            Statement::Expression { expression, .. } => self.types.get(&(&expression.node as *const Expression)) == Some(&Type::Never),
+            _ => false
+        }
+    }
+
+    /// The type of an if with both branches, which is whichever branch type the other one fits into.
+    fn branch_type(&self, then_type: Type, else_type: Type) -> TypeResult {
+        if self.is_subtype(&else_type, &then_type) {
+            Ok(then_type)
+        } else if self.is_subtype(&then_type, &else_type) {
+            Ok(else_type)
+        } else {
+            Err(format!("If branches have incompatible types {:?} and {:?}", then_type, else_type))
+        }
+    }
+
+    /// Checks that a pattern can match values of `scrutinee_type`, and declares the variable it binds.
+    fn check_pattern(&mut self, pattern: &Pattern, scrutinee_type: &Type) -> TypeResult<()> {
+        let resolved = self.resolve(scrutinee_type);
+        let matches = match pattern {
+            Pattern::IntegerLiteral(_) | Pattern::NumberLiteral(_) => is_numeric(&resolved),
+            Pattern::StringLiteral(_) => matches!(&resolved, Type::Identifier { name, .. } if name == "String"),
+            Pattern::CharLiteral(_) => resolved == Type::Character,
+            Pattern::BooleanLiteral(_) => resolved == Type::Boolean,
+            Pattern::Binding(name) => {
+                self.declare(name.clone(), scrutinee_type.clone());
+                true
+            }
+        };
+        if !matches {
+            return Err(format!("Pattern {} can't match a value of type {:?}", pattern, scrutinee_type));
+        }
+        Ok(())
+    }
+
+    /// The parameter and return types for calling a value of type `callee_type`, if it can be called.
+    /// Functions are called directly. Instances of a struct with a `call` method are called through it, with the
+    /// instance passed as the method's first parameter.
+    fn call_signature(&self, callee_type: &Type) -> TypeResult<Option<(Vec<Type>, Type)>> {
+        match self.resolve(callee_type) {
+            Type::Function { params, return_type } => Ok(Some((params, *return_type))),
+            Type::Identifier { name, .. } => {
+                let Some(Type::Function { params, return_type }) = self.methods.get(&name).and_then(|methods| methods.get("call")) else {
+                    return Ok(None);
+                };
+                if !self.current_structs.contains(&name)
+                    && self.private_members.get(&name).is_some_and(|private| private.contains("call")) {
+                    return Err(format!("Member call of {} is private", name));
+                }
+                match params.split_first() {
+                    Some((instance, rest)) if self.is_subtype(callee_type, instance) => Ok(Some((rest.to_vec(), (**return_type).clone()))),
+                    _ => Err(format!("The call method of {} must take an instance of {} first", name, name))
+                }
+            },
+            _ => Ok(None)
+        }
+    }
+
+    /// The size of a type in bytes, if it's known at compile time.
+    /// Fields are laid out one after another with no padding.
+    pub fn size_of(&self, ty: &Type) -> TypeResult<u64> {
+        self.size_of_inner(ty, &mut Vec::new())
+    }
+
+    /// `visiting` is the structs whose sizes we're in the middle of finding, so we can catch structs that contain themselves.
+    fn size_of_inner(&self, ty: &Type, visiting: &mut Vec<String>) -> TypeResult<u64> {
+        let ty = self.resolve(ty);
+        if let Some(size) = primitive_size(&ty) {
+            return Ok(size);
+        }
+        match &ty {
+            Type::Tuple(elements) => elements.iter().map(|element| self.size_of_inner(element, visiting)).sum(),
+            Type::Record(fields) => fields.iter().map(|(_, field_type)| self.size_of_inner(field_type, visiting)).sum(),
+            Type::Array { element, size: Some(size) } => Ok(self.size_of_inner(element, visiting)? * *size as u64),
+            Type::Identifier { name, generics } if generics.is_empty() && self.structs.contains_key(name) => {
+                if visiting.contains(name) {
+                    return Err(format!("Struct {} contains itself, so it doesn't have a fixed size", name));
+                }
+                visiting.push(name.clone());
+                let size = self.structs[name].iter().map(|(_, field_type)| self.size_of_inner(field_type, visiting)).sum();
+                visiting.pop();
+                size
+            },
+            _ => Err(format!("Type {:?} doesn't have a size known at compile time", ty))
+        }
+    }
+
+    /// Conditions have to be booleans; other values are never implicitly truthy.
+    fn check_condition(&mut self, condition: &Expression, construct: &str) -> TypeResult<()> {
+        let condition_type = self.check_expression(condition, Some(&Type::Boolean))?;
+        if !self.is_equivalent(&condition_type, &Type::Boolean) {
+            return Err(format!("{} conditions must be booleans, found {:?}", construct, condition_type));
+        }
+        Ok(())
+    }
+
+    /// Checks both operands of a binary operation and makes sure they have the same type.
+    /// If only one side is made of number literals, the other side is checked first so the literals can take on its type.
+    fn check_operands(&mut self, left: &Expression, right: &Expression, expected: Option<&Type>) -> TypeResult {
+        let (left_type, right_type) = if is_number_literal(left) && !is_number_literal(right) {
+            let right_type = self.check_expression(right, expected)?;
+            (self.check_expression(left, Some(&right_type))?, right_type)
+        } else {
+            let left_type = self.check_expression(left, expected)?;
+            let right_type = self.check_expression(right, Some(&left_type))?;
+            (left_type, right_type)
+        };
+
+        if !self.is_equivalent(&left_type, &right_type) {
+            return Err(format!("Mismatched operand types {:?} and {:?}", left_type, right_type));
+        }
+        Ok(self.resolve(&left_type))
+    }
+
+    fn expect_assignable(&self, actual: &Type, expected: &Type, message: &str) -> TypeResult<()> {
+        if self.is_subtype(actual, expected) {
+            Ok(())
+        } else {
+            Err(format!("{}: expected {:?}, found {:?}", message, expected, actual))
+        }
+    }
+
+    /// Follows type aliases until we reach a type that isn't one.
+    fn resolve(&self, ty: &Type) -> Type {
+        match ty {
+            Type::Identifier { name, generics } if generics.is_empty() => {
+                match self.aliases.get(name) {
+                    Some(alias) => self.resolve(alias),
+                    None => ty.clone()
+                }
+            },
+            _ => ty.clone()
+        }
+    }
+
+    /// The named fields of a type, if it has any.
+    fn fields_of(&self, ty: &Type) -> Option<Vec<(String, Type)>> {
+        match self.resolve(ty) {
+            Type::Record(fields) => Some(fields),
+            Type::Identifier { name, .. } => self.structs.get(&name).cloned(),
+            _ => None
+        }
+    }
+
+    /// Whether a value of type `sub` can be used where `sup` is expected.
+    /// - Records follow the configured `SubtypingRules`. Field order never matters.
+    /// - Functions are contravariant in their parameters and covariant in their return type.
+    /// - Tuples must have the same length, and their elements follow the depth rule like record fields do.
+    /// - Arrays are invariant, since they can be written to through either type. So are maps, in both their keys and values.
+    ///   A fixed-size array can be used where an array of any size is expected, but not where one of a different size is.
+    /// - Optionals are covariant, since they can't be changed in place.
+    /// - Shared references are covariant. Mutable references are invariant for the same reason as arrays, but can be used as shared ones.
+    /// - Never is a subtype of every type, since there are no values of it to go wrong.
+    /// - Every other type is only a subtype of itself. In particular, nil and the empty tuple never unify.
+    ///   See `Type::Nil` for why.
+    pub fn is_subtype(&self, sub: &Type, sup: &Type) -> bool {
+        match (self.resolve(sub), self.resolve(sup)) {
+            (Type::Never, _) => true,
+            (Type::Record(sub_fields), Type::Record(sup_fields)) => {
+                if !self.rules.width && sub_fields.len() != sup_fields.len() {
+                    return false;
+                }
+                sup_fields.iter().all(|(name, sup_type)| {
+                    sub_fields.iter().find(|(sub_name, _)| sub_name == name).is_some_and(|(_, sub_type)| {
+                        if self.rules.depth {
+                            self.is_subtype(sub_type, sup_type)
+                        } else {
+                            self.is_equivalent(sub_type, sup_type)
+                        }
+                    })
+                })
+            },
+            (Type::Tuple(sub_elements), Type::Tuple(sup_elements)) => {
+                sub_elements.len() == sup_elements.len() && sub_elements.iter().zip(sup_elements.iter()).all(|(sub_type, sup_type)| {
+                    if self.rules.depth {
+                        self.is_subtype(sub_type, sup_type)
+                    } else {
+                        self.is_equivalent(sub_type, sup_type)
+                    }
+                })
+            },
+            (Type::Function { params: sub_params, return_type: sub_return }, Type::Function { params: sup_params, return_type: sup_return }) => {
+                sub_params.len() == sup_params.len()
+                    && sub_params.iter().zip(sup_params.iter()).all(|(sub_param, sup_param)| self.is_subtype(sup_param, sub_param))
+                    && self.is_subtype(&sub_return, &sup_return)
+            },
+            (Type::Array { element: sub_element, size: sub_size }, Type::Array { element: sup_element, size: sup_size }) => {
+                (sup_size.is_none() || sub_size == sup_size) && self.is_equivalent(&sub_element, &sup_element)
+            },
+            (Type::Map { key: sub_key, value: sub_value }, Type::Map { key: sup_key, value: sup_value }) => {
+                self.is_equivalent(&sub_key, &sup_key) && self.is_equivalent(&sub_value, &sup_value)
+            },
+            (Type::Optional(sub_inner), Type::Optional(sup_inner)) => self.is_subtype(&sub_inner, &sup_inner),
+            (Type::Reference { mutable: sub_mutable, inner: sub_inner }, Type::Reference { mutable: sup_mutable, inner: sup_inner }) => {
+                if sup_mutable {
+                    sub_mutable && self.is_equivalent(&sub_inner, &sup_inner)
+                } else {
+                    self.is_subtype(&sub_inner, &sup_inner)
+                }
+            },
+            (sub, sup) => sub == sup
+        }
+    }
+
+    fn is_equivalent(&self, a: &Type, b: &Type) -> bool {
+        self.is_subtype(a, b) && self.is_subtype(b, a)
+    }
+}
+
+/// The names declared in one scope.
+#[derive(Default)]
+struct Scope {
+    types: HashMap<String, Type>,
+    /// The constants that were set to an integer made only of literals, like `const n = 7 / 2`.
+    /// Like the literals themselves, these take on whatever integer type is wanted.
+    integer_constants: HashSet<String>,
+    /// How many arguments each function declared here can be called with.
+    arities: HashMap<String, Arity>
+}
+
+/// How many arguments a call to a function can pass.
+/// Function types only list their parameters' types, so this is kept separately for functions that are called by name.
+#[derive(Debug, Clone, Copy)]
+struct Arity {
+    /// The number of parameters without defaults, which every call has to pass.
+    /// A variadic parameter can be passed no arguments at all, so it isn't counted.
+    required: usize,
+    /// Whether the last parameter takes any number of arguments after the others.
+    is_variadic: bool
+}
+
+fn arity(params: &[FunctionParameter], is_variadic: bool) -> Arity {
+    let without_defaults = params.iter().filter(|param| param.default.is_none()).count();
+    Arity { required: without_defaults - usize::from(is_variadic), is_variadic }
+}
+
+fn function_type(params: &[FunctionParameter], return_type: &Type) -> Type {
+    Type::Function {
+        params: params.iter().map(|param| param.param_type.clone()).collect(),
+        return_type: Box::new(return_type.clone())
+    }
+}
+
+/// Collects the bounds on each generic argument of a declaration from the argument itself and its where clauses,
+/// merging bounds that are written more than once.
+/// Errors if a clause names something that isn't one of the declaration's generic arguments.
+fn generic_bounds(generic_args: &[GenericParam], where_clauses: &[WhereClause]) -> TypeResult<HashMap<String, Vec<Type>>> {
+    let mut bounds: HashMap<String, Vec<Type>> = HashMap::new();
+    for arg in generic_args {
+        let arg_bounds = bounds.entry(arg.name.clone()).or_default();
+        for bound in &arg.bounds {
+            if !arg_bounds.contains(bound) {
+                arg_bounds.push(bound.clone());
+            }
+        }
+    }
+    for (name, clause_bounds) in where_clauses {
+        let Some(arg_bounds) = bounds.get_mut(name) else {
+            return Err(format!("Where clause bounds {}, which isn't a generic argument", name));
+        };
+        for bound in clause_bounds {
+            // Where clauses only name their bounds, so they're the same as a bound without generics
+            let bound = Type::Identifier { name: bound.clone(), generics: Vec::new() };
+            if !arg_bounds.contains(&bound) {
+                arg_bounds.push(bound);
+            }
+        }
+    }
+    Ok(bounds)
+}
+
+/// The size of a primitive type in bytes.
+fn primitive_size(ty: &Type) -> Option<u64> {
+    match ty {
+        Type::U8 | Type::I8 | Type::Boolean => Some(1),
+        Type::U16 | Type::I16 => Some(2),
+        Type::U32 | Type::I32 | Type::F32 | Type::Character => Some(4),
+        Type::U64 | Type::I64 | Type::F64 => Some(8),
+        Type::Nil => Some(0),
+        _ => None
+    }
+}
+
+fn is_numeric(ty: &Type) -> bool {
+    matches!(ty,
+        Type::U8 | Type::U16 | Type::U32 | Type::U64 |
+        Type::I8 | Type::I16 | Type::I32 | Type::I64 |
+        Type::F32 | Type::F64
+    )
+}
+
+pub fn is_integer(ty: &Type) -> bool {
+    matches!(ty,
+        Type::U8 | Type::U16 | Type::U32 | Type::U64 |
+        Type::I8 | Type::I16 | Type::I32 | Type::I64
+    )
+}
+
+fn is_float(ty: &Type) -> bool {
+    matches!(ty, Type::F32 | Type::F64)
+}
+
+/// Whether `as` can convert a value of type `from` to type `to`.
+/// Numbers convert between each other, and booleans convert to and from integers (but not floats).
+fn is_valid_cast(from: &Type, to: &Type) -> bool {
+    from == to
+        || (is_numeric(from) && is_numeric(to))
+        || (*from == Type::Boolean && is_integer(to))
+        || (is_integer(from) && *to == Type::Boolean)
+}
+
+/// Whether an expression is made only of number literals, so its type is decided by its surroundings.
+fn is_number_literal(expression: &Expression) -> bool {
+    match expression {
+        Expression::IntegerLiteral { .. } | Expression::NumberLiteral { .. } => true,
+        Expression::UnaryOperation { operator: UnaryOperator::Negate, operand } => is_number_literal(operand),
+        Expression::BinaryOperation { left, right, .. } => is_number_literal(left) && is_number_literal(right),
+        Expression::Grouping(inner) => is_number_literal(inner),
+        _ => false
+    }
+}
+
+/// Whether an expression is arithmetic on integer literals, like `7 / 2`, with no float literals in it.
+fn is_integer_literal(expression: &Expression) -> bool {
+    match expression {
+        Expression::IntegerLiteral { .. } => true,
+        Expression::UnaryOperation { operator: UnaryOperator::Negate, operand } => is_integer_literal(operand),
+        // Comparisons of literals are booleans
+        Expression::BinaryOperation { left, operator, right } => {
+            is_integer_literal(left) && is_integer_literal(right) && !matches!(operator,
+                BinaryOperator::Equal | BinaryOperator::NotEqual | BinaryOperator::LessThan | BinaryOperator::LessThanOrEqual |
+                BinaryOperator::GreaterThan | BinaryOperator::GreaterThanOrEqual | BinaryOperator::And | BinaryOperator::Or
+            )
+        },
+        Expression::Grouping(inner) => is_integer_literal(inner),
+        _ => false
+    }
+}
+
+/// The name a declaration introduces, if it introduces one.
+fn declaration_name(declaration: &Declaration) -> Option<String> {
+    match declaration {
+        Declaration::Function { name, .. } | Declaration::Struct { name, .. } |
+        Declaration::TypeDeclaration { name, .. } | Declaration::Enum { name, .. } | Declaration::Const { name, .. } => Some(name.clone()),
+        Declaration::Import { .. } => None
+    }
+}
+
+/// Whether a loop's body has a `break` that leaves the loop, rather than one nested inside it.
+fn breaks_out(body: &Spanned<Expression>, label: &Option<String>) -> bool {
+    let mut finder = BreakFinder { label, depth: 0, found: false };
+    finder.visit_expression(body);
+    finder.found
+}
+
+struct BreakFinder<'a> {
+    label: &'a Option<String>,
+    /// How many loops inside the body we're in. An unlabeled break only leaves the body's loop at depth 0.
+    depth: usize,
+    found: bool
+}
+
+impl Visitor for BreakFinder<'_> {
+    // Breaks can't leave a function, so nested functions are skipped
+    fn visit_declaration(&mut self, _declaration: &Declaration) {}
+
+    fn visit_statement(&mut self, statement: &Statement) {
+        if let Statement::Break(label) = statement {
+            self.found |= match label {
+                Some(_) => label == self.label,
+                None => self.depth == 0
+            };
+        }
+        walk_statement(self, statement);
+    }
+
+    fn visit_expression(&mut self, expression: &Spanned<Expression>) {
+        let is_loop = matches!(expression.node, Expression::Loop(_));
+        self.depth += usize::from(is_loop);
+        walk_expression(self, expression);
+        self.depth -= usize::from(is_loop);
+    }
+}
+
+/// Whether a block ends in a result value.
+fn has_result(expression: &Expression) -> bool {
+    match expression {
+        Expression::Block { tail, .. } => tail.is_some(),
+        _ => true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser::Parser, tokenizer::Tokenizer};
+
+    macro_rules! check {
+        ($input:expr, $rules:expr) => {
+            {
+                let mut tokenizer = Tokenizer::new($input.to_string());
+                let tokens = tokenizer.tokenize().unwrap();
+                let mut parser = Parser::new(tokens);
+                let program = parser.parse_program().unwrap();
+                TypeChecker::new().with_subtyping($rules).check_program(&program).map(|_| ())
+            }
+        };
+    }
+
+    const STRUCTURAL: SubtypingRules = SubtypingRules { width: true, depth: true };
+
+    #[test]
+    fn test_wider_record() {
+        let input = r#"
+            func length(point: { x: f64, y: f64 }) -> f64 {
+                point.x + point.y
+            }
+            func main(point: { x: f64, y: f64, z: f64 }) -> f64 {
+                length(point)
+            }
+        "#;
+
+        assert_eq!(check!(input, STRUCTURAL), Ok(()));
+        // Without width subtyping, the extra field makes the records incompatible
+        assert!(check!(input, SubtypingRules::default()).is_err());
+    }
+
+    #[test]
+    fn test_record_missing_field() {
+        let result = check!(r#"
+            func length(point: { x: f64, y: f64 }) -> f64 {
+                point.x + point.y
+            }
+            func main(point: { x: f64, z: f64 }) -> f64 {
+                length(point)
+            }
+        "#, STRUCTURAL);
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_record_depth() {
+        let input = r#"
+            func first(line: { start: { x: f64 } }) -> f64 {
+                line.start.x
+            }
+            func main(line: { start: { x: f64, y: f64 } }) -> f64 {
+                first(line)
+            }
+        "#;
+
+        assert_eq!(check!(input, STRUCTURAL), Ok(()));
+        assert!(check!(input, SubtypingRules { width: true, depth: false }).is_err());
+    }
+
+    #[test]
+    fn test_record_field_order() {
+        assert_eq!(check!(r#"
+            func length(point: { x: f64, y: f64 }) -> f64 {
+                point.x + point.y
+            }
+            func main(point: { y: f64, x: f64 }) -> f64 {
+                length(point)
+            }
+        "#, SubtypingRules::default()), Ok(()));
+    }
+
+    #[test]
+    fn test_where_clause() {
+        assert_eq!(check!(r#"
+            func largest<T, U>(a: T, b: T, label: U) -> T where T: Ord + Copy, U: Display {
+                a
+            }
+        "#, SubtypingRules::default()), Ok(()));
+
+        assert!(check!(r#"
+            func largest<T>(a: T, b: T) -> T where U: Ord {
+                a
+            }
+        "#, SubtypingRules::default()).is_err());
+
+        assert!(check!("enum Tree<T> where U: Ord { Leaf, Node(T) }", SubtypingRules::default()).is_err());
+    }
+
+    #[test]
+    fn test_merged_generic_bounds() {
+        let bound = |name: &str| Type::Identifier { name: name.to_string(), generics: vec![] };
+        let bounds = generic_bounds(
+            &[
+                GenericParam { name: "T".to_string(), bounds: vec![bound("Eq")] },
+                GenericParam { name: "U".to_string(), bounds: vec![] }
+            ],
+            &[
+                ("T".to_string(), vec!["Ord".to_string()]),
+                ("T".to_string(), vec!["Ord".to_string(), "Eq".to_string()])
+            ]
+        ).unwrap();
+
+        assert_eq!(bounds["T"], vec![bound("Eq"), bound("Ord")]);
+        assert!(bounds["U"].is_empty());
+    }
+
+    #[test]
+    fn test_optional_and_map_variance() {
+        let checker = TypeChecker::new().with_subtyping(STRUCTURAL);
+        let point = Type::Record(vec![("x".to_string(), Type::I32), ("y".to_string(), Type::I32)]);
+        let line = Type::Record(vec![("x".to_string(), Type::I32)]);
+        let optional = |ty: &Type| Type::Optional(Box::new(ty.clone()));
+        let map = |ty: &Type| Type::Map { key: Box::new(Type::U32), value: Box::new(ty.clone()) };
+
+        assert!(checker.is_subtype(&optional(&point), &optional(&line)));
+        assert!(!checker.is_subtype(&optional(&line), &optional(&point)));
+        assert!(!checker.is_subtype(&map(&point), &map(&line)));
+        assert!(checker.is_subtype(&map(&point), &map(&point)));
+    }
+
+    #[test]
+    fn test_nil_is_not_empty_tuple() {
+        let checker = TypeChecker::new().with_subtyping(STRUCTURAL);
+        assert!(!checker.is_subtype(&Type::Nil, &Type::Tuple(vec![])));
+        assert!(!checker.is_subtype(&Type::Tuple(vec![]), &Type::Nil));
+        assert!(checker.is_subtype(&Type::Tuple(vec![]), &Type::Tuple(vec![])));
+
+        assert!(check!(r#"
+            func takes_nil(value: nil) -> nil {}
+            func main(value: ()) -> nil {
+                takes_nil(value)
+            }
+        "#, STRUCTURAL).is_err());
+        assert!(check!(r#"
+            func takes_unit(value: ()) -> nil {}
+            func main(value: nil) -> nil {
+                takes_unit(value)
+            }
+        "#, STRUCTURAL).is_err());
+    }
+
+    #[test]
+    fn test_member_visibility() {
+        let program = |access: &str| format!(r#"
+            struct Account {{
+                pub owner: i32;
+                balance: i32;
+                pub func total(account: Account) -> i32 {{
+                    account.balance
+                }}
+            }}
+            func main(account: Account) -> i32 {{
+                {}
+            }}
+        "#, access);
+
+        assert_eq!(check!(program("account.owner"), SubtypingRules::default()), Ok(()));
+        assert_eq!(
+            check!(program("account.balance"), SubtypingRules::default()),
+            Err("Member balance of Account is private".to_string())
+        );
+    }
+
+    #[test]
+    fn test_callee_must_be_callable() {
+        let program = |body: &str| format!(r#"
+            struct Adder {{
+                amount: i32;
+                pub func call(adder: Adder, x: i32) -> i32 {{
+                    adder.amount + x
+                }}
+            }}
+            struct Point {{
+                pub x: i32;
+            }}
+            func double(x: i32) -> i32 {{
+                x * 2
+            }}
+            func main(count: i32, adder: Adder, point: Point) -> i32 {{
+                {}
+            }}
+        "#, body);
+
+        assert_eq!(check!(program("double(count)"), SubtypingRules::default()), Ok(()));
+        assert_eq!(check!(program("adder(count)"), SubtypingRules::default()), Ok(()));
+        assert_eq!(
+            check!(program("count(1)"), SubtypingRules::default()),
+            Err("Tried to call a value of type I32, which isn't a function or a struct with a call method".to_string())
+        );
+        assert_eq!(
+            check!(program("point(1)"), SubtypingRules::default()),
+            Err(r#"Tried to call a value of type Identifier { name: "Point", generics: [] }, which isn't a function or a struct with a call method"#.to_string())
+        );
+        assert_eq!(check!(program("adder(count, 1)"), SubtypingRules::default()), Err("Expected 1 arguments, found 2".to_string()));
+        // Method calls call the member with the method's name
+        assert_eq!(
+            check!(program("point.x(1)"), SubtypingRules::default()),
+            Err("Tried to call a value of type I32, which isn't a function or a struct with a call method".to_string())
+        );
+        assert_eq!(check!(program("adder.amount(1)"), SubtypingRules::default()), Err("Member amount of Adder is private".to_string()));
+    }
+
+    #[test]
+    fn test_sizeof() {
+        let checker = TypeChecker::new();
+        assert_eq!(checker.size_of(&Type::I64), Ok(8));
+        assert!(checker.size_of(&Type::array(Type::I64)).is_err());
+        assert_eq!(checker.size_of(&Type::Array { element: Box::new(Type::I64), size: Some(4) }), Ok(32));
+
+        assert_eq!(check!(r#"
+            struct Pair {
+                first: i32;
+                second: bool;
+            }
+            func main() -> u64 {
+                sizeof(Pair) + sizeof(i64)
+            }
+        "#, SubtypingRules::default()), Ok(()));
+        assert!(check!(r#"
+            func main() -> u64 {
+                sizeof([i32])
+            }
+        "#, SubtypingRules::default()).is_err());
+        assert!(check!(r#"
+            struct List {
+                next: List;
+            }
+            func main() -> u64 {
+                sizeof(List)
+            }
+        "#, SubtypingRules::default()).is_err());
+    }
+
+    #[test]
+    fn test_bitwise_operators() {
+        assert_eq!(check!(r#"
+            func main(flags: u8, shift: u8) -> bool {
+                (flags & 15 | 1 << shift) ^ flags == 0
+            }
+        "#, SubtypingRules::default()), Ok(()));
+        assert_eq!(check!(r#"
+            func main(x: f64) -> f64 {
+                x | 1.0
+            }
+        "#, SubtypingRules::default()), Err("Operator | can't be applied to F64".to_string()));
+        assert!(check!(r#"
+            func main(flags: u8, shift: u32) -> u8 {
+                flags >> shift
+            }
+        "#, SubtypingRules::default()).is_err());
+    }
+
+    #[test]
+    fn test_conditions_must_be_booleans() {
+        assert_eq!(check!(r#"
+            func main(x: i32) -> i32 {
+                loop (x > 0) {
+                    x = x - 1;
+                };
+                if (x == 0) { 1 } else { 2 }
+            }
+        "#, SubtypingRules::default()), Ok(()));
+        assert_eq!(check!(r#"
+            func main(x: i32) -> i32 {
+                if (x) { 1 } else { 2 }
+            }
+        "#, SubtypingRules::default()), Err("If conditions must be booleans, found I32".to_string()));
+        assert_eq!(check!(r#"
+            func main(x: i32) -> nil {
+                loop (x) {}
+            }
+        "#, SubtypingRules::default()), Err("While loop conditions must be booleans, found I32".to_string()));
+    }
+
+    #[test]
+    fn test_number_literals() {
+        assert_eq!(check!(r#"
+            func main(x: f32) -> f32 {
+                const a: u8 = 255;
+                const n = 7 / 2;
+                const m: i32 = n;
+                const k: u64 = n * 2;
+                x * 2 + 0.5 + n
+            }
+        "#, SubtypingRules::default()), Ok(()));
+        // Float literals are never integers
+        assert_eq!(check!(r#"
+            func main() -> nil {
+                const n: i32 = 1.5;
+            }
+        "#, SubtypingRules::default()), Err("Mismatched types in declaration of n: expected I32, found F64".to_string()));
+        // Variables aren't as flexible as literals
+        assert!(check!(r#"
+            func main() -> nil {
+                let n = 7;
+                const m: i32 = n;
+            }
+        "#, SubtypingRules::default()).is_err());
+    }
+
+    #[test]
+    fn test_never() {
+        assert_eq!(check!(r#"
+            func f() -> never {
+                loop {}
+            }
+        "#, SubtypingRules::default()), Ok(()));
+        // Never can stand in for any value
+        assert_eq!(check!(r#"
+            func fail(message: String) -> never {
+                loop {}
+            }
+            func pick(x: i32) -> i32 {
+                const y: i32 = if (x > 0) { x } else { return 0; };
+                if (y > 10) { fail("too big") } else { y }
+            }
+        "#, SubtypingRules::default()), Ok(()));
+        // A loop that can be broken out of finishes
+        assert_eq!(check!(r#"
+            func f() -> never {
+                loop { break; }
+            }
+        "#, SubtypingRules::default()), Err("Function f returns the wrong type: expected Never, found Nil".to_string()));
+        assert!(check!(r#"
+            func f() -> never {
+                'outer: loop {
+                    loop { break 'outer; }
+                }
+            }
+        "#, SubtypingRules::default()).is_err());
+        // Breaking out of an inner loop doesn't leave the outer one
+        assert_eq!(check!(r#"
+            func f() -> never {
+                loop {
+                    loop { break; }
+                }
+            }
+        "#, SubtypingRules::default()), Ok(()));
+    }
+
+    #[test]
+    fn test_await_needs_async() {
+        assert_eq!(check!(r#"
+            async func load(id: i32) -> i32 {
+                id
+            }
+            async func fetch(id: i32) -> i32 {
+                await load(id) + 1
+            }
+        "#, SubtypingRules::default()), Ok(()));
+        assert_eq!(check!(r#"
+            async func load(id: i32) -> i32 {
+                id
+            }
+            func fetch(id: i32) -> i32 {
+                await load(id)
+            }
+        "#, SubtypingRules::default()), Err("await can only be used inside async functions".to_string()));
+        // Functions nested in async functions aren't async themselves
+        assert_eq!(check!(r#"
+            async func outer(id: i32) -> i32 {
+                func inner(id: i32) -> i32 {
+                    await outer(id)
+                }
+                return id;
+            }
+        "#, SubtypingRules::default()), Err("await can only be used inside async functions".to_string()));
+    }
+
+    #[test]
+    fn test_try() {
+        assert_eq!(check!(r#"
+            func first(values: [i32?]?) -> i32? {
+                values?[0]
+            }
+            func log(value: i32?) -> nil {
+                const next: i32 = value? + 1;
+            }
+        "#, SubtypingRules::default()), Ok(()));
+        assert_eq!(check!("func main(value: i32) -> i32? { value? }", SubtypingRules::default()),
+            Err("? can only be used on optionals, found I32".to_string()));
+        assert_eq!(check!("func main(value: i32?) -> i32 { value? }", SubtypingRules::default()),
+            Err("? can only be used in functions that return optionals, found I32".to_string()));
+    }
+
+    #[test]
+    fn test_match() {
+        assert_eq!(check!(r#"
+            func sign(x: i32) -> i32 {
+                match (x) {
+                    0 => 0,
+                    other => other / other
+                }
+            }
+        "#, SubtypingRules::default()), Ok(()));
+        assert_eq!(check!(r#"
+            func main(x: i32) -> i32 {
+                match (x) {
+                    true => 1,
+                    other => 0
+                }
+            }
+        "#, SubtypingRules::default()), Err("Pattern true can't match a value of type I32".to_string()));
+        assert_eq!(check!(r#"
+            func main(x: bool) -> i32 {
+                match (x) {
+                    true => 1,
+                    false => 'a'
+                }
+            }
+        "#, SubtypingRules::default()), Err("Match arms have incompatible types I32 and Character".to_string()));
+    }
+
+    #[test]
+    fn test_array_literals() {
+        assert_eq!(check!(r#"
+            func main() -> nil {
+                const values: [u8] = [1, 2, 3];
+                const empty: [bool] = [];
+                const nested: [[i32]] = [[1], []];
+                const fixed: [[i32; 2]; 2] = [[1, 2], [3, 4]];
+                const unsized: [i32] = fixed[0];
+            }
+        "#, SubtypingRules::default()), Ok(()));
+        assert_eq!(check!("func main() -> nil { typeof([]); }", SubtypingRules::default()),
+            Err("Can't infer the type of an empty array literal without a type".to_string()));
+        assert_eq!(check!("func main() -> nil { typeof(['a', true]); }", SubtypingRules::default()),
+            Err("Mismatched array element: expected Character, found Boolean".to_string()));
+        assert_eq!(check!("func main() -> nil { const pair: [i32; 2] = [1, 2, 3]; }", SubtypingRules::default()),
+            Err("Expected an array literal with 2 elements, found 3".to_string()));
+        // Arrays that aren't fixed-size might have any length, so they can't be used as fixed-size ones
+        assert_eq!(check!("func main(values: [i32]) -> nil { const pair: [i32; 2] = values; }", SubtypingRules::default()),
+            Err("Mismatched types in declaration of pair: expected Array { element: I32, size: Some(2) }, found Array { element: I32, size: None }".to_string()));
+    }
+
+    #[test]
+    fn test_if_let() {
+        // The binding has the type inside the optional, and is only visible in the then branch
+        assert_eq!(check!(r#"
+            func unwrap_or(maybe_value: i32?, fallback: i32) -> i32 {
+                if (let value = maybe_value) value + 1 else fallback
+            }
+        "#, SubtypingRules::default()), Ok(()));
+        assert_eq!(check!(r#"
+            func main(maybe_value: i32?) -> i32 {
+                if (let value = maybe_value) 0 else value
+            }
+        "#, SubtypingRules::default()), Err("Undefined variable: value".to_string()));
+        assert_eq!(check!(r#"
+            func main(maybe_value: bool?) -> i32 {
+                if (let 'a' = maybe_value) 0 else 1
+            }
+        "#, SubtypingRules::default()), Err("Pattern 'a' can't match a value of type Boolean".to_string()));
+    }
+
+    #[test]
+    fn test_bool_int_casts() {
+        assert_eq!(check!(r#"
+            func main(x: i32, flag: bool) -> i32 {
+                const nonzero: bool = x as bool;
+                if (1 as bool) { flag as i32 } else { true as i32 + 1 }
+            }
+        "#, SubtypingRules::default()), Ok(()));
+        assert_eq!(check!(r#"
+            func main() -> i32 {
+                if (1) { 1 } else { 2 }
+            }
+        "#, SubtypingRules::default()), Err("If conditions must be booleans, found I64".to_string()));
+        // Booleans aren't integers without a cast either
+        assert!(check!(r#"
+            func main() -> i32 {
+                const n: i32 = true;
+                n
+            }
+        "#, SubtypingRules::default()).is_err());
+        assert_eq!(check!(r#"
+            func main(x: f64) -> bool {
+                x as bool
+            }
+        "#, SubtypingRules::default()), Err("Can't cast F64 to Boolean".to_string()));
+    }
+
+    #[test]
+    fn test_ranges() {
+        assert_eq!(check!(r#"
+            func main(n: u32) -> nil {
+                loop (const i: 0..n) {
+                    const index: u32 = i;
+                };
+                loop (const j: -5..=5) {
+                    const offset: i64 = j;
+                };
+            }
+        "#, SubtypingRules::default()), Ok(()));
+        assert_eq!(check!(r#"
+            func main(x: f64) -> nil {
+                loop (const i: x..10) {};
+            }
+        "#, SubtypingRules::default()), Err("Range bounds must be integers, found F64".to_string()));
+        assert!(check!(r#"
+            func main(a: i32, b: u8) -> nil {
+                loop (const i: a..b) {};
+            }
+        "#, SubtypingRules::default()).is_err());
+    }
+
+    #[test]
+    fn test_compound_assignment() {
+        assert_eq!(check!(r#"
+            func main(name: String) -> nil {
+                let count: i32 = 0;
+                count += 2;
+                name += "!";
+            }
+        "#, SubtypingRules::default()), Ok(()));
+        assert_eq!(check!(r#"
+            func main(name: String) -> nil {
+                name -= "!";
+            }
+        "#, SubtypingRules::default()), Err("Operator -= can't be applied to Identifier { name: \"String\", generics: [] }".to_string()));
+    }
+    #[test]
+    fn test_variable_declarations() {
+        assert_eq!(check!(r#"
+            func main() -> nil {
+                const count: i32 = 5;
+                let name: String = "Vixen";
+            }
+        "#, SubtypingRules::default()), Ok(()));
+        assert_eq!(check!(r#"
+            func main() -> nil {
+                const count: i32 = true;
+            }
+        "#, SubtypingRules::default()), Err("Mismatched types in declaration of count: expected I32, found Boolean".to_string()));
+        // Without a type, the variable takes the initializer's type
+        assert_eq!(check!(r#"
+            func main() -> nil {
+                const flag = true;
+                const other: bool = flag;
+            }
+        "#, SubtypingRules::default()), Ok(()));
+        assert_eq!(check!(r#"
+            func main() -> nil {
+                const flag = true;
+                const count: i32 = flag;
+            }
+        "#, SubtypingRules::default()), Err("Mismatched types in declaration of count: expected I32, found Boolean".to_string()));
+        // A variable can be declared without a value, but it has to be assigned before it's read
+        assert_eq!(check!(r#"
+            func main() -> nil {
+                let count: i32;
+                count = 5;
+                const other: i32 = count;
+            }
+        "#, SubtypingRules::default()), Ok(()));
+        assert!(check!(r#"
+            func main() -> nil {
+                let count: i32;
+                const other: i32 = count;
+            }
+        "#, SubtypingRules::default()).unwrap_err().starts_with("Variable count is read before it's definitely assigned"));
+    }
+
+    #[test]
+    fn test_tuples() {
+        assert_eq!(check!(r#"
+            func main(flag: bool) -> nil {
+                const pair: (f32, bool) = (1.5, flag);
+                const empty: () = ();
+            }
+        "#, SubtypingRules::default()), Ok(()));
+        assert_eq!(check!(r#"
+            func main() -> nil {
+                const empty: nil = ();
+            }
+        "#, SubtypingRules::default()), Err("Mismatched types in declaration of empty: expected Nil, found Tuple([])".to_string()));
+    }
+
+    #[test]
+    fn test_constants() {
+        // Functions can use constants declared after them
+        assert_eq!(check!(r#"
+            func main() -> i32 {
+                LIMIT + 1
+            }
+            const LIMIT: i32 = 10;
+        "#, SubtypingRules::default()), Ok(()));
+        assert_eq!(check!(r#"
+            const LIMIT: i32 = true;
+        "#, SubtypingRules::default()), Err("Mismatched types in declaration of LIMIT: expected I32, found Boolean".to_string()));
+    }
+
+    #[test]
+    fn test_default_parameters() {
+        assert_eq!(check!(r#"
+            func scale(value: f64, factor: f64 = 2.5) -> f64 {
+                value * factor
+            }
+        "#, SubtypingRules::default()), Ok(()));
+        assert_eq!(check!(r#"
+            func scale(value: f64, factor: f64 = true) -> f64 {
+                value * factor
+            }
+        "#, SubtypingRules::default()), Err("Mismatched default value for parameter factor: expected F64, found Boolean".to_string()));
+        // Calls can leave out any of the parameters with defaults, from the end
+        assert_eq!(check!(r#"
+            func scale(value: f64, factor: f64 = 2.5, offset: f64 = 0.0) -> f64 {
+                value * factor + offset
+            }
+            func main() -> f64 {
+                scale(1.0) + scale(1.0, 2.0) + scale(1.0, 2.0, 3.0)
+            }
+        "#, SubtypingRules::default()), Ok(()));
+        assert_eq!(check!(r#"
+            func scale(value: f64, factor: f64 = 2.5) -> f64 {
+                value * factor
+            }
+            func main() -> f64 {
+                scale()
+            }
+        "#, SubtypingRules::default()), Err("Expected 1 to 2 arguments, found 0".to_string()));
+        assert_eq!(check!(r#"
+            func scale(value: f64, factor: f64 = 2.5) -> f64 {
+                value * factor
+            }
+            func main() -> f64 {
+                scale(1.0, true)
+            }
+        "#, SubtypingRules::default()), Err("Mismatched argument type: expected F64, found Boolean".to_string()));
+        // Defaults can't see the function's other parameters
+        assert!(check!(r#"
+            func scale(value: f64, factor: f64 = value) -> f64 {
+                value * factor
+            }
+        "#, SubtypingRules::default()).is_err());
+    }
+
+    #[test]
+    fn test_variadic_parameters() {
+        // The rest of the arguments are collected into an array
+        assert_eq!(check!(r#"
+            func first(...values: i32) -> i32 {
+                values[0]
+            }
+        "#, SubtypingRules::default()), Ok(()));
+        assert!(check!(r#"
+            func first(...values: i32) -> i32 {
+                values
+            }
+        "#, SubtypingRules::default()).is_err());
+        // Each argument after the others is checked against the variadic parameter's type
+        assert_eq!(check!(r#"
+            func g(...x: i32) -> nil {}
+            func log(level: u8, ...messages: String) -> nil {}
+            func main() -> nil {
+                g();
+                g(1, 2, 3);
+                log(1);
+                log(1, "a", "b");
+            }
+        "#, SubtypingRules::default()), Ok(()));
+        assert_eq!(check!(r#"
+            func g(...x: i32) -> nil {}
+            func main() -> nil {
+                g(1, true);
+            }
+        "#, SubtypingRules::default()), Err("Mismatched argument type: expected I32, found Boolean".to_string()));
+        assert_eq!(check!(r#"
+            func log(level: u8, ...messages: String) -> nil {}
+            func main() -> nil {
+                log();
+            }
+        "#, SubtypingRules::default()), Err("Expected at least 1 arguments, found 0".to_string()));
+    }
+
+    #[test]
+    fn test_anonymous_struct_creation() {
+        assert_eq!(check!(r#"
+            struct Point {
+                x: i32;
+            }
+            func main() -> nil {
+                const point: Point = new { x: 1 };
+            }
+        "#, SubtypingRules::default()), Ok(()));
+        assert_eq!(check!(r#"
+            func main() -> nil {
+                const point = new { x: 1 };
+            }
+        "#, SubtypingRules::default()), Err("Can't infer the type of a struct creation without a type".to_string()));
+    }
+
+    #[test]
+    fn test_references() {
+        let input = |argument_type: &str| format!(r#"
+            func read(value: &i32) -> nil {{}}
+            func main(value: {}) -> nil {{
+                read(value);
+            }}
+        "#, argument_type);
+        assert_eq!(check!(input("&i32"), SubtypingRules::default()), Ok(()));
+        // Mutable references can be used as shared ones, but not the other way around
+        assert_eq!(check!(input("&mut i32"), SubtypingRules::default()), Ok(()));
+        assert!(check!(input("i32"), SubtypingRules::default()).is_err());
+        assert!(check!(r#"
+            func write(value: &mut i32) -> nil {}
+            func main(value: &i32) -> nil {
+                write(value);
+            }
+        "#, SubtypingRules::default()).is_err());
+    }
+
+    #[test]
+    fn test_member_assignment() {
+        let structs = r#"
+            struct Point {
+                pub x: i32;
+                pub y: i32;
+            }
+            func reset(point: &mut Point) -> nil {
+                point.x = 0;
+            }
+        "#;
+        // Fields are reached through references
+        assert_eq!(check!(format!(r#"{}
+            func main() -> i32 {{
+                let point: Point = new Point {{ x: 1, y: 2 }};
+                reset(&mut point);
+                const shared: &Point = &point;
+                shared.x + point.y
+            }}
+        "#, structs), SubtypingRules::default()), Ok(()));
+        // Mutable references can't be made from shared ones
+        assert!(check!(format!(r#"{}
+            func main() -> nil {{
+                let point: Point = new Point {{ x: 1, y: 2 }};
+                reset(&point);
+            }}
+        "#, structs), SubtypingRules::default()).is_err());
+        assert_eq!(check!(format!(r#"{}
+            func peek(point: &Point) -> nil {{
+                point.y += 1;
+            }}
+        "#, structs), SubtypingRules::default()), Err("Can't assign to member y through a shared reference".to_string()));
+        assert_eq!(check!(format!(r#"{}
+            func main(point: Point) -> nil {{
+                point.y = true;
+            }}
+        "#, structs), SubtypingRules::default()), Err("Mismatched types in assignment to member y: expected I32, found Boolean".to_string()));
+
+        // Elements work the same way
+        assert_eq!(check!(r#"
+            func clear(values: &mut [i32]) -> i32 {
+                values[0] = 0;
+                values[1] += values[0];
+                values[1]
+            }
+        "#, SubtypingRules::default()), Ok(()));
+        assert_eq!(check!(r#"
+            func clear(values: &[i32]) -> nil {
+                values[0] = 0;
+            }
+        "#, SubtypingRules::default()), Err("Can't assign to an element through a shared reference".to_string()));
+    }
+}